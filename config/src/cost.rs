@@ -0,0 +1,292 @@
+//! Rough cost estimates of verifying WHIR proofs on-chain.
+//!
+//! Shared by the parameter advisor and the on-chain quote, so clients can budget rent and
+//! compute units before proving and uploading.
+//!
+//! The per-unit compute unit and heap costs below are hand-picked, not yet derived from
+//! measurements. The program test "Estimates the compute units of a verification within half of
+//! the measured cost" measures verifying the sample proof and fails once the estimate is off by
+//! more than half; recalibrate them from the checkpoints the program logs with the
+//! `cu-instrumentation` feature then, and note the measured configuration here.
+
+use whir_common::parameters::MerkleProofStrategy;
+
+use crate::{
+    field::BASE_FIELD_SIZE_BYTES, field_size_bytes, merkle::HASH_BLOCK_CU, WhirParams,
+    DIGEST_SIZE_BYTES,
+};
+
+/// Size of a proof-of-work nonce in bytes.
+const POW_NONCE_BYTES: usize = 8;
+
+/// Compute unit cost of an extension field multiplication on-chain, hand-picked.
+const FIELD_MUL_CU: u64 = 60;
+
+/// Fixed compute unit cost of a verification (parameters, transcript setup), hand-picked.
+const BASE_CU: u64 = 50_000;
+
+/// Compute unit cost of a sumcheck round: evaluating the quadratic round polynomial and squeezing
+/// the folding randomness from the transcript, hand-picked.
+const SUMCHECK_ROUND_CU: u64 = 2_500;
+
+/// Compute unit cost of checking a proof-of-work nonce, hand-picked.
+const POW_CHECK_CU: u64 = 2_000;
+
+/// Fixed heap usage of a verification (parameters, transcript, statement), hand-picked.
+const BASE_HEAP_BYTES: usize = 12 * 1024;
+
+/// Proof size estimate, counting the commitments, out-of-domain answers, sumcheck polynomials and
+/// PoW nonces of every round, and the opened leaves with their Merkle paths.
+///
+/// The leaves of the initial codeword are base field elements, one per committed polynomial, the
+/// later ones extension field elements. Compressed Merkle proofs share the upper levels of their
+/// paths, which is estimated as if the queried leaves were spread evenly.
+pub fn estimate_proof_size(params: &WhirParams) -> usize {
+    let field_size = field_size_bytes();
+    let sumcheck_round = |pow_bits: f64| 3 * field_size + nonce_bytes(pow_bits);
+    let compressed = params.merkle_proof_strategy == MerkleProofStrategy::Compressed;
+    let mut leaf_element_size = BASE_FIELD_SIZE_BYTES * params.batch_size;
+
+    let mut log_domain_size = params.mv_parameters.num_variables + params.starting_log_inv_rate;
+    let mut size = DIGEST_SIZE_BYTES + params.committment_ood_samples * field_size;
+    size += params.folding_factor.at_round(0) * sumcheck_round(params.starting_folding_pow_bits);
+
+    for (round, round_params) in params.round_parameters.iter().enumerate() {
+        let folding_factor = params.folding_factor.at_round(round);
+        size += DIGEST_SIZE_BYTES + round_params.ood_samples * field_size;
+        size += nonce_bytes(round_params.pow_bits);
+        size += opening_size(
+            round_params.num_queries,
+            (1 << folding_factor) * leaf_element_size,
+            log_domain_size.saturating_sub(folding_factor),
+            compressed,
+        );
+        leaf_element_size = field_size;
+        size += params.folding_factor.at_round(round + 1)
+            * sumcheck_round(round_params.folding_pow_bits);
+        log_domain_size -= 1;
+    }
+
+    let folding_factor = params
+        .folding_factor
+        .at_round(params.round_parameters.len());
+    size += (1 << params.final_sumcheck_rounds) * field_size;
+    size += nonce_bytes(params.final_pow_bits);
+    size += opening_size(
+        params.final_queries,
+        (1 << folding_factor) * leaf_element_size,
+        log_domain_size.saturating_sub(folding_factor),
+        compressed,
+    );
+    size += params.final_sumcheck_rounds * sumcheck_round(params.final_folding_pow_bits);
+    size
+}
+
+/// Compute unit estimate of a verification, split by the work it models, see
+/// [`estimate_verify_cost`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CuEstimate {
+    /// Building the parameters and the transcript, parsing the commitment and the statement.
+    pub setup: u64,
+    /// Hashing the opened leaves and the nodes of their Merkle paths.
+    pub merkle: u64,
+    /// Folding the opened leaves and evaluating the final polynomial at them.
+    pub folding: u64,
+    /// Sumcheck rounds, each evaluating a round polynomial and squeezing a challenge.
+    pub sumcheck: u64,
+    /// Proof-of-work checks, one hash per nonce.
+    pub pow: u64,
+}
+
+impl CuEstimate {
+    pub fn total(&self) -> u64 {
+        self.setup + self.merkle + self.folding + self.sumcheck + self.pow
+    }
+}
+
+/// Compute unit estimate of verifying a proof of the parameters on-chain.
+///
+/// Models the cost as hash invocations per opened leaf and path node ([`HASH_BLOCK_CU`] per
+/// 64-byte block, following the Merkle configuration), field multiplications per folded leaf,
+/// and fixed costs per sumcheck round, proof-of-work check and verification. The per-unit costs
+/// are hand-picked, see the module docs.
+pub fn estimate_verify_cost(params: &WhirParams) -> CuEstimate {
+    let field_size = field_size_bytes();
+    let compressed = params.merkle_proof_strategy == MerkleProofStrategy::Compressed;
+    let mut leaf_element_size = BASE_FIELD_SIZE_BYTES * params.batch_size;
+    let mut log_domain_size = params.mv_parameters.num_variables + params.starting_log_inv_rate;
+    let mut estimate = CuEstimate {
+        setup: BASE_CU,
+        ..Default::default()
+    };
+    let sumcheck_rounds = |estimate: &mut CuEstimate, rounds: usize, pow_bits: f64| {
+        estimate.sumcheck += rounds as u64 * SUMCHECK_ROUND_CU;
+        estimate.pow += rounds as u64 * pow_check_cu(pow_bits);
+    };
+    let mut queries = |estimate: &mut CuEstimate, num_queries: usize, folding_factor: usize| {
+        let leaf_size = (1 << folding_factor) * leaf_element_size;
+        let depth = log_domain_size.saturating_sub(folding_factor);
+        let shared = if compressed {
+            num_queries.max(1).ilog2() as usize
+        } else {
+            0
+        };
+        let leaf_blocks = leaf_size.div_ceil(64) as u64;
+        let path_nodes = depth.saturating_sub(shared).max(1) as u64;
+        estimate.merkle += num_queries as u64 * (leaf_blocks + path_nodes) * HASH_BLOCK_CU;
+        estimate.folding +=
+            num_queries as u64 * (folding_factor << folding_factor) as u64 * FIELD_MUL_CU;
+        leaf_element_size = field_size;
+        log_domain_size -= 1;
+    };
+
+    sumcheck_rounds(
+        &mut estimate,
+        params.folding_factor.at_round(0),
+        params.starting_folding_pow_bits,
+    );
+    for (round, round_params) in params.round_parameters.iter().enumerate() {
+        estimate.pow += pow_check_cu(round_params.pow_bits);
+        queries(
+            &mut estimate,
+            round_params.num_queries,
+            params.folding_factor.at_round(round),
+        );
+        sumcheck_rounds(
+            &mut estimate,
+            params.folding_factor.at_round(round + 1),
+            round_params.folding_pow_bits,
+        );
+    }
+
+    estimate.pow += pow_check_cu(params.final_pow_bits);
+    queries(
+        &mut estimate,
+        params.final_queries,
+        params
+            .folding_factor
+            .at_round(params.round_parameters.len()),
+    );
+    estimate.folding +=
+        params.final_queries as u64 * (1u64 << params.final_sumcheck_rounds) * FIELD_MUL_CU;
+    sumcheck_rounds(
+        &mut estimate,
+        params.final_sumcheck_rounds,
+        params.final_folding_pow_bits,
+    );
+    estimate
+}
+
+/// Total compute unit estimate of a verification, see [`estimate_verify_cost`].
+pub fn estimate_compute_units(params: &WhirParams) -> u64 {
+    estimate_verify_cost(params).total()
+}
+
+/// Rough heap estimate of a verification: the fixed usage, the out-of-domain answers, the final
+/// polynomial and the opened leaves of the round opening the most, parsed into field elements.
+///
+/// The proof itself isn't counted: it is read in place from the proof account, or from the
+/// instruction data, which is already allocated when the estimate is checked. Neither are the
+/// openings of the other rounds, so the estimate doesn't reject verifications that fit the heap.
+pub fn estimate_heap_bytes(params: &WhirParams) -> usize {
+    let ood_answers = params.committment_ood_samples
+        + params
+            .round_parameters
+            .iter()
+            .map(|round_params| round_params.ood_samples)
+            .sum::<usize>();
+    let final_polynomial = (1 << params.final_sumcheck_rounds) * field_size_bytes();
+    BASE_HEAP_BYTES
+        + largest_opened_leaves(params)
+        + final_polynomial
+        + ood_answers * std::mem::size_of::<crate::F>()
+}
+
+/// Size of the opened leaves of the round opening the most, including the final queries.
+fn largest_opened_leaves(params: &WhirParams) -> usize {
+    let mut leaf_element_size = BASE_FIELD_SIZE_BYTES * params.batch_size;
+    let mut largest = 0;
+    for (round, round_params) in params.round_parameters.iter().enumerate() {
+        let leaf_size = (1 << params.folding_factor.at_round(round)) * leaf_element_size;
+        largest = largest.max(round_params.num_queries * leaf_size);
+        leaf_element_size = field_size_bytes();
+    }
+    let folding_factor = params
+        .folding_factor
+        .at_round(params.round_parameters.len());
+    largest.max(params.final_queries * (1 << folding_factor) * leaf_element_size)
+}
+
+fn pow_check_cu(pow_bits: f64) -> u64 {
+    if pow_bits > 0.0 {
+        POW_CHECK_CU
+    } else {
+        0
+    }
+}
+
+fn nonce_bytes(pow_bits: f64) -> usize {
+    if pow_bits > 0.0 {
+        POW_NONCE_BYTES
+    } else {
+        0
+    }
+}
+
+/// Returns the number of `chunk_size`-byte upload transactions of a proof of the parameters.
+pub fn estimate_upload_chunks(params: &WhirParams, chunk_size: usize) -> usize {
+    estimate_proof_size(params).div_ceil(chunk_size)
+}
+
+/// Size of opening `num_queries` leaves of `leaf_size` bytes in a tree of depth `depth`, with their
+/// Merkle paths.
+fn opening_size(num_queries: usize, leaf_size: usize, depth: usize, compressed: bool) -> usize {
+    let shared = if compressed {
+        num_queries.max(1).ilog2() as usize
+    } else {
+        0
+    };
+    num_queries * (leaf_size + depth.saturating_sub(shared).max(1) * DIGEST_SIZE_BYTES)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_whir_params, create_whir_params_with, ProtocolOptions, ReedSolomonBackend};
+
+    #[test]
+    fn proof_size_follows_merkle_proof_strategy() {
+        let compressed = create_whir_params(10, 100, 16, 4, 1);
+        let options = ProtocolOptions {
+            merkle_proof_strategy: MerkleProofStrategy::Uncompressed,
+            ..Default::default()
+        };
+        let uncompressed =
+            create_whir_params_with(10, 100, 16, 4, 1, options, ReedSolomonBackend::default());
+        assert!(estimate_proof_size(&uncompressed) > estimate_proof_size(&compressed));
+        assert_eq!(
+            estimate_upload_chunks(&compressed, 800),
+            estimate_proof_size(&compressed).div_ceil(800)
+        );
+    }
+
+    #[test]
+    fn verify_cost_grows_with_queries() {
+        let cost = estimate_verify_cost(&create_whir_params(10, 100, 16, 4, 1));
+        assert_eq!(
+            cost.total(),
+            estimate_compute_units(&create_whir_params(10, 100, 16, 4, 1))
+        );
+        let cheaper = estimate_verify_cost(&create_whir_params(10, 64, 16, 4, 1));
+        assert!(cheaper.merkle < cost.merkle);
+        assert!(cheaper.total() < cost.total());
+    }
+
+    #[test]
+    fn heap_estimate_leaves_out_the_proof() {
+        let params = create_whir_params(10, 100, 16, 4, 1);
+        let heap = estimate_heap_bytes(&params);
+        assert!(heap > BASE_HEAP_BYTES + largest_opened_leaves(&params));
+        assert!(heap < BASE_HEAP_BYTES + estimate_proof_size(&params));
+    }
+}
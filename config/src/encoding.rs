@@ -0,0 +1,182 @@
+//! On-wire encodings for proof (transcript) bytes.
+//!
+//! The WHIR transcript is dominated by field elements and Merkle digests, which are
+//! uniformly random and don't compress. The remaining bytes are mostly hint metadata
+//! serialized by arkworks: `u64` length prefixes and `u64` leaf indices of Merkle
+//! multi-proofs, which are small numbers padded with zero bytes. The packed encoding
+//! collapses these zero runs, which is cheap to decode on-chain.
+//!
+//! Zero runs are the only structure this suite removes. Canonical field elements of the
+//! supported fields fill their serialized bytes, so packing them saves next to nothing, and
+//! zero runs already shrink a `u64` Merkle index to a few bytes. Delta-encoding the indices
+//! would make the decoder parse the transcript's hint layout, which belongs to the WHIR fork.
+//!
+//! Decoding takes the maximum decoded length and fails as soon as the output would exceed it,
+//! so a few encoded bytes can't expand to an arbitrarily large buffer.
+
+/// Encoding of the proof bytes as uploaded to the verifier.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(rename_all = "snake_case")
+)]
+#[repr(u8)]
+pub enum ProofEncoding {
+    /// The transcript bytes as produced by the prover.
+    #[default]
+    Raw = 0,
+    /// Zero runs are replaced by a `0x00` marker followed by the run length (1..=255).
+    ZeroRunLength = 1,
+}
+
+/// Error returned when decoding malformed proof bytes.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EncodingError {
+    /// The encoding id is not known.
+    UnknownEncoding(u8),
+    /// The encoded bytes end in the middle of a zero run marker.
+    Truncated,
+    /// A zero run has a length of zero.
+    EmptyRun,
+    /// The decoded bytes would exceed the maximum length.
+    TooLong,
+}
+
+impl TryFrom<u8> for ProofEncoding {
+    type Error = EncodingError;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            0 => Ok(ProofEncoding::Raw),
+            1 => Ok(ProofEncoding::ZeroRunLength),
+            other => Err(EncodingError::UnknownEncoding(other)),
+        }
+    }
+}
+
+impl ProofEncoding {
+    /// Encodes raw transcript bytes.
+    pub fn encode(self, bytes: &[u8]) -> Vec<u8> {
+        match self {
+            ProofEncoding::Raw => bytes.to_vec(),
+            ProofEncoding::ZeroRunLength => encode_zero_runs(bytes),
+        }
+    }
+
+    /// Decodes bytes produced by [`ProofEncoding::encode`] back to the raw transcript, failing
+    /// if it's longer than `max_len` bytes.
+    pub fn decode(self, bytes: &[u8], max_len: usize) -> Result<Vec<u8>, EncodingError> {
+        match self {
+            ProofEncoding::Raw if bytes.len() > max_len => Err(EncodingError::TooLong),
+            ProofEncoding::Raw => Ok(bytes.to_vec()),
+            ProofEncoding::ZeroRunLength => decode_zero_runs(bytes, max_len),
+        }
+    }
+
+    /// Picks the encoding producing the fewest bytes for the given transcript.
+    pub fn smallest_for(bytes: &[u8]) -> (Self, Vec<u8>) {
+        let packed = encode_zero_runs(bytes);
+        if packed.len() < bytes.len() {
+            (ProofEncoding::ZeroRunLength, packed)
+        } else {
+            (ProofEncoding::Raw, bytes.to_vec())
+        }
+    }
+}
+
+fn encode_zero_runs(bytes: &[u8]) -> Vec<u8> {
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] != 0 {
+            out.push(bytes[i]);
+            i += 1;
+            continue;
+        }
+        let mut run = 0usize;
+        while i < bytes.len() && bytes[i] == 0 && run < u8::MAX as usize {
+            run += 1;
+            i += 1;
+        }
+        out.push(0);
+        out.push(run as u8);
+    }
+    out
+}
+
+fn decode_zero_runs(bytes: &[u8], max_len: usize) -> Result<Vec<u8>, EncodingError> {
+    let mut out = Vec::with_capacity(bytes.len().saturating_mul(2).min(max_len));
+    let mut iter = bytes.iter();
+    while let Some(&b) = iter.next() {
+        let run = if b != 0 {
+            1
+        } else {
+            match *iter.next().ok_or(EncodingError::Truncated)? {
+                0 => return Err(EncodingError::EmptyRun),
+                run => run as usize,
+            }
+        };
+        if out.len() + run > max_len {
+            return Err(EncodingError::TooLong);
+        }
+        if b != 0 {
+            out.push(b);
+        } else {
+            out.resize(out.len() + run, 0);
+        }
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zero_run_length_round_trip() {
+        let mut bytes = vec![1, 2, 0, 0, 0, 3, 0];
+        bytes.extend(std::iter::repeat(0).take(600));
+        bytes.push(7);
+
+        let encoded = ProofEncoding::ZeroRunLength.encode(&bytes);
+        assert!(encoded.len() < bytes.len());
+        assert_eq!(
+            ProofEncoding::ZeroRunLength.decode(&encoded, bytes.len()),
+            Ok(bytes)
+        );
+    }
+
+    #[test]
+    fn zero_run_length_rejects_malformed_input() {
+        assert_eq!(
+            ProofEncoding::ZeroRunLength.decode(&[5, 0], usize::MAX),
+            Err(EncodingError::Truncated)
+        );
+        assert_eq!(
+            ProofEncoding::ZeroRunLength.decode(&[0, 0], usize::MAX),
+            Err(EncodingError::EmptyRun)
+        );
+    }
+
+    #[test]
+    fn decoding_stops_at_the_maximum_length() {
+        // Two bytes decode to 255 zeros, the limit is checked before expanding the run.
+        assert_eq!(
+            ProofEncoding::ZeroRunLength.decode(&[0, 255], 254),
+            Err(EncodingError::TooLong)
+        );
+        assert_eq!(
+            ProofEncoding::ZeroRunLength.decode(&[0, 255, 1], 255),
+            Err(EncodingError::TooLong)
+        );
+        assert_eq!(
+            ProofEncoding::ZeroRunLength.decode(&[0, 255], 255),
+            Ok(vec![0; 255])
+        );
+        assert_eq!(
+            ProofEncoding::Raw.decode(&[1, 2, 3], 2),
+            Err(EncodingError::TooLong)
+        );
+    }
+}
@@ -0,0 +1,146 @@
+//! Field selection and fixed-width decoding of serialized field elements.
+//!
+//! Proofs are over an extension field [`F`] of a prime field [`BaseField`], selected with a cargo
+//! feature of `whir-config`, forwarded by the prover and the program, and both must be built with
+//! the same one:
+//!
+//! - default: the quadratic extension of the 64-bit Goldilocks field.
+//! - `field-goldilocks-3`: the cubic extension of the Goldilocks field.
+//! - `field-128`: a 128-bit prime field, without extension.
+//!
+//! A base field element is serialized as its canonical value in little-endian bytes, and an
+//! extension field element as its coefficients, matching `ark-serialize`'s compressed form. As the
+//! layout is fixed, the on-chain verifier decodes elements directly instead of going through the
+//! generic deserialization, saving compute units and allocations in its hot paths.
+
+use ark_ff::{AdditiveGroup, BigInt, Field as _, PrimeField};
+
+use crate::{BaseField, F};
+
+const _: () = assert!(
+    cfg!(feature = "field-goldilocks-3") as u8 + cfg!(feature = "field-128") as u8 <= 1,
+    "At most one `field-*` feature of `whir-config` can be enabled"
+);
+
+#[cfg(not(any(feature = "field-goldilocks-3", feature = "field-128")))]
+mod selected {
+    pub use whir_common::crypto::fields::{Field64 as BaseField, Field64_2 as Field};
+
+    pub const BASE_FIELD_SIZE_BYTES: usize = 8;
+    pub const EXTENSION_DEGREE: usize = 2;
+}
+
+#[cfg(feature = "field-goldilocks-3")]
+mod selected {
+    pub use whir_common::crypto::fields::{Field64 as BaseField, Field64_3 as Field};
+
+    pub const BASE_FIELD_SIZE_BYTES: usize = 8;
+    pub const EXTENSION_DEGREE: usize = 3;
+}
+
+#[cfg(feature = "field-128")]
+mod selected {
+    pub use whir_common::crypto::fields::{Field128 as BaseField, Field128 as Field};
+
+    pub const BASE_FIELD_SIZE_BYTES: usize = 16;
+    pub const EXTENSION_DEGREE: usize = 1;
+}
+
+/// Field selected by the `field-*` features.
+pub type Field = selected::Field;
+
+/// Prime field of [`Field`].
+pub type PrimeBaseField = selected::BaseField;
+
+/// Degree of [`F`] over [`BaseField`].
+pub const EXTENSION_DEGREE: usize = selected::EXTENSION_DEGREE;
+
+/// Serialized size of a [`BaseField`] element in bytes.
+pub const BASE_FIELD_SIZE_BYTES: usize = selected::BASE_FIELD_SIZE_BYTES;
+
+/// Serialized size of an [`F`] element in bytes.
+pub const FIELD_SIZE_BYTES: usize = EXTENSION_DEGREE * BASE_FIELD_SIZE_BYTES;
+
+/// Number of 64-bit limbs of a [`BaseField`] element.
+const BASE_FIELD_LIMBS: usize = BASE_FIELD_SIZE_BYTES / 8;
+
+/// Decodes a base field element, rejecting non-canonical values.
+pub fn decode_base_field(bytes: [u8; BASE_FIELD_SIZE_BYTES]) -> Option<BaseField> {
+    let mut limbs = [0u64; BASE_FIELD_LIMBS];
+    for (limb, chunk) in limbs.iter_mut().zip(bytes.chunks_exact(8)) {
+        *limb = u64::from_le_bytes(chunk.try_into().expect("8-byte chunk"));
+    }
+    BaseField::from_bigint(BigInt(limbs))
+}
+
+/// Decodes the field element at the start of `bytes`, rejecting non-canonical coefficients.
+pub fn decode_field(bytes: &[u8]) -> Option<F> {
+    let bytes = bytes.get(..FIELD_SIZE_BYTES)?;
+    let mut coefficients = [BaseField::ZERO; EXTENSION_DEGREE];
+    for (coefficient, chunk) in coefficients
+        .iter_mut()
+        .zip(bytes.chunks_exact(BASE_FIELD_SIZE_BYTES))
+    {
+        *coefficient = decode_base_field(chunk.try_into().ok()?)?;
+    }
+    F::from_base_prime_field_elems(coefficients)
+}
+
+/// Maps [`FIELD_SIZE_BYTES`] uniformly random bytes to a field element, reducing each
+/// coefficient's bytes modulo the base field. The bias is negligible for fields of 64 bits and
+/// more.
+pub fn field_from_uniform_bytes(bytes: &[u8; FIELD_SIZE_BYTES]) -> F {
+    let coefficients = bytes
+        .chunks_exact(BASE_FIELD_SIZE_BYTES)
+        .map(BaseField::from_le_bytes_mod_order);
+    F::from_base_prime_field_elems(coefficients).expect("one coefficient per extension degree")
+}
+
+/// Decodes consecutive field elements, ignoring trailing bytes shorter than an element.
+pub fn decode_field_elements(bytes: &[u8]) -> impl Iterator<Item = Option<F>> + '_ {
+    bytes.chunks_exact(FIELD_SIZE_BYTES).map(decode_field)
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_ff::Field as _;
+    use ark_serialize::{CanonicalDeserialize, CanonicalSerialize, Compress};
+    use ark_std::UniformRand;
+
+    use super::*;
+
+    #[test]
+    fn decoding_matches_ark_serialize() {
+        assert_eq!(
+            F::default().serialized_size(Compress::Yes),
+            FIELD_SIZE_BYTES
+        );
+        let mut rng = ark_std::test_rng();
+        for _ in 0..100 {
+            let element = F::rand(&mut rng);
+            let mut bytes = Vec::new();
+            element.serialize_compressed(&mut bytes).unwrap();
+            assert_eq!(decode_field(&bytes), Some(element));
+        }
+    }
+
+    #[test]
+    fn rejects_non_canonical_elements() {
+        let bytes = [0xff; FIELD_SIZE_BYTES];
+        assert!(F::deserialize_compressed(&bytes[..]).is_err());
+        assert_eq!(decode_field(&bytes), None);
+        assert_eq!(decode_field(&bytes[1..]), None);
+    }
+
+    #[test]
+    fn uniform_bytes_reduce_coefficients() {
+        let mut bytes = [0u8; FIELD_SIZE_BYTES];
+        bytes[0] = 7;
+        let mut expected = [BaseField::ZERO; EXTENSION_DEGREE];
+        expected[0] = BaseField::from(7u64);
+        assert_eq!(
+            field_from_uniform_bytes(&bytes),
+            F::from_base_prime_field_elems(expected).unwrap()
+        );
+    }
+}
@@ -1,29 +1,41 @@
 //! WHIR configuration constants and types.
 
+pub mod cost;
+pub mod encoding;
+pub mod field;
+pub mod merkle;
+pub mod params;
+pub mod pow;
+pub mod public_inputs;
+pub mod transcript;
+pub mod validation;
+
 use std::sync::Arc;
 
-use ark_serialize::{CanonicalSerialize, Compress};
-use spongefish_pow::blake3::Blake3PoW;
-use whir_common::crypto::fields::Field64_2;
-use whir_common::crypto::merkle_tree::blake3::{
-    Blake3Compress, Blake3LeafHash, Blake3MerkleTreeParams,
-};
-use whir_common::crypto::merkle_tree::parameters::default_config;
-use whir_common::ntt::RSDefault;
+use whir_common::ntt::{RSDefault, ReedSolomon};
 use whir_common::parameters::{
     default_max_pow, DeduplicationStrategy, FoldingFactor, MerkleProofStrategy,
     MultivariateParameters, ProtocolParameters, SoundnessType,
 };
 use whir_common::whir::parameters::WhirConfig;
 
-/// The field type used for WHIR proofs.
-pub type F = Field64_2;
+/// The field type used for WHIR proofs, selected by the `field-*` features, see [`field`].
+pub type F = field::Field;
+
+/// The base prime field of [`F`]. Polynomials are committed over it and opened at points of `F`.
+pub type BaseField = field::PrimeBaseField;
 
-/// Merkle tree configuration.
-pub type MerkleConfig = Blake3MerkleTreeParams<F>;
+pub use merkle::MerkleConfig;
 
-/// Proof-of-work strategy.
-pub type PowStrategy = Blake3PoW;
+/// Proof-of-work strategy: BLAKE3 grinding by default, Keccak grinding with the `pow-keccak`
+/// feature, matching the grinding function of a surrounding proof system. The prover and the
+/// program must be built with the same one.
+#[cfg(not(feature = "pow-keccak"))]
+pub type PowStrategy = spongefish_pow::blake3::Blake3PoW;
+
+/// Proof-of-work strategy, see the default configuration.
+#[cfg(feature = "pow-keccak")]
+pub type PowStrategy = spongefish_pow::keccak::KeccakPoW;
 
 /// Number of variables in the multilinear polynomial.
 pub const NUM_VARIABLES: usize = 6;
@@ -40,16 +52,113 @@ pub const FOLDING_FACTOR: usize = 4;
 /// Proof-of-work bits.
 pub const POW_BITS: usize = default_max_pow(NUM_VARIABLES, STARTING_LOG_INV_RATE);
 
+/// Number of polynomials per commitment verified by the on-chain program. Its verify instructions
+/// take no batch size, so proofs of batched commitments fail there with a transcript mismatch.
+pub const ON_CHAIN_BATCH_SIZE: usize = 1;
+
 /// Domain separator for WHIR proofs (must match between prover and verifier).
 pub const DOMAIN_SEPARATOR: &str = "whir-solana";
 
+/// Size of a Merkle tree digest in bytes.
+pub const DIGEST_SIZE_BYTES: usize = 32;
+
 /// Returns the serialized size of a field element in bytes.
 pub fn field_size_bytes() -> usize {
-    F::default().serialized_size(Compress::Yes)
+    field::FIELD_SIZE_BYTES
+}
+
+/// Returns the size of the commitment at the start of a proof: the Merkle root followed by the
+/// out-of-domain answers.
+pub fn commitment_size_bytes(params: &WhirParams) -> usize {
+    DIGEST_SIZE_BYTES + params.committment_ood_samples * field_size_bytes()
 }
 
 pub type WhirParams = WhirConfig<F, MerkleConfig, PowStrategy>;
 
+/// `pow_bits` of the no-PoW configuration: proofs reach the security level through queries alone,
+/// the prover skips grinding and the proofs carry no PoW nonces.
+pub const NO_POW_BITS: usize = 0;
+
+/// Returns whether no round of the parameters grinds, as built with [`NO_POW_BITS`] when the
+/// security level is reachable without proof of work.
+pub fn is_pow_free(params: &WhirParams) -> bool {
+    params.starting_folding_pow_bits == 0.0
+        && params.final_pow_bits == 0.0
+        && params.final_folding_pow_bits == 0.0
+        && params
+            .round_parameters
+            .iter()
+            .all(|round| round.pow_bits == 0.0 && round.folding_pow_bits == 0.0)
+}
+
+/// Reed-Solomon encoders (NTT backends) of the parameters, over [`F`] and over [`BaseField`].
+///
+/// Only the prover encodes, so provers can inject faster or hardware-accelerated implementations
+/// while the verifier keeps the default, without changing the proofs.
+#[derive(Clone)]
+pub struct ReedSolomonBackend {
+    pub extension: Arc<dyn ReedSolomon<F>>,
+    pub base: Arc<dyn ReedSolomon<BaseField>>,
+}
+
+impl Default for ReedSolomonBackend {
+    fn default() -> Self {
+        Self {
+            extension: Arc::new(RSDefault),
+            base: Arc::new(RSDefault),
+        }
+    }
+}
+
+/// Protocol options beyond the parameters of [`create_whir_params`].
+///
+/// The defaults are the layout the program verifies with `verify_v2`. Uncompressed Merkle proofs
+/// and disabled query deduplication produce larger proofs that are cheaper to verify, for other
+/// verifiers. The soundness type is also passed to `verify_v3`.
+///
+/// A first-round folding factor different from the others' trades the size of the initial
+/// codeword's openings against the number of rounds. The fork's folding schedules are constant
+/// from the second round on, so per-round schedules such as `[5, 4, 4, 3]` aren't expressible.
+#[derive(Clone, Copy, Debug)]
+pub struct ProtocolOptions {
+    /// Number of polynomials committed at once.
+    pub batch_size: usize,
+    pub merkle_proof_strategy: MerkleProofStrategy,
+    pub deduplication_strategy: DeduplicationStrategy,
+    /// Soundness assumption the query counts are derived under: unique decoding needs the most
+    /// queries, the list-decoding conjecture the fewest.
+    pub soundness_type: SoundnessType,
+    /// Folding factor of the first round, `None` for the same folding factor in all rounds.
+    pub first_round_folding_factor: Option<usize>,
+}
+
+impl ProtocolOptions {
+    /// Returns the folding schedule of parameters folding by `folding_factor` from the second
+    /// round on.
+    pub fn folding_schedule(&self, folding_factor: usize) -> FoldingFactor {
+        FoldingFactor::ConstantFromSecondRound(
+            self.first_round_folding_factor.unwrap_or(folding_factor),
+            folding_factor,
+        )
+    }
+}
+
+impl Default for ProtocolOptions {
+    fn default() -> Self {
+        Self {
+            batch_size: ON_CHAIN_BATCH_SIZE,
+            merkle_proof_strategy: MerkleProofStrategy::Compressed,
+            deduplication_strategy: DeduplicationStrategy::Enabled,
+            soundness_type: SoundnessType::ConjectureList,
+            first_round_folding_factor: None,
+        }
+    }
+}
+
+/// Builds the WHIR parameters.
+///
+/// The program builds the parameters once per verification and its `cu-instrumentation` feature
+/// reports their cost as the `parameters` phase.
 pub fn create_whir_params(
     num_variables: usize,
     security_level: usize,
@@ -57,35 +166,48 @@ pub fn create_whir_params(
     folding_factor: usize,
     starting_log_inv_rate: usize,
 ) -> WhirParams {
-    // No need for a real RNG for parameter creation.
-    let mut rng = ark_std::test_rng();
-
-    let reed_solomon = Arc::new(RSDefault);
-    let basefield_reed_solomon = Arc::new(RSDefault);
-
-    let (leaf_hash_params, two_to_one_params) =
-        default_config::<F, Blake3LeafHash<F>, Blake3Compress>(&mut rng);
+    create_whir_params_with(
+        num_variables,
+        security_level,
+        pow_bits,
+        folding_factor,
+        starting_log_inv_rate,
+        ProtocolOptions::default(),
+        ReedSolomonBackend::default(),
+    )
+}
 
+/// Builds the WHIR parameters of [`create_whir_params`] with the given protocol options and
+/// Reed-Solomon encoders.
+pub fn create_whir_params_with(
+    num_variables: usize,
+    security_level: usize,
+    pow_bits: usize,
+    folding_factor: usize,
+    starting_log_inv_rate: usize,
+    options: ProtocolOptions,
+    reed_solomon: ReedSolomonBackend,
+) -> WhirParams {
     let mv_params = MultivariateParameters::<F>::new(num_variables);
 
     let protocol_params = ProtocolParameters::<MerkleConfig, PowStrategy> {
         initial_statement: true,
         security_level,
         pow_bits,
-        folding_factor: FoldingFactor::ConstantFromSecondRound(folding_factor, folding_factor),
-        leaf_hash_params,
-        two_to_one_params,
-        soundness_type: SoundnessType::ConjectureList,
+        folding_factor: options.folding_schedule(folding_factor),
+        leaf_hash_params: merkle::LEAF_HASH_PARAMS,
+        two_to_one_params: merkle::COMPRESS_PARAMS,
+        soundness_type: options.soundness_type,
         _pow_parameters: Default::default(),
         starting_log_inv_rate,
-        batch_size: 1,
-        deduplication_strategy: DeduplicationStrategy::Enabled,
-        merkle_proof_strategy: MerkleProofStrategy::Compressed,
+        batch_size: options.batch_size,
+        deduplication_strategy: options.deduplication_strategy,
+        merkle_proof_strategy: options.merkle_proof_strategy,
     };
 
     WhirConfig::new(
-        reed_solomon,
-        basefield_reed_solomon,
+        reed_solomon.extension,
+        reed_solomon.base,
         mv_params,
         protocol_params,
     )
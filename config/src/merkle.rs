@@ -0,0 +1,471 @@
+//! Merkle tree configurations of the WHIR commitments.
+//!
+//! Merkle path verification dominates the verifier's compute units. The default configuration
+//! hashes with BLAKE3 computed in BPF. Syscall configurations hash through a Solana syscall
+//! on-chain and through the same hash function natively off-chain, so the prover and the program
+//! produce identical digests. A configuration is selected with a cargo feature of `whir-config`,
+//! forwarded by the prover and the program, and both must be built with the same one:
+//!
+//! - `merkle-blake3-syscall`: BLAKE3 via `sol_blake3`.
+//! - `merkle-keccak-syscall`: Keccak-256 via `sol_keccak256`, for commitments also checked on EVM
+//!   chains.
+//! - `merkle-poseidon-syscall`: Poseidon over BN254 via `sol_poseidon`, for commitments later
+//!   opened inside SNARK circuits.
+//! - `merkle-sha256-syscall`: SHA-256 via `sol_sha256`, for deployments restricted to
+//!   FIPS-approved primitives.
+//!
+//! The syscall configurations hash leaves of up to [`LEAF_BUFFER_BYTES`] without allocating, and
+//! [`verify_multi_path`] verifies the authentication paths of all queries of a round in one
+//! allocation-free pass, hashing the nodes shared by several paths once.
+
+use std::{borrow::Borrow, fmt::Debug, marker::PhantomData};
+
+use ark_crypto_primitives::{
+    crh::{CRHScheme, TwoToOneCRHScheme},
+    Error,
+};
+use ark_serialize::CanonicalSerialize;
+use ark_std::rand::RngCore;
+use whir_common::crypto::merkle_tree::{digest::GenericDigest, parameters::MerkleTreeParams};
+
+use crate::DIGEST_SIZE_BYTES;
+
+/// A digest of the syscall configurations.
+pub type Digest = GenericDigest<DIGEST_SIZE_BYTES>;
+
+/// A hash function computed by a Solana syscall on-chain and natively off-chain.
+pub trait SyscallHasher: Clone + Copy + Debug + Default + Send + Sync + 'static {
+    /// Hashes the concatenation of `inputs`.
+    fn hashv(inputs: &[&[u8]]) -> [u8; DIGEST_SIZE_BYTES];
+
+    /// Hashes two child digests into their parent.
+    fn compress(
+        left: &[u8; DIGEST_SIZE_BYTES],
+        right: &[u8; DIGEST_SIZE_BYTES],
+    ) -> [u8; DIGEST_SIZE_BYTES] {
+        Self::hashv(&[left, right])
+    }
+}
+
+/// Hashes a leaf, the concatenation of its compressed field elements.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SyscallLeafHash<T, H>(PhantomData<(T, H)>);
+
+impl<T: CanonicalSerialize + Send, H: SyscallHasher> CRHScheme for SyscallLeafHash<T, H> {
+    type Input = [T];
+    type Output = Digest;
+    type Parameters = ();
+
+    fn setup<R: RngCore>(_rng: &mut R) -> Result<Self::Parameters, Error> {
+        Ok(())
+    }
+
+    fn evaluate<I: Borrow<Self::Input>>(
+        _parameters: &Self::Parameters,
+        input: I,
+    ) -> Result<Self::Output, Error> {
+        let input = input.borrow();
+        let leaf_len: usize = input.iter().map(|element| element.compressed_size()).sum();
+        if leaf_len > LEAF_BUFFER_BYTES {
+            let mut leaf = Vec::with_capacity(leaf_len);
+            for element in input {
+                element.serialize_compressed(&mut leaf)?;
+            }
+            return Ok(GenericDigest(H::hashv(&[&leaf])));
+        }
+        let mut buffer = [0u8; LEAF_BUFFER_BYTES];
+        let mut writer = &mut buffer[..];
+        for element in input {
+            element.serialize_compressed(&mut writer)?;
+        }
+        Ok(GenericDigest(H::hashv(&[&buffer[..leaf_len]])))
+    }
+}
+
+/// Leaves up to this size are serialized on the stack instead of the heap before hashing.
+pub const LEAF_BUFFER_BYTES: usize = 512;
+
+/// Hashes two child digests into their parent.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SyscallCompress<H>(PhantomData<H>);
+
+impl<H: SyscallHasher> TwoToOneCRHScheme for SyscallCompress<H> {
+    type Input = Digest;
+    type Output = Digest;
+    type Parameters = ();
+
+    fn setup<R: RngCore>(_rng: &mut R) -> Result<Self::Parameters, Error> {
+        Ok(())
+    }
+
+    fn evaluate<I: Borrow<Self::Input>>(
+        _parameters: &Self::Parameters,
+        left_input: I,
+        right_input: I,
+    ) -> Result<Self::Output, Error> {
+        Ok(GenericDigest(H::compress(
+            &left_input.borrow().0,
+            &right_input.borrow().0,
+        )))
+    }
+
+    fn compress<I: Borrow<Self::Output>>(
+        parameters: &Self::Parameters,
+        left_input: I,
+        right_input: I,
+    ) -> Result<Self::Output, Error> {
+        Self::evaluate(parameters, left_input, right_input)
+    }
+}
+
+/// Verifies the authentication paths of `leaves`, (leaf index, leaf digest) pairs sorted by
+/// strictly increasing index, in a tree of `depth` levels against `root`.
+///
+/// The paths are verified together, level by level from the leaves up, overwriting `leaves` with
+/// the nodes of each level, so nothing is allocated and a node shared by several paths is hashed
+/// once. `auth_nodes` are the siblings that can't be computed from the nodes of the level below,
+/// level by level and in index order within a level.
+///
+/// Returns whether the paths lead to `root` with exactly `auth_nodes`.
+pub fn verify_multi_path<'a, H: SyscallHasher>(
+    leaves: &mut [(usize, [u8; DIGEST_SIZE_BYTES])],
+    depth: usize,
+    auth_nodes: impl IntoIterator<Item = &'a [u8; DIGEST_SIZE_BYTES]>,
+    root: &[u8; DIGEST_SIZE_BYTES],
+) -> bool {
+    if leaves.is_empty()
+        || leaves.windows(2).any(|pair| pair[0].0 >= pair[1].0)
+        || leaves
+            .last()
+            .is_some_and(|(index, _)| index.checked_shr(depth as u32).unwrap_or(0) != 0)
+    {
+        return false;
+    }
+    let mut auth_nodes = auth_nodes.into_iter();
+    let mut len = leaves.len();
+    for _ in 0..depth {
+        let (mut read, mut write) = (0, 0);
+        while read < len {
+            let (index, digest) = leaves[read];
+            let parent =
+                if index.is_multiple_of(2) && read + 1 < len && leaves[read + 1].0 == index + 1 {
+                    read += 2;
+                    H::compress(&digest, &leaves[read - 1].1)
+                } else {
+                    let Some(sibling) = auth_nodes.next() else {
+                        return false;
+                    };
+                    read += 1;
+                    if index.is_multiple_of(2) {
+                        H::compress(&digest, sibling)
+                    } else {
+                        H::compress(sibling, &digest)
+                    }
+                };
+            leaves[write] = (index / 2, parent);
+            write += 1;
+        }
+        len = write;
+    }
+    auth_nodes.next().is_none() && leaves[0].1 == *root
+}
+
+/// Merkle tree configuration hashing leaves and nodes with `H`.
+pub type SyscallMerkleTreeParams<T, H> =
+    MerkleTreeParams<T, SyscallLeafHash<T, H>, SyscallCompress<H>, Digest>;
+
+/// BLAKE3, via `sol_blake3` on-chain.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Blake3Syscall;
+
+impl SyscallHasher for Blake3Syscall {
+    fn hashv(inputs: &[&[u8]]) -> [u8; DIGEST_SIZE_BYTES] {
+        solana_blake3_hasher::hashv(inputs).to_bytes()
+    }
+}
+
+/// Keccak-256, via `sol_keccak256` on-chain. The digests match the EVM's `keccak256`.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Keccak256Syscall;
+
+impl SyscallHasher for Keccak256Syscall {
+    fn hashv(inputs: &[&[u8]]) -> [u8; DIGEST_SIZE_BYTES] {
+        solana_keccak_hasher::hashv(inputs).to_bytes()
+    }
+}
+
+/// SHA-256, via `sol_sha256` on-chain.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Sha256Syscall;
+
+impl SyscallHasher for Sha256Syscall {
+    fn hashv(inputs: &[&[u8]]) -> [u8; DIGEST_SIZE_BYTES] {
+        solana_sha256_hasher::hashv(inputs).to_bytes()
+    }
+}
+
+/// Poseidon over the BN254 scalar field with the x^5 S-box, via `sol_poseidon` on-chain. The
+/// digests match the circomlib Poseidon used by SNARK circuits.
+///
+/// Leaves are split into 31-byte big-endian chunks, each below the field modulus, and absorbed in
+/// a chain of at most [`POSEIDON_MAX_INPUTS`] inputs per hash, each link taking the previous
+/// digest as its first input. Two child digests, field elements themselves, are hashed directly.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct PoseidonSyscall;
+
+/// Maximum number of inputs of a `sol_poseidon` hash.
+pub const POSEIDON_MAX_INPUTS: usize = 12;
+
+/// Bytes of a leaf chunk absorbed as one field element.
+const POSEIDON_CHUNK_BYTES: usize = 31;
+
+impl PoseidonSyscall {
+    fn poseidon(inputs: &[&[u8]]) -> [u8; DIGEST_SIZE_BYTES] {
+        solana_poseidon::hashv(
+            solana_poseidon::Parameters::Bn254X5,
+            solana_poseidon::Endianness::BigEndian,
+            inputs,
+        )
+        .expect("chunks and digests are canonical field elements")
+        .to_bytes()
+    }
+}
+
+impl SyscallHasher for PoseidonSyscall {
+    fn hashv(inputs: &[&[u8]]) -> [u8; DIGEST_SIZE_BYTES] {
+        let bytes = inputs.concat();
+        let mut chunks = bytes.chunks(POSEIDON_CHUNK_BYTES);
+        let first: Vec<&[u8]> = chunks.by_ref().take(POSEIDON_MAX_INPUTS).collect();
+        let mut digest = Self::poseidon(&first);
+        loop {
+            let link: Vec<&[u8]> = std::iter::once(&digest[..])
+                .chain(chunks.by_ref().take(POSEIDON_MAX_INPUTS - 1))
+                .collect();
+            if link.len() == 1 {
+                return digest;
+            }
+            digest = Self::poseidon(&link);
+        }
+    }
+
+    fn compress(
+        left: &[u8; DIGEST_SIZE_BYTES],
+        right: &[u8; DIGEST_SIZE_BYTES],
+    ) -> [u8; DIGEST_SIZE_BYTES] {
+        Self::poseidon(&[left, right])
+    }
+}
+
+const _: () = assert!(
+    cfg!(feature = "merkle-blake3-syscall") as u8
+        + cfg!(feature = "merkle-keccak-syscall") as u8
+        + cfg!(feature = "merkle-poseidon-syscall") as u8
+        + cfg!(feature = "merkle-sha256-syscall") as u8
+        <= 1,
+    "At most one `merkle-*` feature of `whir-config` can be enabled"
+);
+
+#[cfg(not(any(
+    feature = "merkle-blake3-syscall",
+    feature = "merkle-keccak-syscall",
+    feature = "merkle-poseidon-syscall",
+    feature = "merkle-sha256-syscall"
+)))]
+mod selected {
+    use whir_common::crypto::merkle_tree::blake3::{
+        Blake3Compress, Blake3LeafHash, Blake3MerkleTreeParams,
+    };
+
+    use crate::F;
+
+    pub type MerkleConfig = Blake3MerkleTreeParams<F>;
+    pub type LeafHash = Blake3LeafHash<F>;
+    pub type CompressHash = Blake3Compress;
+    pub const HASH_BLOCK_CU: u64 = 1_000;
+}
+
+#[cfg(feature = "merkle-blake3-syscall")]
+mod selected {
+    use super::*;
+    use crate::F;
+
+    pub type MerkleConfig = SyscallMerkleTreeParams<F, Blake3Syscall>;
+    pub type LeafHash = SyscallLeafHash<F, Blake3Syscall>;
+    pub type CompressHash = SyscallCompress<Blake3Syscall>;
+    pub const HASH_BLOCK_CU: u64 = 120;
+}
+
+#[cfg(feature = "merkle-keccak-syscall")]
+mod selected {
+    use super::*;
+    use crate::F;
+
+    pub type MerkleConfig = SyscallMerkleTreeParams<F, Keccak256Syscall>;
+    pub type LeafHash = SyscallLeafHash<F, Keccak256Syscall>;
+    pub type CompressHash = SyscallCompress<Keccak256Syscall>;
+    pub const HASH_BLOCK_CU: u64 = 120;
+}
+
+#[cfg(feature = "merkle-poseidon-syscall")]
+mod selected {
+    use super::*;
+    use crate::F;
+
+    pub type MerkleConfig = SyscallMerkleTreeParams<F, PoseidonSyscall>;
+    pub type LeafHash = SyscallLeafHash<F, PoseidonSyscall>;
+    pub type CompressHash = SyscallCompress<PoseidonSyscall>;
+    // A 64-byte block is three field elements; `sol_poseidon` costs 61 * n^2 + 542 for n inputs.
+    pub const HASH_BLOCK_CU: u64 = 1_100;
+}
+
+#[cfg(feature = "merkle-sha256-syscall")]
+mod selected {
+    use super::*;
+    use crate::F;
+
+    pub type MerkleConfig = SyscallMerkleTreeParams<F, Sha256Syscall>;
+    pub type LeafHash = SyscallLeafHash<F, Sha256Syscall>;
+    pub type CompressHash = SyscallCompress<Sha256Syscall>;
+    pub const HASH_BLOCK_CU: u64 = 120;
+}
+
+/// Merkle tree configuration selected by the `merkle-*` features.
+pub type MerkleConfig = selected::MerkleConfig;
+
+/// Leaf hash of [`MerkleConfig`].
+pub type LeafHash = selected::LeafHash;
+
+/// Two-to-one hash of [`MerkleConfig`].
+pub type CompressHash = selected::CompressHash;
+
+/// Rough compute unit cost of hashing a 64-byte block of a Merkle leaf or path on-chain.
+pub const HASH_BLOCK_CU: u64 = selected::HASH_BLOCK_CU;
+
+/// Parameters of [`LeafHash`]. None of the configurations take any, so they are fixed at compile
+/// time instead of being set up from an RNG, and the prover and the program agree on them by
+/// construction.
+pub const LEAF_HASH_PARAMS: <LeafHash as CRHScheme>::Parameters = ();
+
+/// Parameters of [`CompressHash`], see [`LEAF_HASH_PARAMS`].
+pub const COMPRESS_PARAMS: <CompressHash as TwoToOneCRHScheme>::Parameters = ();
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::F;
+
+    #[test]
+    fn blake3_syscall_matches_blake3() {
+        let left = GenericDigest([1; DIGEST_SIZE_BYTES]);
+        let right = GenericDigest([2; DIGEST_SIZE_BYTES]);
+        let parent = SyscallCompress::<Blake3Syscall>::compress(&(), left, right).unwrap();
+        assert_eq!(
+            parent.0,
+            *blake3::hash(&[[1; DIGEST_SIZE_BYTES], [2; DIGEST_SIZE_BYTES]].concat()).as_bytes()
+        );
+    }
+
+    #[test]
+    fn keccak_syscall_matches_evm_keccak256() {
+        // keccak256("abc")
+        let expected = [
+            0x4e, 0x03, 0x65, 0x7a, 0xea, 0x45, 0xa9, 0x4f, 0xc7, 0xd4, 0x7b, 0xa8, 0x26, 0xc8,
+            0xd6, 0x67, 0xc0, 0xd1, 0xe6, 0xe3, 0x3a, 0x64, 0xa0, 0x36, 0xec, 0x44, 0xf5, 0x8f,
+            0xa1, 0x2d, 0x6c, 0x45,
+        ];
+        assert_eq!(Keccak256Syscall::hashv(&[b"a", b"bc"]), expected);
+    }
+
+    #[test]
+    fn sha256_syscall_matches_sha256() {
+        // sha256("abc")
+        let expected = [
+            0xba, 0x78, 0x16, 0xbf, 0x8f, 0x01, 0xcf, 0xea, 0x41, 0x41, 0x40, 0xde, 0x5d, 0xae,
+            0x22, 0x23, 0xb0, 0x03, 0x61, 0xa3, 0x96, 0x17, 0x7a, 0x9c, 0xb4, 0x10, 0xff, 0x61,
+            0xf2, 0x00, 0x15, 0xad,
+        ];
+        assert_eq!(Sha256Syscall::hashv(&[b"ab", b"c"]), expected);
+    }
+
+    #[test]
+    fn leaf_hash_matches_across_buffers() {
+        let leaf_hash = |elements: &[F]| {
+            let mut bytes = Vec::new();
+            for element in elements {
+                element.serialize_compressed(&mut bytes).unwrap();
+            }
+            (
+                SyscallLeafHash::<F, Sha256Syscall>::evaluate(&(), elements)
+                    .unwrap()
+                    .0,
+                Sha256Syscall::hashv(&[&bytes]),
+            )
+        };
+        let small: Vec<F> = (0..4u64).map(F::from).collect();
+        let large: Vec<F> = (0..64u64).map(F::from).collect();
+        let (digest, expected) = leaf_hash(&small);
+        assert_eq!(digest, expected);
+        let (digest, expected) = leaf_hash(&large);
+        assert_eq!(digest, expected);
+    }
+
+    #[test]
+    fn multi_path_verifies_shared_paths() {
+        let mut levels = vec![(0..8u8)
+            .map(|leaf| Sha256Syscall::hashv(&[&[leaf]]))
+            .collect::<Vec<_>>()];
+        while levels.last().unwrap().len() > 1 {
+            let level = levels.last().unwrap();
+            let parents = level
+                .chunks(2)
+                .map(|pair| Sha256Syscall::compress(&pair[0], &pair[1]))
+                .collect();
+            levels.push(parents);
+        }
+        let root = levels[3][0];
+        let leaves = || vec![(1, levels[0][1]), (2, levels[0][2]), (5, levels[0][5])];
+        let auth_nodes = [levels[0][0], levels[0][3], levels[0][4], levels[1][3]];
+
+        assert!(verify_multi_path::<Sha256Syscall>(
+            &mut leaves(),
+            3,
+            &auth_nodes,
+            &root
+        ));
+        let mut tampered = leaves();
+        tampered[1].1[0] ^= 1;
+        assert!(!verify_multi_path::<Sha256Syscall>(
+            &mut tampered,
+            3,
+            &auth_nodes,
+            &root
+        ));
+        assert!(!verify_multi_path::<Sha256Syscall>(
+            &mut leaves(),
+            3,
+            &auth_nodes[..3],
+            &root
+        ));
+        assert!(!verify_multi_path::<Sha256Syscall>(
+            &mut leaves()[..0],
+            3,
+            &auth_nodes,
+            &root
+        ));
+    }
+
+    #[test]
+    fn poseidon_syscall_chains_long_leaves() {
+        let leaf = [7u8; POSEIDON_CHUNK_BYTES * (POSEIDON_MAX_INPUTS + 1)];
+        let chunks: Vec<&[u8]> = leaf.chunks(POSEIDON_CHUNK_BYTES).collect();
+        let first = PoseidonSyscall::poseidon(&chunks[..POSEIDON_MAX_INPUTS]);
+        assert_eq!(
+            PoseidonSyscall::hashv(&[&leaf]),
+            PoseidonSyscall::poseidon(&[&first, chunks[POSEIDON_MAX_INPUTS]])
+        );
+        assert_eq!(
+            PoseidonSyscall::hashv(&[&leaf[..POSEIDON_CHUNK_BYTES]]),
+            PoseidonSyscall::poseidon(&[chunks[0]])
+        );
+    }
+}
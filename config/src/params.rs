@@ -0,0 +1,298 @@
+//! Canonical encoding and digest of WHIR parameters.
+//!
+//! The same logical parameters must hash identically in the prover, the CLI and the program, so
+//! the encoding is of the derived [`WhirParams`] rather than of any crate's argument struct: the
+//! build's field, Merkle hash, transcript sponge and proof-of-work choices, the protocol knobs,
+//! and the per-round query, out-of-domain and proof-of-work counts. Proof-of-work bits are
+//! rounded up, as their floating-point values may differ in the last bits across targets.
+//!
+//! All integers are little-endian `u32`s, enums and build choices single bytes. The encoding
+//! starts with [`PARAMS_ENCODING_VERSION`], bumped on any change to it.
+//!
+//! The parameters clients pass, on the other hand, travel as [`VersionedParams`]: a Borsh enum
+//! whose variant index is the wire format version. The program's `VersionedParamsArgs` mirrors it
+//! byte for byte in instruction data and proof accounts, and the prover writes it next to its
+//! proofs. New knobs go into a new version instead of changing [`ParamsV1`], so decoders reject
+//! parameters of versions they don't know rather than misreading them.
+
+use whir_common::parameters::{DeduplicationStrategy, MerkleProofStrategy, SoundnessType};
+
+use crate::{
+    create_whir_params_with, field,
+    validation::{validate_params_with, ParamsError},
+    ProtocolOptions, ReedSolomonBackend, WhirParams,
+};
+
+/// Version of the encoding of [`encode_params`].
+pub const PARAMS_ENCODING_VERSION: u8 = 1;
+
+/// Domain separation prefix of [`params_digest`].
+const PARAMS_DIGEST_DOMAIN: &[u8] = b"whir-solana params";
+
+/// Identifies the Merkle configuration selected by the `merkle-*` features.
+const MERKLE_ID: u8 = if cfg!(feature = "merkle-blake3-syscall") {
+    1
+} else if cfg!(feature = "merkle-keccak-syscall") {
+    2
+} else if cfg!(feature = "merkle-poseidon-syscall") {
+    3
+} else if cfg!(feature = "merkle-sha256-syscall") {
+    4
+} else {
+    0
+};
+
+/// Identifies the transcript sponge selected by the `transcript-*` features.
+const TRANSCRIPT_ID: u8 = cfg!(feature = "transcript-sha256") as u8;
+
+/// Identifies the proof-of-work strategy selected by the `pow-*` features.
+const POW_ID: u8 = cfg!(feature = "pow-keccak") as u8;
+
+/// Returns the canonical encoding of the parameters, see the module docs.
+pub fn encode_params(params: &WhirParams) -> Vec<u8> {
+    let mut encoded = vec![
+        PARAMS_ENCODING_VERSION,
+        field::EXTENSION_DEGREE as u8,
+        field::BASE_FIELD_SIZE_BYTES as u8,
+        MERKLE_ID,
+        TRANSCRIPT_ID,
+        POW_ID,
+        match params.soundness_type {
+            SoundnessType::UniqueDecoding => 0,
+            SoundnessType::ProvableList => 1,
+            SoundnessType::ConjectureList => 2,
+        },
+        match params.merkle_proof_strategy {
+            MerkleProofStrategy::Compressed => 0,
+            MerkleProofStrategy::Uncompressed => 1,
+        },
+        match params.deduplication_strategy {
+            DeduplicationStrategy::Enabled => 0,
+            DeduplicationStrategy::Disabled => 1,
+        },
+    ];
+    let mut push = |value: usize| encoded.extend_from_slice(&(value as u32).to_le_bytes());
+    let pow = |bits: f64| bits.ceil() as usize;
+
+    push(params.mv_parameters.num_variables);
+    push(params.security_level);
+    push(params.max_pow_bits);
+    push(params.starting_log_inv_rate);
+    push(params.batch_size);
+    push(params.folding_factor.at_round(0));
+    push(params.folding_factor.at_round(1));
+    push(params.committment_ood_samples);
+    push(pow(params.starting_folding_pow_bits));
+    push(params.round_parameters.len());
+    for round in &params.round_parameters {
+        push(round.num_queries);
+        push(round.ood_samples);
+        push(pow(round.pow_bits));
+        push(pow(round.folding_pow_bits));
+    }
+    push(params.final_queries);
+    push(params.final_sumcheck_rounds);
+    push(pow(params.final_pow_bits));
+    push(pow(params.final_folding_pow_bits));
+    encoded
+}
+
+/// Returns the SHA-256 digest of the parameters' canonical encoding, identifying them across
+/// crates, e.g. in the program's verification quote.
+pub fn params_digest(params: &WhirParams) -> [u8; 32] {
+    solana_sha256_hasher::hashv(&[PARAMS_DIGEST_DOMAIN, &encode_params(params)]).to_bytes()
+}
+
+/// Soundness type of [`ParamsV1`], encoded as its variant index.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ParamsSoundness {
+    #[default]
+    ConjectureList = 0,
+    ProvableList = 1,
+    UniqueDecoding = 2,
+}
+
+impl From<ParamsSoundness> for SoundnessType {
+    fn from(soundness: ParamsSoundness) -> Self {
+        match soundness {
+            ParamsSoundness::ConjectureList => SoundnessType::ConjectureList,
+            ParamsSoundness::ProvableList => SoundnessType::ProvableList,
+            ParamsSoundness::UniqueDecoding => SoundnessType::UniqueDecoding,
+        }
+    }
+}
+
+/// Version 1 of the wire format of WHIR parameters: the arguments of `verify_v2` and the
+/// soundness type of `verify_v3`. Borsh-encoded as five little-endian `u32`s followed by the
+/// soundness type's byte.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ParamsV1 {
+    pub num_variables: u32,
+    pub security_level: u32,
+    pub pow_bits: u32,
+    pub folding_factor: u32,
+    pub starting_log_inv_rate: u32,
+    pub soundness_type: ParamsSoundness,
+}
+
+/// Encoded length of [`ParamsV1`].
+const PARAMS_V1_LEN: usize = 5 * 4 + 1;
+
+/// WHIR parameters in a versioned wire format, see the module docs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VersionedParams {
+    V1(ParamsV1),
+}
+
+/// Error returned when decoding malformed [`VersionedParams`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParamsDecodeError {
+    /// The version is newer than this build knows.
+    UnknownVersion(u8),
+    /// The soundness type is not known.
+    UnknownSoundnessType(u8),
+    /// The encoding is shorter or longer than its version's.
+    InvalidLength,
+}
+
+impl VersionedParams {
+    /// Returns the Borsh encoding: the version's variant index followed by its fields.
+    pub fn encode(&self) -> Vec<u8> {
+        let VersionedParams::V1(params) = self;
+        let mut encoded = Vec::with_capacity(1 + PARAMS_V1_LEN);
+        encoded.push(0);
+        for value in [
+            params.num_variables,
+            params.security_level,
+            params.pow_bits,
+            params.folding_factor,
+            params.starting_log_inv_rate,
+        ] {
+            encoded.extend_from_slice(&value.to_le_bytes());
+        }
+        encoded.push(params.soundness_type as u8);
+        encoded
+    }
+
+    /// Decodes parameters encoded by [`Self::encode`].
+    pub fn decode(bytes: &[u8]) -> Result<Self, ParamsDecodeError> {
+        let (&version, fields) = bytes
+            .split_first()
+            .ok_or(ParamsDecodeError::InvalidLength)?;
+        if version != 0 {
+            return Err(ParamsDecodeError::UnknownVersion(version));
+        }
+        if fields.len() != PARAMS_V1_LEN {
+            return Err(ParamsDecodeError::InvalidLength);
+        }
+        let field = |i: usize| u32::from_le_bytes(fields[4 * i..4 * i + 4].try_into().unwrap());
+        let soundness_type = match fields[PARAMS_V1_LEN - 1] {
+            0 => ParamsSoundness::ConjectureList,
+            1 => ParamsSoundness::ProvableList,
+            2 => ParamsSoundness::UniqueDecoding,
+            other => return Err(ParamsDecodeError::UnknownSoundnessType(other)),
+        };
+        Ok(VersionedParams::V1(ParamsV1 {
+            num_variables: field(0),
+            security_level: field(1),
+            pow_bits: field(2),
+            folding_factor: field(3),
+            starting_log_inv_rate: field(4),
+            soundness_type,
+        }))
+    }
+
+    /// Returns the protocol options of the parameters.
+    pub fn protocol_options(&self) -> ProtocolOptions {
+        let VersionedParams::V1(params) = self;
+        ProtocolOptions {
+            soundness_type: params.soundness_type.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Builds the WHIR parameters without validating them.
+    pub fn to_whir_params(&self) -> WhirParams {
+        let VersionedParams::V1(params) = self;
+        create_whir_params_with(
+            params.num_variables as usize,
+            params.security_level as usize,
+            params.pow_bits as usize,
+            params.folding_factor as usize,
+            params.starting_log_inv_rate as usize,
+            self.protocol_options(),
+            ReedSolomonBackend::default(),
+        )
+    }
+
+    /// Validates and builds the WHIR parameters, see [`validate_params_with`].
+    pub fn validate(&self) -> Result<WhirParams, ParamsError> {
+        let VersionedParams::V1(params) = self;
+        validate_params_with(
+            params.num_variables as usize,
+            params.security_level as usize,
+            params.pow_bits as usize,
+            params.folding_factor as usize,
+            params.starting_log_inv_rate as usize,
+            self.protocol_options(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_whir_params;
+
+    #[test]
+    fn digest_identifies_parameters() {
+        let params = create_whir_params(6, 100, 16, 4, 1);
+        assert_eq!(encode_params(&params)[0], PARAMS_ENCODING_VERSION);
+        assert_eq!(
+            params_digest(&params),
+            params_digest(&create_whir_params(6, 100, 16, 4, 1))
+        );
+        assert_ne!(
+            params_digest(&params),
+            params_digest(&create_whir_params(6, 100, 16, 2, 1))
+        );
+        assert_ne!(
+            params_digest(&params),
+            params_digest(&create_whir_params(6, 90, 16, 4, 1))
+        );
+    }
+
+    #[test]
+    fn versioned_params_wire_format() {
+        let params = VersionedParams::V1(ParamsV1 {
+            num_variables: 6,
+            security_level: 100,
+            pow_bits: 16,
+            folding_factor: 4,
+            starting_log_inv_rate: 1,
+            soundness_type: ParamsSoundness::ProvableList,
+        });
+        let encoded = params.encode();
+        assert_eq!(
+            encoded,
+            [0, 6, 0, 0, 0, 100, 0, 0, 0, 16, 0, 0, 0, 4, 0, 0, 0, 1, 0, 0, 0, 1]
+        );
+        assert_eq!(VersionedParams::decode(&encoded), Ok(params));
+
+        let mut newer = encoded.clone();
+        newer[0] = 1;
+        assert_eq!(
+            VersionedParams::decode(&newer),
+            Err(ParamsDecodeError::UnknownVersion(1))
+        );
+        assert_eq!(
+            VersionedParams::decode(&encoded[..encoded.len() - 1]),
+            Err(ParamsDecodeError::InvalidLength)
+        );
+        assert_eq!(
+            params_digest(&params.to_whir_params()),
+            params_digest(&params.validate().unwrap())
+        );
+    }
+}
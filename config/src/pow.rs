@@ -0,0 +1,99 @@
+//! Derivation of the proof-of-work bits of WHIR parameters.
+//!
+//! `pow_bits` caps the grinding of every round: query counts are derived for the security level
+//! minus `pow_bits`, and each round grinds for what its queries leave to reach the security level.
+//! The fork's `default_max_pow` picks a cap from the number of variables and the rate alone, so
+//! that grinding doesn't dominate proving time. [`recommended_pow_bits`] starts from that cap,
+//! raises it until the parameters reach the security level under the given soundness type, and
+//! returns the grinding they actually need.
+
+use whir_common::parameters::default_max_pow;
+
+use crate::{create_whir_params_with, ProtocolOptions, ReedSolomonBackend, WhirParams};
+
+/// Returns the proof-of-work bits of the most grinding round of the parameters, rounded up.
+pub fn required_pow_bits(params: &WhirParams) -> usize {
+    let rounds = params
+        .round_parameters
+        .iter()
+        .flat_map(|round| [round.pow_bits, round.folding_pow_bits]);
+    [
+        params.starting_folding_pow_bits,
+        params.final_pow_bits,
+        params.final_folding_pow_bits,
+    ]
+    .into_iter()
+    .chain(rounds)
+    .fold(0.0, f64::max)
+    .ceil() as usize
+}
+
+/// Returns the recommended `pow_bits` of the parameters, see the module docs.
+///
+/// Parameters built with the returned value reach the security level, unless no `pow_bits` up to
+/// the security level does.
+pub fn recommended_pow_bits(
+    num_variables: usize,
+    security_level: usize,
+    folding_factor: usize,
+    starting_log_inv_rate: usize,
+    options: ProtocolOptions,
+) -> usize {
+    let build = |pow_bits| {
+        create_whir_params_with(
+            num_variables,
+            security_level,
+            pow_bits,
+            folding_factor,
+            starting_log_inv_rate,
+            options,
+            ReedSolomonBackend::default(),
+        )
+    };
+    let mut pow_bits = default_max_pow(num_variables, starting_log_inv_rate).min(security_level);
+    let mut params = build(pow_bits);
+    while !params.check_pow_bits() && pow_bits < security_level {
+        pow_bits += 1;
+        params = build(pow_bits);
+    }
+    required_pow_bits(&params).min(pow_bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use whir_common::parameters::SoundnessType;
+
+    use super::*;
+    use crate::validation::validate_params_with;
+
+    #[test]
+    fn recommended_pow_bits_reach_security_level() {
+        for soundness_type in [
+            SoundnessType::ConjectureList,
+            SoundnessType::ProvableList,
+            SoundnessType::UniqueDecoding,
+        ] {
+            let options = ProtocolOptions {
+                soundness_type,
+                ..Default::default()
+            };
+            let pow_bits = recommended_pow_bits(
+                crate::NUM_VARIABLES,
+                crate::SECURITY_LEVEL_BITS,
+                crate::FOLDING_FACTOR,
+                crate::STARTING_LOG_INV_RATE,
+                options,
+            );
+            let params = validate_params_with(
+                crate::NUM_VARIABLES,
+                crate::SECURITY_LEVEL_BITS,
+                pow_bits,
+                crate::FOLDING_FACTOR,
+                crate::STARTING_LOG_INV_RATE,
+                options,
+            )
+            .unwrap();
+            assert!(required_pow_bits(&params) <= pow_bits);
+        }
+    }
+}
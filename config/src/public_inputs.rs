@@ -0,0 +1,116 @@
+//! Typed public inputs bound into a proof's evaluation constraint.
+//!
+//! Instead of choosing raw field bytes, applications list their public inputs (block heights,
+//! state roots, account keys) and open the committed polynomial at a point derived from them.
+//! The inputs are encoded canonically, each prefixed with its type tag, and hashed with BLAKE3
+//! in key derivation mode. The hash output is expanded into one extension field element per
+//! variable, so the verified statement commits to the exact typed inputs.
+
+use ark_serialize::CanonicalSerialize;
+use whir_common::poly_utils::multilinear::MultilinearPoint;
+
+use crate::{
+    field::{field_from_uniform_bytes, FIELD_SIZE_BYTES},
+    field_size_bytes, F,
+};
+
+/// BLAKE3 key derivation context of the evaluation point.
+const PUBLIC_INPUTS_CONTEXT: &str = "whir-solana public inputs to evaluation point";
+
+/// A typed public input.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PublicInput {
+    U64(u64),
+    /// A 32-byte hash, e.g. a state root.
+    Hash([u8; 32]),
+    /// A 32-byte public key, e.g. an account or program id.
+    Pubkey([u8; 32]),
+}
+
+impl PublicInput {
+    fn tag(&self) -> u8 {
+        match self {
+            PublicInput::U64(_) => 0,
+            PublicInput::Hash(_) => 1,
+            PublicInput::Pubkey(_) => 2,
+        }
+    }
+}
+
+/// Encodes public inputs canonically: their number as a little-endian `u32`, followed by each
+/// input's type tag and its little-endian or raw bytes.
+pub fn encode_public_inputs(inputs: &[PublicInput]) -> Vec<u8> {
+    let mut encoded = Vec::with_capacity(4 + inputs.len() * 33);
+    encoded.extend_from_slice(&(inputs.len() as u32).to_le_bytes());
+    for input in inputs {
+        encoded.push(input.tag());
+        match input {
+            PublicInput::U64(value) => encoded.extend_from_slice(&value.to_le_bytes()),
+            PublicInput::Hash(bytes) | PublicInput::Pubkey(bytes) => {
+                encoded.extend_from_slice(bytes)
+            }
+        }
+    }
+    encoded
+}
+
+/// Derives the evaluation point of a polynomial of `num_variables` variables from public inputs.
+pub fn public_inputs_eval_point(
+    inputs: &[PublicInput],
+    num_variables: usize,
+) -> MultilinearPoint<F> {
+    let mut hasher = blake3::Hasher::new_derive_key(PUBLIC_INPUTS_CONTEXT);
+    hasher.update(&encode_public_inputs(inputs));
+    let mut output = hasher.finalize_xof();
+    let mut coordinate = || {
+        let mut bytes = [0u8; FIELD_SIZE_BYTES];
+        output.fill(&mut bytes);
+        field_from_uniform_bytes(&bytes)
+    };
+    MultilinearPoint((0..num_variables).map(|_| coordinate()).collect())
+}
+
+/// Returns the serialized evaluation point derived from public inputs, as stored in an
+/// evaluation constraint of the on-chain verifier.
+pub fn public_inputs_eval_point_bytes(inputs: &[PublicInput], num_variables: usize) -> Vec<u8> {
+    let mut bytes = Vec::with_capacity(num_variables * field_size_bytes());
+    for coordinate in public_inputs_eval_point(inputs, num_variables).0 {
+        coordinate
+            .serialize_compressed(&mut bytes)
+            .expect("serializing into a vector doesn't fail");
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoding_distinguishes_types() {
+        let key = [7u8; 32];
+        assert_ne!(
+            encode_public_inputs(&[PublicInput::Hash(key)]),
+            encode_public_inputs(&[PublicInput::Pubkey(key)])
+        );
+        assert_eq!(
+            encode_public_inputs(&[PublicInput::U64(1)]),
+            [1, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]
+        );
+    }
+
+    #[test]
+    fn eval_point_depends_on_inputs() {
+        let inputs = [PublicInput::U64(42), PublicInput::Hash([1u8; 32])];
+        let bytes = public_inputs_eval_point_bytes(&inputs, 6);
+        assert_eq!(bytes.len(), 6 * field_size_bytes());
+        assert_eq!(bytes, public_inputs_eval_point_bytes(&inputs, 6));
+        assert_ne!(
+            bytes,
+            public_inputs_eval_point_bytes(
+                &[PublicInput::U64(43), PublicInput::Hash([1u8; 32])],
+                6
+            )
+        );
+    }
+}
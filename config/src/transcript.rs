@@ -0,0 +1,232 @@
+//! Fiat-Shamir transcript construction shared by the prover and the verifiers.
+//!
+//! The prover and the on-chain verifier only agree on a proof if they build the exact same
+//! transcript: same protocol label, same absorb order and same labels inside `whir_common`
+//! and `spongefish`. All transcripts are built here, and the tests below pin the behavior
+//! of the pinned dependency revisions against the proof in `sample-proof/`.
+//!
+//! The duplex sponge of the transcripts is selected independently of the Merkle configuration
+//! with a cargo feature of `whir-config`, forwarded by the prover and the program:
+//!
+//! - default: spongefish's Keccak-f\[1600\] sponge.
+//! - `transcript-sha256`: a sponge over SHA-256, for verifiers on chains and in circuits with
+//!   SHA-256 but no Keccak permutation.
+
+use spongefish::{
+    ByteDomainSeparator, DomainSeparator, DomainSeparatorMismatch, ProverState, UnitTranscript,
+    VerifierState,
+};
+use whir_common::whir::domainsep::WhirDomainSeparator;
+
+use crate::{WhirParams, DOMAIN_SEPARATOR};
+
+#[cfg(not(feature = "transcript-sha256"))]
+mod selected {
+    pub type TranscriptHash = spongefish::DefaultHash;
+}
+
+#[cfg(feature = "transcript-sha256")]
+mod selected {
+    pub type TranscriptHash = spongefish::duplex_sponge::legacy::DigestBridge<sha2::Sha256>;
+}
+
+/// Duplex sponge of the transcripts, selected by the `transcript-*` features.
+pub type TranscriptHash = selected::TranscriptHash;
+
+/// Domain separator of the transcripts.
+pub type TranscriptDomainSeparator = DomainSeparator<TranscriptHash>;
+
+/// Prover state of the transcripts.
+pub type TranscriptProverState = ProverState<TranscriptHash>;
+
+/// Verifier state of the transcripts, reading a proof's bytes.
+pub type TranscriptVerifierState<'a> = VerifierState<'a, TranscriptHash>;
+
+/// Maximum length of a domain label in bytes.
+pub const MAX_DOMAIN_LABEL_LEN: usize = 64;
+
+/// Label of the application context absorbed before the commitment.
+const CONTEXT_LABEL: &str = "app-context";
+
+/// Builds the domain separator (transcript pattern) of a WHIR proof with the given parameters.
+///
+/// `domain_label` gives independent Fiat-Shamir domains to protocols sharing a deployment, an
+/// empty label selects [`DOMAIN_SEPARATOR`]. A non-empty `context` is absorbed before the
+/// commitment, binding the proof to it. An empty context leaves the transcript unchanged.
+pub fn domain_separator(
+    params: &WhirParams,
+    domain_label: &str,
+    context: &[u8],
+) -> TranscriptDomainSeparator {
+    domain_separator_for_context_len(params, domain_label, context.len())
+}
+
+/// Builds the domain separator of [`domain_separator`], which only depends on the context's
+/// length.
+fn domain_separator_for_context_len(
+    params: &WhirParams,
+    domain_label: &str,
+    context_len: usize,
+) -> TranscriptDomainSeparator {
+    let mut domainsep = TranscriptDomainSeparator::new(resolve_domain_label(domain_label));
+    if context_len > 0 {
+        domainsep = domainsep.add_bytes(context_len, CONTEXT_LABEL);
+    }
+    domainsep.commit_statement(params).add_whir_proof(params)
+}
+
+/// Returns the label of a transcript with `domain_label`, [`DOMAIN_SEPARATOR`] if it's empty.
+pub fn resolve_domain_label(domain_label: &str) -> &str {
+    if domain_label.is_empty() {
+        DOMAIN_SEPARATOR
+    } else {
+        domain_label
+    }
+}
+
+/// Returns the transcript pattern of [`domain_separator`] for contexts of `context_len` bytes,
+/// which can be stored and turned back into the domain separator with [`from_pattern`] instead
+/// of rebuilding it.
+pub fn transcript_pattern(params: &WhirParams, domain_label: &str, context_len: usize) -> Vec<u8> {
+    domain_separator_for_context_len(params, domain_label, context_len)
+        .as_bytes()
+        .to_vec()
+}
+
+/// Returns the domain separator of a pattern from [`transcript_pattern`], or `None` if it isn't
+/// valid UTF-8.
+pub fn from_pattern(pattern: &[u8]) -> Option<TranscriptDomainSeparator> {
+    let pattern = std::str::from_utf8(pattern).ok()?;
+    Some(TranscriptDomainSeparator::from_string(pattern.to_owned()))
+}
+
+/// Absorbs the application context into a prover or verifier state created from
+/// [`domain_separator`] with the same context.
+pub fn bind_context<T: UnitTranscript<u8>>(
+    state: &mut T,
+    context: &[u8],
+) -> Result<(), DomainSeparatorMismatch> {
+    if context.is_empty() {
+        return Ok(());
+    }
+    state.public_units(context)
+}
+
+/// Encodes an application context from the calling program, a slot and arbitrary data.
+///
+/// This is the canonical layout for binding proofs to an on-chain consumer. Applications are
+/// free to use their own layout as long as the prover and the consumer agree on it.
+pub fn encode_context(program_id: Option<[u8; 32]>, slot: Option<u64>, data: &[u8]) -> Vec<u8> {
+    let mut context = Vec::with_capacity(1 + 32 + 1 + 8 + data.len());
+    match program_id {
+        Some(id) => {
+            context.push(1);
+            context.extend_from_slice(&id);
+        }
+        None => context.push(0),
+    }
+    match slot {
+        Some(slot) => {
+            context.push(1);
+            context.extend_from_slice(&slot.to_le_bytes());
+        }
+        None => context.push(0),
+    }
+    context.extend_from_slice(data);
+    context
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_serialize::CanonicalDeserialize;
+    use whir_common::{
+        poly_utils::multilinear::MultilinearPoint,
+        whir::{
+            committer::reader::CommitmentReader,
+            statement::{Statement, Weights},
+        },
+    };
+    use whir_verifier::Verifier;
+
+    use super::*;
+    use crate::{create_whir_params, field_size_bytes, F};
+
+    const SAMPLE_PROOF: &[u8] = include_bytes!("../../sample-proof/proof.bin");
+    const SAMPLE_EVAL_POINT: &[u8] = include_bytes!("../../sample-proof/eval-point.bin");
+    const SAMPLE_EVAL_VALUE: &[u8] = include_bytes!("../../sample-proof/eval-value.bin");
+
+    const BREAKING_CHANGE: &str = "the transcript semantics of whir_common/spongefish changed: \
+        proofs from existing provers are rejected by verifiers built with the new revision. \
+        If the dependency bump is intentional, regenerate `sample-proof/` with the native prover \
+        and upgrade provers and the on-chain program together";
+
+    /// Parameters of the sample proof, see `sample-proof/metadata.json`.
+    fn sample_params() -> WhirParams {
+        create_whir_params(6, 100, 4, 4, 1)
+    }
+
+    #[test]
+    #[cfg_attr(
+        any(feature = "field-goldilocks-3", feature = "field-128"),
+        ignore = "the sample proof uses the default field"
+    )]
+    fn field_encoding_is_pinned() {
+        assert_eq!(field_size_bytes(), 16, "{BREAKING_CHANGE}");
+        assert_eq!(SAMPLE_EVAL_POINT.len(), 6 * field_size_bytes());
+        F::deserialize_compressed(SAMPLE_EVAL_VALUE).expect(BREAKING_CHANGE);
+    }
+
+    #[test]
+    #[cfg_attr(
+        any(
+            feature = "merkle-blake3-syscall",
+            feature = "merkle-keccak-syscall",
+            feature = "merkle-poseidon-syscall",
+            feature = "merkle-sha256-syscall",
+            feature = "field-goldilocks-3",
+            feature = "field-128",
+            feature = "transcript-sha256",
+            feature = "pow-keccak"
+        ),
+        ignore = "the sample proof uses the default Merkle configuration, field, sponge and PoW"
+    )]
+    fn sample_proof_verifies() {
+        let params = sample_params();
+        let mut verifier_state =
+            domain_separator(&params, DOMAIN_SEPARATOR, &[]).to_verifier_state(SAMPLE_PROOF);
+
+        let parsed_commitment = CommitmentReader::new(&params)
+            .parse_commitment(&mut verifier_state)
+            .expect(BREAKING_CHANGE);
+
+        let eval_point = SAMPLE_EVAL_POINT
+            .chunks_exact(field_size_bytes())
+            .map(|chunk| F::deserialize_compressed(chunk).expect(BREAKING_CHANGE))
+            .collect();
+        let eval_value = F::deserialize_compressed(SAMPLE_EVAL_VALUE).expect(BREAKING_CHANGE);
+        let mut statement = Statement::new(6);
+        statement.add_constraint(
+            Weights::evaluation(MultilinearPoint(eval_point)),
+            eval_value,
+        );
+
+        Verifier::new(&params)
+            .verify(&mut verifier_state, parsed_commitment, statement)
+            .expect(BREAKING_CHANGE);
+    }
+
+    #[test]
+    fn pattern_round_trips() {
+        let params = sample_params();
+        let pattern = transcript_pattern(&params, "label", 7);
+        assert_eq!(
+            from_pattern(&pattern).unwrap().as_bytes(),
+            domain_separator(&params, "label", &[0; 7]).as_bytes()
+        );
+    }
+
+    #[test]
+    fn protocol_label_is_pinned() {
+        assert_eq!(DOMAIN_SEPARATOR, "whir-solana", "{BREAKING_CHANGE}");
+    }
+}
@@ -0,0 +1,243 @@
+//! Validation of WHIR parameters before building them.
+//!
+//! `WhirConfig::new` panics on some invalid parameters (folding factors larger than the number of
+//! variables, evaluation domains larger than the field's two-adic subgroup) and silently builds
+//! parameters missing the security level on others (when reaching it needs more proof of work
+//! than allowed). [`validate_params`] checks for all of them and reports the first problem found.
+
+use ark_ff::{FftField, PrimeField};
+
+use crate::{
+    create_whir_params_with, field::EXTENSION_DEGREE, BaseField, ProtocolOptions,
+    ReedSolomonBackend, WhirParams, F,
+};
+
+/// Reasons parameters are rejected by [`validate_params`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ParamsError {
+    /// The folding factor is zero or doesn't fit the number of variables.
+    InvalidFoldingFactor {
+        folding_factor: usize,
+        num_variables: usize,
+    },
+    /// The starting rate is 1: a zero log inverse rate leaves the code without any distance.
+    InvalidRate,
+    /// The starting evaluation domain is larger than the field's two-adic subgroup.
+    DomainTooLarge {
+        log_domain_size: usize,
+        max_log_domain_size: usize,
+    },
+    /// The security level is zero or not below the field size in bits.
+    UnachievableSecurity {
+        security_level: usize,
+        field_size_bits: usize,
+    },
+    /// Reaching the security level needs more proof of work than `pow_bits` per round.
+    InsufficientPowBits,
+    /// The batch size is zero.
+    InvalidBatchSize,
+}
+
+/// Size of [`F`] in bits.
+pub fn field_size_bits() -> usize {
+    BaseField::MODULUS_BIT_SIZE as usize * EXTENSION_DEGREE
+}
+
+/// Validates the number of polynomials committed at once, see `ProtocolOptions`.
+pub fn validate_batch_size(batch_size: usize) -> Result<(), ParamsError> {
+    if batch_size == 0 {
+        return Err(ParamsError::InvalidBatchSize);
+    }
+    Ok(())
+}
+
+/// Validates and builds the WHIR parameters of `create_whir_params`.
+pub fn validate_params(
+    num_variables: usize,
+    security_level: usize,
+    pow_bits: usize,
+    folding_factor: usize,
+    starting_log_inv_rate: usize,
+) -> Result<WhirParams, ParamsError> {
+    validate_params_with(
+        num_variables,
+        security_level,
+        pow_bits,
+        folding_factor,
+        starting_log_inv_rate,
+        ProtocolOptions::default(),
+    )
+}
+
+/// Validates and builds the WHIR parameters of `create_whir_params_with` with the given protocol
+/// options. The proof-of-work check depends on their soundness type.
+pub fn validate_params_with(
+    num_variables: usize,
+    security_level: usize,
+    pow_bits: usize,
+    folding_factor: usize,
+    starting_log_inv_rate: usize,
+    options: ProtocolOptions,
+) -> Result<WhirParams, ParamsError> {
+    validate_batch_size(options.batch_size)?;
+    let first_round_folding_factor = options.first_round_folding_factor.unwrap_or(folding_factor);
+    if folding_factor == 0
+        || first_round_folding_factor == 0
+        || options
+            .folding_schedule(folding_factor)
+            .check_validity(num_variables)
+            .is_err()
+    {
+        // Report the first round's factor if it's the invalid one.
+        let folding_factor =
+            if first_round_folding_factor == 0 || first_round_folding_factor > num_variables {
+                first_round_folding_factor
+            } else {
+                folding_factor
+            };
+        return Err(ParamsError::InvalidFoldingFactor {
+            folding_factor,
+            num_variables,
+        });
+    }
+    if starting_log_inv_rate == 0 {
+        return Err(ParamsError::InvalidRate);
+    }
+    let log_domain_size = num_variables + starting_log_inv_rate;
+    let max_log_domain_size = F::TWO_ADICITY as usize;
+    if log_domain_size > max_log_domain_size {
+        return Err(ParamsError::DomainTooLarge {
+            log_domain_size,
+            max_log_domain_size,
+        });
+    }
+    let field_size_bits = field_size_bits();
+    if security_level == 0 || security_level >= field_size_bits {
+        return Err(ParamsError::UnachievableSecurity {
+            security_level,
+            field_size_bits,
+        });
+    }
+
+    let params = create_whir_params_with(
+        num_variables,
+        security_level,
+        pow_bits,
+        folding_factor,
+        starting_log_inv_rate,
+        options,
+        ReedSolomonBackend::default(),
+    );
+    if !params.check_pow_bits() {
+        return Err(ParamsError::InsufficientPowBits);
+    }
+    Ok(params)
+}
+
+#[cfg(test)]
+mod tests {
+    use whir_common::parameters::SoundnessType;
+
+    use super::*;
+
+    #[test]
+    fn accepts_default_params() {
+        assert!(validate_params(
+            crate::NUM_VARIABLES,
+            crate::SECURITY_LEVEL_BITS,
+            crate::POW_BITS,
+            crate::FOLDING_FACTOR,
+            crate::STARTING_LOG_INV_RATE
+        )
+        .is_ok());
+    }
+
+    #[test]
+    fn soundness_type_sets_query_counts() {
+        let queries = |soundness_type| {
+            let options = ProtocolOptions {
+                soundness_type,
+                ..Default::default()
+            };
+            validate_params_with(6, 32, 5, 2, 1, options)
+                .unwrap()
+                .round_parameters
+                .iter()
+                .map(|round| round.num_queries)
+                .sum::<usize>()
+        };
+        assert!(queries(SoundnessType::UniqueDecoding) > queries(SoundnessType::ProvableList));
+        assert!(queries(SoundnessType::ProvableList) > queries(SoundnessType::ConjectureList));
+    }
+
+    #[test]
+    fn first_round_folds_by_its_own_factor() {
+        let options = ProtocolOptions {
+            first_round_folding_factor: Some(4),
+            ..Default::default()
+        };
+        let params = validate_params_with(10, 32, 5, 2, 1, options).unwrap();
+        assert_eq!(params.folding_factor.at_round(0), 4);
+        assert_eq!(params.folding_factor.at_round(1), 2);
+
+        let options = ProtocolOptions {
+            first_round_folding_factor: Some(11),
+            ..Default::default()
+        };
+        assert_eq!(
+            validate_params_with(10, 32, 5, 2, 1, options).err(),
+            Some(ParamsError::InvalidFoldingFactor {
+                folding_factor: 11,
+                num_variables: 10
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_params() {
+        assert_eq!(
+            validate_params(4, 100, 16, 5, 1).err(),
+            Some(ParamsError::InvalidFoldingFactor {
+                folding_factor: 5,
+                num_variables: 4
+            })
+        );
+        assert_eq!(
+            validate_params(6, 100, 16, 0, 1).err(),
+            Some(ParamsError::InvalidFoldingFactor {
+                folding_factor: 0,
+                num_variables: 6
+            })
+        );
+        assert_eq!(
+            validate_params(6, 100, 16, 4, 0).err(),
+            Some(ParamsError::InvalidRate)
+        );
+        assert_eq!(
+            validate_batch_size(0).err(),
+            Some(ParamsError::InvalidBatchSize)
+        );
+        let options = ProtocolOptions {
+            batch_size: 0,
+            ..Default::default()
+        };
+        assert_eq!(
+            validate_params_with(6, 100, 16, 4, 1, options).err(),
+            Some(ParamsError::InvalidBatchSize)
+        );
+        assert_eq!(
+            validate_params(F::TWO_ADICITY as usize, 100, 16, 4, 1).err(),
+            Some(ParamsError::DomainTooLarge {
+                log_domain_size: F::TWO_ADICITY as usize + 1,
+                max_log_domain_size: F::TWO_ADICITY as usize
+            })
+        );
+        assert_eq!(
+            validate_params(6, field_size_bits(), 16, 4, 1).err(),
+            Some(ParamsError::UnachievableSecurity {
+                security_level: field_size_bits(),
+                field_size_bits: field_size_bits()
+            })
+        );
+    }
+}
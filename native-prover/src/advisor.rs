@@ -0,0 +1,161 @@
+//! Parameter advisor
+//!
+//! Searches the WHIR parameter space for configurations that reach a target security level
+//! while fitting Solana's proof size and compute unit budgets.
+
+use whir_config::{
+    cost::{estimate_compute_units, estimate_proof_size},
+    pow::recommended_pow_bits,
+    validation::{validate_params, ParamsError},
+};
+
+use crate::ProofConfig;
+
+/// Folding factors considered by the search.
+const FOLDING_FACTORS: std::ops::RangeInclusive<usize> = 1..=6;
+
+/// Starting log inverse rates considered by the search.
+const LOG_INV_RATES: std::ops::RangeInclusive<usize> = 1..=5;
+
+/// Proof-of-work bits considered by the search, in addition to the recommended ones.
+const POW_BITS: [usize; 4] = [0, 8, 16, 20];
+
+/// Constraints for the parameter search.
+#[derive(Clone, Debug)]
+pub struct AdvisorQuery {
+    pub num_variables: usize,
+    pub security_level: usize,
+    pub max_compute_units: u64,
+    pub max_proof_bytes: usize,
+}
+
+/// A viable configuration together with its estimated costs.
+#[derive(Clone)]
+pub struct Recommendation {
+    pub config: ProofConfig,
+    pub proof_size: usize,
+    pub compute_units: u64,
+}
+
+/// Returns all viable configurations for the query, cheapest in compute units first.
+pub fn advise(query: &AdvisorQuery) -> Vec<Recommendation> {
+    let mut recommendations = Vec::new();
+
+    for folding_factor in FOLDING_FACTORS {
+        for starting_log_inv_rate in LOG_INV_RATES {
+            let mut pow_candidates = POW_BITS.to_vec();
+            pow_candidates.push(recommended_pow_bits(
+                query.num_variables,
+                query.security_level,
+                folding_factor,
+                starting_log_inv_rate,
+                Default::default(),
+            ));
+            pow_candidates.sort_unstable();
+            pow_candidates.dedup();
+
+            for pow_bits in pow_candidates {
+                let params = match validate_params(
+                    query.num_variables,
+                    query.security_level,
+                    pow_bits,
+                    folding_factor,
+                    starting_log_inv_rate,
+                ) {
+                    Ok(params) => params,
+                    Err(ParamsError::InsufficientPowBits) => continue,
+                    // The other errors don't depend on the proof of work.
+                    Err(_) => break,
+                };
+                let config = ProofConfig {
+                    num_variables: query.num_variables,
+                    security_level: query.security_level,
+                    pow_bits,
+                    starting_log_inv_rate,
+                    folding_factor,
+                    ..Default::default()
+                };
+
+                let proof_size = estimate_proof_size(&params);
+                let compute_units = estimate_compute_units(&params);
+                if proof_size <= query.max_proof_bytes && compute_units <= query.max_compute_units {
+                    recommendations.push(Recommendation {
+                        config,
+                        proof_size,
+                        compute_units,
+                    });
+                }
+            }
+        }
+    }
+
+    recommendations.sort_by_key(|r| (r.compute_units, r.proof_size));
+    recommendations
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn query(max_compute_units: u64, max_proof_bytes: usize) -> AdvisorQuery {
+        AdvisorQuery {
+            num_variables: 10,
+            security_level: 100,
+            max_compute_units,
+            max_proof_bytes,
+        }
+    }
+
+    #[test]
+    fn recommends_valid_configurations_cheapest_first() {
+        let recommendations = advise(&query(u64::MAX, usize::MAX));
+        assert!(!recommendations.is_empty());
+        for r in &recommendations {
+            let config = &r.config;
+            assert_eq!(config.num_variables, 10);
+            assert_eq!(config.security_level, 100);
+            let params = validate_params(
+                config.num_variables,
+                config.security_level,
+                config.pow_bits,
+                config.folding_factor,
+                config.starting_log_inv_rate,
+            )
+            .unwrap();
+            assert_eq!(r.proof_size, estimate_proof_size(&params));
+            assert_eq!(r.compute_units, estimate_compute_units(&params));
+        }
+        assert!(recommendations.windows(2).all(
+            |w| (w[0].compute_units, w[0].proof_size) <= (w[1].compute_units, w[1].proof_size)
+        ));
+    }
+
+    #[test]
+    fn keeps_only_configurations_within_the_budgets() {
+        let all = advise(&query(u64::MAX, usize::MAX));
+        let cheapest = &all[0];
+        let max_proof_bytes = all.iter().map(|r| r.proof_size).min().unwrap();
+        let within = advise(&query(cheapest.compute_units, max_proof_bytes));
+        assert!(within
+            .iter()
+            .all(|r| r.compute_units <= cheapest.compute_units && r.proof_size <= max_proof_bytes));
+        assert_eq!(
+            within.len(),
+            all.iter()
+                .filter(|r| r.compute_units <= cheapest.compute_units
+                    && r.proof_size <= max_proof_bytes)
+                .count()
+        );
+        assert!(advise(&query(0, usize::MAX)).is_empty());
+        assert!(advise(&query(u64::MAX, 0)).is_empty());
+    }
+
+    #[test]
+    fn recommends_nothing_for_unachievable_security() {
+        let query = AdvisorQuery {
+            security_level: 1000,
+            ..query(u64::MAX, usize::MAX)
+        };
+        assert!(advise(&query).is_empty());
+    }
+}
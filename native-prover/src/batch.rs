@@ -0,0 +1,249 @@
+//! Batched proofs of several polynomials.
+//!
+//! A configuration with `batch_size = N > 1` proves claims about `N` polynomials of
+//! `num_variables` variables each with a single commitment and a single proof. The fork's batched
+//! commitments open a random combination of the polynomials, which can't carry claims about the
+//! individual ones, so the polynomials are instead stacked into one polynomial of
+//! `num_variables + log2(N)` variables whose evaluation at the bits of `i` (most significant
+//! first) followed by a point `z` is the `i`-th polynomial's evaluation at `z`. Claims about the
+//! individual polynomials are evaluation constraints of the stacked one, so batch proofs are
+//! verified on-chain like any other proof, with the stacked polynomial's number of variables.
+
+use whir_common::poly_utils::{coeffs::CoefficientList, multilinear::MultilinearPoint};
+use whir_config::{params::params_digest, validation::validate_params_with};
+
+use crate::{
+    create_whir_params, generate_statement_proof, serialize_eval_point, serialize_field,
+    statement_from_constraints, verify_statement_proof, BaseField, ProofConfig, ProofEncoding,
+    Weights, F,
+};
+
+/// A claim of a batch proof about one of its polynomials.
+#[derive(Clone)]
+pub struct BatchClaim {
+    /// Index of the polynomial in the batch.
+    pub polynomial: usize,
+    /// The serialized evaluation point in the stacked polynomial, as passed to the program.
+    pub eval_point: Vec<u8>,
+    /// The serialized evaluation value.
+    pub eval_value: Vec<u8>,
+}
+
+/// A proof of claims about a batch of polynomials, see the module docs.
+#[derive(Clone)]
+pub struct BatchProof {
+    /// The serialized proof bytes, encoded with `encoding`.
+    pub proof_bytes: Vec<u8>,
+    /// The on-wire encoding of `proof_bytes`.
+    pub encoding: ProofEncoding,
+    /// The claims, in the order of the proof's constraints.
+    pub claims: Vec<BatchClaim>,
+    /// Number of variables of the stacked polynomial, verified on-chain.
+    pub num_variables: usize,
+    /// Digest of the stacked polynomial's WHIR parameters.
+    pub params_digest: [u8; 32],
+}
+
+/// Returns the configuration of the stacked polynomial of a batch configuration, validating it.
+pub fn stacked_config(config: &ProofConfig) -> anyhow::Result<ProofConfig> {
+    anyhow::ensure!(
+        config.batch_size.is_power_of_two(),
+        "batches hold a power of two polynomials, batch_size is {}",
+        config.batch_size
+    );
+    let stacked = ProofConfig {
+        num_variables: config.num_variables + config.batch_size.ilog2() as usize,
+        batch_size: whir_config::ON_CHAIN_BATCH_SIZE,
+        ..config.clone()
+    };
+    validate_params_with(
+        stacked.num_variables,
+        stacked.security_level,
+        stacked.pow_bits,
+        stacked.folding_factor,
+        stacked.starting_log_inv_rate,
+        stacked.protocol_options(),
+    )
+    .map_err(|error| anyhow::anyhow!("invalid WHIR parameters of the batch: {error:?}"))?;
+    Ok(stacked)
+}
+
+/// Stacks the polynomials of a batch, see the module docs.
+pub fn stack_polynomials(
+    polynomials: &[CoefficientList<BaseField>],
+) -> anyhow::Result<CoefficientList<BaseField>> {
+    anyhow::ensure!(
+        polynomials.len().is_power_of_two(),
+        "batches hold a power of two polynomials, got {}",
+        polynomials.len()
+    );
+    let num_variables = polynomials[0].num_variables();
+    anyhow::ensure!(
+        polynomials
+            .iter()
+            .all(|polynomial| polynomial.num_variables() == num_variables),
+        "the polynomials of a batch must have the same number of variables"
+    );
+    // Interpolates over the selector variables: the coefficients of a selector monomial are the
+    // selected polynomial's minus those of the polynomials its bits cover.
+    let block_len = 1 << num_variables;
+    let mut coeffs: Vec<BaseField> = polynomials
+        .iter()
+        .flat_map(|polynomial| polynomial.coeffs().iter().copied())
+        .collect();
+    let mut half = block_len;
+    while half < coeffs.len() {
+        for chunk in coeffs.chunks_exact_mut(2 * half) {
+            let (low, high) = chunk.split_at_mut(half);
+            for (high, low) in high.iter_mut().zip(low.iter()) {
+                *high -= *low;
+            }
+        }
+        half *= 2;
+    }
+    Ok(CoefficientList::new(coeffs))
+}
+
+/// Returns the point of the stacked polynomial evaluating the `index`-th of `batch_size`
+/// polynomials at `eval_point`.
+pub fn stacked_eval_point(
+    batch_size: usize,
+    index: usize,
+    eval_point: &MultilinearPoint<F>,
+) -> MultilinearPoint<F> {
+    let selector_bits = batch_size.ilog2() as usize;
+    let selector =
+        (0..selector_bits).map(|k| F::from(((index >> (selector_bits - 1 - k)) & 1) as u64));
+    MultilinearPoint(selector.chain(eval_point.0.iter().copied()).collect())
+}
+
+/// Generate one proof of the evaluations of `config.batch_size` polynomials, each opened at the
+/// points paired with its index in `openings`.
+pub fn generate_batch_proof(
+    config: &ProofConfig,
+    polynomials: &[CoefficientList<BaseField>],
+    openings: &[(usize, MultilinearPoint<F>)],
+) -> anyhow::Result<BatchProof> {
+    anyhow::ensure!(
+        polynomials.len() == config.batch_size,
+        "expected {} polynomials, got {}",
+        config.batch_size,
+        polynomials.len()
+    );
+    anyhow::ensure!(!openings.is_empty(), "no claims to prove");
+    let stacked = stacked_config(config)?;
+    let polynomial = stack_polynomials(polynomials)?;
+
+    let mut constraints = Vec::with_capacity(openings.len());
+    let mut claims = Vec::with_capacity(openings.len());
+    for (index, eval_point) in openings {
+        let opened = polynomials
+            .get(*index)
+            .ok_or_else(|| anyhow::anyhow!("no polynomial {index} in the batch"))?;
+        let value = opened.evaluate_at_extension(eval_point);
+        let point = stacked_eval_point(config.batch_size, *index, eval_point);
+        claims.push(BatchClaim {
+            polynomial: *index,
+            eval_point: serialize_eval_point(&point)?,
+            eval_value: serialize_field(&value)?,
+        });
+        constraints.push((Weights::evaluation(point), value));
+    }
+    let statement = statement_from_constraints(stacked.num_variables, constraints);
+    let proof_bytes = generate_statement_proof(&stacked, &polynomial, &statement)?;
+
+    Ok(BatchProof {
+        proof_bytes,
+        encoding: stacked.encoding,
+        claims,
+        num_variables: stacked.num_variables,
+        params_digest: params_digest(&create_whir_params(&stacked)),
+    })
+}
+
+/// Verify a proof of [`generate_batch_proof`] against `(polynomial index, point, value)` claims.
+pub fn verify_batch_proof(
+    config: &ProofConfig,
+    proof: &BatchProof,
+    claims: &[(usize, MultilinearPoint<F>, F)],
+) -> anyhow::Result<()> {
+    let stacked = stacked_config(config)?;
+    anyhow::ensure!(
+        claims
+            .iter()
+            .all(|(index, _, _)| *index < config.batch_size),
+        "claims must be about polynomials of the batch"
+    );
+    let statement = statement_from_constraints(
+        stacked.num_variables,
+        claims.iter().map(|(index, eval_point, value)| {
+            (
+                Weights::evaluation(stacked_eval_point(config.batch_size, *index, eval_point)),
+                *value,
+            )
+        }),
+    );
+    verify_statement_proof(&stacked, &proof.proof_bytes, proof.encoding, &statement)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_test_polynomial, ProofConfigBuilder};
+
+    #[test]
+    fn proves_claims_about_each_polynomial() -> anyhow::Result<()> {
+        let config = ProofConfigBuilder::dev()
+            .num_variables(4)
+            .batch_size(4)
+            .build()
+            .unwrap();
+        let polynomials: Vec<_> = (0..4u64)
+            .map(|k| {
+                let base = create_test_polynomial(config.num_variables);
+                CoefficientList::new(
+                    base.coeffs()
+                        .iter()
+                        .map(|coeff| *coeff * BaseField::from(k + 1))
+                        .collect(),
+                )
+            })
+            .collect();
+        let stacked = stack_polynomials(&polynomials)?;
+        for (index, polynomial) in polynomials.iter().enumerate() {
+            let point = MultilinearPoint(vec![F::from(index as u64 + 2); config.num_variables]);
+            assert_eq!(
+                stacked.evaluate_at_extension(&stacked_eval_point(4, index, &point)),
+                polynomial.evaluate_at_extension(&point)
+            );
+        }
+
+        let openings: Vec<_> = [(0, 5u64), (2, 6), (3, 7), (2, 8)]
+            .into_iter()
+            .map(|(index, x)| {
+                (
+                    index,
+                    MultilinearPoint(vec![F::from(x); config.num_variables]),
+                )
+            })
+            .collect();
+        let proof = generate_batch_proof(&config, &polynomials, &openings)?;
+        assert_eq!(proof.num_variables, config.num_variables + 2);
+        let mut claims: Vec<_> = openings
+            .iter()
+            .map(|(index, point)| {
+                (
+                    *index,
+                    point.clone(),
+                    polynomials[*index].evaluate_at_extension(point),
+                )
+            })
+            .collect();
+        verify_batch_proof(&config, &proof, &claims)?;
+
+        claims[1].0 = 1;
+        assert!(verify_batch_proof(&config, &proof, &claims).is_err());
+        assert!(generate_batch_proof(&config, &polynomials[..2], &openings).is_err());
+        Ok(())
+    }
+}
@@ -0,0 +1,187 @@
+//! Builder and presets of [`ProofConfig`].
+//!
+//! Starts from a named preset, adjusts single settings and checks the resulting parameters with
+//! `whir_config::validation::validate_params_with` at [`ProofConfigBuilder::build`], so invalid
+//! configurations fail before proving instead of inside the WHIR parameter derivation.
+
+use whir_common::parameters::{DeduplicationStrategy, MerkleProofStrategy, SoundnessType};
+use whir_config::{
+    pow::recommended_pow_bits,
+    validation::{validate_params_with, ParamsError},
+    ReedSolomonBackend,
+};
+
+use crate::{ProofConfig, ProofEncoding};
+
+/// Builds a [`ProofConfig`], see the module docs.
+#[derive(Clone, Default)]
+pub struct ProofConfigBuilder {
+    config: ProofConfig,
+}
+
+impl ProofConfig {
+    /// Returns a builder starting from the default configuration, see [`ProofConfigBuilder`].
+    pub fn builder() -> ProofConfigBuilder {
+        ProofConfigBuilder::default()
+    }
+}
+
+impl ProofConfigBuilder {
+    /// Fast proofs with low security, for tests and local development only.
+    pub fn dev() -> Self {
+        Self::default()
+            .security_level(32)
+            .starting_log_inv_rate(1)
+            .folding_factor(2)
+            .recommended_pow_bits()
+    }
+
+    /// 120 bits of security, with a lower rate than the default, for proofs securing high value.
+    pub fn high_security() -> Self {
+        Self::default()
+            .security_level(120)
+            .starting_log_inv_rate(2)
+            .recommended_pow_bits()
+    }
+
+    /// Sets the proof-of-work bits recommended for the current settings, see
+    /// `whir_config::pow::recommended_pow_bits`. Call it after changing the other parameters.
+    pub fn recommended_pow_bits(self) -> Self {
+        let config = &self.config;
+        let pow_bits = recommended_pow_bits(
+            config.num_variables,
+            config.security_level,
+            config.folding_factor,
+            config.starting_log_inv_rate,
+            config.protocol_options(),
+        );
+        self.pow_bits(pow_bits)
+    }
+
+    pub fn num_variables(mut self, num_variables: usize) -> Self {
+        self.config.num_variables = num_variables;
+        self
+    }
+
+    pub fn security_level(mut self, security_level: usize) -> Self {
+        self.config.security_level = security_level;
+        self
+    }
+
+    pub fn pow_bits(mut self, pow_bits: usize) -> Self {
+        self.config.pow_bits = pow_bits;
+        self
+    }
+
+    pub fn starting_log_inv_rate(mut self, starting_log_inv_rate: usize) -> Self {
+        self.config.starting_log_inv_rate = starting_log_inv_rate;
+        self
+    }
+
+    pub fn folding_factor(mut self, folding_factor: usize) -> Self {
+        self.config.folding_factor = folding_factor;
+        self
+    }
+
+    pub fn first_round_folding_factor(mut self, folding_factor: usize) -> Self {
+        self.config.first_round_folding_factor = Some(folding_factor);
+        self
+    }
+
+    pub fn encoding(mut self, encoding: ProofEncoding) -> Self {
+        self.config.encoding = encoding;
+        self
+    }
+
+    pub fn domain_label(mut self, domain_label: impl Into<String>) -> Self {
+        self.config.domain_label = domain_label.into();
+        self
+    }
+
+    pub fn context(mut self, context: Vec<u8>) -> Self {
+        self.config.context = context;
+        self
+    }
+
+    pub fn batch_size(mut self, batch_size: usize) -> Self {
+        self.config.batch_size = batch_size;
+        self
+    }
+
+    pub fn merkle_proof_strategy(mut self, strategy: MerkleProofStrategy) -> Self {
+        self.config.merkle_proof_strategy = strategy;
+        self
+    }
+
+    pub fn deduplication_strategy(mut self, strategy: DeduplicationStrategy) -> Self {
+        self.config.deduplication_strategy = strategy;
+        self
+    }
+
+    pub fn soundness_type(mut self, soundness_type: SoundnessType) -> Self {
+        self.config.soundness_type = soundness_type;
+        self
+    }
+
+    pub fn num_threads(mut self, num_threads: usize) -> Self {
+        self.config.num_threads = Some(num_threads);
+        self
+    }
+
+    pub fn reed_solomon(mut self, reed_solomon: ReedSolomonBackend) -> Self {
+        self.config.reed_solomon = reed_solomon;
+        self
+    }
+
+    /// Returns the configuration, failing if its WHIR parameters are invalid.
+    pub fn build(self) -> Result<ProofConfig, ParamsError> {
+        let config = self.config;
+        validate_params_with(
+            config.num_variables,
+            config.security_level,
+            config.pow_bits,
+            config.folding_factor,
+            config.starting_log_inv_rate,
+            config.protocol_options(),
+        )?;
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_build() {
+        assert!(ProofConfig::builder().build().is_ok());
+        let dev = ProofConfigBuilder::dev().build().unwrap();
+        assert_eq!((dev.security_level, dev.folding_factor), (32, 2));
+    }
+
+    #[test]
+    fn build_checks_parameters() {
+        let error = ProofConfigBuilder::dev()
+            .num_variables(4)
+            .folding_factor(5)
+            .build()
+            .err();
+        assert_eq!(
+            error,
+            Some(ParamsError::InvalidFoldingFactor {
+                folding_factor: 5,
+                num_variables: 4
+            })
+        );
+    }
+
+    #[test]
+    fn build_checks_batch_size() {
+        let error = ProofConfigBuilder::dev().batch_size(0).build().err();
+        assert_eq!(error, Some(ParamsError::InvalidBatchSize));
+
+        let config = ProofConfigBuilder::dev().batch_size(4).build().unwrap();
+        assert_eq!(config.protocol_options().batch_size, 4);
+        assert_eq!(crate::create_whir_params(&config).batch_size, 4);
+    }
+}
@@ -0,0 +1,147 @@
+//! WHIR CLI
+//!
+//! Tooling around WHIR parameters for the Solana verifier.
+
+use clap::{Parser, Subcommand};
+use whir_proof_generator::advisor::{advise, AdvisorQuery};
+
+#[derive(Parser)]
+#[command(name = "whir-cli", about = "WHIR tooling for the Solana verifier")]
+struct Cli {
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Search for parameters that fit the given security target and Solana budgets.
+    Advise {
+        /// Number of variables in the multilinear polynomial.
+        #[arg(long)]
+        num_vars: usize,
+        /// Target security level in bits.
+        #[arg(long, default_value_t = whir_config::SECURITY_LEVEL_BITS)]
+        target_security: usize,
+        /// Maximum compute units of the verify instruction, e.g. `1.4M` or `200k`.
+        #[arg(long, default_value = "1.4M", value_parser = parse_compute_units)]
+        max_cu: u64,
+        /// Maximum proof size in KiB.
+        #[arg(long, default_value_t = 80)]
+        max_proof_kb: usize,
+        /// Number of configurations to print.
+        #[arg(long, default_value_t = 10)]
+        top: usize,
+    },
+}
+
+fn main() -> anyhow::Result<()> {
+    match Cli::parse().command {
+        Command::Advise {
+            num_vars,
+            target_security,
+            max_cu,
+            max_proof_kb,
+            top,
+        } => {
+            let query = AdvisorQuery {
+                num_variables: num_vars,
+                security_level: target_security,
+                max_compute_units: max_cu,
+                max_proof_bytes: max_proof_kb * 1024,
+            };
+            let recommendations = advise(&query);
+            if recommendations.is_empty() {
+                anyhow::bail!("no configuration fits the given constraints");
+            }
+
+            println!(
+                "{:>4}  {:>7}  {:>8}  {:>8}  {:>10}  {:>12}",
+                "rank", "folding", "log_rate", "pow_bits", "proof_size", "compute_units"
+            );
+            for (rank, r) in recommendations.iter().take(top).enumerate() {
+                println!(
+                    "{:>4}  {:>7}  {:>8}  {:>8}  {:>10}  {:>12}",
+                    rank + 1,
+                    r.config.folding_factor,
+                    r.config.starting_log_inv_rate,
+                    r.config.pow_bits,
+                    r.proof_size,
+                    r.compute_units
+                );
+            }
+            println!(
+                "{} viable configuration(s) found, estimates are approximate",
+                recommendations.len()
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Parses a compute unit count with an optional `k`/`M` suffix.
+fn parse_compute_units(s: &str) -> Result<u64, String> {
+    let (number, multiplier) = match s.strip_suffix(['M', 'm']) {
+        Some(n) => (n, 1_000_000.0),
+        None => match s.strip_suffix(['K', 'k']) {
+            Some(n) => (n, 1_000.0),
+            None => (s, 1.0),
+        },
+    };
+    let value = number
+        .parse::<f64>()
+        .map(|value| value * multiplier)
+        .ok()
+        // Rejects negative, NaN and infinite counts, which `as u64` would silently saturate.
+        .filter(|value| (0.0..=u64::MAX as f64).contains(value))
+        .ok_or_else(|| format!("invalid compute unit count: {s}"))?;
+    Ok(value as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_compute_unit_counts() {
+        assert_eq!(parse_compute_units("200000"), Ok(200_000));
+        assert_eq!(parse_compute_units("200k"), Ok(200_000));
+        assert_eq!(parse_compute_units("200K"), Ok(200_000));
+        assert_eq!(parse_compute_units("1.4M"), Ok(1_400_000));
+        assert_eq!(parse_compute_units("1.4m"), Ok(1_400_000));
+        assert_eq!(parse_compute_units("0"), Ok(0));
+    }
+
+    #[test]
+    fn rejects_invalid_compute_unit_counts() {
+        for s in [
+            "", "k", "M", "1.4G", "abc", "1,4M", "-5", "-1k", "NaN", "inf", "1e30M",
+        ] {
+            assert!(parse_compute_units(s).is_err(), "accepted {s:?}");
+        }
+    }
+
+    #[test]
+    fn parses_the_advise_command() {
+        let cli =
+            Cli::try_parse_from(["whir-cli", "advise", "--num-vars", "20", "--max-cu", "200k"])
+                .unwrap();
+        let Command::Advise {
+            num_vars,
+            target_security,
+            max_cu,
+            max_proof_kb,
+            top,
+        } = cli.command;
+        assert_eq!(num_vars, 20);
+        assert_eq!(target_security, whir_config::SECURITY_LEVEL_BITS);
+        assert_eq!(max_cu, 200_000);
+        assert_eq!(max_proof_kb, 80);
+        assert_eq!(top, 10);
+
+        assert!(
+            Cli::try_parse_from(["whir-cli", "advise", "--num-vars", "20", "--max-cu", "x"])
+                .is_err()
+        );
+        assert!(Cli::try_parse_from(["whir-cli", "advise"]).is_err());
+    }
+}
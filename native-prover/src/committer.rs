@@ -0,0 +1,470 @@
+//! Committing to polynomials ahead of opening them.
+//!
+//! [`generate_statement_proof`](crate::generate_statement_proof) commits and opens in one go.
+//! Applications publishing a commitment first, e.g. in a rollup block, and proving claims about
+//! it later split the two phases with a [`Committer`]: [`Committer::commit`] returns the
+//! commitment's bytes and a [`Witness`] to keep, and [`Committer::open`] later turns the witness
+//! and a statement into the proof.
+//!
+//! The commitment is the start of the proof's transcript, so the witness carries the transcript
+//! state and opens a single statement. Combine claims into one statement, see
+//! [`statement_from_constraints`](crate::statement_from_constraints), to prove several.
+//!
+//! Long-lived services save a commitment with [`Committer::save_witness`] and reload its witness
+//! with [`Committer::load_witness`], e.g. after a restart, without keeping the polynomial's source
+//! around. Neither the fork's witness nor the transcript state are serializable, so the file holds
+//! the commitment and the polynomial, and loading recommits to the polynomial, checking that the
+//! commitment is unchanged.
+//!
+//! A callback set with [`Committer::on_progress`] is told of each [`ProverPhase`] as it completes,
+//! with an estimate of the share of the proof's work done, to drive progress UIs and health
+//! checks. The fork's prover runs its rounds (folding, Merkle trees, proof-of-work grinding)
+//! without hooks, so they are reported as a whole.
+//!
+//! Likewise, a [`CancellationToken`] set with [`Committer::with_cancellation`] is checked between
+//! the phases: once cancelled, commitments and openings fail with [`Cancelled`] at the next check,
+//! e.g. when a proof request was superseded. A running phase completes first, and the committer's
+//! threads are released with it.
+
+use std::{
+    fmt, fs,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+};
+
+use anyhow::Context;
+use ark_ff::{BigInteger, PrimeField};
+use rayon::{ThreadPool, ThreadPoolBuilder};
+use whir_common::{
+    poly_utils::coeffs::CoefficientList,
+    whir::{
+        committer::{writer::CommitmentWriter, Witness as WhirWitness},
+        statement::Statement,
+    },
+};
+use whir_config::{
+    params::params_digest,
+    transcript::{self, TranscriptProverState},
+    WhirParams, DIGEST_SIZE_BYTES,
+};
+use whir_prover::Prover;
+
+use crate::{
+    create_whir_params, polynomial::polynomial_from_bytes, BaseField, MerkleConfig, ProofConfig, F,
+};
+
+/// Magic bytes and format version of witness files, see [`Committer::save_witness`].
+const WITNESS_FILE_MAGIC: &[u8] = b"whir-witness";
+const WITNESS_FILE_VERSION: u8 = 1;
+
+/// Commits to polynomials and opens the commitments, see the module docs.
+pub struct Committer {
+    config: ProofConfig,
+    params: WhirParams,
+    /// Pool of `config.num_threads` threads, if set.
+    pool: Option<ThreadPool>,
+    on_progress: Option<Box<dyn Fn(Progress) + Send + Sync>>,
+    cancellation: Option<CancellationToken>,
+}
+
+/// Cancels the proofs of the committers it is set on, see the module docs. Clones share the
+/// cancellation.
+#[derive(Clone, Debug, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// Error of commitments and openings after their [`CancellationToken`] was cancelled.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Cancelled;
+
+impl fmt::Display for Cancelled {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("proof generation was cancelled")
+    }
+}
+
+impl std::error::Error for Cancelled {}
+
+/// Phases of proof generation, reported to [`Committer::on_progress`] as they complete.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProverPhase {
+    /// The Reed-Solomon encoding, Merkle tree and out-of-domain samples of the commitment.
+    Commitment,
+    /// The sumcheck rounds, folds, Merkle openings and proof of work of the WHIR prover.
+    Opening,
+}
+
+/// Progress of a proof, see [`Committer::on_progress`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Progress {
+    /// The completed phase.
+    pub phase: ProverPhase,
+    /// Estimated share of the proof's work done, in percent.
+    pub percent: u8,
+}
+
+/// The transcript bytes of a commitment, the start of the proof bytes before their encoding.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CommitmentBytes(pub Vec<u8>);
+
+impl CommitmentBytes {
+    /// Returns the commitment's Merkle root, as recorded by the program for verified proofs.
+    pub fn root(&self) -> [u8; DIGEST_SIZE_BYTES] {
+        self.0[..DIGEST_SIZE_BYTES]
+            .try_into()
+            .expect("commitments start with their Merkle root")
+    }
+}
+
+/// What the prover keeps of a commitment to open it, see [`Committer::open`].
+pub struct Witness {
+    prover_state: TranscriptProverState,
+    witness: WhirWitness<F, MerkleConfig>,
+}
+
+impl Committer {
+    /// Creates a committer for proofs of `config`.
+    pub fn new(config: &ProofConfig) -> anyhow::Result<Self> {
+        anyhow::ensure!(
+            config.batch_size == whir_config::ON_CHAIN_BATCH_SIZE,
+            "PCS proofs commit to a single polynomial, batch_size must be {}",
+            whir_config::ON_CHAIN_BATCH_SIZE
+        );
+        let pool = config
+            .num_threads
+            .map(|num_threads| ThreadPoolBuilder::new().num_threads(num_threads).build())
+            .transpose()
+            .context("failed to create the prover's thread pool")?;
+        Ok(Self {
+            config: config.clone(),
+            params: create_whir_params(config),
+            pool,
+            on_progress: None,
+            cancellation: None,
+        })
+    }
+
+    /// Checks `token` between the phases of commitments and openings, failing with [`Cancelled`]
+    /// once it's cancelled.
+    pub fn with_cancellation(mut self, token: CancellationToken) -> Self {
+        self.cancellation = Some(token);
+        self
+    }
+
+    fn check_cancelled(&self) -> anyhow::Result<()> {
+        match &self.cancellation {
+            Some(token) if token.is_cancelled() => Err(Cancelled.into()),
+            _ => Ok(()),
+        }
+    }
+
+    /// Reports the progress of commitments and openings to `on_progress`, e.g. sending it over a
+    /// channel.
+    pub fn on_progress(mut self, on_progress: impl Fn(Progress) + Send + Sync + 'static) -> Self {
+        self.on_progress = Some(Box::new(on_progress));
+        self
+    }
+
+    fn report(&self, phase: ProverPhase) {
+        if let Some(on_progress) = &self.on_progress {
+            let percent = match phase {
+                ProverPhase::Commitment => commitment_percent(&self.params),
+                ProverPhase::Opening => 100,
+            };
+            on_progress(Progress { phase, percent });
+        }
+    }
+
+    /// Runs `op` in the committer's thread pool, or in the global pool if it has none.
+    fn install<T: Send>(&self, op: impl FnOnce() -> T + Send) -> T {
+        match &self.pool {
+            Some(pool) => pool.install(op),
+            None => op(),
+        }
+    }
+
+    /// Commits to the polynomial, returning the commitment and the witness to open it with.
+    pub fn commit(
+        &self,
+        polynomial: &CoefficientList<BaseField>,
+    ) -> anyhow::Result<(CommitmentBytes, Witness)> {
+        let config = &self.config;
+        anyhow::ensure!(
+            polynomial.num_variables() == config.num_variables,
+            "the polynomial has {} variables, the configuration {}",
+            polynomial.num_variables(),
+            config.num_variables
+        );
+        self.check_cancelled()?;
+        let domainsep =
+            transcript::domain_separator(&self.params, &config.domain_label, &config.context);
+        let mut prover_state: TranscriptProverState = domainsep.to_prover_state();
+        transcript::bind_context(&mut prover_state, &config.context)?;
+
+        let committer = CommitmentWriter::new(self.params.clone());
+        let witness = self.install(|| committer.commit(&mut prover_state, polynomial))?;
+        let commitment = CommitmentBytes(prover_state.narg_string().to_vec());
+        self.report(ProverPhase::Commitment);
+        self.check_cancelled()?;
+        Ok((
+            commitment,
+            Witness {
+                prover_state,
+                witness,
+            },
+        ))
+    }
+
+    /// Proves the statement about the committed polynomial. Returns the proof bytes, encoded with
+    /// the configuration's encoding and starting with the commitment before encoding.
+    pub fn open(&self, witness: Witness, statement: &Statement<F>) -> anyhow::Result<Vec<u8>> {
+        let Witness {
+            mut prover_state,
+            witness,
+        } = witness;
+        self.check_cancelled()?;
+        let prover = Prover::new(self.params.clone());
+        self.install(|| prover.prove(&mut prover_state, statement.clone(), witness))?;
+        self.report(ProverPhase::Opening);
+        Ok(self.config.encoding.encode(prover_state.narg_string()))
+    }
+
+    /// Saves the commitment to the polynomial to a file, to reload its witness later with
+    /// [`Self::load_witness`]. The file starts with a format version and the parameters' digest,
+    /// followed by the length-prefixed commitment and the polynomial's canonically encoded
+    /// coefficients.
+    pub fn save_witness(
+        &self,
+        path: impl AsRef<Path>,
+        commitment: &CommitmentBytes,
+        polynomial: &CoefficientList<BaseField>,
+    ) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let mut bytes = WITNESS_FILE_MAGIC.to_vec();
+        bytes.push(WITNESS_FILE_VERSION);
+        bytes.extend_from_slice(&params_digest(&self.params));
+        bytes.extend_from_slice(&(commitment.0.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(&commitment.0);
+        for coeff in polynomial.coeffs() {
+            bytes.extend_from_slice(&coeff.into_bigint().to_bytes_le());
+        }
+        fs::write(path, bytes).with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// Reloads a commitment saved with [`Self::save_witness`] under the same parameters, returning
+    /// it and a fresh witness to open it with.
+    pub fn load_witness(
+        &self,
+        path: impl AsRef<Path>,
+    ) -> anyhow::Result<(CommitmentBytes, Witness)> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).with_context(|| format!("failed to read {}", path.display()))?;
+        let (saved, polynomial) = parse_witness_file(&bytes, &params_digest(&self.params))
+            .with_context(|| format!("invalid witness file {}", path.display()))?;
+        let (commitment, witness) = self.commit(&polynomial)?;
+        anyhow::ensure!(
+            commitment == saved,
+            "the recomputed commitment differs from the saved one in {}",
+            path.display()
+        );
+        Ok((commitment, witness))
+    }
+}
+
+/// Estimates the commitment's share of a proof's work in percent. Encoding and hashing dominate
+/// both phases: the commitment's codeword has `2^(num_variables + starting_log_inv_rate)` entries
+/// and each of the `R` rounds commits to one of half the previous size, so the commitment takes
+/// about `2^R / (2^(R + 1) - 1)` of the work.
+fn commitment_percent(params: &WhirParams) -> u8 {
+    let rounds = params.round_parameters.len().min(16) as u32;
+    (100 * (1u64 << rounds) / ((1u64 << (rounds + 1)) - 1)) as u8
+}
+
+/// Splits a witness file into the saved commitment and the polynomial.
+fn parse_witness_file(
+    bytes: &[u8],
+    expected_digest: &[u8; 32],
+) -> anyhow::Result<(CommitmentBytes, CoefficientList<BaseField>)> {
+    let rest = bytes
+        .strip_prefix(WITNESS_FILE_MAGIC)
+        .context("not a witness file")?;
+    let (&version, rest) = rest.split_first().context("truncated witness file")?;
+    anyhow::ensure!(
+        version == WITNESS_FILE_VERSION,
+        "unsupported witness file version {version}"
+    );
+    let (digest, rest) = rest
+        .split_first_chunk::<32>()
+        .context("truncated witness file")?;
+    anyhow::ensure!(
+        digest == expected_digest,
+        "the witness was saved under other parameters"
+    );
+    let (len, rest) = rest
+        .split_first_chunk::<4>()
+        .context("truncated witness file")?;
+    let len = u32::from_le_bytes(*len) as usize;
+    anyhow::ensure!(rest.len() >= len, "truncated witness file");
+    let (commitment, coeffs) = rest.split_at(len);
+    Ok((
+        CommitmentBytes(commitment.to_vec()),
+        polynomial_from_bytes(coeffs)?,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use whir_common::poly_utils::multilinear::MultilinearPoint;
+
+    use super::*;
+    use crate::{
+        create_test_polynomial, statement_from_constraints, verify_statement_proof,
+        ProofConfigBuilder, ProofEncoding, Weights,
+    };
+
+    #[test]
+    fn opens_commitments_later() -> anyhow::Result<()> {
+        let config = ProofConfigBuilder::dev()
+            .encoding(ProofEncoding::Raw)
+            .build()
+            .unwrap();
+        let committer = Committer::new(&config)?;
+        let poly = create_test_polynomial(config.num_variables);
+        let (commitment, witness) = committer.commit(&poly)?;
+
+        let eval_point = MultilinearPoint(vec![F::from(3u64); config.num_variables]);
+        let statement = statement_from_constraints(
+            config.num_variables,
+            [(
+                Weights::evaluation(eval_point.clone()),
+                poly.evaluate_at_extension(&eval_point),
+            )],
+        );
+        let proof_bytes = committer.open(witness, &statement)?;
+        assert!(proof_bytes.starts_with(&commitment.0));
+        verify_statement_proof(&config, &proof_bytes, config.encoding, &statement)?;
+
+        assert!(committer
+            .commit(&create_test_polynomial(config.num_variables + 1))
+            .is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn proofs_dont_depend_on_the_thread_count() -> anyhow::Result<()> {
+        let config = ProofConfigBuilder::dev().build().unwrap();
+        let poly = create_test_polynomial(config.num_variables);
+        let (commitment, _) = Committer::new(&config)?.commit(&poly)?;
+        for num_threads in [1, 2] {
+            let config = ProofConfig {
+                num_threads: Some(num_threads),
+                ..config.clone()
+            };
+            assert_eq!(Committer::new(&config)?.commit(&poly)?.0, commitment);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn reports_progress() -> anyhow::Result<()> {
+        let config = ProofConfigBuilder::dev().build().unwrap();
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let committer = Committer::new(&config)?.on_progress(move |progress| {
+            sender.send(progress).unwrap();
+        });
+        let poly = create_test_polynomial(config.num_variables);
+        let (_, witness) = committer.commit(&poly)?;
+        let eval_point = MultilinearPoint(vec![F::from(4u64); config.num_variables]);
+        let statement = statement_from_constraints(
+            config.num_variables,
+            [(
+                Weights::evaluation(eval_point.clone()),
+                poly.evaluate_at_extension(&eval_point),
+            )],
+        );
+        committer.open(witness, &statement)?;
+
+        let progress: Vec<_> = receiver.try_iter().collect();
+        assert_eq!(progress.len(), 2);
+        assert_eq!(progress[0].phase, ProverPhase::Commitment);
+        assert!((50..100).contains(&progress[0].percent));
+        assert_eq!(
+            progress[1],
+            Progress {
+                phase: ProverPhase::Opening,
+                percent: 100
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn cancels_between_phases() -> anyhow::Result<()> {
+        let config = ProofConfigBuilder::dev().build().unwrap();
+        let token = CancellationToken::new();
+        let cancel_after_commit = token.clone();
+        let committer = Committer::new(&config)?
+            .with_cancellation(token.clone())
+            .on_progress(move |progress| {
+                if progress.phase == ProverPhase::Commitment {
+                    cancel_after_commit.cancel();
+                }
+            });
+        let poly = create_test_polynomial(config.num_variables);
+        let error = committer.commit(&poly).err().unwrap();
+        assert_eq!(error.downcast_ref::<Cancelled>(), Some(&Cancelled));
+        assert!(token.is_cancelled());
+        assert!(committer.commit(&poly).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn reloads_saved_witnesses() -> anyhow::Result<()> {
+        let config = ProofConfigBuilder::dev().build().unwrap();
+        let committer = Committer::new(&config)?;
+        let poly = create_test_polynomial(config.num_variables);
+        let (commitment, _) = committer.commit(&poly)?;
+
+        let path = std::env::temp_dir().join(format!("whir-witness-{}.bin", std::process::id()));
+        committer.save_witness(&path, &commitment, &poly)?;
+        let (loaded, witness) = committer.load_witness(&path)?;
+        assert_eq!(loaded, commitment);
+
+        let eval_point = MultilinearPoint(vec![F::from(9u64); config.num_variables]);
+        let statement = statement_from_constraints(
+            config.num_variables,
+            [(
+                Weights::evaluation(eval_point.clone()),
+                poly.evaluate_at_extension(&eval_point),
+            )],
+        );
+        let proof_bytes = committer.open(witness, &statement)?;
+        verify_statement_proof(&config, &proof_bytes, config.encoding, &statement)?;
+
+        // Witnesses only reload under the parameters they were saved with.
+        let other = ProofConfigBuilder::dev()
+            .security_level(40)
+            .recommended_pow_bits()
+            .build()
+            .unwrap();
+        assert!(Committer::new(&other)?.load_witness(&path).is_err());
+        std::fs::remove_file(&path)?;
+        Ok(())
+    }
+}
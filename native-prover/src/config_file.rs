@@ -0,0 +1,91 @@
+//! Loading [`ProofConfig`]s from TOML or JSON files.
+//!
+//! Parameter sets can be versioned alongside deployments and shared by the CLI, services and
+//! tests instead of being recompiled. Missing fields take their default value, and the Reed-Solomon
+//! backend, not being data, is always the default. Loaded configurations are validated like
+//! [`ProofConfigBuilder::build`](crate::ProofConfigBuilder::build).
+
+use std::{fs, path::Path};
+
+use anyhow::{bail, Context};
+use whir_config::validation::validate_params_with;
+
+use crate::ProofConfig;
+
+impl ProofConfig {
+    /// Loads a configuration from a `.toml` or `.json` file.
+    pub fn from_file(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let contents = fs::read_to_string(path)
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("toml") => Self::from_toml_str(&contents),
+            Some("json") => Self::from_json_str(&contents),
+            _ => bail!(
+                "unsupported config file {}, expected .toml or .json",
+                path.display()
+            ),
+        }
+        .with_context(|| format!("invalid config file {}", path.display()))
+    }
+
+    /// Parses a TOML configuration.
+    pub fn from_toml_str(contents: &str) -> anyhow::Result<Self> {
+        toml::from_str::<Self>(contents)?.validated()
+    }
+
+    /// Parses a JSON configuration.
+    pub fn from_json_str(contents: &str) -> anyhow::Result<Self> {
+        serde_json::from_str::<Self>(contents)?.validated()
+    }
+
+    fn validated(self) -> anyhow::Result<Self> {
+        validate_params_with(
+            self.num_variables,
+            self.security_level,
+            self.pow_bits,
+            self.folding_factor,
+            self.starting_log_inv_rate,
+            self.protocol_options(),
+        )
+        .map_err(|error| anyhow::anyhow!("invalid WHIR parameters: {error:?}"))?;
+        Ok(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{ProofConfigBuilder, ProofEncoding};
+
+    #[test]
+    fn json_round_trips() {
+        let config = ProofConfigBuilder::dev()
+            .encoding(ProofEncoding::ZeroRunLength)
+            .context(vec![1, 2, 3])
+            .build()
+            .unwrap();
+        let loaded = ProofConfig::from_json_str(&serde_json::to_string(&config).unwrap()).unwrap();
+        assert_eq!(loaded.security_level, config.security_level);
+        assert_eq!(loaded.folding_factor, config.folding_factor);
+        assert_eq!(loaded.encoding, config.encoding);
+        assert_eq!(loaded.context, config.context);
+    }
+
+    #[test]
+    fn toml_fills_in_defaults() {
+        let config = ProofConfig::from_toml_str(
+            r#"
+            security_level = 32
+            pow_bits = 5
+            folding_factor = 2
+            encoding = "zero_run_length"
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.num_variables, whir_config::NUM_VARIABLES);
+        assert_eq!(config.folding_factor, 2);
+        assert_eq!(config.encoding, ProofEncoding::ZeroRunLength);
+        assert!(ProofConfig::from_toml_str("folding_factor = 0").is_err());
+    }
+}
@@ -3,44 +3,93 @@
 //! This library provides functionality to generate WHIR proofs that can be verified
 //! by the Solana program.
 
+pub mod advisor;
+pub mod batch;
+pub mod builder;
+pub mod committer;
+pub mod config_file;
+pub mod polynomial;
+
 use ark_ff::Field;
 use ark_serialize::CanonicalSerialize;
-use spongefish::{DomainSeparator, ProverState, VerifierState};
+use serde::{Deserialize, Serialize};
 use whir_common::{
-    poly_utils::{coeffs::CoefficientList, multilinear::MultilinearPoint},
-    whir::{
-        committer::{reader::CommitmentReader, writer::CommitmentWriter},
-        domainsep::WhirDomainSeparator,
-        statement::{Statement, Weights},
-    },
+    poly_utils::{coeffs::CoefficientList, evals::EvaluationsList, multilinear::MultilinearPoint},
+    whir::committer::reader::CommitmentReader,
+};
+use whir_config::{
+    params::{params_digest, ParamsSoundness, ParamsV1, VersionedParams},
+    public_inputs::public_inputs_eval_point,
+    transcript::{self, TranscriptVerifierState},
+    ProtocolOptions, WhirParams,
 };
-use whir_config::WhirParams;
-use whir_prover::Prover;
 use whir_verifier::Verifier;
 
-pub use whir_config::{MerkleConfig, PowStrategy, F, DOMAIN_SEPARATOR};
+pub use builder::ProofConfigBuilder;
+pub use committer::{
+    CancellationToken, Cancelled, CommitmentBytes, Committer, Progress, ProverPhase, Witness,
+};
+pub use whir_common::parameters::{DeduplicationStrategy, MerkleProofStrategy, SoundnessType};
+pub use whir_common::whir::statement::{Statement, Weights};
+pub use whir_config::encoding::ProofEncoding;
+pub use whir_config::public_inputs::PublicInput;
+pub use whir_config::{
+    BaseField, MerkleConfig, PowStrategy, ReedSolomonBackend, DOMAIN_SEPARATOR, F,
+};
 
 /// A serializable proof that can be sent to Solana.
 #[derive(Clone)]
 pub struct WhirProof {
-    /// The serialized proof bytes.
+    /// The serialized proof bytes, encoded with `encoding`.
     pub proof_bytes: Vec<u8>,
+    /// The on-wire encoding of `proof_bytes`.
+    pub encoding: ProofEncoding,
     /// The polynomial evaluation point.
     pub eval_point: Vec<u8>,
     /// The claimed evaluation value.
     pub eval_value: Vec<u8>,
     /// Number of variables in the polynomial.
     pub num_variables: usize,
+    /// Digest of the WHIR parameters, see `whir_config::params::params_digest`.
+    pub params_digest: [u8; 32],
 }
 
-/// Configuration for proof generation.
-#[derive(Clone)]
+/// Configuration for proof generation, loadable from TOML or JSON files, see [`config_file`].
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ProofConfig {
     pub num_variables: usize,
     pub security_level: usize,
     pub pow_bits: usize,
     pub starting_log_inv_rate: usize,
     pub folding_factor: usize,
+    /// Folding factor of the first round, `None` for `folding_factor`. The on-chain program only
+    /// verifies proofs folding by the same factor in all rounds.
+    pub first_round_folding_factor: Option<usize>,
+    pub encoding: ProofEncoding,
+    /// Domain separator label, must match the label of the proof account on-chain.
+    pub domain_label: String,
+    /// Application context the proof is bound to, see `whir_config::transcript::encode_context`.
+    pub context: Vec<u8>,
+    /// Number of polynomials committed at once. Single-polynomial proofs must use
+    /// `whir_config::ON_CHAIN_BATCH_SIZE`, the default, larger batches are proven with [`batch`].
+    pub batch_size: usize,
+    /// Compressed Merkle proofs are smaller, uncompressed ones cheaper to verify. Proofs for the
+    /// on-chain program must be compressed, the default.
+    pub merkle_proof_strategy: MerkleProofStrategy,
+    /// Deduplicating repeated queries makes proofs smaller. Proofs for the on-chain program must
+    /// be deduplicated, the default.
+    pub deduplication_strategy: DeduplicationStrategy,
+    /// Soundness assumption of the query counts. Proofs under other types than the default
+    /// `ConjectureList` are verified on-chain with `verify_v3`.
+    pub soundness_type: SoundnessType,
+    /// Number of threads committing and proving, `None` for rayon's global pool. Services cap the
+    /// CPU usage of each proof with it. Proofs don't depend on it.
+    pub num_threads: Option<usize>,
+    /// Reed-Solomon encoders used for committing, e.g. a hardware-accelerated NTT. Proofs don't
+    /// depend on them, the verifier always uses the default.
+    #[serde(skip)]
+    pub reed_solomon: ReedSolomonBackend,
 }
 
 impl Default for ProofConfig {
@@ -51,85 +100,277 @@ impl Default for ProofConfig {
             pow_bits: whir_config::POW_BITS,
             starting_log_inv_rate: whir_config::STARTING_LOG_INV_RATE,
             folding_factor: whir_config::FOLDING_FACTOR,
+            first_round_folding_factor: None,
+            encoding: ProofEncoding::Raw,
+            domain_label: DOMAIN_SEPARATOR.to_string(),
+            context: Vec::new(),
+            batch_size: whir_config::ON_CHAIN_BATCH_SIZE,
+            merkle_proof_strategy: MerkleProofStrategy::Compressed,
+            deduplication_strategy: DeduplicationStrategy::Enabled,
+            soundness_type: SoundnessType::ConjectureList,
+            num_threads: None,
+            reed_solomon: ReedSolomonBackend::default(),
+        }
+    }
+}
+
+impl ProofConfig {
+    /// Returns the protocol options of the WHIR parameters.
+    pub fn protocol_options(&self) -> ProtocolOptions {
+        ProtocolOptions {
+            batch_size: self.batch_size,
+            merkle_proof_strategy: self.merkle_proof_strategy,
+            deduplication_strategy: self.deduplication_strategy,
+            soundness_type: self.soundness_type,
+            first_round_folding_factor: self.first_round_folding_factor,
+        }
+    }
+
+    /// Returns the WHIR parameters in the versioned wire format the on-chain program takes, see
+    /// `whir_config::params`, or `None` if the program doesn't verify them, e.g. for batched
+    /// commitments.
+    pub fn versioned_params(&self) -> Option<VersionedParams> {
+        let verified_on_chain = self.batch_size == whir_config::ON_CHAIN_BATCH_SIZE
+            && matches!(self.merkle_proof_strategy, MerkleProofStrategy::Compressed)
+            && matches!(self.deduplication_strategy, DeduplicationStrategy::Enabled)
+            && self.first_round_folding_factor.is_none();
+        if !verified_on_chain {
+            return None;
         }
+        Some(VersionedParams::V1(ParamsV1 {
+            num_variables: self.num_variables as u32,
+            security_level: self.security_level as u32,
+            pow_bits: self.pow_bits as u32,
+            folding_factor: self.folding_factor as u32,
+            starting_log_inv_rate: self.starting_log_inv_rate as u32,
+            soundness_type: match self.soundness_type {
+                SoundnessType::ConjectureList => ParamsSoundness::ConjectureList,
+                SoundnessType::ProvableList => ParamsSoundness::ProvableList,
+                SoundnessType::UniqueDecoding => ParamsSoundness::UniqueDecoding,
+            },
+        }))
     }
 }
 
 pub fn create_whir_params(config: &ProofConfig) -> WhirParams {
-    whir_config::create_whir_params(
+    whir_config::create_whir_params_with(
         config.num_variables,
         config.security_level,
         config.pow_bits,
         config.folding_factor,
         config.starting_log_inv_rate,
+        config.protocol_options(),
+        config.reed_solomon.clone(),
     )
 }
 
 /// Create a test polynomial with coefficients in the base prime field
-pub fn create_test_polynomial(
-    num_variables: usize,
-) -> CoefficientList<<F as Field>::BasePrimeField> {
+pub fn create_test_polynomial(num_variables: usize) -> CoefficientList<BaseField> {
     let num_coeffs = 1 << num_variables;
-    CoefficientList::new(
-        (0..num_coeffs)
-            .map(<F as Field>::BasePrimeField::from)
-            .collect(),
-    )
+    CoefficientList::new((0..num_coeffs).map(BaseField::from).collect())
+}
+
+/// Generate a WHIR proof for PCS of a polynomial given by its evaluations over the boolean
+/// hypercube instead of its coefficients, see [`polynomial::polynomial_from_evaluations`].
+pub fn generate_pcs_proof_from_evaluations(
+    config: &ProofConfig,
+    evaluations: &[BaseField],
+    eval_point: &MultilinearPoint<F>,
+) -> anyhow::Result<WhirProof> {
+    let polynomial = polynomial::polynomial_from_evaluations(evaluations.to_vec())?;
+    generate_pcs_proof(config, &polynomial, eval_point)
 }
 
 /// Generate a WHIR proof for PCS (Polynomial Commitment Scheme)
 ///
 /// This generates a proof that the polynomial evaluates to a specific value at a given point.
+/// The polynomial is committed over the base prime field, which keeps the committed codeword
+/// small, and evaluated at a point of the extension field `F`.
 pub fn generate_pcs_proof(
     config: &ProofConfig,
-    polynomial: &CoefficientList<<F as Field>::BasePrimeField>,
+    polynomial: &CoefficientList<BaseField>,
     eval_point: &MultilinearPoint<F>,
 ) -> anyhow::Result<WhirProof> {
     let params = create_whir_params(config);
 
-    // Create domain separator
-    let domainsep = DomainSeparator::new(DOMAIN_SEPARATOR)
-        .commit_statement(&params)
-        .add_whir_proof(&params);
+    // Compute expected evaluation
+    let expected_value = polynomial.evaluate_at_extension(eval_point);
 
-    let mut prover_state: ProverState = domainsep.to_prover_state();
+    // Create statement with evaluation constraint
+    let statement = statement_from_constraints(
+        config.num_variables,
+        [(Weights::evaluation(eval_point.clone()), expected_value)],
+    );
+    let proof_bytes = generate_statement_proof(config, polynomial, &statement)?;
 
-    // Create commitment
-    let committer = CommitmentWriter::new(params.clone());
-    let witness = committer.commit(&mut prover_state, polynomial)?;
+    Ok(WhirProof {
+        proof_bytes,
+        encoding: config.encoding,
+        eval_point: serialize_eval_point(eval_point)?,
+        eval_value: serialize_field(&expected_value)?,
+        num_variables: config.num_variables,
+        params_digest: params_digest(&params),
+    })
+}
 
-    // Create statement with evaluation constraint
-    let mut statement = Statement::new(config.num_variables);
+/// A proof opening one commitment at several points, see [`generate_multi_opening_proof`].
+#[derive(Clone)]
+pub struct MultiOpeningProof {
+    /// The serialized proof bytes, encoded with `encoding`.
+    pub proof_bytes: Vec<u8>,
+    /// The on-wire encoding of `proof_bytes`.
+    pub encoding: ProofEncoding,
+    /// The serialized evaluation points, in the order of the constraints.
+    pub eval_points: Vec<Vec<u8>>,
+    /// The serialized evaluation values, one per point.
+    pub eval_values: Vec<Vec<u8>>,
+    /// Number of variables in the polynomial.
+    pub num_variables: usize,
+    /// Digest of the WHIR parameters, see `whir_config::params::params_digest`.
+    pub params_digest: [u8; 32],
+}
 
-    // Compute expected evaluation
-    let expected_value = polynomial.evaluate_at_extension(eval_point);
+/// Generate one WHIR proof opening the polynomial's commitment at all `eval_points`, sharing the
+/// commitment, the rounds and their proof of work between the evaluations. On-chain, the first
+/// point and value initialize the proof account and the others are added in order via
+/// `add_constraint`.
+pub fn generate_multi_opening_proof(
+    config: &ProofConfig,
+    polynomial: &CoefficientList<BaseField>,
+    eval_points: &[MultilinearPoint<F>],
+) -> anyhow::Result<MultiOpeningProof> {
+    anyhow::ensure!(!eval_points.is_empty(), "no evaluation points to open at");
+    let params = create_whir_params(config);
+    let eval_values: Vec<F> = eval_points
+        .iter()
+        .map(|point| polynomial.evaluate_at_extension(point))
+        .collect();
+    let statement = opening_statement(config.num_variables, eval_points, &eval_values);
+    let proof_bytes = generate_statement_proof(config, polynomial, &statement)?;
+
+    Ok(MultiOpeningProof {
+        proof_bytes,
+        encoding: config.encoding,
+        eval_points: eval_points
+            .iter()
+            .map(serialize_eval_point)
+            .collect::<anyhow::Result<_>>()?,
+        eval_values: eval_values
+            .iter()
+            .map(serialize_field)
+            .collect::<anyhow::Result<_>>()?,
+        num_variables: config.num_variables,
+        params_digest: params_digest(&params),
+    })
+}
+
+/// Verify a proof of [`generate_multi_opening_proof`] against the evaluation points and values.
+pub fn verify_multi_opening_proof(
+    config: &ProofConfig,
+    proof: &MultiOpeningProof,
+    eval_points: &[MultilinearPoint<F>],
+    eval_values: &[F],
+) -> anyhow::Result<()> {
+    anyhow::ensure!(
+        !eval_points.is_empty() && eval_points.len() == eval_values.len(),
+        "expected one value per evaluation point, got {} points and {} values",
+        eval_points.len(),
+        eval_values.len()
+    );
+    let statement = opening_statement(config.num_variables, eval_points, eval_values);
+    verify_statement_proof(config, &proof.proof_bytes, proof.encoding, &statement)
+}
 
-    let weights = Weights::evaluation(eval_point.clone());
-    statement.add_constraint(weights, expected_value);
+fn opening_statement(
+    num_variables: usize,
+    eval_points: &[MultilinearPoint<F>],
+    eval_values: &[F],
+) -> Statement<F> {
+    statement_from_constraints(
+        num_variables,
+        eval_points
+            .iter()
+            .zip(eval_values)
+            .map(|(point, value)| (Weights::evaluation(point.clone()), *value)),
+    )
+}
 
-    // Generate proof
-    let prover = Prover::new(params.clone());
-    prover.prove(&mut prover_state, statement.clone(), witness)?;
+pub(crate) fn serialize_eval_point(eval_point: &MultilinearPoint<F>) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    for coordinate in eval_point.0.iter() {
+        coordinate.serialize_compressed(&mut bytes)?;
+    }
+    Ok(bytes)
+}
 
-    // Serialize the proof
-    let proof_bytes = prover_state.narg_string().to_vec();
+pub(crate) fn serialize_field(value: &F) -> anyhow::Result<Vec<u8>> {
+    let mut bytes = Vec::new();
+    value.serialize_compressed(&mut bytes)?;
+    Ok(bytes)
+}
 
-    // Serialize eval point
-    let mut eval_point_bytes = Vec::new();
-    for p in eval_point.0.iter() {
-        p.serialize_compressed(&mut eval_point_bytes)?;
+/// Builds a statement of `(weights, value)` constraints on a polynomial of `num_variables`
+/// variables, e.g. evaluations at several points or linear combinations of coefficients.
+pub fn statement_from_constraints(
+    num_variables: usize,
+    constraints: impl IntoIterator<Item = (Weights<F>, F)>,
+) -> Statement<F> {
+    let mut statement = Statement::new(num_variables);
+    for (weights, value) in constraints {
+        statement.add_constraint(weights, value);
     }
+    statement
+}
 
-    // Serialize evaluation value
-    let mut eval_value_bytes = Vec::new();
-    expected_value.serialize_compressed(&mut eval_value_bytes)?;
+/// Returns the constraint of the linear claim `sum_x weights(x) * polynomial(x)` over the boolean
+/// hypercube, e.g. a random linear combination of evaluations or a claim left by a sumcheck.
+/// `weights` are given by their evaluations over the hypercube, in the order of
+/// [`polynomial::polynomial_evaluations`]. The on-chain program only verifies evaluation
+/// constraints, proofs with linear constraints are verified natively.
+pub fn linear_constraint(
+    polynomial: &CoefficientList<BaseField>,
+    weights: Vec<F>,
+) -> anyhow::Result<(Weights<F>, F)> {
+    let evaluations = polynomial::polynomial_evaluations(polynomial);
+    anyhow::ensure!(
+        weights.len() == evaluations.len(),
+        "linear weights need {} evaluations, got {}",
+        evaluations.len(),
+        weights.len()
+    );
+    let value = weights
+        .iter()
+        .zip(evaluations)
+        .map(|(weight, evaluation)| *weight * F::from_base_prime_field(evaluation))
+        .sum();
+    Ok((Weights::linear(EvaluationsList::new(weights)), value))
+}
 
-    Ok(WhirProof {
-        proof_bytes,
-        eval_point: eval_point_bytes,
-        eval_value: eval_value_bytes,
-        num_variables: config.num_variables,
-    })
+/// Generate a WHIR proof of a caller-constructed statement about the polynomial, all of whose
+/// constraints are proven at once. Returns the proof bytes, encoded with `config.encoding`.
+///
+/// The on-chain program verifies statements of evaluation constraints: those of a shared
+/// statement account, followed by the proof account's own in the order they were added.
+pub fn generate_statement_proof(
+    config: &ProofConfig,
+    polynomial: &CoefficientList<BaseField>,
+    statement: &Statement<F>,
+) -> anyhow::Result<Vec<u8>> {
+    let committer = Committer::new(config)?;
+    let (_, witness) = committer.commit(polynomial)?;
+    committer.open(witness, statement)
+}
+
+/// Generate a WHIR proof opening the polynomial at the evaluation point derived from typed public
+/// inputs, see `whir_config::public_inputs`. The on-chain verifier derives the same point in
+/// `add_public_inputs_constraint`.
+pub fn generate_public_inputs_proof(
+    config: &ProofConfig,
+    polynomial: &CoefficientList<BaseField>,
+    public_inputs: &[PublicInput],
+) -> anyhow::Result<WhirProof> {
+    let eval_point = public_inputs_eval_point(public_inputs, config.num_variables);
+    generate_pcs_proof(config, polynomial, &eval_point)
 }
 
 /// Verify a proof.
@@ -138,27 +379,39 @@ pub fn verify_proof(
     proof: &WhirProof,
     eval_point: &MultilinearPoint<F>,
     eval_value: F,
+) -> anyhow::Result<()> {
+    let statement = statement_from_constraints(
+        config.num_variables,
+        [(Weights::evaluation(eval_point.clone()), eval_value)],
+    );
+    verify_statement_proof(config, &proof.proof_bytes, proof.encoding, &statement)
+}
+
+/// Verify a proof of a statement, see [`generate_statement_proof`].
+pub fn verify_statement_proof(
+    config: &ProofConfig,
+    proof_bytes: &[u8],
+    encoding: ProofEncoding,
+    statement: &Statement<F>,
 ) -> anyhow::Result<()> {
     let params = create_whir_params(config);
 
-    let domainsep = DomainSeparator::new(DOMAIN_SEPARATOR)
-        .commit_statement(&params)
-        .add_whir_proof(&params);
+    let domainsep = transcript::domain_separator(&params, &config.domain_label, &config.context);
 
     // Reconstruct verifier state from proof.
-    let mut verifier_state: VerifierState = domainsep.to_verifier_state(&proof.proof_bytes);
+    let proof_bytes = encoding
+        .decode(proof_bytes, usize::MAX)
+        .map_err(|e| anyhow::anyhow!("failed to decode proof bytes: {e:?}"))?;
+    let mut verifier_state: TranscriptVerifierState = domainsep.to_verifier_state(&proof_bytes);
+    transcript::bind_context(&mut verifier_state, &config.context)?;
 
     // Parse commitment.
     let commitment_reader = CommitmentReader::new(&params);
     let parsed_commitment = commitment_reader.parse_commitment(&mut verifier_state)?;
 
-    // Create statement.
-    let mut statement = Statement::new(config.num_variables);
-    statement.add_constraint(Weights::evaluation(eval_point.clone()), eval_value);
-
     // Verify.
     let verifier = Verifier::new(&params);
-    verifier.verify(&mut verifier_state, parsed_commitment, statement)?;
+    verifier.verify(&mut verifier_state, parsed_commitment, statement.clone())?;
 
     Ok(())
 }
@@ -167,15 +420,13 @@ pub fn verify_proof(
 mod tests {
     use super::*;
 
+    fn test_config() -> ProofConfig {
+        ProofConfigBuilder::dev().build().unwrap()
+    }
+
     #[test]
     fn test_proof_generation_and_verification() -> anyhow::Result<()> {
-        let config = ProofConfig {
-            num_variables: 6, // Small for testing
-            security_level: 32,
-            pow_bits: 5,
-            starting_log_inv_rate: 1,
-            folding_factor: 2,
-        };
+        let config = test_config();
 
         // Create test polynomial.
         let poly = create_test_polynomial(config.num_variables);
@@ -201,4 +452,424 @@ mod tests {
 
         Ok(())
     }
+
+    #[test]
+    fn test_statement_with_multiple_constraints() -> anyhow::Result<()> {
+        let config = test_config();
+        let poly = create_test_polynomial(config.num_variables);
+        let points: Vec<_> = (0..3)
+            .map(|k| {
+                MultilinearPoint(
+                    (0..config.num_variables)
+                        .map(|i| F::from((i + k + 1) as u64))
+                        .collect(),
+                )
+            })
+            .collect();
+        let constraints = |poly: &CoefficientList<BaseField>| {
+            points
+                .iter()
+                .map(|point| {
+                    (
+                        Weights::evaluation(point.clone()),
+                        poly.evaluate_at_extension(point),
+                    )
+                })
+                .collect::<Vec<_>>()
+        };
+        let statement = statement_from_constraints(config.num_variables, constraints(&poly));
+
+        let proof_bytes = generate_statement_proof(&config, &poly, &statement)?;
+        verify_statement_proof(&config, &proof_bytes, config.encoding, &statement)?;
+
+        // A wrong value of any constraint fails verification.
+        let mut wrong = constraints(&poly);
+        wrong[2].1 += F::from(1u64);
+        let wrong_statement = statement_from_constraints(config.num_variables, wrong);
+        assert!(
+            verify_statement_proof(&config, &proof_bytes, config.encoding, &wrong_statement)
+                .is_err()
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_linear_constraint() -> anyhow::Result<()> {
+        let config = test_config();
+        let poly = create_test_polynomial(config.num_variables);
+        let weights: Vec<F> = (0..1u64 << config.num_variables)
+            .map(|i| F::from(3 * i + 1))
+            .collect();
+        let eval_point = MultilinearPoint(vec![F::from(5u64); config.num_variables]);
+        let (linear_weights, linear_value) = linear_constraint(&poly, weights.clone())?;
+        let statement = statement_from_constraints(
+            config.num_variables,
+            [
+                (
+                    Weights::evaluation(eval_point.clone()),
+                    poly.evaluate_at_extension(&eval_point),
+                ),
+                (linear_weights, linear_value),
+            ],
+        );
+
+        let proof_bytes = generate_statement_proof(&config, &poly, &statement)?;
+        verify_statement_proof(&config, &proof_bytes, config.encoding, &statement)?;
+
+        let (linear_weights, _) = linear_constraint(&poly, weights)?;
+        let wrong_statement = statement_from_constraints(
+            config.num_variables,
+            [(linear_weights, linear_value + F::from(1u64))],
+        );
+        assert!(
+            verify_statement_proof(&config, &proof_bytes, config.encoding, &wrong_statement)
+                .is_err()
+        );
+        assert!(linear_constraint(&poly, vec![F::from(1u64)]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_multi_opening_proof() -> anyhow::Result<()> {
+        let config = test_config();
+        let poly = create_test_polynomial(config.num_variables);
+        let points: Vec<_> = (0..4u64)
+            .map(|k| MultilinearPoint(vec![F::from(k + 7); config.num_variables]))
+            .collect();
+        let values: Vec<F> = points
+            .iter()
+            .map(|point| poly.evaluate_at_extension(point))
+            .collect();
+
+        let proof = generate_multi_opening_proof(&config, &poly, &points)?;
+        assert_eq!(proof.eval_points.len(), points.len());
+        verify_multi_opening_proof(&config, &proof, &points, &values)?;
+
+        let mut wrong_values = values.clone();
+        wrong_values[1] += F::from(1u64);
+        assert!(verify_multi_opening_proof(&config, &proof, &points, &wrong_values).is_err());
+        assert!(verify_multi_opening_proof(&config, &proof, &points, &values[1..]).is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_proof_from_evaluations() -> anyhow::Result<()> {
+        let config = test_config();
+        let evaluations: Vec<BaseField> = (0..1u64 << config.num_variables)
+            .map(|i| BaseField::from(i * i + 3))
+            .collect();
+        let eval_point = MultilinearPoint(
+            (0..config.num_variables)
+                .map(|i| F::from((i + 2) as u64))
+                .collect(),
+        );
+
+        let proof = generate_pcs_proof_from_evaluations(&config, &evaluations, &eval_point)?;
+        let expected_value = polynomial::polynomial_from_evaluations(evaluations)?
+            .evaluate_at_extension(&eval_point);
+        verify_proof(&config, &proof, &eval_point, expected_value)?;
+        Ok(())
+    }
+
+    #[test]
+    fn test_injected_reed_solomon_backend() -> anyhow::Result<()> {
+        let backend = ReedSolomonBackend {
+            extension: std::sync::Arc::new(whir_common::ntt::RSDefault),
+            base: std::sync::Arc::new(whir_common::ntt::RSDefault),
+        };
+        let config = ProofConfigBuilder::dev()
+            .reed_solomon(backend.clone())
+            .build()
+            .unwrap();
+        assert!(std::sync::Arc::ptr_eq(
+            &config.reed_solomon.extension,
+            &backend.extension
+        ));
+        assert!(std::sync::Arc::ptr_eq(
+            &config.reed_solomon.base,
+            &backend.base
+        ));
+
+        // Only committing uses the encoders, the proof verifies with the default ones.
+        let poly = create_test_polynomial(config.num_variables);
+        let eval_point = MultilinearPoint(vec![F::from(3u64); config.num_variables]);
+        let expected_value = poly.evaluate_at_extension(&eval_point);
+        let proof = generate_pcs_proof(&config, &poly, &eval_point)?;
+        verify_proof(&test_config(), &proof, &eval_point, expected_value)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_prover_and_program_params_match() {
+        // The program builds its parameters from the instruction arguments with
+        // `whir_config::create_whir_params`, with the same compile-time Merkle hash parameters
+        // (`whir_config::merkle::LEAF_HASH_PARAMS`, `COMPRESS_PARAMS`) instead of an RNG seed.
+        let config = ProofConfig::default();
+        let prover_params = create_whir_params(&config);
+        let program_params = whir_config::create_whir_params(
+            config.num_variables,
+            config.security_level,
+            config.pow_bits,
+            config.folding_factor,
+            config.starting_log_inv_rate,
+        );
+        assert_eq!(
+            transcript::transcript_pattern(&prover_params, &config.domain_label, 0),
+            transcript::transcript_pattern(&program_params, &config.domain_label, 0)
+        );
+        assert_eq!(
+            whir_config::commitment_size_bytes(&prover_params),
+            whir_config::commitment_size_bytes(&program_params)
+        );
+        assert_eq!(
+            whir_config::cost::estimate_proof_size(&prover_params),
+            whir_config::cost::estimate_proof_size(&program_params)
+        );
+        assert_eq!(
+            params_digest(&prover_params),
+            params_digest(&program_params)
+        );
+    }
+
+    #[test]
+    fn test_default_config_matches_fixed_params() {
+        // `fixed-params` builds of the program only verify the `whir_config` defaults, which the
+        // default configuration must keep proving.
+        assert_eq!(
+            ProofConfig::default().versioned_params(),
+            Some(VersionedParams::V1(ParamsV1 {
+                num_variables: whir_config::NUM_VARIABLES as u32,
+                security_level: whir_config::SECURITY_LEVEL_BITS as u32,
+                pow_bits: whir_config::POW_BITS as u32,
+                folding_factor: whir_config::FOLDING_FACTOR as u32,
+                starting_log_inv_rate: whir_config::STARTING_LOG_INV_RATE as u32,
+                soundness_type: ParamsSoundness::ConjectureList,
+            }))
+        );
+    }
+
+    #[test]
+    fn test_versioned_params_round_trip() {
+        let config = ProofConfig {
+            soundness_type: SoundnessType::UniqueDecoding,
+            ..ProofConfig::default()
+        };
+        let params = config.versioned_params().unwrap();
+        let decoded = VersionedParams::decode(&params.encode()).unwrap();
+        assert_eq!(
+            params_digest(&decoded.validate().unwrap()),
+            params_digest(&create_whir_params(&config))
+        );
+
+        let batched = ProofConfig {
+            batch_size: 2,
+            ..ProofConfig::default()
+        };
+        assert!(batched.versioned_params().is_none());
+    }
+
+    #[test]
+    fn test_proof_size_estimate_is_close() -> anyhow::Result<()> {
+        let config = ProofConfig::default();
+        let poly = create_test_polynomial(config.num_variables);
+        let eval_point = MultilinearPoint(vec![F::from(3u64); config.num_variables]);
+        let proof = generate_pcs_proof(&config, &poly, &eval_point)?;
+
+        // Random queries make the exact size vary, the estimate is meant for sizing accounts.
+        let estimate = whir_config::cost::estimate_proof_size(&create_whir_params(&config));
+        let actual = proof.proof_bytes.len();
+        println!("Proof size: {actual} bytes, estimated {estimate} bytes");
+        assert!(estimate.abs_diff(actual) * 2 <= actual);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_proof_without_grinding_verifies() -> anyhow::Result<()> {
+        let config = ProofConfig {
+            pow_bits: whir_config::NO_POW_BITS,
+            ..test_config()
+        };
+        let params = create_whir_params(&config);
+        assert!(params.check_pow_bits());
+        assert!(whir_config::is_pow_free(&params));
+
+        let poly = create_test_polynomial(config.num_variables);
+        let eval_point = MultilinearPoint(vec![F::from(3u64); config.num_variables]);
+        let expected_value = poly.evaluate_at_extension(&eval_point);
+
+        let proof = generate_pcs_proof(&config, &poly, &eval_point)?;
+        verify_proof(&config, &proof, &eval_point, expected_value)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_proof_with_grinding_verifies() -> anyhow::Result<()> {
+        let grinding = if cfg!(feature = "pow-keccak") {
+            "KeccakPoW"
+        } else {
+            "Blake3PoW"
+        };
+        assert!(std::any::type_name::<PowStrategy>().ends_with(grinding));
+
+        let config = ProofConfig::default();
+        assert!(whir_config::pow::required_pow_bits(&create_whir_params(&config)) > 0);
+        let poly = create_test_polynomial(config.num_variables);
+        let eval_point = MultilinearPoint(vec![F::from(3u64); config.num_variables]);
+        let expected_value = poly.evaluate_at_extension(&eval_point);
+
+        let proof = generate_pcs_proof(&config, &poly, &eval_point)?;
+        verify_proof(&config, &proof, &eval_point, expected_value)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_uncompressed_proof_verifies() -> anyhow::Result<()> {
+        let config = ProofConfig {
+            merkle_proof_strategy: MerkleProofStrategy::Uncompressed,
+            deduplication_strategy: DeduplicationStrategy::Disabled,
+            ..test_config()
+        };
+        let poly = create_test_polynomial(config.num_variables);
+        let eval_point = MultilinearPoint(vec![F::from(3u64); config.num_variables]);
+        let expected_value = poly.evaluate_at_extension(&eval_point);
+
+        let proof = generate_pcs_proof(&config, &poly, &eval_point)?;
+        let compressed = generate_pcs_proof(&test_config(), &poly, &eval_point)?;
+        assert!(proof.proof_bytes.len() > compressed.proof_bytes.len());
+
+        verify_proof(&config, &proof, &eval_point, expected_value)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_proofs_verify_under_each_soundness_type() -> anyhow::Result<()> {
+        let num_variables = test_config().num_variables;
+        let poly = create_test_polynomial(num_variables);
+        let eval_point = MultilinearPoint(vec![F::from(3u64); num_variables]);
+        let expected_value = poly.evaluate_at_extension(&eval_point);
+
+        for soundness_type in [
+            SoundnessType::ConjectureList,
+            SoundnessType::ProvableList,
+            SoundnessType::UniqueDecoding,
+        ] {
+            let config = ProofConfig {
+                soundness_type,
+                ..test_config()
+            };
+            let proof = generate_pcs_proof(&config, &poly, &eval_point)?;
+            verify_proof(&config, &proof, &eval_point, expected_value)?;
+        }
+
+        // The query counts differ, so proofs only verify under their own soundness type.
+        let config = ProofConfig {
+            soundness_type: SoundnessType::UniqueDecoding,
+            ..test_config()
+        };
+        let proof = generate_pcs_proof(&config, &poly, &eval_point)?;
+        assert!(verify_proof(&test_config(), &proof, &eval_point, expected_value).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_first_round_folding_factor_proof_verifies() -> anyhow::Result<()> {
+        let config = ProofConfig {
+            num_variables: 8,
+            first_round_folding_factor: Some(4),
+            ..test_config()
+        };
+        let poly = create_test_polynomial(config.num_variables);
+        let eval_point = MultilinearPoint(vec![F::from(3u64); config.num_variables]);
+        let expected_value = poly.evaluate_at_extension(&eval_point);
+
+        let proof = generate_pcs_proof(&config, &poly, &eval_point)?;
+        verify_proof(&config, &proof, &eval_point, expected_value)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_packed_proof_verifies() -> anyhow::Result<()> {
+        let config = ProofConfig {
+            encoding: ProofEncoding::ZeroRunLength,
+            ..test_config()
+        };
+        let poly = create_test_polynomial(config.num_variables);
+        let eval_point = MultilinearPoint(vec![F::from(3u64); config.num_variables]);
+        let expected_value = poly.evaluate_at_extension(&eval_point);
+
+        let packed = generate_pcs_proof(&config, &poly, &eval_point)?;
+        println!("Packed proof size: {} bytes", packed.proof_bytes.len());
+
+        verify_proof(&config, &packed, &eval_point, expected_value)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_proof_is_bound_to_context() -> anyhow::Result<()> {
+        let config = ProofConfig {
+            context: transcript::encode_context(Some([7; 32]), Some(42), b"order-1"),
+            ..test_config()
+        };
+        let poly = create_test_polynomial(config.num_variables);
+        let eval_point = MultilinearPoint(vec![F::from(3u64); config.num_variables]);
+        let expected_value = poly.evaluate_at_extension(&eval_point);
+
+        let proof = generate_pcs_proof(&config, &poly, &eval_point)?;
+        verify_proof(&config, &proof, &eval_point, expected_value)?;
+
+        let other_context = ProofConfig {
+            context: transcript::encode_context(Some([7; 32]), Some(43), b"order-1"),
+            ..config.clone()
+        };
+        assert!(verify_proof(&other_context, &proof, &eval_point, expected_value).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_proof_is_bound_to_domain_label() -> anyhow::Result<()> {
+        let config = ProofConfig {
+            domain_label: "my-protocol".to_string(),
+            ..test_config()
+        };
+        let poly = create_test_polynomial(config.num_variables);
+        let eval_point = MultilinearPoint(vec![F::from(3u64); config.num_variables]);
+        let expected_value = poly.evaluate_at_extension(&eval_point);
+
+        let proof = generate_pcs_proof(&config, &poly, &eval_point)?;
+        verify_proof(&config, &proof, &eval_point, expected_value)?;
+        assert!(verify_proof(&test_config(), &proof, &eval_point, expected_value).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_public_inputs_proof_verifies() -> anyhow::Result<()> {
+        let config = test_config();
+        let poly = create_test_polynomial(config.num_variables);
+        let public_inputs = [PublicInput::U64(42), PublicInput::Hash([1; 32])];
+
+        let proof = generate_public_inputs_proof(&config, &poly, &public_inputs)?;
+        assert_eq!(
+            proof.eval_point,
+            whir_config::public_inputs::public_inputs_eval_point_bytes(
+                &public_inputs,
+                config.num_variables
+            )
+        );
+
+        let eval_point = public_inputs_eval_point(&public_inputs, config.num_variables);
+        let expected_value = poly.evaluate_at_extension(&eval_point);
+        verify_proof(&config, &proof, &eval_point, expected_value)?;
+
+        Ok(())
+    }
 }
@@ -5,14 +5,19 @@
 use std::fs;
 use whir_common::poly_utils::multilinear::MultilinearPoint;
 use whir_proof_generator::{
-    create_test_polynomial, generate_pcs_proof, verify_proof, ProofConfig, F,
+    create_test_polynomial, generate_pcs_proof, generate_public_inputs_proof,
+    polynomial::polynomial_from_file, verify_proof, ProofConfig, PublicInput, SoundnessType, F,
 };
 
 fn main() -> anyhow::Result<()> {
     println!("WHIR Proof Generator for Solana");
     println!("================================");
 
-    let config = ProofConfig::default();
+    // An optional TOML or JSON config file replaces the default configuration.
+    let config = match std::env::args().nth(1) {
+        Some(path) => ProofConfig::from_file(path)?,
+        None => ProofConfig::default(),
+    };
 
     println!("Configuration:");
     println!("  - Number of variables: {}", config.num_variables);
@@ -23,10 +28,27 @@ fn main() -> anyhow::Result<()> {
         config.starting_log_inv_rate
     );
     println!("  - Folding factor: {}", config.folding_factor);
+    println!("  - Proof encoding: {:?}", config.encoding);
     println!();
 
-    println!("Creating test polynomial...");
-    let polynomial = create_test_polynomial(config.num_variables);
+    // An optional file of canonically encoded coefficients replaces the test polynomial.
+    let polynomial = match std::env::args().nth(2) {
+        Some(path) => {
+            println!("Reading polynomial from {path}...");
+            let polynomial = polynomial_from_file(path)?;
+            anyhow::ensure!(
+                polynomial.num_variables() == config.num_variables,
+                "the polynomial has {} variables, the configuration {}",
+                polynomial.num_variables(),
+                config.num_variables
+            );
+            polynomial
+        }
+        None => {
+            println!("Creating test polynomial...");
+            create_test_polynomial(config.num_variables)
+        }
+    };
     println!(
         "  - Polynomial has {} coefficients",
         1 << config.num_variables
@@ -69,9 +91,12 @@ fn main() -> anyhow::Result<()> {
 
     let metadata = serde_json::json!({
         "num_variables": proof.num_variables,
+        "encoding": proof.encoding as u8,
         "proof_size": proof.proof_bytes.len(),
         "eval_point_size": proof.eval_point.len(),
         "eval_value_size": proof.eval_value.len(),
+        "params_digest": proof.params_digest,
+        "versioned_params": config.versioned_params().map(|params| params.encode()),
         "config": {
             "security_level": config.security_level,
             "pow_bits": config.pow_bits,
@@ -81,5 +106,41 @@ fn main() -> anyhow::Result<()> {
     });
     fs::write("proof/metadata.json", metadata.to_string()).expect("Failed to write metadata.json");
     println!("Saved: proof/metadata.json");
+
+    // Proof of the first transition of the example rollup, from the zero state root to
+    // `[1; 32]`, see `rollup_example::transition_inputs`.
+    println!("Generating example rollup proof...");
+    let transition_inputs = [
+        PublicInput::Hash([0; 32]),
+        PublicInput::Hash([1; 32]),
+        PublicInput::U64(1),
+    ];
+    let rollup_proof = generate_public_inputs_proof(&config, &polynomial, &transition_inputs)?;
+
+    fs::write("proof/rollup-proof.bin", &rollup_proof.proof_bytes)
+        .expect("Failed to write rollup-proof.bin");
+    println!("Saved: proof/rollup-proof.bin");
+
+    fs::write("proof/rollup-eval-value.bin", &rollup_proof.eval_value)
+        .expect("Failed to write rollup-eval-value.bin");
+    println!("Saved: proof/rollup-eval-value.bin");
+
+    // Proofs of the same evaluation under the other soundness types, verified with `verify_v3`.
+    for (soundness_type, name) in [
+        (SoundnessType::ProvableList, "provable-list"),
+        (SoundnessType::UniqueDecoding, "unique-decoding"),
+    ] {
+        println!("Generating {name} proof...");
+        let config = ProofConfig {
+            soundness_type,
+            ..config.clone()
+        };
+        let proof = generate_pcs_proof(&config, &polynomial, &eval_point)?;
+        verify_proof(&config, &proof, &eval_point, expected_value)?;
+
+        let path = format!("proof/{name}-proof.bin");
+        fs::write(&path, &proof.proof_bytes).expect("Failed to write soundness type proof");
+        println!("Saved: {path}");
+    }
     Ok(())
 }
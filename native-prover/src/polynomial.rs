@@ -0,0 +1,235 @@
+//! Building the polynomials to prove from application data.
+//!
+//! A polynomial in `n` variables is given by its `2^n` coefficients over the base field, in the
+//! order of [`CoefficientList`]. Bytes hold the coefficients' canonical little-endian encodings,
+//! `whir_config::field::BASE_FIELD_SIZE_BYTES` each, as decoded by the on-chain program. Values
+//! at or above the field's modulus are rejected instead of being silently reduced, so the proven
+//! polynomial is exactly the supplied data.
+//!
+//! Pipelines producing the polynomial's evaluations over the boolean hypercube instead pass them
+//! to [`polynomial_from_evaluations`], which converts them to coefficients. The evaluation at
+//! index `i` is at the point whose first coordinate is the most significant bit of `i`.
+//!
+//! Readers and files are decoded in chunks of [`READ_CHUNK_BYTES`], so reading a polynomial only
+//! needs memory for its coefficients, not a second copy of its encoding. Committing still needs
+//! the whole coefficient vector in memory.
+
+use std::{
+    fs::File,
+    io::{ErrorKind, Read},
+    path::Path,
+};
+
+use anyhow::{ensure, Context};
+use whir_common::poly_utils::coeffs::CoefficientList;
+use whir_config::field::{decode_base_field, BASE_FIELD_SIZE_BYTES};
+
+use crate::BaseField;
+
+/// Size of the chunks readers and files are decoded in, a whole number of coefficients.
+pub const READ_CHUNK_BYTES: usize = (1 << 20) / BASE_FIELD_SIZE_BYTES * BASE_FIELD_SIZE_BYTES;
+
+/// Builds a polynomial from the canonical encodings of its coefficients, see the module docs.
+pub fn polynomial_from_bytes(bytes: &[u8]) -> anyhow::Result<CoefficientList<BaseField>> {
+    check_whole_coefficients(bytes.len())?;
+    let mut coeffs = Vec::with_capacity(bytes.len() / BASE_FIELD_SIZE_BYTES);
+    decode_coefficients(bytes, &mut coeffs)?;
+    polynomial_from_coeffs(coeffs)
+}
+
+fn check_whole_coefficients(len: usize) -> anyhow::Result<()> {
+    ensure!(
+        len % BASE_FIELD_SIZE_BYTES == 0,
+        "polynomial bytes must hold {BASE_FIELD_SIZE_BYTES}-byte coefficients, got {len} bytes"
+    );
+    Ok(())
+}
+
+/// Decodes whole coefficients, appending them to `coeffs`.
+fn decode_coefficients(bytes: &[u8], coeffs: &mut Vec<BaseField>) -> anyhow::Result<()> {
+    for chunk in bytes.chunks_exact(BASE_FIELD_SIZE_BYTES) {
+        let index = coeffs.len();
+        let coeff = decode_base_field(chunk.try_into().expect("coefficient-sized chunk"))
+            .with_context(|| format!("coefficient {index} is not a canonical field element"))?;
+        coeffs.push(coeff);
+    }
+    Ok(())
+}
+
+/// Builds a polynomial from coefficients given as integers, rejecting values at or above the
+/// field's modulus.
+pub fn polynomial_from_u64s(values: &[u64]) -> anyhow::Result<CoefficientList<BaseField>> {
+    let coeffs = values
+        .iter()
+        .enumerate()
+        .map(|(index, value)| {
+            let mut bytes = [0u8; BASE_FIELD_SIZE_BYTES];
+            bytes[..8].copy_from_slice(&value.to_le_bytes());
+            decode_base_field(bytes)
+                .with_context(|| format!("coefficient {index} ({value}) exceeds the field"))
+        })
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    polynomial_from_coeffs(coeffs)
+}
+
+/// Reads the canonical encodings of a polynomial's coefficients, see [`polynomial_from_bytes`].
+pub fn polynomial_from_reader(reader: impl Read) -> anyhow::Result<CoefficientList<BaseField>> {
+    read_coefficients(reader, 0)
+}
+
+/// Reads a polynomial from a file of its coefficients' canonical encodings, see
+/// [`polynomial_from_bytes`].
+pub fn polynomial_from_file(path: impl AsRef<Path>) -> anyhow::Result<CoefficientList<BaseField>> {
+    let path = path.as_ref();
+    let file = File::open(path).with_context(|| format!("failed to open {}", path.display()))?;
+    let len = file.metadata()?.len() as usize;
+    check_whole_coefficients(len)
+        .and_then(|()| read_coefficients(file, len / BASE_FIELD_SIZE_BYTES))
+        .with_context(|| format!("invalid polynomial {}", path.display()))
+}
+
+/// Decodes the coefficients of a reader in chunks of [`READ_CHUNK_BYTES`].
+fn read_coefficients(
+    mut reader: impl Read,
+    capacity: usize,
+) -> anyhow::Result<CoefficientList<BaseField>> {
+    let mut coeffs = Vec::with_capacity(capacity);
+    let mut chunk = vec![0u8; READ_CHUNK_BYTES];
+    let mut filled = 0;
+    loop {
+        let read = match reader.read(&mut chunk[filled..]) {
+            Ok(read) => read,
+            Err(error) if error.kind() == ErrorKind::Interrupted => continue,
+            Err(error) => return Err(error).context("failed to read polynomial"),
+        };
+        filled += read;
+        // Decodes the whole coefficients read so far, keeping a partial one for the next read.
+        let whole = filled - filled % BASE_FIELD_SIZE_BYTES;
+        if read == 0 || whole == chunk.len() {
+            decode_coefficients(&chunk[..whole], &mut coeffs)?;
+            chunk.copy_within(whole..filled, 0);
+            filled -= whole;
+        }
+        if read == 0 {
+            break;
+        }
+    }
+    check_whole_coefficients(filled)?;
+    polynomial_from_coeffs(coeffs)
+}
+
+/// Builds a polynomial from its evaluations over the boolean hypercube, see the module docs.
+pub fn polynomial_from_evaluations(
+    mut evaluations: Vec<BaseField>,
+) -> anyhow::Result<CoefficientList<BaseField>> {
+    ensure!(
+        evaluations.len().is_power_of_two(),
+        "a multilinear polynomial has a power of two evaluations, got {}",
+        evaluations.len()
+    );
+    inverse_wavelet_transform(&mut evaluations);
+    polynomial_from_coeffs(evaluations)
+}
+
+/// Returns the polynomial's evaluations over the boolean hypercube, the inverse of
+/// [`polynomial_from_evaluations`].
+pub fn polynomial_evaluations(polynomial: &CoefficientList<BaseField>) -> Vec<BaseField> {
+    let mut values = polynomial.coeffs().to_vec();
+    let mut half = 1;
+    while half < values.len() {
+        for chunk in values.chunks_exact_mut(2 * half) {
+            let (low, high) = chunk.split_at_mut(half);
+            for (high, low) in high.iter_mut().zip(low.iter()) {
+                *high += *low;
+            }
+        }
+        half *= 2;
+    }
+    values
+}
+
+/// Converts evaluations over the hypercube to coefficients in place, inverting the transform of
+/// every variable: the coefficient of a monomial is its evaluation minus those of its divisors'.
+fn inverse_wavelet_transform(values: &mut [BaseField]) {
+    let mut half = 1;
+    while half < values.len() {
+        for chunk in values.chunks_exact_mut(2 * half) {
+            let (low, high) = chunk.split_at_mut(half);
+            for (high, low) in high.iter_mut().zip(low.iter()) {
+                *high -= *low;
+            }
+        }
+        half *= 2;
+    }
+}
+
+fn polynomial_from_coeffs(coeffs: Vec<BaseField>) -> anyhow::Result<CoefficientList<BaseField>> {
+    ensure!(
+        coeffs.len().is_power_of_two(),
+        "a multilinear polynomial has a power of two coefficients, got {}",
+        coeffs.len()
+    );
+    Ok(CoefficientList::new(coeffs))
+}
+
+#[cfg(test)]
+mod tests {
+    use ark_ff::{BigInteger, PrimeField};
+    use whir_common::poly_utils::multilinear::MultilinearPoint;
+
+    use super::*;
+    use crate::create_test_polynomial;
+
+    #[test]
+    fn builds_polynomials_from_bytes_and_integers() -> anyhow::Result<()> {
+        let expected = create_test_polynomial(3);
+        let bytes: Vec<u8> = expected
+            .coeffs()
+            .iter()
+            .flat_map(|coeff| coeff.into_bigint().to_bytes_le())
+            .collect();
+        assert_eq!(polynomial_from_bytes(&bytes)?.coeffs(), expected.coeffs());
+        assert_eq!(
+            polynomial_from_reader(&bytes[..])?.coeffs(),
+            expected.coeffs()
+        );
+        // Readers returning a few bytes at a time split coefficients across reads.
+        let trickle = std::io::Read::chain(&bytes[..3], &bytes[3..]);
+        assert_eq!(polynomial_from_reader(trickle)?.coeffs(), expected.coeffs());
+        assert_eq!(
+            polynomial_from_u64s(&(0..8).collect::<Vec<_>>())?.coeffs(),
+            expected.coeffs()
+        );
+        assert_eq!(polynomial_from_u64s(&[5])?.num_variables(), 0);
+        Ok(())
+    }
+
+    #[test]
+    fn rejects_invalid_polynomials() {
+        assert!(polynomial_from_u64s(&[1, 2, 3]).is_err());
+        assert!(polynomial_from_u64s(&[]).is_err());
+        assert!(polynomial_from_bytes(&[0; BASE_FIELD_SIZE_BYTES + 1]).is_err());
+        assert!(polynomial_from_bytes(&[0xff; 2 * BASE_FIELD_SIZE_BYTES]).is_err());
+        assert!(polynomial_from_evaluations(vec![BaseField::from(1u64); 3]).is_err());
+    }
+
+    #[test]
+    fn converts_evaluations_to_coefficients() -> anyhow::Result<()> {
+        let num_variables = 4;
+        let expected = create_test_polynomial(num_variables);
+        let evaluations = (0..1 << num_variables)
+            .map(|index: usize| {
+                let point = (0..num_variables)
+                    .map(|k| BaseField::from(((index >> (num_variables - 1 - k)) & 1) as u64))
+                    .collect();
+                expected.evaluate(&MultilinearPoint(point))
+            })
+            .collect();
+        assert_eq!(polynomial_evaluations(&expected), evaluations);
+        assert_eq!(
+            polynomial_from_evaluations(evaluations)?.coeffs(),
+            expected.coeffs()
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,160 @@
+//! Example Rollup Settlement Program
+//!
+//! A reference integration of the WHIR verifier: it stores a rollup's state root and only
+//! advances it after verifying a WHIR proof of the state transition via CPI.
+//!
+//! The transition from `state_root` to `new_state_root` at `block_height` is bound to the proof
+//! as typed public inputs (see `whir_config::public_inputs`): the proof account must contain an
+//! evaluation constraint at the point derived from [`transition_inputs`], e.g. added via the
+//! verifier's `add_public_inputs_constraint`.
+
+use anchor_lang::prelude::*;
+use whir_config::public_inputs::{public_inputs_eval_point_bytes, PublicInput};
+use whir_verifier_solana::{
+    cpi::accounts::VerifyProof as VerifyProofAccounts, program::WhirVerifierSolana, LoadProofData,
+    ProgramConfig, ProofData, Treasury, WhirParamsArgs,
+};
+
+declare_id!("HipAjMJUTwsqgJCuD7deHbQiyAFRgqUWunwNJb1TXiVh");
+
+#[program]
+pub mod rollup_example {
+    use super::*;
+
+    /// Create the rollup of the signing sequencer, starting at `state_root`. Transitions are
+    /// verified with `params`.
+    pub fn initialize(
+        ctx: Context<Initialize>,
+        params: WhirParamsArgs,
+        state_root: [u8; 32],
+    ) -> Result<()> {
+        ctx.accounts.rollup.set_inner(Rollup {
+            sequencer: ctx.accounts.sequencer.key(),
+            params,
+            state_root,
+            block_height: 0,
+            bump: ctx.bumps.rollup,
+        });
+        Ok(())
+    }
+
+    /// Verify the proof in the proof account via CPI and advance the rollup to `new_state_root`.
+    pub fn settle(ctx: Context<Settle>, new_state_root: [u8; 32]) -> Result<()> {
+        let rollup = &ctx.accounts.rollup;
+        let block_height = rollup.block_height + 1;
+        let expected_point = public_inputs_eval_point_bytes(
+            &transition_inputs(&rollup.state_root, &new_state_root, block_height),
+            rollup.params.num_variables as usize,
+        );
+
+        let accounts = &ctx.accounts;
+        let cpi_accounts = VerifyProofAccounts {
+            proof_data: accounts.proof_data.to_account_info(),
+            statement: None,
+            eval_value_account: None,
+            external_proof_account: None,
+            transcript_pattern: None,
+            fee: whir_verifier_solana::__cpi_client_accounts_verification_fee::VerificationFee {
+                config: accounts.verifier_config.to_account_info(),
+                treasury: Some(accounts.treasury.to_account_info()),
+                caller: accounts.sequencer.to_account_info(),
+                system_program: Some(accounts.system_program.to_account_info()),
+                caller_token_account: None,
+                gate_mint: None,
+                token_program: None,
+                allowlist: None,
+                stats: None,
+                verification_log: None,
+                instructions_sysvar: None,
+            },
+        };
+        whir_verifier_solana::cpi::verify_v2(
+            CpiContext::new(accounts.verifier_program.to_account_info(), cpi_accounts),
+            rollup.params.clone(),
+        )?;
+
+        // The verified statement must open the commitment at the transition's point.
+        let metadata = accounts.proof_data.load_current()?.metadata()?;
+        require!(
+            metadata
+                .constraints
+                .iter()
+                .any(|constraint| constraint.eval_point == expected_point),
+            RollupError::TransitionNotProven
+        );
+
+        let rollup = &mut ctx.accounts.rollup;
+        rollup.state_root = new_state_root;
+        rollup.block_height = block_height;
+        Ok(())
+    }
+}
+
+/// Returns the public inputs of the transition from `state_root` to `new_state_root` at
+/// `block_height`, matching the prover's.
+pub fn transition_inputs(
+    state_root: &[u8; 32],
+    new_state_root: &[u8; 32],
+    block_height: u64,
+) -> [PublicInput; 3] {
+    [
+        PublicInput::Hash(*state_root),
+        PublicInput::Hash(*new_state_root),
+        PublicInput::U64(block_height),
+    ]
+}
+
+/// A rollup's settled state, a PDA per sequencer.
+#[account]
+#[derive(InitSpace)]
+pub struct Rollup {
+    /// Submits the transitions.
+    pub sequencer: Pubkey,
+    /// Parameters the transition proofs are verified with.
+    pub params: WhirParamsArgs,
+    pub state_root: [u8; 32],
+    /// Number of settled transitions.
+    pub block_height: u64,
+    pub bump: u8,
+}
+
+impl Rollup {
+    pub const SEED: &'static [u8] = b"rollup";
+}
+
+#[derive(Accounts)]
+pub struct Initialize<'info> {
+    #[account(
+        init,
+        payer = sequencer,
+        space = 8 + Rollup::INIT_SPACE,
+        seeds = [Rollup::SEED, sequencer.key().as_ref()],
+        bump
+    )]
+    pub rollup: Account<'info, Rollup>,
+    #[account(mut)]
+    pub sequencer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Settle<'info> {
+    #[account(mut, seeds = [Rollup::SEED, sequencer.key().as_ref()], bump = rollup.bump)]
+    pub rollup: Account<'info, Rollup>,
+    /// Pays the verification fee.
+    #[account(mut)]
+    pub sequencer: Signer<'info>,
+    #[account(mut)]
+    pub proof_data: AccountLoader<'info, ProofData>,
+    pub verifier_config: Account<'info, ProgramConfig>,
+    #[account(mut)]
+    pub treasury: Account<'info, Treasury>,
+    pub verifier_program: Program<'info, WhirVerifierSolana>,
+    pub system_program: Program<'info, System>,
+}
+
+#[error_code]
+pub enum RollupError {
+    #[msg("The verified proof doesn't prove the state transition")]
+    TransitionNotProven,
+}
@@ -0,0 +1,339 @@
+//! Program-wide configuration managed by an admin.
+//!
+//! The admin controls all privileged instructions. It can be a multisig, the program's own
+//! [`crate::multisig`] or e.g. a Squads vault PDA signing via CPI, and is handed over in two steps
+//! so that a typo can't lock the program.
+
+use anchor_lang::prelude::*;
+use whir_config::encoding::{EncodingError, ProofEncoding};
+
+use crate::{fees::Treasury, program::WhirVerifierSolana, WhirError};
+
+/// Maximum number of callers exempt from the verification fee.
+pub const MAX_FEE_EXEMPT: usize = 32;
+
+/// Maximum number of signers on the allowlist.
+pub const MAX_ALLOWLISTED: usize = 64;
+
+/// Program-wide configuration, a singleton PDA.
+#[account]
+#[derive(InitSpace)]
+pub struct ProgramConfig {
+    pub admin: Pubkey,
+    /// Proposed new admin, which becomes the admin once it accepts.
+    pub pending_admin: Option<Pubkey>,
+    /// Fee in lamports charged per verification and collected in the treasury.
+    pub fee_lamports: u64,
+    /// Callers that don't pay the verification fee.
+    #[max_len(MAX_FEE_EXEMPT)]
+    pub fee_exempt: Vec<Pubkey>,
+    /// If set, callers must hold (or burn) tokens of a mint to verify.
+    pub token_gate: Option<TokenGate>,
+    /// Restrict proof submission and verification to the signers on the allowlist.
+    pub allowlist_enforced: bool,
+    pub size_limits: SizeLimits,
+    /// Minimum security level in bits of the parameters proofs are verified with.
+    pub min_security_level: u32,
+    pub bump: u8,
+}
+
+/// Caps on uploaded data, keeping accounts and verification within rent and heap limits.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, InitSpace)]
+pub struct SizeLimits {
+    /// Maximum length of an uploaded chunk in bytes.
+    pub max_chunk_len: u32,
+    /// Maximum total length of a proof in bytes, including buffers or an external source.
+    pub max_proof_len: u32,
+    /// Maximum length of a serialized evaluation point in bytes.
+    pub max_eval_point_len: u32,
+}
+
+impl SizeLimits {
+    /// Upper bound of every limit: Solana's 10 MiB account size limit.
+    pub const MAX_LEN: u32 = 10 * 1024 * 1024;
+
+    /// Limits of a newly initialized configuration, and of the released `verify()`.
+    pub const DEFAULT: Self = Self {
+        max_chunk_len: 1024,
+        max_proof_len: Self::MAX_LEN,
+        max_eval_point_len: 1024,
+    };
+
+    /// Checks that no limit is zero or above [`Self::MAX_LEN`] and that a chunk fits in a proof.
+    pub fn validate(&self) -> Result<()> {
+        let in_range = |limit: u32| (1..=Self::MAX_LEN).contains(&limit);
+        require!(
+            in_range(self.max_chunk_len)
+                && in_range(self.max_proof_len)
+                && in_range(self.max_eval_point_len)
+                && self.max_chunk_len <= self.max_proof_len,
+            WhirError::InvalidSizeLimits
+        );
+        Ok(())
+    }
+
+    pub fn check_chunk(&self, chunk_len: usize) -> Result<()> {
+        require!(
+            chunk_len <= self.max_chunk_len as usize,
+            WhirError::ChunkTooLarge
+        );
+        Ok(())
+    }
+
+    pub fn check_proof(&self, proof_len: usize) -> Result<()> {
+        require!(
+            proof_len <= self.max_proof_len as usize,
+            WhirError::ProofTooLarge
+        );
+        Ok(())
+    }
+
+    /// Decodes proof bytes, failing as soon as the decoded proof exceeds the proof limit.
+    pub fn decode_proof(&self, encoding: ProofEncoding, bytes: &[u8]) -> Result<Vec<u8>> {
+        encoding
+            .decode(bytes, self.max_proof_len as usize)
+            .map_err(|error| match error {
+                EncodingError::TooLong => WhirError::ProofTooLarge.into(),
+                _ => WhirError::InvalidProofEncoding.into(),
+            })
+    }
+
+    pub fn check_eval_point(&self, eval_point_len: usize) -> Result<()> {
+        require!(
+            eval_point_len <= self.max_eval_point_len as usize,
+            WhirError::EvalPointTooLarge
+        );
+        Ok(())
+    }
+}
+
+/// Requirement for callers to hold or burn an amount of an SPL token to verify, enforced by every
+/// verify instruction taking [`crate::VerificationFee`].
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct TokenGate {
+    pub mint: Pubkey,
+    pub amount: u64,
+    /// Burn `amount` tokens per verification instead of only checking the balance.
+    pub burn: bool,
+}
+
+impl ProgramConfig {
+    pub const SEED: &'static [u8] = b"config";
+
+    /// Minimum security level of a newly initialized configuration, and of the released
+    /// `verify()`: the security level of the default parameters.
+    pub const DEFAULT_MIN_SECURITY_LEVEL: u32 = whir_config::SECURITY_LEVEL_BITS as u32;
+
+    /// Checks that parameters of `security_level` bits meet the configured minimum.
+    pub fn check_security_level(&self, security_level: u32) -> Result<()> {
+        require!(
+            security_level >= self.min_security_level,
+            WhirError::InsufficientSecurity
+        );
+        Ok(())
+    }
+
+    /// Whether `signer` may submit and verify proofs.
+    pub fn allows(&self, signer: &Pubkey, allowlist: Option<&Allowlist>) -> bool {
+        !self.allowlist_enforced || allowlist.is_some_and(|a| a.signers.contains(signer))
+    }
+}
+
+/// Signers approved to submit and verify proofs when the allowlist is enforced, a singleton PDA.
+#[account]
+#[derive(InitSpace)]
+pub struct Allowlist {
+    #[max_len(MAX_ALLOWLISTED)]
+    pub signers: Vec<Pubkey>,
+    pub bump: u8,
+}
+
+impl Allowlist {
+    pub const SEED: &'static [u8] = b"allowlist";
+}
+
+#[derive(Accounts)]
+pub struct InitializeConfig<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ProgramConfig::INIT_SPACE,
+        seeds = [ProgramConfig::SEED],
+        bump
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Treasury::INIT_SPACE,
+        seeds = [Treasury::SEED],
+        bump
+    )]
+    pub treasury: Account<'info, Treasury>,
+    pub admin: Signer<'info>,
+    /// Pays the rent of the configuration and the treasury.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(constraint = program.programdata_address()? == Some(program_data.key()) @ WhirError::Unauthorized)]
+    pub program: Program<'info, WhirVerifierSolana>,
+    #[account(constraint = program_data.upgrade_authority_address == Some(admin.key()) @ WhirError::Unauthorized)]
+    pub program_data: Account<'info, ProgramData>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateConfig<'info> {
+    #[account(
+        mut,
+        seeds = [ProgramConfig::SEED],
+        bump = config.bump,
+        has_one = admin @ WhirError::Unauthorized
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitAllowlist<'info> {
+    #[account(
+        seeds = [ProgramConfig::SEED],
+        bump = config.bump,
+        has_one = admin @ WhirError::Unauthorized
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + Allowlist::INIT_SPACE,
+        seeds = [Allowlist::SEED],
+        bump
+    )]
+    pub allowlist: Account<'info, Allowlist>,
+    pub admin: Signer<'info>,
+    /// Pays the allowlist's rent, so that a PDA admin such as the admin multisig can create it.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateAllowlist<'info> {
+    #[account(
+        seeds = [ProgramConfig::SEED],
+        bump = config.bump,
+        has_one = admin @ WhirError::Unauthorized
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut, seeds = [Allowlist::SEED], bump = allowlist.bump)]
+    pub allowlist: Account<'info, Allowlist>,
+    pub admin: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct AcceptAdmin<'info> {
+    #[account(
+        mut,
+        seeds = [ProgramConfig::SEED],
+        bump = config.bump,
+        constraint = config.pending_admin == Some(new_admin.key()) @ WhirError::Unauthorized
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    pub new_admin: Signer<'info>,
+}
+
+pub fn process_initialize_config(ctx: Context<InitializeConfig>, fee_lamports: u64) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    config.admin = ctx.accounts.admin.key();
+    config.pending_admin = None;
+    config.fee_lamports = fee_lamports;
+    config.fee_exempt = Vec::new();
+    config.token_gate = None;
+    config.allowlist_enforced = false;
+    config.size_limits = SizeLimits::DEFAULT;
+    config.min_security_level = ProgramConfig::DEFAULT_MIN_SECURITY_LEVEL;
+    config.bump = ctx.bumps.config;
+    ctx.accounts.treasury.bump = ctx.bumps.treasury;
+    Ok(())
+}
+
+pub fn process_set_fee(ctx: Context<UpdateConfig>, fee_lamports: u64) -> Result<()> {
+    ctx.accounts.config.fee_lamports = fee_lamports;
+    Ok(())
+}
+
+pub fn process_set_fee_exempt(
+    ctx: Context<UpdateConfig>,
+    caller: Pubkey,
+    exempt: bool,
+) -> Result<()> {
+    let fee_exempt = &mut ctx.accounts.config.fee_exempt;
+    fee_exempt.retain(|key| *key != caller);
+    if exempt {
+        require!(
+            fee_exempt.len() < MAX_FEE_EXEMPT,
+            WhirError::TooManyFeeExemptions
+        );
+        fee_exempt.push(caller);
+    }
+    Ok(())
+}
+
+pub fn process_set_token_gate(
+    ctx: Context<UpdateConfig>,
+    token_gate: Option<TokenGate>,
+) -> Result<()> {
+    ctx.accounts.config.token_gate = token_gate;
+    Ok(())
+}
+
+pub fn process_set_allowlist_enforced(ctx: Context<UpdateConfig>, enforced: bool) -> Result<()> {
+    ctx.accounts.config.allowlist_enforced = enforced;
+    Ok(())
+}
+
+pub fn process_init_allowlist(ctx: Context<InitAllowlist>) -> Result<()> {
+    let allowlist = &mut ctx.accounts.allowlist;
+    allowlist.signers = Vec::new();
+    allowlist.bump = ctx.bumps.allowlist;
+    Ok(())
+}
+
+pub fn process_set_allowlisted(
+    ctx: Context<UpdateAllowlist>,
+    signer: Pubkey,
+    allowed: bool,
+) -> Result<()> {
+    let signers = &mut ctx.accounts.allowlist.signers;
+    signers.retain(|key| *key != signer);
+    if allowed {
+        require!(signers.len() < MAX_ALLOWLISTED, WhirError::AllowlistFull);
+        signers.push(signer);
+    }
+    Ok(())
+}
+
+pub fn process_propose_admin(ctx: Context<UpdateConfig>, new_admin: Option<Pubkey>) -> Result<()> {
+    ctx.accounts.config.pending_admin = new_admin;
+    Ok(())
+}
+
+pub fn process_accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+    let config = &mut ctx.accounts.config;
+    config.admin = ctx.accounts.new_admin.key();
+    config.pending_admin = None;
+    Ok(())
+}
+
+pub fn process_set_size_limits(ctx: Context<UpdateConfig>, size_limits: SizeLimits) -> Result<()> {
+    size_limits.validate()?;
+    ctx.accounts.config.size_limits = size_limits;
+    Ok(())
+}
+
+pub fn process_set_min_security_level(
+    ctx: Context<UpdateConfig>,
+    min_security_level: u32,
+) -> Result<()> {
+    ctx.accounts.config.min_security_level = min_security_level;
+    Ok(())
+}
@@ -0,0 +1,285 @@
+//! Optimistic claims: bonded evaluation claims with a WHIR proof as the escalation path.
+//!
+//! A submitter posts a claimed evaluation of a commitment together with a bond. Until the
+//! challenge deadline anyone can challenge the claim by matching the bond, demanding a proof. A
+//! proof verified against the claim's parameters and statement accepts the claim and pays both
+//! bonds to the submitter. An unchallenged claim is accepted after the challenge deadline, while a
+//! challenged claim without a verifying proof by the response deadline is slashed, paying both
+//! bonds to the challenger.
+
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer},
+};
+use whir_verifier_core::{hash_binding, DigestHasher};
+
+use crate::{
+    admin::ProgramConfig, commitment::check_ood_answers, process_verify, validate_constraint,
+    EvalConstraint, Sha256Digest, SoundnessTypeArg, VerifyProof, WhirError, WhirParamsArgs,
+};
+
+/// Slots after posting during which a claim can be challenged, about an hour.
+pub const CHALLENGE_WINDOW_SLOTS: u64 = 9_000;
+
+/// Slots after a challenge during which the submitter can prove the claim, about an hour.
+pub const RESPONSE_WINDOW_SLOTS: u64 = 9_000;
+
+/// A claimed evaluation of a committed polynomial.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ClaimedEvaluation {
+    /// Merkle root of the commitment.
+    pub root: [u8; 32],
+    /// Serialized out-of-domain answers of the commitment.
+    pub ood_answers: Vec<u8>,
+    pub eval_point: Vec<u8>,
+    pub eval_value: Vec<u8>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ClaimStatus {
+    /// Posted and challengeable until the challenge deadline.
+    Pending,
+    /// Challenged, the submitter must prove it by the response deadline.
+    Challenged,
+    /// Proven, or not challenged in time.
+    Accepted,
+    /// Challenged and not proven in time.
+    Slashed,
+}
+
+/// A bonded evaluation claim, a PDA per submitter and claim id.
+#[account]
+pub struct Claim {
+    pub submitter: Pubkey,
+    pub claim_id: u64,
+    /// Parameters a proof of the claim is verified with.
+    pub params: WhirParamsArgs,
+    pub evaluation: ClaimedEvaluation,
+    /// Digest a proof of the claim verifies with, see `ProofData::verified_digest`.
+    pub digest: [u8; 32],
+    /// Bond of the submitter, matched by the challenger.
+    pub bond_lamports: u64,
+    pub challenge_deadline_slot: u64,
+    pub challenger: Option<Pubkey>,
+    /// Set when the claim is challenged.
+    pub response_deadline_slot: u64,
+    pub status: ClaimStatus,
+    pub bump: u8,
+}
+
+impl Claim {
+    pub const SEED: &'static [u8] = b"claim";
+
+    /// Account size, including the discriminator, for the given evaluation.
+    pub fn space(evaluation: &ClaimedEvaluation) -> usize {
+        let evaluation_size = 32
+            + 4
+            + evaluation.ood_answers.len()
+            + 4
+            + evaluation.eval_point.len()
+            + 4
+            + evaluation.eval_value.len();
+        8 + 32 + 8 + WhirParamsArgs::INIT_SPACE + evaluation_size + 32 + 8 + 8 + 33 + 8 + 1 + 1
+    }
+
+    /// The bonds held by the claim.
+    fn bonds(&self) -> u64 {
+        match self.challenger {
+            Some(_) => self.bond_lamports * 2,
+            None => self.bond_lamports,
+        }
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(claim_id: u64, params: WhirParamsArgs, evaluation: ClaimedEvaluation)]
+pub struct PostClaim<'info> {
+    #[account(
+        init,
+        payer = submitter,
+        space = Claim::space(&evaluation),
+        seeds = [Claim::SEED, submitter.key().as_ref(), &claim_id.to_le_bytes()],
+        bump
+    )]
+    pub claim: Account<'info, Claim>,
+    #[account(mut)]
+    pub submitter: Signer<'info>,
+    #[account(seeds = [ProgramConfig::SEED], bump = config.bump)]
+    pub config: Account<'info, ProgramConfig>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ChallengeClaim<'info> {
+    #[account(
+        mut,
+        constraint = claim.status == ClaimStatus::Pending @ WhirError::ClaimNotChallengeable,
+        constraint = Clock::get()?.slot <= claim.challenge_deadline_slot @ WhirError::ClaimNotChallengeable
+    )]
+    pub claim: Account<'info, Claim>,
+    #[account(mut)]
+    pub challenger: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ProveClaim<'info> {
+    pub verify: VerifyProof<'info>,
+    #[account(mut, has_one = submitter)]
+    pub claim: Account<'info, Claim>,
+    /// CHECK: Receives the bonds, matches the claim's submitter.
+    #[account(mut)]
+    pub submitter: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SettleClaim<'info> {
+    #[account(mut, has_one = submitter)]
+    pub claim: Account<'info, Claim>,
+    /// CHECK: Receives the bond of an accepted claim, matches the claim's submitter.
+    #[account(mut)]
+    pub submitter: UncheckedAccount<'info>,
+    /// CHECK: Receives the bonds of a slashed claim, required for challenged claims.
+    #[account(mut)]
+    pub challenger: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct CloseClaim<'info> {
+    #[account(
+        mut,
+        close = submitter,
+        has_one = submitter,
+        constraint = matches!(claim.status, ClaimStatus::Accepted | ClaimStatus::Slashed) @ WhirError::ClaimNotSettleable
+    )]
+    pub claim: Account<'info, Claim>,
+    pub submitter: Signer<'info>,
+}
+
+pub fn process_post_claim(
+    ctx: Context<PostClaim>,
+    claim_id: u64,
+    params: WhirParamsArgs,
+    evaluation: ClaimedEvaluation,
+    bond_lamports: u64,
+) -> Result<()> {
+    check_ood_answers(&params, &evaluation.ood_answers)?;
+    ctx.accounts
+        .config
+        .check_security_level(params.security_level)?;
+    let size_limits = &ctx.accounts.config.size_limits;
+    size_limits.check_eval_point(evaluation.eval_point.len())?;
+    let constraint = EvalConstraint {
+        eval_point: evaluation.eval_point.clone(),
+        eval_value: evaluation.eval_value.clone(),
+    };
+    validate_constraint(&constraint, params.num_variables as usize, true)?;
+
+    transfer(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.submitter.to_account_info(),
+                to: ctx.accounts.claim.to_account_info(),
+            },
+        ),
+        bond_lamports,
+    )?;
+
+    // Same digest as the verification of a proof with the claimed commitment and statement,
+    // under the claim's parameters and the default domain label, without a context.
+    let mut hasher = Sha256Digest::default();
+    hash_binding(&mut hasher, &params.to_whir_params(), "", &[]);
+    hasher.update(&evaluation.root);
+    hasher.update(&evaluation.ood_answers);
+    hasher.update(&evaluation.eval_point);
+    hasher.update(&evaluation.eval_value);
+    ctx.accounts.claim.set_inner(Claim {
+        submitter: ctx.accounts.submitter.key(),
+        claim_id,
+        params,
+        evaluation,
+        digest: hasher.finalize(),
+        bond_lamports,
+        challenge_deadline_slot: Clock::get()?.slot + CHALLENGE_WINDOW_SLOTS,
+        challenger: None,
+        response_deadline_slot: 0,
+        status: ClaimStatus::Pending,
+        bump: ctx.bumps.claim,
+    });
+    Ok(())
+}
+
+pub fn process_challenge_claim(ctx: Context<ChallengeClaim>) -> Result<()> {
+    transfer(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.challenger.to_account_info(),
+                to: ctx.accounts.claim.to_account_info(),
+            },
+        ),
+        ctx.accounts.claim.bond_lamports,
+    )?;
+
+    let claim = &mut ctx.accounts.claim;
+    claim.challenger = Some(ctx.accounts.challenger.key());
+    claim.response_deadline_slot = Clock::get()?.slot + RESPONSE_WINDOW_SLOTS;
+    claim.status = ClaimStatus::Challenged;
+    Ok(())
+}
+
+pub fn process_prove_claim(ctx: Context<ProveClaim>) -> Result<()> {
+    let claim = &ctx.accounts.claim;
+    let open = match claim.status {
+        ClaimStatus::Pending => true,
+        ClaimStatus::Challenged => Clock::get()?.slot <= claim.response_deadline_slot,
+        ClaimStatus::Accepted | ClaimStatus::Slashed => false,
+    };
+    require!(open, WhirError::ClaimNotSettleable);
+
+    let params = claim.params.clone();
+    let digest = process_verify(
+        &mut ctx.accounts.verify,
+        ctx.remaining_accounts,
+        &params,
+        SoundnessTypeArg::default(),
+    )?;
+    require!(
+        digest == ctx.accounts.claim.digest,
+        WhirError::ProofNotVerified
+    );
+
+    let claim = &mut ctx.accounts.claim;
+    let bonds = claim.bonds();
+    claim.sub_lamports(bonds)?;
+    ctx.accounts.submitter.add_lamports(bonds)?;
+    claim.status = ClaimStatus::Accepted;
+    Ok(())
+}
+
+pub fn process_settle_claim(ctx: Context<SettleClaim>) -> Result<()> {
+    let slot = Clock::get()?.slot;
+    let claim = &mut ctx.accounts.claim;
+    let bonds = claim.bonds();
+    match claim.status {
+        ClaimStatus::Pending if slot > claim.challenge_deadline_slot => {
+            claim.sub_lamports(bonds)?;
+            ctx.accounts.submitter.add_lamports(bonds)?;
+            claim.status = ClaimStatus::Accepted;
+        }
+        ClaimStatus::Challenged if slot > claim.response_deadline_slot => {
+            let challenger = ctx
+                .accounts
+                .challenger
+                .as_ref()
+                .filter(|challenger| Some(challenger.key()) == claim.challenger)
+                .ok_or(WhirError::Unauthorized)?;
+            claim.sub_lamports(bonds)?;
+            challenger.add_lamports(bonds)?;
+            claim.status = ClaimStatus::Slashed;
+        }
+        _ => return err!(WhirError::ClaimNotSettleable),
+    }
+    Ok(())
+}
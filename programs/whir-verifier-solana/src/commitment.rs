@@ -0,0 +1,87 @@
+//! Accepted commitments: commitments checked for well-formedness before any opening proof.
+//!
+//! A dApp can acknowledge a commitment as soon as it's published and accept opening proofs for
+//! it later, by comparing a verified proof's `ProofData::verified_commitment` with the accepted
+//! root. Accepting doesn't verify anything about the committed polynomial.
+//!
+//! Accepted commitments are keyed by their payer as well as their root, so accepting a root with
+//! other parameters or out-of-domain answers first can't keep anyone else from accepting it.
+
+use anchor_lang::{prelude::*, solana_program::hash::Hasher};
+use whir_config::{field::decode_field_elements, field_size_bytes, DIGEST_SIZE_BYTES};
+
+use crate::{checked_whir_params, WhirError, WhirParamsArgs};
+
+/// A well-formed commitment, a PDA keyed by its Merkle root and payer.
+#[account]
+#[derive(InitSpace)]
+pub struct AcceptedCommitment {
+    pub root: [u8; DIGEST_SIZE_BYTES],
+    /// SHA-256 of the commitment bytes: the root followed by the out-of-domain answers.
+    pub commitment_hash: [u8; 32],
+    /// Parameters the commitment was checked against.
+    pub params: WhirParamsArgs,
+    /// Paid the account's rent.
+    pub payer: Pubkey,
+    pub slot: u64,
+    pub bump: u8,
+}
+
+impl AcceptedCommitment {
+    pub const SEED: &'static [u8] = b"commitment";
+}
+
+#[derive(Accounts)]
+#[instruction(params: WhirParamsArgs, root: [u8; 32])]
+pub struct AcceptCommitment<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + AcceptedCommitment::INIT_SPACE,
+        seeds = [AcceptedCommitment::SEED, root.as_ref(), payer.key().as_ref()],
+        bump
+    )]
+    pub accepted_commitment: Account<'info, AcceptedCommitment>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn process_accept_commitment(
+    ctx: Context<AcceptCommitment>,
+    params: WhirParamsArgs,
+    root: [u8; 32],
+    ood_answers: Vec<u8>,
+) -> Result<()> {
+    check_ood_answers(&params, &ood_answers)?;
+
+    let mut hasher = Hasher::default();
+    hasher.hash(&root);
+    hasher.hash(&ood_answers);
+    ctx.accounts
+        .accepted_commitment
+        .set_inner(AcceptedCommitment {
+            root,
+            commitment_hash: hasher.result().to_bytes(),
+            params,
+            payer: ctx.accounts.payer.key(),
+            slot: Clock::get()?.slot,
+            bump: ctx.bumps.accepted_commitment,
+        });
+    Ok(())
+}
+
+/// Checks that the out-of-domain answers of a commitment are the expected number of valid field
+/// elements for the parameters.
+pub(crate) fn check_ood_answers(params: &WhirParamsArgs, ood_answers: &[u8]) -> Result<()> {
+    let whir_params = checked_whir_params(params)?;
+    require_eq!(
+        ood_answers.len(),
+        whir_params.committment_ood_samples * field_size_bytes(),
+        WhirError::CommitmentParseError
+    );
+    for answer in decode_field_elements(ood_answers) {
+        answer.ok_or(WhirError::CommitmentParseError)?;
+    }
+    Ok(())
+}
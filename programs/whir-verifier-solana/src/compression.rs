@@ -0,0 +1,177 @@
+//! Verification history in a concurrent Merkle tree of the SPL account-compression program.
+//!
+//! Verified digests are appended as leaves to a single tree instead of creating an account per
+//! verification, so large histories cost the rent of the tree only. Leaves are logged through the
+//! SPL noop program, from which indexers rebuild the tree and serve membership proofs.
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{
+        hash::hash,
+        instruction::{AccountMeta, Instruction},
+        program::invoke_signed,
+        pubkey,
+    },
+};
+
+use crate::{admin::ProgramConfig, LoadProofData, ProofData, WhirError};
+
+/// The SPL account-compression program.
+pub const ACCOUNT_COMPRESSION_ID: Pubkey = pubkey!("cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK");
+
+/// The SPL noop program, logging the appended leaves for indexers.
+pub const NOOP_ID: Pubkey = pubkey!("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV");
+
+/// The tree holding the verified digests, a singleton PDA and the tree's authority.
+#[account]
+#[derive(InitSpace)]
+pub struct DigestTree {
+    pub merkle_tree: Pubkey,
+    /// Number of digests appended so far, i.e. the index of the next leaf.
+    pub num_leaves: u64,
+    pub bump: u8,
+}
+
+impl DigestTree {
+    pub const SEED: &'static [u8] = b"digest_tree";
+}
+
+#[derive(Accounts)]
+pub struct InitDigestTree<'info> {
+    #[account(
+        seeds = [ProgramConfig::SEED],
+        bump = config.bump,
+        has_one = admin @ WhirError::Unauthorized
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + DigestTree::INIT_SPACE,
+        seeds = [DigestTree::SEED],
+        bump
+    )]
+    pub digest_tree: Account<'info, DigestTree>,
+    /// CHECK: Allocated for the tree's depth and buffer size by the admin, initialized by the
+    /// account-compression program.
+    #[account(mut, owner = ACCOUNT_COMPRESSION_ID)]
+    pub merkle_tree: UncheckedAccount<'info>,
+    pub admin: Signer<'info>,
+    /// Pays the rent of the digest tree PDA.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: The SPL account-compression program.
+    #[account(address = ACCOUNT_COMPRESSION_ID)]
+    pub compression_program: UncheckedAccount<'info>,
+    /// CHECK: The SPL noop program.
+    #[account(address = NOOP_ID)]
+    pub noop_program: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct AppendVerifiedDigest<'info> {
+    #[account(
+        mut,
+        constraint = proof_data.load_current()?.verified_digest().is_some() @ WhirError::ProofNotVerified,
+        constraint = proof_data.load_current()?.digest_appended == 0 @ WhirError::DigestAlreadyAppended
+    )]
+    pub proof_data: AccountLoader<'info, ProofData>,
+    #[account(mut, seeds = [DigestTree::SEED], bump = digest_tree.bump, has_one = merkle_tree)]
+    pub digest_tree: Account<'info, DigestTree>,
+    /// CHECK: The digest tree's Merkle tree, checked by the account-compression program.
+    #[account(mut)]
+    pub merkle_tree: UncheckedAccount<'info>,
+    /// CHECK: The SPL account-compression program.
+    #[account(address = ACCOUNT_COMPRESSION_ID)]
+    pub compression_program: UncheckedAccount<'info>,
+    /// CHECK: The SPL noop program.
+    #[account(address = NOOP_ID)]
+    pub noop_program: UncheckedAccount<'info>,
+    /// Minimum security level of the verified parameters.
+    #[account(seeds = [ProgramConfig::SEED], bump = config.bump)]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+pub fn process_init_digest_tree(
+    ctx: Context<InitDigestTree>,
+    max_depth: u32,
+    max_buffer_size: u32,
+) -> Result<()> {
+    let merkle_tree = ctx.accounts.merkle_tree.key();
+    let digest_tree = &mut ctx.accounts.digest_tree;
+    digest_tree.merkle_tree = merkle_tree;
+    digest_tree.num_leaves = 0;
+    digest_tree.bump = ctx.bumps.digest_tree;
+
+    let accounts = &ctx.accounts;
+    let mut args = max_depth.to_le_bytes().to_vec();
+    args.extend_from_slice(&max_buffer_size.to_le_bytes());
+    invoke_compression(
+        "init_empty_merkle_tree",
+        &args,
+        &accounts.merkle_tree,
+        &accounts.digest_tree,
+        &accounts.noop_program,
+        &accounts.compression_program,
+    )
+}
+
+pub fn process_append_verified_digest(ctx: Context<AppendVerifiedDigest>) -> Result<()> {
+    let accounts = &ctx.accounts;
+    let digest = {
+        let mut proof_data = accounts.proof_data.load_current_mut()?;
+        let digest = proof_data
+            .verified_digest()
+            .ok_or(WhirError::ProofNotVerified)?;
+        proof_data.check_verified_params(&digest, &accounts.config)?;
+        proof_data.digest_appended = 1;
+        digest
+    };
+    invoke_compression(
+        "append",
+        &digest,
+        &accounts.merkle_tree,
+        &accounts.digest_tree,
+        &accounts.noop_program,
+        &accounts.compression_program,
+    )?;
+
+    ctx.accounts.digest_tree.num_leaves += 1;
+    Ok(())
+}
+
+/// Calls an instruction of the account-compression program on the tree, signed by the digest
+/// tree PDA as the tree's authority.
+fn invoke_compression<'info>(
+    instruction: &str,
+    args: &[u8],
+    merkle_tree: &UncheckedAccount<'info>,
+    digest_tree: &Account<'info, DigestTree>,
+    noop_program: &UncheckedAccount<'info>,
+    compression_program: &UncheckedAccount<'info>,
+) -> Result<()> {
+    // Anchor instruction discriminator of the account-compression program.
+    let mut data = hash(format!("global:{instruction}").as_bytes()).to_bytes()[..8].to_vec();
+    data.extend_from_slice(args);
+    let instruction = Instruction {
+        program_id: ACCOUNT_COMPRESSION_ID,
+        accounts: vec![
+            AccountMeta::new(merkle_tree.key(), false),
+            AccountMeta::new_readonly(digest_tree.key(), true),
+            AccountMeta::new_readonly(noop_program.key(), false),
+        ],
+        data,
+    };
+    invoke_signed(
+        &instruction,
+        &[
+            merkle_tree.to_account_info(),
+            digest_tree.to_account_info(),
+            noop_program.to_account_info(),
+            compression_program.to_account_info(),
+        ],
+        &[&[DigestTree::SEED, &[digest_tree.bump]]],
+    )?;
+    Ok(())
+}
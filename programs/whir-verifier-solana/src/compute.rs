@@ -0,0 +1,36 @@
+//! Compute budget hint of the verifier.
+//!
+//! A verification running out of compute units is aborted by the runtime with an opaque
+//! "exceeded CUs" error after burning the whole budget. The WHIR verifier runs as one call inside
+//! the fork and can't be suspended and resumed in a later transaction, so before it starts,
+//! [`log_compute_units`] compares the remaining compute units with the estimate of the whole
+//! verification and logs both numbers if the estimate exceeds them, so clients seeing the abort
+//! know to retry with a higher compute unit limit. The estimate is rough, see
+//! `whir_config::cost`, so it never fails a verification that might fit the budget.
+
+use anchor_lang::prelude::*;
+
+/// Logs the estimate and the remaining compute units if fewer than `estimate` remain. Does
+/// nothing off-chain, where the remaining compute units are unknown.
+pub fn log_compute_units(estimate: u64) {
+    if let Some(remaining) = remaining_compute_units() {
+        if estimate > remaining {
+            msg!(
+                "Verification may need about {} compute units, {} remain, request a higher limit \
+                 if it runs out",
+                estimate,
+                remaining
+            );
+        }
+    }
+}
+
+#[cfg(target_os = "solana")]
+fn remaining_compute_units() -> Option<u64> {
+    Some(anchor_lang::solana_program::compute_units::sol_remaining_compute_units())
+}
+
+#[cfg(not(target_os = "solana"))]
+fn remaining_compute_units() -> Option<u64> {
+    None
+}
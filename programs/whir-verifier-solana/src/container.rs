@@ -0,0 +1,141 @@
+//! Multi-proof containers: several independent proofs in one proof account.
+//!
+//! The proofs are uploaded back to back into the proof region, e.g. via `upload_chunk_v2`, and
+//! indexed in the account's metadata with their own statements. Once the upload is finalized, each
+//! proof is verified on its own by index, sharing the account's encoding, domain label and
+//! context. This saves the rent and account churn of one proof account per small proof.
+
+use anchor_lang::prelude::*;
+use whir_config::encoding::ProofEncoding;
+
+use crate::{
+    verify_whir_proof, EvalConstraint, LoadProofData, ProofData, ProofSource, ProofVerified,
+    SoundnessTypeArg, UploadChunk, VerifyProof, VersionedParamsArgs, WhirError, WhirParamsArgs,
+};
+
+/// A proof in the proof region of a container, see the module documentation.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ContainedProof {
+    /// Byte offset of the proof in the proof region.
+    pub offset: u32,
+    pub len: u32,
+    pub constraints: Vec<EvalConstraint>,
+    /// Digest of the verified commitment and statement, set by a successful verification.
+    pub verified_digest: Option<[u8; 32]>,
+    /// Parameters the proof was verified with, set with `verified_digest`.
+    pub verified_params: Option<VersionedParamsArgs>,
+}
+
+pub fn process_add_contained_proof(
+    ctx: Context<UploadChunk>,
+    offset: u32,
+    len: u32,
+    eval_point_bytes: Vec<u8>,
+    eval_value_bytes: Vec<u8>,
+) -> Result<()> {
+    let size_limits = ctx.accounts.size_limits();
+    size_limits.check_eval_point(eval_point_bytes.len())?;
+    let end = offset
+        .checked_add(len)
+        .ok_or(WhirError::InvalidProofIndex)?;
+    size_limits.check_proof(end as usize)?;
+    ctx.accounts
+        .proof_data
+        .load_current_mut()?
+        .update_metadata(|metadata| {
+            metadata.contained_proofs.push(ContainedProof {
+                offset,
+                len,
+                constraints: vec![EvalConstraint {
+                    eval_point: eval_point_bytes,
+                    eval_value: eval_value_bytes,
+                }],
+                verified_digest: None,
+                verified_params: None,
+            })
+        })
+}
+
+/// Verifies the contained proof at `index`, returning its digest.
+pub fn process_verify_contained_proof(
+    accounts: &mut VerifyProof,
+    args: &WhirParamsArgs,
+    index: u32,
+) -> Result<[u8; 32]> {
+    accounts.fee.use_requested_heap_frame()?;
+    accounts.fee.charge()?;
+
+    let index = index as usize;
+    let (digest, user_metadata) = {
+        let proof_data_info = accounts.proof_data.to_account_info();
+        let data = proof_data_info.try_borrow_data()?;
+        let (proof_data, uploaded_proof) = ProofData::split(&data)?;
+        // Uploads could otherwise overwrite the bytes of an already verified proof.
+        require!(proof_data.is_finalized(), WhirError::ProofNotFinalized);
+        if let Some(expires_at_slot) = proof_data.expires_at_slot() {
+            require!(
+                Clock::get()?.slot <= expires_at_slot,
+                WhirError::ProofExpired
+            );
+        }
+        let metadata = proof_data.metadata()?;
+        let contained = metadata
+            .contained_proofs
+            .get(index)
+            .ok_or(WhirError::InvalidProofIndex)?;
+        let start = contained.offset as usize;
+        let stored_proof = uploaded_proof
+            .get(start..start + contained.len as usize)
+            .ok_or(WhirError::InvalidProofIndex)?;
+        let encoding = ProofEncoding::try_from(proof_data.encoding)
+            .map_err(|_| WhirError::InvalidProofEncoding)?;
+        let proof_bytes = accounts
+            .fee
+            .size_limits()
+            .decode_proof(encoding, stored_proof)?;
+
+        let digest = verify_whir_proof(
+            &proof_bytes,
+            &metadata.domain_label,
+            &metadata.context,
+            contained
+                .constraints
+                .iter()
+                .map(|c| (c.eval_point.as_slice(), c.eval_value.as_slice())),
+            args,
+            SoundnessTypeArg::default(),
+            accounts.fee.min_security_level(),
+            accounts.transcript_pattern.as_deref(),
+        )?;
+        (digest, metadata.user_metadata)
+    };
+
+    let mut metadata = accounts.proof_data.load_current()?.metadata()?;
+    let contained = &mut metadata.contained_proofs[index];
+    // Like the proof account's own digest, a recorded digest can't be replaced.
+    require!(
+        !matches!(contained.verified_digest, Some(verified) if verified != digest),
+        WhirError::VerifiedDigestMismatch
+    );
+    contained.verified_digest = Some(digest);
+    contained.verified_params = Some(VersionedParamsArgs::new(args, SoundnessTypeArg::default()));
+    accounts
+        .proof_data
+        .load_current_mut()?
+        .set_metadata(&metadata)?;
+
+    let proof_data_key = accounts.proof_data.key();
+    emit!(ProofVerified {
+        proof_data: proof_data_key,
+        digest,
+        caller: accounts.fee.caller.key(),
+        user_metadata,
+    });
+    accounts.fee.record_verification(
+        args,
+        digest,
+        ProofSource::ProofAccount,
+        Some(proof_data_key),
+    )?;
+    Ok(digest)
+}
@@ -0,0 +1,299 @@
+//! Pay-on-proof escrows: lamports or SPL tokens released to a recipient once a proof verifies.
+//!
+//! An escrow is locked against the digest of a (commitment, statement) pair, see
+//! `ProofData::verified_digest`. Anyone can release it by passing a proof account verified with
+//! that digest, under parameters meeting the configured minimum security level, and the depositor
+//! can take the funds back once the escrow expired.
+
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer},
+};
+use anchor_spl::token_interface::{
+    close_account, transfer_checked, CloseAccount, Mint, TokenAccount, TokenInterface,
+    TransferChecked,
+};
+
+use crate::{LoadProofData, ProgramConfig, ProofData, WhirError};
+
+/// Funds locked until a proof with `digest` verifies, a PDA per depositor and digest.
+#[account]
+#[derive(InitSpace)]
+pub struct Escrow {
+    pub depositor: Pubkey,
+    pub recipient: Pubkey,
+    /// Digest of the commitment and statement the proof must verify.
+    pub digest: [u8; 32],
+    /// Lamports held by the escrow account, or tokens held by its vault.
+    pub amount: u64,
+    /// Mint of the escrowed tokens, `None` for lamports.
+    pub mint: Option<Pubkey>,
+    /// The depositor can refund the escrow after this slot.
+    pub expires_at_slot: u64,
+    pub bump: u8,
+}
+
+impl Escrow {
+    pub const SEED: &'static [u8] = b"escrow";
+    /// Seed of the token account holding an escrow's tokens, owned by the escrow.
+    pub const VAULT_SEED: &'static [u8] = b"escrow_vault";
+}
+
+#[derive(Accounts)]
+#[instruction(digest: [u8; 32])]
+pub struct CreateEscrow<'info> {
+    #[account(
+        init,
+        payer = depositor,
+        space = 8 + Escrow::INIT_SPACE,
+        seeds = [Escrow::SEED, depositor.key().as_ref(), digest.as_ref()],
+        bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(digest: [u8; 32])]
+pub struct CreateTokenEscrow<'info> {
+    #[account(
+        init,
+        payer = depositor,
+        space = 8 + Escrow::INIT_SPACE,
+        seeds = [Escrow::SEED, depositor.key().as_ref(), digest.as_ref()],
+        bump
+    )]
+    pub escrow: Account<'info, Escrow>,
+    #[account(
+        init,
+        payer = depositor,
+        seeds = [Escrow::VAULT_SEED, escrow.key().as_ref()],
+        bump,
+        token::mint = mint,
+        token::authority = escrow,
+        token::token_program = token_program
+    )]
+    pub vault: InterfaceAccount<'info, TokenAccount>,
+    pub mint: InterfaceAccount<'info, Mint>,
+    #[account(mut, token::mint = mint, token::token_program = token_program)]
+    pub depositor_token_account: InterfaceAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseEscrow<'info> {
+    #[account(mut, close = depositor, has_one = depositor, has_one = recipient)]
+    pub escrow: Account<'info, Escrow>,
+    /// Holds a proof verified with the escrow's digest, under parameters meeting the minimum
+    /// security level of `config`.
+    pub proof_data: AccountLoader<'info, ProofData>,
+    /// CHECK: Receives the escrowed lamports, matches the escrow's recipient.
+    #[account(mut)]
+    pub recipient: UncheckedAccount<'info>,
+    /// CHECK: Receives the escrow's rent, matches the escrow's depositor.
+    #[account(mut)]
+    pub depositor: UncheckedAccount<'info>,
+    /// Token account holding the escrowed tokens, required for token escrows like the accounts below.
+    #[account(mut)]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    /// Token account receiving the tokens, owned by the recipient.
+    #[account(mut)]
+    pub destination_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    /// Minimum security level of the verified parameters.
+    #[account(seeds = [ProgramConfig::SEED], bump = config.bump)]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct RefundEscrow<'info> {
+    #[account(
+        mut,
+        close = depositor,
+        has_one = depositor,
+        constraint = Clock::get()?.slot > escrow.expires_at_slot @ WhirError::EscrowNotExpired
+    )]
+    pub escrow: Account<'info, Escrow>,
+    #[account(mut)]
+    pub depositor: Signer<'info>,
+    /// Token account holding the escrowed tokens, required for token escrows like the accounts below.
+    #[account(mut)]
+    pub vault: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub mint: Option<InterfaceAccount<'info, Mint>>,
+    /// Token account receiving the tokens, owned by the depositor.
+    #[account(mut)]
+    pub destination_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+}
+
+pub fn process_create_escrow(
+    ctx: Context<CreateEscrow>,
+    digest: [u8; 32],
+    recipient: Pubkey,
+    amount: u64,
+    expires_at_slot: u64,
+) -> Result<()> {
+    transfer(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            Transfer {
+                from: ctx.accounts.depositor.to_account_info(),
+                to: ctx.accounts.escrow.to_account_info(),
+            },
+        ),
+        amount,
+    )?;
+    ctx.accounts.escrow.set_inner(Escrow {
+        depositor: ctx.accounts.depositor.key(),
+        recipient,
+        digest,
+        amount,
+        mint: None,
+        expires_at_slot,
+        bump: ctx.bumps.escrow,
+    });
+    Ok(())
+}
+
+pub fn process_create_token_escrow(
+    ctx: Context<CreateTokenEscrow>,
+    digest: [u8; 32],
+    recipient: Pubkey,
+    amount: u64,
+    expires_at_slot: u64,
+) -> Result<()> {
+    let accounts = &ctx.accounts;
+    transfer_checked(
+        CpiContext::new(
+            accounts.token_program.to_account_info(),
+            TransferChecked {
+                from: accounts.depositor_token_account.to_account_info(),
+                mint: accounts.mint.to_account_info(),
+                to: accounts.vault.to_account_info(),
+                authority: accounts.depositor.to_account_info(),
+            },
+        ),
+        amount,
+        accounts.mint.decimals,
+    )?;
+    let escrow = Escrow {
+        depositor: accounts.depositor.key(),
+        recipient,
+        digest,
+        amount,
+        mint: Some(accounts.mint.key()),
+        expires_at_slot,
+        bump: ctx.bumps.escrow,
+    };
+    ctx.accounts.escrow.set_inner(escrow);
+    Ok(())
+}
+
+pub fn process_release_escrow(ctx: Context<ReleaseEscrow>) -> Result<()> {
+    let accounts = &ctx.accounts;
+    accounts
+        .proof_data
+        .load_current()?
+        .check_verified_params(&accounts.escrow.digest, &accounts.config)?;
+    pay_out(
+        &accounts.escrow,
+        &accounts.recipient.to_account_info(),
+        EscrowTokenAccounts {
+            vault: accounts.vault.as_ref(),
+            mint: accounts.mint.as_ref(),
+            destination_token_account: accounts.destination_token_account.as_ref(),
+            token_program: accounts.token_program.as_ref(),
+        },
+    )
+}
+
+pub fn process_refund_escrow(ctx: Context<RefundEscrow>) -> Result<()> {
+    let accounts = &ctx.accounts;
+    pay_out(
+        &accounts.escrow,
+        &accounts.depositor.to_account_info(),
+        EscrowTokenAccounts {
+            vault: accounts.vault.as_ref(),
+            mint: accounts.mint.as_ref(),
+            destination_token_account: accounts.destination_token_account.as_ref(),
+            token_program: accounts.token_program.as_ref(),
+        },
+    )
+}
+
+/// The optional token accounts of releasing or refunding an escrow.
+struct EscrowTokenAccounts<'a, 'info> {
+    vault: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+    mint: Option<&'a InterfaceAccount<'info, Mint>>,
+    destination_token_account: Option<&'a InterfaceAccount<'info, TokenAccount>>,
+    token_program: Option<&'a Interface<'info, TokenInterface>>,
+}
+
+/// Pays the escrowed funds to `to`, or for token escrows to a token account owned by `to`. The
+/// vault is closed, its rent going to the depositor along with the escrow's.
+fn pay_out<'info>(
+    escrow: &Account<'info, Escrow>,
+    to: &AccountInfo<'info>,
+    token: EscrowTokenAccounts<'_, 'info>,
+) -> Result<()> {
+    let Some(mint_key) = escrow.mint else {
+        escrow.sub_lamports(escrow.amount)?;
+        to.add_lamports(escrow.amount)?;
+        return Ok(());
+    };
+
+    let (Some(vault), Some(mint), Some(destination), Some(token_program)) = (
+        token.vault,
+        token.mint,
+        token.destination_token_account,
+        token.token_program,
+    ) else {
+        return err!(WhirError::InvalidEscrowAccounts);
+    };
+    let (vault_key, _) =
+        Pubkey::find_program_address(&[Escrow::VAULT_SEED, escrow.key().as_ref()], &crate::ID);
+    require_keys_eq!(vault.key(), vault_key, WhirError::InvalidEscrowAccounts);
+    require_keys_eq!(mint.key(), mint_key, WhirError::InvalidEscrowAccounts);
+    require_keys_eq!(
+        destination.owner,
+        to.key(),
+        WhirError::InvalidEscrowAccounts
+    );
+
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        Escrow::SEED,
+        escrow.depositor.as_ref(),
+        escrow.digest.as_ref(),
+        &[escrow.bump],
+    ]];
+    transfer_checked(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            TransferChecked {
+                from: vault.to_account_info(),
+                mint: mint.to_account_info(),
+                to: destination.to_account_info(),
+                authority: escrow.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        escrow.amount,
+        mint.decimals,
+    )?;
+    // The vault's rent goes to the escrow, which returns it to the depositor when it's closed.
+    close_account(CpiContext::new_with_signer(
+        token_program.to_account_info(),
+        CloseAccount {
+            account: vault.to_account_info(),
+            destination: escrow.to_account_info(),
+            authority: escrow.to_account_info(),
+        },
+        signer_seeds,
+    ))
+}
@@ -0,0 +1,223 @@
+//! Verification fees collected into a treasury PDA and SPL token gating.
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::sysvar::instructions as sysvar_instructions,
+    system_program::{transfer, Transfer},
+};
+use anchor_spl::token_interface::{burn, Burn, Mint, TokenAccount, TokenInterface};
+
+use crate::{
+    admin::{Allowlist, ProgramConfig, SizeLimits},
+    heap,
+    stats::ProgramStats,
+    verification_log::{ProofSource, VerificationLog, VerificationLogEntry},
+    WhirError, WhirParamsArgs,
+};
+
+/// Treasury collecting the verification fees, a singleton PDA.
+#[account]
+#[derive(InitSpace)]
+pub struct Treasury {
+    pub bump: u8,
+}
+
+impl Treasury {
+    pub const SEED: &'static [u8] = b"treasury";
+}
+
+/// Accounts charging the verification fee, enforcing the token gate and the allowlist, recording
+/// usage statistics and sizing the heap, part of every verify instruction but the released
+/// `verify()`.
+///
+/// The configuration and the caller are required, so the fee, the token gate, the allowlist, the
+/// size limits and the minimum security level bind every caller. The other accounts are only
+/// needed by the features using them.
+#[derive(Accounts)]
+pub struct VerificationFee<'info> {
+    #[account(seeds = [ProgramConfig::SEED], bump = config.bump)]
+    pub config: Account<'info, ProgramConfig>,
+    /// Required if a fee is charged.
+    #[account(mut, seeds = [Treasury::SEED], bump = treasury.bump)]
+    pub treasury: Option<Account<'info, Treasury>>,
+    /// Pays the fee, unless exempt, and receives the proof account's bounty.
+    #[account(mut)]
+    pub caller: Signer<'info>,
+    /// Required if a fee is charged.
+    pub system_program: Option<Program<'info, System>>,
+    /// The caller's token account of the gate mint, required if a token gate is configured.
+    #[account(mut)]
+    pub caller_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    /// The gate mint, required if the token gate burns tokens.
+    #[account(mut)]
+    pub gate_mint: Option<InterfaceAccount<'info, Mint>>,
+    pub token_program: Option<Interface<'info, TokenInterface>>,
+    /// Required if the allowlist is enforced.
+    #[account(seeds = [Allowlist::SEED], bump = allowlist.bump)]
+    pub allowlist: Option<Account<'info, Allowlist>>,
+    /// Updated if passed, see [`ProgramStats`].
+    #[account(mut, seeds = [ProgramStats::SEED], bump = stats.bump)]
+    pub stats: Option<Account<'info, ProgramStats>>,
+    /// Appended to if passed, see [`VerificationLog`].
+    #[account(mut, seeds = [VerificationLog::SEED], bump = verification_log.load()?.bump)]
+    pub verification_log: Option<AccountLoader<'info, VerificationLog>>,
+    /// CHECK: The instructions sysvar, checked by address. Read for the requested heap frame if
+    /// passed, see [`crate::heap`].
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+}
+
+impl VerificationFee<'_> {
+    /// Lets the allocator use the heap frame requested by the transaction, see [`crate::heap`].
+    pub fn use_requested_heap_frame(&self) -> Result<()> {
+        heap::use_requested_heap_frame(self.instructions_sysvar.as_ref())
+    }
+
+    /// Size limits of the configuration.
+    pub fn size_limits(&self) -> &SizeLimits {
+        &self.config.size_limits
+    }
+
+    /// Minimum security level of the configuration.
+    pub fn min_security_level(&self) -> u32 {
+        self.config.min_security_level
+    }
+
+    /// Records a successful verification in the stats and the verification log, if passed.
+    /// `proof_data` is the proof account the proof was read from, if `source` is one.
+    pub fn record_verification(
+        &mut self,
+        params: &WhirParamsArgs,
+        digest: [u8; 32],
+        source: ProofSource,
+        proof_data: Option<Pubkey>,
+    ) -> Result<()> {
+        if let Some(stats) = &mut self.stats {
+            stats.record_verification(params);
+        }
+        if let Some(verification_log) = &self.verification_log {
+            verification_log.load_mut()?.push(VerificationLogEntry {
+                digest,
+                proof_data: proof_data.unwrap_or_default(),
+                caller: self.caller.key(),
+                slot: Clock::get()?.slot,
+                source: source as u8,
+                padding: [0; 7],
+            });
+        }
+        Ok(())
+    }
+
+    /// Records a failed verification in the stats, if passed.
+    pub fn record_failure(&mut self, params: &WhirParamsArgs) {
+        if let Some(stats) = &mut self.stats {
+            stats.record_failure(params);
+        }
+    }
+
+    /// Enforces the allowlist and the token gate and transfers the configured fee from the caller
+    /// to the treasury.
+    pub fn charge(&self) -> Result<()> {
+        let config = &self.config;
+        let caller = &self.caller;
+        require!(
+            config.allows(&caller.key(), self.allowlist.as_deref()),
+            WhirError::NotAllowlisted
+        );
+        self.check_token_gate(config, caller)?;
+
+        let fee = config.fee_lamports;
+        if fee == 0 || config.fee_exempt.contains(&caller.key()) {
+            return Ok(());
+        }
+        let (Some(treasury), Some(system_program)) = (&self.treasury, &self.system_program) else {
+            return err!(WhirError::MissingFeeAccounts);
+        };
+        transfer(
+            CpiContext::new(
+                system_program.to_account_info(),
+                Transfer {
+                    from: caller.to_account_info(),
+                    to: treasury.to_account_info(),
+                },
+            ),
+            fee,
+        )
+    }
+
+    fn check_token_gate(&self, config: &ProgramConfig, caller: &Signer) -> Result<()> {
+        let Some(gate) = &config.token_gate else {
+            return Ok(());
+        };
+        let token_account = self
+            .caller_token_account
+            .as_ref()
+            .ok_or(WhirError::TokenGateNotSatisfied)?;
+        require_keys_eq!(
+            token_account.owner,
+            caller.key(),
+            WhirError::TokenGateNotSatisfied
+        );
+        require_keys_eq!(
+            token_account.mint,
+            gate.mint,
+            WhirError::TokenGateNotSatisfied
+        );
+        require!(
+            token_account.amount >= gate.amount,
+            WhirError::TokenGateNotSatisfied
+        );
+        if !gate.burn {
+            return Ok(());
+        }
+
+        let mint = self
+            .gate_mint
+            .as_ref()
+            .ok_or(WhirError::TokenGateNotSatisfied)?;
+        require_keys_eq!(mint.key(), gate.mint, WhirError::TokenGateNotSatisfied);
+        let token_program = self
+            .token_program
+            .as_ref()
+            .ok_or(WhirError::TokenGateNotSatisfied)?;
+        burn(
+            CpiContext::new(
+                token_program.to_account_info(),
+                Burn {
+                    mint: mint.to_account_info(),
+                    from: token_account.to_account_info(),
+                    authority: caller.to_account_info(),
+                },
+            ),
+            gate.amount,
+        )
+    }
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFees<'info> {
+    #[account(
+        seeds = [ProgramConfig::SEED],
+        bump = config.bump,
+        has_one = admin @ WhirError::Unauthorized
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(mut, seeds = [Treasury::SEED], bump = treasury.bump)]
+    pub treasury: Account<'info, Treasury>,
+    pub admin: Signer<'info>,
+    /// CHECK: Any account can receive the withdrawn fees.
+    #[account(mut)]
+    pub recipient: UncheckedAccount<'info>,
+}
+
+pub fn process_withdraw_fees(ctx: Context<WithdrawFees>, amount: u64) -> Result<()> {
+    let treasury = ctx.accounts.treasury.to_account_info();
+    let rent_exempt = Rent::get()?.minimum_balance(treasury.data_len());
+    require!(
+        treasury.lamports().saturating_sub(rent_exempt) >= amount,
+        WhirError::InsufficientTreasuryBalance
+    );
+    treasury.sub_lamports(amount)?;
+    ctx.accounts.recipient.add_lamports(amount)?;
+    Ok(())
+}
@@ -0,0 +1,208 @@
+//! Heap allocation of the verifier.
+//!
+//! Verification keeps the decoded proof, the parsed commitment and the opened leaves alive until
+//! it completes. The default Solana allocator never frees anything, assumes the default 32 KiB
+//! heap and aborts with an opaque error once it is exhausted. With the `custom-heap` feature,
+//! enabled by default, the program instead allocates with [`BumpAllocator`], which frees and grows
+//! the latest allocation in place, so growing vectors don't leak their previous buffers, and uses
+//! the whole heap frame requested via `ComputeBudgetInstruction::request_heap_frame`.
+//!
+//! The requested frame is read from the instructions sysvar, passed to the verify instructions as
+//! an optional account. Before verifying, [`check_heap`] compares the free heap with an estimate of
+//! the verification's needs and fails with `OutOfMemory`, logging both sizes, instead of aborting
+//! midway.
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{entrypoint::HEAP_LENGTH, sysvar::instructions as sysvar_instructions},
+};
+
+use crate::WhirError;
+
+/// Largest heap frame a transaction can request.
+pub const MAX_HEAP_FRAME_BYTES: usize = 256 * 1024;
+
+/// The compute budget program.
+const COMPUTE_BUDGET_ID: Pubkey = pubkey!("ComputeBudget111111111111111111111111111111");
+
+/// Tag of the compute budget program's `RequestHeapFrame(u32)` instruction.
+const REQUEST_HEAP_FRAME_TAG: u8 = 1;
+
+/// Returns the heap frame requested by the transaction of the instructions sysvar.
+pub fn requested_heap_frame(instructions: &AccountInfo) -> Result<usize> {
+    let num_instructions = {
+        let data = instructions.try_borrow_data()?;
+        let count = data.get(..2).ok_or(ProgramError::InvalidAccountData)?;
+        u16::from_le_bytes([count[0], count[1]])
+    };
+    for index in 0..num_instructions {
+        let ix = sysvar_instructions::load_instruction_at_checked(index.into(), instructions)?;
+        if ix.program_id == COMPUTE_BUDGET_ID && ix.data.first() == Some(&REQUEST_HEAP_FRAME_TAG) {
+            if let Some(bytes) = ix.data.get(1..5) {
+                let frame = u32::from_le_bytes(bytes.try_into().expect("4 bytes")) as usize;
+                return Ok(frame.min(MAX_HEAP_FRAME_BYTES));
+            }
+        }
+    }
+    Ok(HEAP_LENGTH)
+}
+
+/// Lets the allocator use the heap frame requested by the transaction, if the instructions sysvar
+/// is passed. Otherwise, the default 32 KiB frame is assumed.
+pub fn use_requested_heap_frame(instructions: Option<&UncheckedAccount>) -> Result<()> {
+    if let Some(instructions) = instructions {
+        allocator::set_heap_frame(requested_heap_frame(instructions)?);
+    }
+    Ok(())
+}
+
+/// Fails with `OutOfMemory` if fewer than `required` heap bytes are free. Always passes if the
+/// free heap is unknown, i.e. off-chain, with the default allocator or as a dependency.
+pub fn check_heap(required: usize) -> Result<()> {
+    if let Some(free) = allocator::free_heap() {
+        if required > free {
+            msg!(
+                "Verification needs about {} heap bytes, {} are free, request a larger heap frame",
+                required,
+                free
+            );
+            return Err(error!(WhirError::OutOfMemory).with_values((required, free)));
+        }
+    }
+    Ok(())
+}
+
+/// Bump allocator over the heap frame, see the module docs.
+pub struct BumpAllocator;
+
+#[cfg(all(
+    target_os = "solana",
+    feature = "custom-heap",
+    not(feature = "no-entrypoint")
+))]
+mod allocator {
+    use std::{
+        alloc::{GlobalAlloc, Layout},
+        ptr,
+    };
+
+    use anchor_lang::solana_program::{
+        entrypoint::{HEAP_LENGTH, HEAP_START_ADDRESS},
+        log::{sol_log, sol_log_64},
+    };
+
+    use super::{BumpAllocator, MAX_HEAP_FRAME_BYTES};
+
+    const HEAP_START: usize = HEAP_START_ADDRESS as usize;
+
+    /// Allocator state, kept at the start of the zero-initialized heap.
+    #[repr(C)]
+    struct HeapState {
+        /// Address of the next allocation, zero before the first one.
+        next: usize,
+        /// Address of the latest allocation still in use, zero if unknown.
+        last: usize,
+        /// Usable heap frame length, zero for the default.
+        frame_len: usize,
+    }
+
+    /// Returns the allocator state, initialized on first use.
+    ///
+    /// # Safety
+    ///
+    /// Programs are single-threaded, no other reference to the state may be alive.
+    unsafe fn state() -> &'static mut HeapState {
+        let state = &mut *(HEAP_START as *mut HeapState);
+        if state.next == 0 {
+            state.next = HEAP_START + std::mem::size_of::<HeapState>();
+        }
+        state
+    }
+
+    fn heap_end(state: &HeapState) -> usize {
+        let frame_len = match state.frame_len {
+            0 => HEAP_LENGTH,
+            frame_len => frame_len,
+        };
+        HEAP_START + frame_len
+    }
+
+    pub fn set_heap_frame(frame_len: usize) {
+        // SAFETY: No allocation is in progress.
+        let state = unsafe { state() };
+        state.frame_len = frame_len.clamp(HEAP_LENGTH, MAX_HEAP_FRAME_BYTES);
+    }
+
+    pub fn free_heap() -> Option<usize> {
+        // SAFETY: No allocation is in progress.
+        let state = unsafe { state() };
+        Some(heap_end(state) - state.next)
+    }
+
+    unsafe impl GlobalAlloc for BumpAllocator {
+        unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+            let state = state();
+            let start = state.next.next_multiple_of(layout.align());
+            match start.checked_add(layout.size()) {
+                Some(end) if end <= heap_end(state) => {
+                    state.next = end;
+                    state.last = start;
+                    start as *mut u8
+                }
+                _ => {
+                    sol_log("Out of heap memory, requested and free bytes:");
+                    sol_log_64(
+                        layout.size() as u64,
+                        (heap_end(state) - state.next) as u64,
+                        0,
+                        0,
+                        0,
+                    );
+                    ptr::null_mut()
+                }
+            }
+        }
+
+        unsafe fn dealloc(&self, ptr: *mut u8, _layout: Layout) {
+            let state = state();
+            // Only the latest allocation can be freed, the rest is reclaimed with the frame.
+            if ptr as usize == state.last {
+                state.next = state.last;
+                state.last = 0;
+            }
+        }
+
+        unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+            let state = state();
+            if ptr as usize == state.last {
+                if let Some(end) = state.last.checked_add(new_size) {
+                    if end <= heap_end(state) {
+                        state.next = end;
+                        return ptr;
+                    }
+                }
+            }
+            let new_ptr = self.alloc(Layout::from_size_align_unchecked(new_size, layout.align()));
+            if !new_ptr.is_null() {
+                ptr::copy_nonoverlapping(ptr, new_ptr, layout.size().min(new_size));
+            }
+            new_ptr
+        }
+    }
+
+    #[global_allocator]
+    static ALLOCATOR: BumpAllocator = BumpAllocator;
+}
+
+#[cfg(not(all(
+    target_os = "solana",
+    feature = "custom-heap",
+    not(feature = "no-entrypoint")
+)))]
+mod allocator {
+    pub fn set_heap_frame(_frame_len: usize) {}
+
+    pub fn free_heap() -> Option<usize> {
+        None
+    }
+}
@@ -3,26 +3,101 @@
 //! It accepts a proof and WHIR parameters, making the program universal as it can,
 //! theoretically, verify any WHIR proof.
 
-use anchor_lang::prelude::*;
-use ark_serialize::CanonicalDeserialize;
-use spongefish::DomainSeparator;
-use whir_common::{
-    poly_utils::multilinear::MultilinearPoint,
-    whir::{
-        committer::reader::CommitmentReader,
-        domainsep::WhirDomainSeparator,
-        statement::{Statement, Weights},
+use anchor_lang::{
+    prelude::*,
+    solana_program::{
+        hash::{hash, Hasher},
+        sysvar::instructions as sysvar_instructions,
     },
+    system_program::{transfer, Transfer},
+    Discriminator,
 };
-use whir_config::{field_size_bytes, DOMAIN_SEPARATOR, F};
-use whir_verifier::Verifier;
+use anchor_spl::{
+    token_2022::Token2022,
+    token_interface::{Mint, TokenAccount},
+};
+use whir_common::parameters::SoundnessType;
+use whir_config::{
+    commitment_size_bytes, cost, create_whir_params_with,
+    encoding::ProofEncoding,
+    field::decode_field,
+    field_size_bytes, is_pow_free,
+    params::{params_digest, ParamsSoundness, ParamsV1, VersionedParams},
+    transcript,
+    validation::{validate_params_with, ParamsError},
+    ProtocolOptions, ReedSolomonBackend, WhirParams, DIGEST_SIZE_BYTES, NO_POW_BITS,
+};
+use whir_verifier_core::{deserialize_eval_point, verify_proof, DigestHasher, VerifyError};
+
+/// Logs like `msg!`, only with the `debug-logs` feature. Formatting and logging cost compute
+/// units on every execution, release builds only keep compact logs.
+macro_rules! debug_msg {
+    ($($arg:tt)*) => {
+        #[cfg(feature = "debug-logs")]
+        msg!($($arg)*);
+    };
+}
+
+/// Logs the remaining compute units at the end of a verification phase, only with the
+/// `cu-instrumentation` feature. Differences between consecutive checkpoints are the phases' costs.
+macro_rules! cu_checkpoint {
+    ($phase:expr) => {
+        #[cfg(feature = "cu-instrumentation")]
+        {
+            msg!("CU checkpoint: {}", $phase);
+            anchor_lang::solana_program::log::sol_log_compute_units();
+        }
+        #[cfg(not(feature = "cu-instrumentation"))]
+        let _ = $phase;
+    };
+}
+
+pub mod admin;
+pub mod claims;
+pub mod commitment;
+pub mod compression;
+pub mod compute;
+pub mod container;
+pub mod escrow;
+pub mod fees;
+pub mod heap;
+pub mod migration;
+pub mod multisig;
+pub mod oracle;
+pub mod public_inputs;
+pub mod receipt;
+pub mod registry;
+pub mod sampling;
+pub mod simulation;
+pub mod stats;
+pub mod transcript_pattern;
+pub mod verification_log;
+
+pub use admin::*;
+pub use claims::*;
+pub use commitment::*;
+pub use compression::*;
+pub use compute::*;
+pub use container::*;
+pub use escrow::*;
+pub use fees::*;
+pub use heap::*;
+pub use migration::*;
+pub use multisig::*;
+pub use oracle::*;
+pub use public_inputs::*;
+pub use receipt::*;
+pub use registry::*;
+pub use sampling::*;
+pub use simulation::*;
+pub use stats::*;
+pub use transcript_pattern::*;
+pub use verification_log::*;
 
 declare_id!("AnycMJFRbi6gLYUtLH9YGVcE9F7PxnC1BijCWQMM3h9a");
 
 #[program]
 pub mod whir_verifier_solana {
-    use whir_config::create_whir_params;
-
     use super::*;
 
     /// Initialize a proof account to store proof data across multiple transactions.
@@ -30,91 +105,1253 @@ pub mod whir_verifier_solana {
         ctx: Context<InitProof>,
         eval_point_bytes: Vec<u8>,
         eval_value_bytes: Vec<u8>,
+        encoding: u8,
     ) -> Result<()> {
-        let proof_data = &mut ctx.accounts.proof_data;
-        proof_data.payer = ctx.accounts.payer.key();
-        proof_data.proof = Vec::new();
-        proof_data.eval_point = eval_point_bytes;
-        proof_data.eval_value = eval_value_bytes;
-        Ok(())
+        let accounts = &ctx.accounts;
+        process_init_proof(
+            &accounts.proof_data,
+            accounts.payer.key(),
+            accounts.size_limits(),
+            EvalConstraint {
+                eval_point: eval_point_bytes,
+                eval_value: eval_value_bytes,
+            },
+            encoding,
+            String::new(),
+            Vec::new(),
+        )
+    }
+
+    /// Initialize a proof account with a domain separator label, giving protocols sharing this
+    /// deployment independent Fiat-Shamir domains. An empty label selects the default one.
+    pub fn init_proof_v2(
+        ctx: Context<InitProof>,
+        eval_point_bytes: Vec<u8>,
+        eval_value_bytes: Vec<u8>,
+        encoding: u8,
+        domain_label: String,
+    ) -> Result<()> {
+        let accounts = &ctx.accounts;
+        process_init_proof(
+            &accounts.proof_data,
+            accounts.payer.key(),
+            accounts.size_limits(),
+            EvalConstraint {
+                eval_point: eval_point_bytes,
+                eval_value: eval_value_bytes,
+            },
+            encoding,
+            domain_label,
+            Vec::new(),
+        )
+    }
+
+    /// Initialize a proof account with a domain separator label and opaque user metadata, e.g. an
+    /// order id, batch number or rollup block height, echoed in the verification and receipt events
+    /// for indexing. The user metadata is at most `MAX_USER_METADATA_LEN` bytes.
+    pub fn init_proof_v3(
+        ctx: Context<InitProof>,
+        eval_point_bytes: Vec<u8>,
+        eval_value_bytes: Vec<u8>,
+        encoding: u8,
+        domain_label: String,
+        user_metadata: Vec<u8>,
+    ) -> Result<()> {
+        let accounts = &ctx.accounts;
+        process_init_proof(
+            &accounts.proof_data,
+            accounts.payer.key(),
+            accounts.size_limits(),
+            EvalConstraint {
+                eval_point: eval_point_bytes,
+                eval_value: eval_value_bytes,
+            },
+            encoding,
+            domain_label,
+            user_metadata,
+        )
+    }
+
+    /// Create, fund and initialize a proof account of `space` bytes in a single instruction.
+    ///
+    /// The proof account is a new keypair signing the transaction. Accounts created by a program
+    /// are limited to 10 KiB, bigger proofs need a pre-created account and `init_proof_v2`.
+    pub fn create_proof(
+        ctx: Context<CreateProof>,
+        _space: u32,
+        eval_point_bytes: Vec<u8>,
+        eval_value_bytes: Vec<u8>,
+        encoding: u8,
+        domain_label: String,
+    ) -> Result<()> {
+        let accounts = &ctx.accounts;
+        process_init_proof(
+            &accounts.proof_data,
+            accounts.payer.key(),
+            &accounts.config.size_limits,
+            EvalConstraint {
+                eval_point: eval_point_bytes,
+                eval_value: eval_value_bytes,
+            },
+            encoding,
+            domain_label,
+            Vec::new(),
+        )
     }
 
     /// Upload a chunk of proof data to the proof account.
     pub fn upload_chunk(ctx: Context<UploadChunk>, chunk: Vec<u8>) -> Result<()> {
-        ctx.accounts.proof_data.proof.extend_from_slice(&chunk);
+        process_upload_chunk(
+            &ctx.accounts.proof_data,
+            ctx.accounts.size_limits(),
+            None,
+            &chunk,
+        )?;
+        if let Some(stats) = &mut ctx.accounts.stats {
+            stats.record_upload(chunk.len());
+        }
         Ok(())
     }
 
-    /// Verify the proof stored in the proof account.
+    /// Upload a chunk of proof data at a byte offset of the proof, by the authority or one of the
+    /// proof account's uploaders. Chunks can be uploaded concurrently and in any order.
+    pub fn upload_chunk_v2(ctx: Context<UploadChunkAt>, offset: u32, chunk: Vec<u8>) -> Result<()> {
+        process_upload_chunk(
+            &ctx.accounts.proof_data,
+            &ctx.accounts.config.size_limits,
+            Some(offset),
+            &chunk,
+        )?;
+        if let Some(stats) = &mut ctx.accounts.stats {
+            stats.record_upload(chunk.len());
+        }
+        Ok(())
+    }
+
+    /// Append an evaluation constraint to the statement of the proof account.
+    ///
+    /// Allows building statements with more constraints than fit in a single transaction.
+    pub fn add_constraint(
+        ctx: Context<UploadChunk>,
+        eval_point_bytes: Vec<u8>,
+        eval_value_bytes: Vec<u8>,
+    ) -> Result<()> {
+        ctx.accounts
+            .size_limits()
+            .check_eval_point(eval_point_bytes.len())?;
+        ctx.accounts
+            .proof_data
+            .load_current_mut()?
+            .update_metadata(|metadata| {
+                metadata.constraints.push(EvalConstraint {
+                    eval_point: eval_point_bytes,
+                    eval_value: eval_value_bytes,
+                })
+            })
+    }
+
+    /// Append an evaluation constraint whose point is derived from typed public inputs, for a
+    /// polynomial of `num_variables` variables, see [`public_inputs`].
+    pub fn add_public_inputs_constraint(
+        ctx: Context<UploadChunk>,
+        num_variables: u32,
+        public_inputs: Vec<PublicInputArg>,
+        eval_value_bytes: Vec<u8>,
+    ) -> Result<()> {
+        process_add_public_inputs_constraint(ctx, num_variables, public_inputs, eval_value_bytes)
+    }
+
+    /// Index a proof at `offset..offset + len` of the proof region with its evaluation constraint,
+    /// turning the proof account into a container of independent proofs, see [`container`].
+    pub fn add_contained_proof(
+        ctx: Context<UploadChunk>,
+        offset: u32,
+        len: u32,
+        eval_point_bytes: Vec<u8>,
+        eval_value_bytes: Vec<u8>,
+    ) -> Result<()> {
+        process_add_contained_proof(ctx, offset, len, eval_point_bytes, eval_value_bytes)
+    }
+
+    /// Mark the proof and statement as complete. No more chunks or constraints can be added.
+    pub fn finalize_upload(ctx: Context<UploadChunk>) -> Result<()> {
+        ctx.accounts.proof_data.load_current_mut()?.finalized = 1;
+        Ok(())
+    }
+
+    /// Verify the proof stored in the proof account, the released fee-free instruction. It only
+    /// checks the proof, against the default size limits and minimum security level, and records
+    /// nothing; the other verify instructions charge the configured fee and record the proof.
     pub fn verify(
-        ctx: Context<VerifyProof>,
+        ctx: Context<VerifyProofV1>,
         num_variables: u8,
         security_level: u8,
         pow_bits: u8,
         folding_factor: u8,
         starting_log_inv_rate: u8,
     ) -> Result<()> {
-        let proof_data = &ctx.accounts.proof_data;
-        let proof_bytes = proof_data.proof.as_slice();
-        let eval_point_bytes = proof_data.eval_point.as_slice();
-        let eval_value_bytes = proof_data.eval_value.as_slice();
-
-        msg!("WHIR Verifier: Starting verification");
-        msg!(
-            "Config: num_vars={}, security={}, pow_bits={}",
-            num_variables,
-            security_level,
-            pow_bits
-        );
+        let params = WhirParamsArgs {
+            num_variables: num_variables.into(),
+            security_level: security_level.into(),
+            pow_bits: pow_bits.into(),
+            folding_factor: folding_factor.into(),
+            starting_log_inv_rate: starting_log_inv_rate.into(),
+        };
+        let accounts = &*ctx.accounts;
+        heap::use_requested_heap_frame(accounts.instructions_sysvar.as_ref())?;
+        let stored_proof = StoredProofAccounts {
+            proof_data: &accounts.proof_data,
+            statement: accounts.statement.as_ref(),
+            eval_value_account: accounts.eval_value_account.as_ref(),
+            external_proof_account: accounts.external_proof_account.as_ref(),
+            transcript_pattern: accounts.transcript_pattern.as_deref(),
+        };
+        verify_stored_proof(
+            &stored_proof,
+            &SizeLimits::DEFAULT,
+            ProgramConfig::DEFAULT_MIN_SECURITY_LEVEL,
+            ctx.remaining_accounts,
+            &params,
+            SoundnessTypeArg::default(),
+        )?;
+        Ok(())
+    }
 
-        let params = create_whir_params(
-            num_variables as usize,
-            security_level as usize,
-            pow_bits as usize,
-            folding_factor as usize,
-            starting_log_inv_rate as usize,
-        );
+    /// Verify the proof stored in the proof account, taking the WHIR parameters as a struct.
+    pub fn verify_v2(ctx: Context<VerifyProof>, params: WhirParamsArgs) -> Result<()> {
+        process_verify(
+            ctx.accounts,
+            ctx.remaining_accounts,
+            &params,
+            SoundnessTypeArg::default(),
+        )?;
+        Ok(())
+    }
+
+    /// Verify the proof stored in the proof account like `verify_v2`, but complete with the
+    /// outcome instead of failing if the proof doesn't verify, counting the failure in the stats.
+    /// The fee is charged either way.
+    pub fn verify_with_outcome(
+        ctx: Context<VerifyProof>,
+        params: WhirParamsArgs,
+    ) -> Result<SimulationResult> {
+        let accounts = &mut *ctx.accounts;
+        let stored_proof = StoredProofAccounts {
+            proof_data: &accounts.proof_data,
+            statement: accounts.statement.as_ref(),
+            eval_value_account: accounts.eval_value_account.as_ref(),
+            external_proof_account: accounts.external_proof_account.as_ref(),
+            transcript_pattern: accounts.transcript_pattern.as_deref(),
+        };
+        verify_and_record_outcome(
+            &stored_proof,
+            &mut accounts.fee,
+            ctx.remaining_accounts,
+            &params,
+        )
+    }
+
+    /// Verify the proof stored in the proof account, whose parameters are derived under
+    /// `soundness_type` instead of the list-decoding conjecture of `verify_v2`.
+    pub fn verify_v3(
+        ctx: Context<VerifyProof>,
+        params: WhirParamsArgs,
+        soundness_type: SoundnessTypeArg,
+    ) -> Result<()> {
+        process_verify(
+            ctx.accounts,
+            ctx.remaining_accounts,
+            &params,
+            soundness_type,
+        )?;
+        Ok(())
+    }
+
+    /// Verify the proof stored in the proof account, taking the WHIR parameters in the versioned
+    /// wire format shared with the prover, see `whir_config::params`.
+    pub fn verify_v4(ctx: Context<VerifyProof>, params: VersionedParamsArgs) -> Result<()> {
+        let (args, soundness_type) = params.parts();
+        process_verify(ctx.accounts, ctx.remaining_accounts, args, soundness_type)?;
+        Ok(())
+    }
+
+    /// Record the parameters the proof was generated with. Verifying the proof with any other
+    /// parameters then fails with `ParamsMismatch`.
+    pub fn set_proof_params(ctx: Context<UploadChunk>, params: VersionedParamsArgs) -> Result<()> {
+        ctx.accounts
+            .proof_data
+            .load_current_mut()?
+            .update_metadata(|metadata| metadata.params = Some(params))
+    }
+
+    /// Bind the proof to a shared statement account, or unbind it. The verify instructions then
+    /// require exactly this statement account, so callers can't pick the statement.
+    pub fn set_statement(ctx: Context<UploadChunk>, statement: Option<Pubkey>) -> Result<()> {
+        ctx.accounts
+            .proof_data
+            .load_current_mut()?
+            .update_metadata(|metadata| metadata.statement = statement)
+    }
+
+    /// Verify the proof and, on success, close the proof account, refunding its rent to the payer.
+    ///
+    /// If the receipt accounts are passed, a receipt token is minted before closing. The verified
+    /// digest is returned, as the proof account can't be consumed after it's closed.
+    pub fn verify_and_close(
+        ctx: Context<VerifyAndClose>,
+        params: WhirParamsArgs,
+    ) -> Result<[u8; 32]> {
+        process_verify_and_close(ctx.accounts, ctx.remaining_accounts, &params)
+    }
+
+    /// Verify the contained proof at `index` of a finalized container, returning its digest.
+    ///
+    /// Only the contained proof's own constraints are verified, shared statement accounts, eval
+    /// value sources, buffers and external proofs don't apply to contained proofs.
+    pub fn verify_contained_proof(
+        ctx: Context<VerifyProof>,
+        params: WhirParamsArgs,
+        index: u32,
+    ) -> Result<[u8; 32]> {
+        process_verify_contained_proof(ctx.accounts, &params, index)
+    }
+
+    /// Check the proof account's inputs against the WHIR parameters without verifying the proof.
+    ///
+    /// Surfaces malformed evaluation points and values, incompatible folding factors, parameters
+    /// needing more proof of work than `pow_bits` and missing or oversized proofs with precise
+    /// errors, much cheaper than a failing `verify`.
+    pub fn validate_proof_inputs(
+        ctx: Context<ValidateProofInputs>,
+        params: WhirParamsArgs,
+    ) -> Result<()> {
+        process_validate_proof_inputs(ctx.accounts, &params)
+    }
+
+    /// Run the verification of `verify_v2` without failing or modifying any account, returning
+    /// the outcome and, on failure, the failing stage and error code, see [`SimulationResult`].
+    ///
+    /// Meant for RPC simulation, to check a proof without paying for a failed `verify`. No fee is
+    /// charged.
+    pub fn simulate_verify(
+        ctx: Context<SimulateVerify>,
+        params: WhirParamsArgs,
+    ) -> Result<SimulationResult> {
+        process_simulate_verify(ctx, &params)
+    }
+
+    /// Build the transcript pattern of the parameters, domain label and context length once and
+    /// cache it in a PDA, see [`TranscriptPattern`].
+    ///
+    /// Verify instructions passed the PDA load the Fiat-Shamir domain separator from it instead of
+    /// rebuilding it.
+    pub fn cache_transcript_pattern(
+        ctx: Context<CacheTranscriptPattern>,
+        params: WhirParamsArgs,
+        domain_label: String,
+        context_len: u32,
+    ) -> Result<()> {
+        process_cache_transcript_pattern(ctx, params, domain_label, context_len)
+    }
+
+    /// Check a commitment's well-formedness against the WHIR parameters and record it as accepted
+    /// in a PDA keyed by its Merkle root and payer, see [`AcceptedCommitment`].
+    ///
+    /// Lets dApps acknowledge a commitment before any opening proof is uploaded. The commitment is
+    /// the root followed by the serialized out-of-domain answers.
+    pub fn accept_commitment(
+        ctx: Context<AcceptCommitment>,
+        params: WhirParamsArgs,
+        root: [u8; 32],
+        ood_answers: Vec<u8>,
+    ) -> Result<()> {
+        process_accept_commitment(ctx, params, root, ood_answers)
+    }
+
+    /// Request an opening of an accepted commitment at an evaluation point derived from the most
+    /// recent slot hash, see [`sampling`].
+    pub fn request_sample(ctx: Context<RequestSample>, sample_id: u64) -> Result<()> {
+        sampling::process_request_sample(ctx, sample_id)
+    }
+
+    /// Record the evaluation of a sample request from a proof verified for the sampled commitment
+    /// with a constraint at the sampled point.
+    pub fn fulfill_sample(ctx: Context<FulfillSample>) -> Result<()> {
+        sampling::process_fulfill_sample(ctx)
+    }
+
+    /// Verify a proof passed directly in the instruction data.
+    ///
+    /// For configurations whose proofs fit in a single transaction, this skips the proof account
+    /// lifecycle entirely.
+    pub fn verify_inline(
+        ctx: Context<VerifyInline>,
+        params: WhirParamsArgs,
+        proof_bytes: Vec<u8>,
+        eval_point_bytes: Vec<u8>,
+        eval_value_bytes: Vec<u8>,
+    ) -> Result<()> {
+        ctx.accounts.fee.use_requested_heap_frame()?;
+        ctx.accounts.fee.charge()?;
+        let size_limits = ctx.accounts.fee.size_limits();
+        size_limits.check_proof(proof_bytes.len())?;
+        size_limits.check_eval_point(eval_point_bytes.len())?;
+        let digest = verify_whir_proof(
+            &proof_bytes,
+            "",
+            &[],
+            std::iter::once((eval_point_bytes.as_slice(), eval_value_bytes.as_slice())),
+            &params,
+            SoundnessTypeArg::default(),
+            ctx.accounts.fee.min_security_level(),
+            None,
+        )?;
+        ctx.accounts
+            .fee
+            .record_verification(&params, digest, ProofSource::InstructionData, None)
+    }
+
+    /// Carry a proof chunk for `verify_from_instructions` in the same transaction. Does nothing.
+    pub fn proof_chunk(_ctx: Context<CarryProofChunk>, _chunk: Vec<u8>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Verify a proof assembled from the `proof_chunk` instructions preceding this one in the
+    /// same transaction, in order, after the previous `verify_from_instructions` if any. Allows
+    /// single-transaction verification without any account.
+    pub fn verify_from_instructions(
+        ctx: Context<VerifyFromInstructions>,
+        params: WhirParamsArgs,
+        eval_point_bytes: Vec<u8>,
+        eval_value_bytes: Vec<u8>,
+    ) -> Result<()> {
+        ctx.accounts.fee.use_requested_heap_frame()?;
+        ctx.accounts.fee.charge()?;
+        let proof_bytes = collect_proof_chunks(&ctx.accounts.instructions)?;
+        let size_limits = ctx.accounts.fee.size_limits();
+        size_limits.check_proof(proof_bytes.len())?;
+        size_limits.check_eval_point(eval_point_bytes.len())?;
+        let digest = verify_whir_proof(
+            &proof_bytes,
+            "",
+            &[],
+            std::iter::once((eval_point_bytes.as_slice(), eval_value_bytes.as_slice())),
+            &params,
+            SoundnessTypeArg::default(),
+            ctx.accounts.fee.min_security_level(),
+            None,
+        )?;
+        ctx.accounts
+            .fee
+            .record_verification(&params, digest, ProofSource::ProofChunks, None)
+    }
+
+    /// Read the value of the proof's first constraint from `account` at verification time.
+    ///
+    /// Binds the proof to live on-chain state, e.g. an oracle feed or a rollup output account,
+    /// instead of a value copied into the proof account.
+    pub fn set_eval_value_source(
+        ctx: Context<UploadChunk>,
+        account: Pubkey,
+        offset: u32,
+    ) -> Result<()> {
+        ctx.accounts
+            .proof_data
+            .load_current_mut()?
+            .update_metadata(|metadata| {
+                metadata.eval_value_source = Some(EvalValueSource { account, offset })
+            })
+    }
+
+    /// Attach a buffer account to the proof account, extending the proof beyond a single account.
+    ///
+    /// The proof is read as the proof account's bytes followed by its buffers in attach order.
+    pub fn init_buffer(ctx: Context<InitBuffer>) -> Result<()> {
+        let buffer = &mut ctx.accounts.buffer;
+        buffer.version = PROOF_BUFFER_VERSION;
+        buffer.proof_data = ctx.accounts.proof_data.key();
+        buffer.data = Vec::new();
+        let buffer_key = buffer.key();
+        ctx.accounts
+            .proof_data
+            .load_current_mut()?
+            .update_metadata(|metadata| metadata.buffers.push(buffer_key))
+    }
+
+    /// Upload a chunk of proof data to a buffer account.
+    pub fn upload_buffer_chunk(ctx: Context<UploadBufferChunk>, chunk: Vec<u8>) -> Result<()> {
+        let size_limits = &ctx.accounts.config.size_limits;
+        size_limits.check_chunk(chunk.len())?;
+        size_limits.check_proof(ctx.accounts.buffer.data.len() + chunk.len())?;
+        ctx.accounts.buffer.data.extend_from_slice(&chunk);
+        if let Some(stats) = &mut ctx.accounts.stats {
+            stats.record_upload(chunk.len());
+        }
+        Ok(())
+    }
+
+    /// Close a buffer account and reclaim rent, detaching it from its proof account.
+    pub fn close_buffer(ctx: Context<CloseBuffer>) -> Result<()> {
+        let buffer_key = ctx.accounts.buffer.key();
+        ctx.accounts
+            .proof_data
+            .load_current_mut()?
+            .update_metadata(|metadata| metadata.buffers.retain(|key| *key != buffer_key))
+    }
+
+    /// Read the proof bytes from an account owned by another program instead of uploading them.
+    ///
+    /// The bytes at `offset..offset + len` of `account` must have the SHA-256 `hash` at
+    /// verification time, which pins the proof regardless of who controls the account.
+    pub fn set_external_proof(
+        ctx: Context<UploadChunk>,
+        account: Pubkey,
+        offset: u32,
+        len: u32,
+        hash: [u8; 32],
+    ) -> Result<()> {
+        ctx.accounts
+            .proof_data
+            .load_current_mut()?
+            .update_metadata(|metadata| {
+                metadata.external_proof = Some(ExternalProofSource {
+                    account,
+                    offset,
+                    len,
+                    hash,
+                })
+            })
+    }
+
+    /// Bind the proof to an application context, absorbed into the transcript before verification.
+    ///
+    /// The context must match the one used by the prover, see `whir_config::transcript`.
+    pub fn set_context(ctx: Context<UploadChunk>, context: Vec<u8>) -> Result<()> {
+        ctx.accounts
+            .proof_data
+            .load_current_mut()?
+            .update_metadata(|metadata| metadata.context = context)
+    }
+
+    /// Make `verify` reject the proof after the given slot, or remove the expiry.
+    pub fn set_expiry(ctx: Context<UploadChunk>, expires_at_slot: Option<u64>) -> Result<()> {
+        let mut proof_data = ctx.accounts.proof_data.load_current_mut()?;
+        proof_data.has_expiry = expires_at_slot.is_some().into();
+        proof_data.expires_at_slot = expires_at_slot.unwrap_or_default();
+        Ok(())
+    }
+
+    /// Escrow a lamport bounty in the proof account, paid to whoever successfully verifies it.
+    ///
+    /// The account can't be closed or shrunk while it holds the bounty; without an expiry, see
+    /// [`set_expiry`], a bounty on a proof that never verifies stays in the account.
+    pub fn escrow_bounty(ctx: Context<EscrowBounty>, lamports: u64) -> Result<()> {
+        transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                Transfer {
+                    from: ctx.accounts.payer.to_account_info(),
+                    to: ctx.accounts.proof_data.to_account_info(),
+                },
+            ),
+            lamports,
+        )?;
+        let mut proof_data = ctx.accounts.proof_data.load_current_mut()?;
+        proof_data.bounty_lamports = proof_data
+            .bounty_lamports
+            .checked_add(lamports)
+            .ok_or(WhirError::BountyOverflow)?;
+        Ok(())
+    }
 
-        let domainsep = DomainSeparator::new(DOMAIN_SEPARATOR)
-            .commit_statement(&params)
-            .add_whir_proof(&params);
+    /// Record the consumption of a verified proof in the replay-protection registry.
+    ///
+    /// Creates the consumer's registry entry of the proof's (commitment, statement) digest,
+    /// failing if the consumer consumed the digest before. Consuming programs call it via CPI,
+    /// signing as the consumer, to use each proof once.
+    pub fn consume_verification(ctx: Context<ConsumeVerification>, digest: [u8; 32]) -> Result<()> {
+        registry::process_consume_verification(ctx, digest)
+    }
 
-        let mut verifier_state = domainsep.to_verifier_state(proof_bytes);
+    /// Publish the verified evaluations of a proof in the `EvaluationResult` PDA of its commitment
+    /// and evaluation points, making them queryable by other programs.
+    pub fn publish_evaluation(ctx: Context<PublishEvaluation>) -> Result<()> {
+        oracle::process_publish_evaluation(ctx)
+    }
 
-        let commitment_reader = CommitmentReader::new(&params);
-        let parsed_commitment = commitment_reader
-            .parse_commitment(&mut verifier_state)
-            .map_err(|_| WhirError::CommitmentParseError)?;
+    /// Mint a non-transferable receipt token for a verified proof to the owner of
+    /// `recipient_token_account`, if its parameters meet the minimum security level. At most one
+    /// receipt is minted per proof account.
+    pub fn mint_receipt(ctx: Context<MintReceipt>) -> Result<()> {
+        receipt::process_mint_receipt(ctx)
+    }
 
-        let eval_point = deserialize_eval_point(eval_point_bytes)?;
+    /// Append a verified proof's digest as a leaf to the digest tree, see [`compression`], if its
+    /// parameters meet the minimum security level. At most one leaf is appended per proof account.
+    pub fn append_verified_digest(ctx: Context<AppendVerifiedDigest>) -> Result<()> {
+        compression::process_append_verified_digest(ctx)
+    }
 
-        let eval_value = F::deserialize_compressed(eval_value_bytes)
-            .map_err(|_| WhirError::DeserializationError)?;
+    /// Post an evaluation claim of a commitment with a bond of `bond_lamports`, accepted unless
+    /// challenged and not proven in time, see [`claims`].
+    pub fn post_claim(
+        ctx: Context<PostClaim>,
+        claim_id: u64,
+        params: WhirParamsArgs,
+        evaluation: ClaimedEvaluation,
+        bond_lamports: u64,
+    ) -> Result<()> {
+        claims::process_post_claim(ctx, claim_id, params, evaluation, bond_lamports)
+    }
 
-        let mut statement = Statement::new(num_variables as usize);
-        statement.add_constraint(Weights::evaluation(eval_point), eval_value);
+    /// Challenge a pending claim by matching its bond, demanding a proof by the response deadline.
+    pub fn challenge_claim(ctx: Context<ChallengeClaim>) -> Result<()> {
+        claims::process_challenge_claim(ctx)
+    }
 
-        let verifier = Verifier::new(&params);
-        verifier
-            .verify(&mut verifier_state, parsed_commitment, statement)
-            .map_err(|_| WhirError::VerificationFailed)?;
+    /// Verify a proof of a claim with the claim's parameters and statement, accepting the claim
+    /// and paying the bonds to its submitter.
+    pub fn prove_claim(ctx: Context<ProveClaim>) -> Result<()> {
+        claims::process_prove_claim(ctx)
+    }
 
-        msg!("WHIR Verifier: Verification successful!");
+    /// Settle a claim after its deadline: accept it if unchallenged, slash it if challenged and
+    /// not proven.
+    pub fn settle_claim(ctx: Context<SettleClaim>) -> Result<()> {
+        claims::process_settle_claim(ctx)
+    }
 
+    /// Close a settled claim, refunding its rent to the submitter.
+    pub fn close_claim(_ctx: Context<CloseClaim>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Lock lamports until a proof with `digest` verifies, paying them to `recipient`. The
+    /// depositor can refund them after `expires_at_slot`.
+    pub fn create_escrow(
+        ctx: Context<CreateEscrow>,
+        digest: [u8; 32],
+        recipient: Pubkey,
+        amount: u64,
+        expires_at_slot: u64,
+    ) -> Result<()> {
+        escrow::process_create_escrow(ctx, digest, recipient, amount, expires_at_slot)
+    }
+
+    /// Lock SPL tokens until a proof with `digest` verifies, like `create_escrow`.
+    pub fn create_token_escrow(
+        ctx: Context<CreateTokenEscrow>,
+        digest: [u8; 32],
+        recipient: Pubkey,
+        amount: u64,
+        expires_at_slot: u64,
+    ) -> Result<()> {
+        escrow::process_create_token_escrow(ctx, digest, recipient, amount, expires_at_slot)
+    }
+
+    /// Pay an escrow to its recipient, given a proof account verified with the escrow's digest.
+    pub fn release_escrow(ctx: Context<ReleaseEscrow>) -> Result<()> {
+        escrow::process_release_escrow(ctx)
+    }
+
+    /// Return an expired escrow to its depositor.
+    pub fn refund_escrow(ctx: Context<RefundEscrow>) -> Result<()> {
+        escrow::process_refund_escrow(ctx)
+    }
+
+    /// Upgrade a proof, buffer or statement account created by an older program version to the
+    /// current layout, growing it if needed.
+    pub fn migrate(ctx: Context<Migrate>) -> Result<()> {
+        migration::process_migrate(ctx)
+    }
+
+    /// Initialize the program configuration and the fee treasury.
+    ///
+    /// Can only be called once, by the program's upgrade authority, which becomes the admin.
+    pub fn initialize_config(ctx: Context<InitializeConfig>, fee_lamports: u64) -> Result<()> {
+        admin::process_initialize_config(ctx, fee_lamports)
+    }
+
+    /// Propose a new admin, which takes over once it calls `accept_admin`. `None` cancels a
+    /// pending proposal.
+    pub fn propose_admin(ctx: Context<UpdateConfig>, new_admin: Option<Pubkey>) -> Result<()> {
+        admin::process_propose_admin(ctx, new_admin)
+    }
+
+    /// Accept the admin role proposed via `propose_admin`.
+    pub fn accept_admin(ctx: Context<AcceptAdmin>) -> Result<()> {
+        admin::process_accept_admin(ctx)
+    }
+
+    /// Create the admin multisig with its signers and threshold, see [`multisig`]. Only the
+    /// admin can create it, and it has no rights until it accepts the admin role.
+    pub fn init_admin_multisig(
+        ctx: Context<InitAdminMultisig>,
+        signers: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        multisig::process_init_admin_multisig(ctx, signers, threshold)
+    }
+
+    /// Replace the signers and threshold of the admin multisig. Signed by the multisig itself
+    /// via `execute_as_multisig`.
+    pub fn set_multisig_signers(
+        ctx: Context<UpdateAdminMultisig>,
+        signers: Vec<Pubkey>,
+        threshold: u8,
+    ) -> Result<()> {
+        multisig::process_set_multisig_signers(ctx, signers, threshold)
+    }
+
+    /// Invoke an instruction of this program signed by the admin multisig, once at least its
+    /// threshold of signers sign. The first `signer_count` remaining accounts are the signers,
+    /// the others the accounts of the instruction with data `data`.
+    pub fn execute_as_multisig<'info>(
+        ctx: Context<'_, '_, 'info, 'info, ExecuteAsMultisig<'info>>,
+        signer_count: u8,
+        data: Vec<u8>,
+    ) -> Result<()> {
+        multisig::process_execute_as_multisig(ctx, signer_count, data)
+    }
+
+    /// Set the fee in lamports charged per verification.
+    pub fn set_fee(ctx: Context<UpdateConfig>, fee_lamports: u64) -> Result<()> {
+        admin::process_set_fee(ctx, fee_lamports)
+    }
+
+    /// Add or remove a caller from the verification fee exemptions.
+    pub fn set_fee_exempt(ctx: Context<UpdateConfig>, caller: Pubkey, exempt: bool) -> Result<()> {
+        admin::process_set_fee_exempt(ctx, caller, exempt)
+    }
+
+    /// Require callers to hold or burn an SPL token amount to verify, or remove the requirement.
+    pub fn set_token_gate(ctx: Context<UpdateConfig>, token_gate: Option<TokenGate>) -> Result<()> {
+        admin::process_set_token_gate(ctx, token_gate)
+    }
+
+    /// Restrict proof submission and verification to allowlisted signers, or lift the restriction.
+    pub fn set_allowlist_enforced(ctx: Context<UpdateConfig>, enforced: bool) -> Result<()> {
+        admin::process_set_allowlist_enforced(ctx, enforced)
+    }
+
+    /// Create the allowlist account.
+    pub fn init_allowlist(ctx: Context<InitAllowlist>) -> Result<()> {
+        admin::process_init_allowlist(ctx)
+    }
+
+    /// Add or remove a signer from the allowlist.
+    pub fn set_allowlisted(
+        ctx: Context<UpdateAllowlist>,
+        signer: Pubkey,
+        allowed: bool,
+    ) -> Result<()> {
+        admin::process_set_allowlisted(ctx, signer, allowed)
+    }
+
+    /// Create the non-transferable Token-2022 mint of the verification receipts.
+    pub fn init_receipt_mint(ctx: Context<InitReceiptMint>) -> Result<()> {
+        receipt::process_init_receipt_mint(ctx)
+    }
+
+    /// Set the caps on chunk, proof and evaluation point sizes, see [`SizeLimits::validate`].
+    pub fn set_size_limits(ctx: Context<UpdateConfig>, size_limits: SizeLimits) -> Result<()> {
+        admin::process_set_size_limits(ctx, size_limits)
+    }
+
+    /// Set the minimum security level in bits of the parameters proofs are verified with.
+    pub fn set_min_security_level(
+        ctx: Context<UpdateConfig>,
+        min_security_level: u32,
+    ) -> Result<()> {
+        admin::process_set_min_security_level(ctx, min_security_level)
+    }
+
+    /// Create the usage statistics account.
+    pub fn init_stats(ctx: Context<InitStats>) -> Result<()> {
+        stats::process_init_stats(ctx)
+    }
+
+    /// Create the verification log account.
+    pub fn init_verification_log(ctx: Context<InitVerificationLog>) -> Result<()> {
+        verification_log::process_init_verification_log(ctx)
+    }
+
+    /// Create the digest tree PDA and initialize its concurrent Merkle tree, which the admin
+    /// allocated and assigned to the account-compression program for `max_depth` and
+    /// `max_buffer_size`.
+    pub fn init_digest_tree(
+        ctx: Context<InitDigestTree>,
+        max_depth: u32,
+        max_buffer_size: u32,
+    ) -> Result<()> {
+        compression::process_init_digest_tree(ctx, max_depth, max_buffer_size)
+    }
+
+    /// Withdraw collected fees from the treasury, keeping it rent-exempt.
+    pub fn withdraw_fees(ctx: Context<WithdrawFees>, amount: u64) -> Result<()> {
+        fees::process_withdraw_fees(ctx, amount)
+    }
+
+    /// Initialize an empty statement account that can be shared by several proofs.
+    pub fn init_statement(ctx: Context<InitStatement>) -> Result<()> {
+        let statement = &mut ctx.accounts.statement;
+        statement.version = STATEMENT_DATA_VERSION;
+        statement.authority = ctx.accounts.authority.key();
+        statement.finalized = false;
+        statement.constraints = Vec::new();
+        Ok(())
+    }
+
+    /// Append an evaluation constraint to a statement account.
+    pub fn add_statement_constraint(
+        ctx: Context<UpdateStatement>,
+        eval_point_bytes: Vec<u8>,
+        eval_value_bytes: Vec<u8>,
+    ) -> Result<()> {
+        ctx.accounts
+            .config
+            .size_limits
+            .check_eval_point(eval_point_bytes.len())?;
+        ctx.accounts.statement.constraints.push(EvalConstraint {
+            eval_point: eval_point_bytes,
+            eval_value: eval_value_bytes,
+        });
+        Ok(())
+    }
+
+    /// Freeze a statement account so proofs can be verified against it.
+    pub fn finalize_statement(ctx: Context<UpdateStatement>) -> Result<()> {
+        ctx.accounts.statement.finalized = true;
+        Ok(())
+    }
+
+    /// Close a statement account and reclaim rent.
+    pub fn close_statement(_ctx: Context<CloseStatement>) -> Result<()> {
         Ok(())
     }
 
     /// Close the proof account and reclaim rent.
+    ///
+    /// Fails while the account holds a bounty, unless the proof expired unverified, in which case
+    /// the bounty is refunded with the rent.
     pub fn close_proof(_ctx: Context<CloseProof>) -> Result<()> {
         Ok(())
     }
+
+    /// Hand over control of the proof account to a new authority, e.g. from a relayer funding the
+    /// rent to the proving service. The payer keeps receiving the rent.
+    pub fn set_proof_authority(
+        ctx: Context<SetProofAuthority>,
+        new_authority: Pubkey,
+    ) -> Result<()> {
+        ctx.accounts.proof_data.load_current_mut()?.authority = new_authority;
+        Ok(())
+    }
+
+    /// Add or remove an uploader allowed to upload chunks via `upload_chunk_v2`, e.g. one of a
+    /// pool of relayers.
+    pub fn set_proof_uploader(
+        ctx: Context<SetProofAuthority>,
+        uploader: Pubkey,
+        allowed: bool,
+    ) -> Result<()> {
+        ctx.accounts
+            .proof_data
+            .load_current_mut()?
+            .set_uploader(uploader, allowed)
+    }
+
+    /// Shrink a finalized proof account to the size of its uploaded proof, refunding the excess
+    /// rent to the payer. Fails while the account holds a bounty.
+    pub fn shrink_proof(_ctx: Context<ShrinkProof>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Return the status of a proof account, see [`ProofStatus`]. Doesn't modify any account.
+    pub fn get_proof_status(ctx: Context<GetProofStatus>) -> Result<ProofStatus> {
+        let capacity = ctx.accounts.proof_data.to_account_info().data_len() as u32;
+        let proof_data = ctx.accounts.proof_data.load_current()?;
+        let metadata = proof_data.metadata()?;
+        Ok(ProofStatus {
+            uploaded_bytes: proof_data.proof_len,
+            capacity,
+            num_buffers: metadata.buffers.len() as u32,
+            finalized: proof_data.is_finalized(),
+            num_constraints: metadata.constraints.len() as u32,
+            verified: proof_data.verified_digest().is_some(),
+        })
+    }
+
+    /// Return the expected proof size, proof account space and rent, and an estimate of the
+    /// verification's compute units for the parameters, see [`VerificationQuote`].
+    pub fn quote_verification(
+        _ctx: Context<QuoteVerification>,
+        params: WhirParamsArgs,
+    ) -> Result<VerificationQuote> {
+        process_quote_verification(&params)
+    }
 }
 
+/// Maximum number of uploaders of a proof account, in addition to its authority.
+pub const MAX_PROOF_UPLOADERS: usize = 8;
+
+/// Maximum length of the user metadata of a proof account in bytes.
+pub const MAX_USER_METADATA_LEN: usize = 256;
+
+/// Capacity of the serialized [`ProofMetadata`] in a proof account's header.
+pub const PROOF_METADATA_CAPACITY: usize = 4096;
+
 /// Account to store proof data across multiple transactions.
-#[account]
+///
+/// A zero-copy header followed by the raw proof bytes, so instructions only touch the bytes they
+/// need instead of deserializing the whole proof. Variable-length metadata is Borsh-serialized in
+/// a fixed area of the header. Flags are `u8`s, non-zero meaning set.
+///
+/// The discriminator is the ASCII of `WHIRPROF` rather than Anchor's default, which the released
+/// Borsh layout uses, so released accounts are never read as a header, see [`migration`].
+#[account(zero_copy, discriminator = [0x57, 0x48, 0x49, 0x52, 0x50, 0x52, 0x4f, 0x46])]
 pub struct ProofData {
+    /// Layout version, see [`migration`].
+    pub version: u8,
+    /// Encoding of the proof bytes, see [`ProofEncoding`].
+    pub encoding: u8,
+    /// Set once the upload is complete, either explicitly or by a successful verification.
+    pub finalized: u8,
+    /// Set by a successful verification.
+    pub verified: u8,
+    /// Set once a verification receipt was minted for the proof.
+    pub receipt_minted: u8,
+    /// Set if the proof can't be verified after `expires_at_slot`.
+    pub has_expiry: u8,
+    /// Number of entries in `uploaders`.
+    pub num_uploaders: u8,
+    /// Set once the verified digest was appended to the digest tree, see [`compression`].
+    pub digest_appended: u8,
+    /// Lamports escrowed in the account, paid to the caller of the first successful verification.
+    pub bounty_lamports: u64,
+    pub expires_at_slot: u64,
+    /// Length of the proof bytes following the header.
+    pub proof_len: u32,
+    /// Length of the serialized metadata in `metadata`.
+    pub metadata_len: u32,
+    /// Funds the account's rent and receives it back when the account is closed or shrunk.
     pub payer: Pubkey,
-    pub proof: Vec<u8>,
+    /// Controls uploads, constraints and closing the account. Set to the payer on initialization.
+    pub authority: Pubkey,
+    /// Keys allowed to upload chunks in addition to the authority, the first `num_uploaders` are set.
+    pub uploaders: [Pubkey; MAX_PROOF_UPLOADERS],
+    /// Digest of the verified commitment and statement and of the parameters, domain label and
+    /// context they were verified under, valid if `verified` is set.
+    pub verified_digest: [u8; 32],
+    /// Merkle root of the verified commitment, valid if `verified` is set.
+    pub verified_commitment: [u8; 32],
+    /// Borsh-serialized [`ProofMetadata`].
+    pub metadata: [u8; PROOF_METADATA_CAPACITY],
+}
+
+impl ProofData {
+    /// Offset of the proof bytes in the account data, after the discriminator and the header.
+    pub const PROOF_OFFSET: usize = 8 + std::mem::size_of::<ProofData>();
+
+    /// Checks that the data of a proof account holds a header of the current layout version,
+    /// before anything else of the header is read.
+    pub fn check_version(data: &[u8]) -> Result<()> {
+        require!(
+            data.len() >= Self::PROOF_OFFSET && data[8] == PROOF_DATA_VERSION,
+            WhirError::UnsupportedAccountVersion
+        );
+        Ok(())
+    }
+
+    /// Splits the data of a proof account into its header and the uploaded proof bytes.
+    pub fn split(data: &[u8]) -> Result<(&ProofData, &[u8])> {
+        Self::check_version(data)?;
+        let (header, proof_region) = data[8..].split_at(std::mem::size_of::<ProofData>());
+        let header: &ProofData = bytemuck::from_bytes(header);
+        let proof_len = (header.proof_len as usize).min(proof_region.len());
+        Ok((header, &proof_region[..proof_len]))
+    }
+
+    /// Splits the data of a proof account into its header and the whole proof region.
+    pub fn split_mut(data: &mut [u8]) -> Result<(&mut ProofData, &mut [u8])> {
+        Self::check_version(data)?;
+        let (header, proof_region) = data[8..].split_at_mut(std::mem::size_of::<ProofData>());
+        Ok((bytemuck::from_bytes_mut(header), proof_region))
+    }
+
+    pub fn is_finalized(&self) -> bool {
+        self.finalized != 0
+    }
+
+    pub fn verified_digest(&self) -> Option<[u8; 32]> {
+        (self.verified != 0).then_some(self.verified_digest)
+    }
+
+    pub fn verified_commitment(&self) -> Option<[u8; 32]> {
+        (self.verified != 0).then_some(self.verified_commitment)
+    }
+
+    /// Whether `key` may upload chunks of the proof.
+    pub fn can_upload(&self, key: &Pubkey) -> bool {
+        self.authority == *key || self.uploaders[..self.num_uploaders as usize].contains(key)
+    }
+
+    pub fn set_uploader(&mut self, uploader: Pubkey, allowed: bool) -> Result<()> {
+        let mut uploaders = self.uploaders[..self.num_uploaders as usize].to_vec();
+        uploaders.retain(|key| *key != uploader);
+        if allowed {
+            require!(
+                uploaders.len() < MAX_PROOF_UPLOADERS,
+                WhirError::TooManyUploaders
+            );
+            uploaders.push(uploader);
+        }
+        self.uploaders = Default::default();
+        self.uploaders[..uploaders.len()].copy_from_slice(&uploaders);
+        self.num_uploaders = uploaders.len() as u8;
+        Ok(())
+    }
+
+    /// Whether the proof, or one of the proofs it contains, verified with `digest`.
+    pub fn has_verified_digest(&self, digest: &[u8; 32]) -> Result<bool> {
+        if self.verified_digest() == Some(*digest) {
+            return Ok(true);
+        }
+        Ok(self
+            .metadata()?
+            .contained_proofs
+            .iter()
+            .any(|contained| contained.verified_digest == Some(*digest)))
+    }
+
+    /// Parameters the proof, or the contained proof, verified with `digest` was verified with.
+    /// `None` if no proof verified with `digest`.
+    pub fn verified_params_of(&self, digest: &[u8; 32]) -> Result<Option<VersionedParamsArgs>> {
+        let metadata = self.metadata()?;
+        if self.verified_digest() == Some(*digest) {
+            return Ok(metadata.verified_params);
+        }
+        Ok(metadata
+            .contained_proofs
+            .into_iter()
+            .find(|contained| contained.verified_digest == Some(*digest))
+            .and_then(|contained| contained.verified_params))
+    }
+
+    /// Checks that a proof verified with `digest` exists in the account and that its parameters
+    /// meet the minimum security level of `config`.
+    pub fn check_verified_params(
+        &self,
+        digest: &[u8; 32],
+        config: &ProgramConfig,
+    ) -> Result<VersionedParamsArgs> {
+        let params = self
+            .verified_params_of(digest)?
+            .ok_or(WhirError::ProofNotVerified)?;
+        config.check_security_level(params.parts().0.security_level)?;
+        Ok(params)
+    }
+
+    pub fn expires_at_slot(&self) -> Option<u64> {
+        (self.has_expiry != 0).then_some(self.expires_at_slot)
+    }
+
+    /// Whether refunding the account's lamports to the payer leaves verifiers' bounty alone: no
+    /// bounty is escrowed, or the proof expired before anyone verified it.
+    pub fn bounty_refundable(&self) -> Result<bool> {
+        if self.bounty_lamports == 0 {
+            return Ok(true);
+        }
+        let slot = Clock::get()?.slot;
+        Ok(self.expires_at_slot().is_some_and(|expiry| slot > expiry))
+    }
+
+    pub fn metadata(&self) -> Result<ProofMetadata> {
+        let len = (self.metadata_len as usize).min(PROOF_METADATA_CAPACITY);
+        ProofMetadata::try_from_slice(&self.metadata[..len])
+            .map_err(|_| error!(ErrorCode::AccountDidNotDeserialize))
+    }
+
+    pub fn set_metadata(&mut self, metadata: &ProofMetadata) -> Result<()> {
+        let mut writer = &mut self.metadata[..];
+        metadata
+            .serialize(&mut writer)
+            .map_err(|_| WhirError::ProofMetadataTooLarge)?;
+        let remaining = writer.len();
+        self.metadata_len = (PROOF_METADATA_CAPACITY - remaining) as u32;
+        Ok(())
+    }
+
+    /// Deserializes the metadata, applies `update` and serializes it back.
+    pub fn update_metadata(&mut self, update: impl FnOnce(&mut ProofMetadata)) -> Result<()> {
+        let mut metadata = self.metadata()?;
+        update(&mut metadata);
+        self.set_metadata(&metadata)
+    }
+}
+
+/// Loads proof accounts, see [`ProofData::check_version`].
+pub trait LoadProofData {
+    /// Like `AccountLoader::load`, but fails with [`WhirError::UnsupportedAccountVersion`] unless
+    /// the account is of the current layout version. Older accounts have to be migrated first.
+    fn load_current(&self) -> Result<std::cell::Ref<'_, ProofData>>;
+
+    /// Like `AccountLoader::load_mut`, checking the layout version like [`Self::load_current`].
+    fn load_current_mut(&self) -> Result<std::cell::RefMut<'_, ProofData>>;
+}
+
+impl LoadProofData for AccountLoader<'_, ProofData> {
+    fn load_current(&self) -> Result<std::cell::Ref<'_, ProofData>> {
+        ProofData::check_version(&self.as_ref().try_borrow_data()?)?;
+        self.load()
+    }
+
+    fn load_current_mut(&self) -> Result<std::cell::RefMut<'_, ProofData>> {
+        ProofData::check_version(&self.as_ref().try_borrow_data()?)?;
+        self.load_mut()
+    }
+}
+
+/// Variable-length metadata of a proof account, stored in its header.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Default)]
+pub struct ProofMetadata {
+    /// Evaluation constraints of the statement, the first one is set in `init_proof`.
+    pub constraints: Vec<EvalConstraint>,
+    /// If set, the value of the first constraint is read from another account during verification.
+    pub eval_value_source: Option<EvalValueSource>,
+    /// Application context absorbed into the transcript, empty if the proof isn't bound to one.
+    pub context: Vec<u8>,
+    /// Domain separator label of the transcript, empty for the default one.
+    pub domain_label: String,
+    /// Buffer accounts holding the rest of the proof, in order.
+    pub buffers: Vec<Pubkey>,
+    /// If set, the proof is read from another account instead of the proof account and buffers.
+    pub external_proof: Option<ExternalProofSource>,
+    /// Index of the independent proofs in the proof region, empty unless the account is a container.
+    pub contained_proofs: Vec<ContainedProof>,
+    /// Opaque data attached by the submitter, echoed in [`ProofVerified`] and [`ReceiptMinted`].
+    pub user_metadata: Vec<u8>,
+    /// If set, the only parameters the proof is verified with, see `set_proof_params`.
+    pub params: Option<VersionedParamsArgs>,
+    /// If set, the shared statement account the proof is verified against, see `set_statement`.
+    pub statement: Option<Pubkey>,
+    /// Parameters the proof was verified with, set with `ProofData::verified_digest`.
+    pub verified_params: Option<VersionedParamsArgs>,
+}
+
+/// Location and hash of proof bytes stored in another account.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct ExternalProofSource {
+    pub account: Pubkey,
+    /// Byte offset of the proof in the account data.
+    pub offset: u32,
+    pub len: u32,
+    /// SHA-256 hash of the proof bytes.
+    pub hash: [u8; 32],
+}
+
+/// Account holding a part of a proof that doesn't fit in its proof account.
+#[account]
+pub struct ProofBuffer {
+    /// Layout version, see [`migration`].
+    pub version: u8,
+    /// The proof account this buffer belongs to.
+    pub proof_data: Pubkey,
+    pub data: Vec<u8>,
+}
+
+impl ProofBuffer {
+    /// Offset of the proof account: after the discriminator and the layout version.
+    const PROOF_DATA_OFFSET: usize = 8 + 1;
+
+    /// Returns the proof account and the data of a serialized proof buffer, borrowed from the
+    /// account data instead of deserialized into a copy.
+    pub fn borrow_parts(account_data: &[u8]) -> Result<(Pubkey, &[u8])> {
+        require!(
+            account_data.starts_with(Self::DISCRIMINATOR),
+            ErrorCode::AccountDiscriminatorMismatch
+        );
+        let (proof_data, rest) = account_data
+            .get(Self::PROOF_DATA_OFFSET..)
+            .and_then(|rest| rest.split_first_chunk::<32>())
+            .ok_or(ErrorCode::AccountDidNotDeserialize)?;
+        let (len, data) = rest
+            .split_first_chunk::<4>()
+            .ok_or(ErrorCode::AccountDidNotDeserialize)?;
+        let data = data
+            .get(..u32::from_le_bytes(*len) as usize)
+            .ok_or(ErrorCode::AccountDidNotDeserialize)?;
+        Ok((Pubkey::new_from_array(*proof_data), data))
+    }
+}
+
+/// Location of a serialized evaluation value in another account.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct EvalValueSource {
+    pub account: Pubkey,
+    /// Byte offset of the value in the account data.
+    pub offset: u32,
+}
+
+/// Account holding a statement that can be shared by several proofs.
+#[account]
+pub struct StatementData {
+    /// Layout version, see [`migration`].
+    pub version: u8,
+    pub authority: Pubkey,
+    /// Statements can only be used for verification once finalized.
+    pub finalized: bool,
+    pub constraints: Vec<EvalConstraint>,
+}
+
+/// Emitted on every successful verification of a proof account.
+#[event]
+pub struct ProofVerified {
+    pub proof_data: Pubkey,
+    pub digest: [u8; 32],
+    pub caller: Pubkey,
+    /// The proof account's user metadata, see [`ProofMetadata::user_metadata`].
+    pub user_metadata: Vec<u8>,
+}
+
+/// Status of a proof account, returned by `get_proof_status`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct ProofStatus {
+    /// Proof bytes uploaded to the proof account itself, excluding buffers.
+    pub uploaded_bytes: u32,
+    /// Size of the proof account in bytes.
+    pub capacity: u32,
+    pub num_buffers: u32,
+    pub finalized: bool,
+    /// Number of the proof account's own constraints.
+    pub num_constraints: u32,
+    /// Whether the proof was successfully verified.
+    pub verified: bool,
+}
+
+/// Expected costs of verifying a proof, returned by `quote_verification`. The estimates are
+/// approximate, see `whir_config::cost`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct VerificationQuote {
+    /// Expected size of the proof in bytes.
+    pub proof_size: u32,
+    /// Size of a proof account holding the whole proof, including its header.
+    pub account_space: u32,
+    /// Rent-exempt balance of a proof account of `account_space` bytes.
+    pub rent_lamports: u64,
+    /// Estimated compute units of verifying the proof.
+    pub compute_units: u64,
+    /// Size of the commitment at the start of the proof: the Merkle root followed by the
+    /// out-of-domain answers, as passed to `accept_commitment` and `post_claim`.
+    pub commitment_size: u32,
+    /// Digest of the WHIR parameters, see `whir_config::params::params_digest`. Matches the
+    /// prover's for the same parameters and build features.
+    pub params_digest: [u8; 32],
+}
+
+/// A claim that the committed polynomial evaluates to `eval_value` at `eval_point`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct EvalConstraint {
     pub eval_point: Vec<u8>,
     pub eval_value: Vec<u8>,
 }
@@ -122,28 +1359,294 @@ pub struct ProofData {
 #[derive(Accounts)]
 pub struct InitProof<'info> {
     #[account(zero)]
-    pub proof_data: Account<'info, ProofData>,
+    pub proof_data: AccountLoader<'info, ProofData>,
     pub payer: Signer<'info>,
+    #[account(
+        seeds = [ProgramConfig::SEED],
+        bump = config.bump,
+        constraint = config.allows(&payer.key(), allowlist.as_deref()) @ WhirError::NotAllowlisted
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    /// Required if the allowlist is enforced.
+    #[account(seeds = [Allowlist::SEED], bump = allowlist.bump)]
+    pub allowlist: Option<Account<'info, Allowlist>>,
+}
+
+impl InitProof<'_> {
+    fn size_limits(&self) -> &SizeLimits {
+        &self.config.size_limits
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(space: u32)]
+pub struct CreateProof<'info> {
+    #[account(init, payer = payer, space = space as usize)]
+    pub proof_data: AccountLoader<'info, ProofData>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    #[account(
+        seeds = [ProgramConfig::SEED],
+        bump = config.bump,
+        constraint = config.allows(&payer.key(), allowlist.as_deref()) @ WhirError::NotAllowlisted
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    /// Required if the allowlist is enforced.
+    #[account(seeds = [Allowlist::SEED], bump = allowlist.bump)]
+    pub allowlist: Option<Account<'info, Allowlist>>,
+    pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
 pub struct UploadChunk<'info> {
-    #[account(mut, has_one = payer)]
-    pub proof_data: Account<'info, ProofData>,
+    #[account(
+        mut,
+        constraint = proof_data.load_current()?.authority == payer.key() @ WhirError::Unauthorized,
+        constraint = !proof_data.load_current()?.is_finalized() @ WhirError::ProofFinalized
+    )]
+    pub proof_data: AccountLoader<'info, ProofData>,
+    /// The proof account's authority. Keeps its name from the released account list, which
+    /// predates separating the payer and the authority.
     pub payer: Signer<'info>,
+    #[account(
+        seeds = [ProgramConfig::SEED],
+        bump = config.bump,
+        constraint = config.allows(&payer.key(), allowlist.as_deref()) @ WhirError::NotAllowlisted
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    /// Required if the allowlist is enforced.
+    #[account(seeds = [Allowlist::SEED], bump = allowlist.bump)]
+    pub allowlist: Option<Account<'info, Allowlist>>,
+    /// Updated if passed, see [`ProgramStats`].
+    #[account(mut, seeds = [ProgramStats::SEED], bump = stats.bump)]
+    pub stats: Option<Account<'info, ProgramStats>>,
+}
+
+impl UploadChunk<'_> {
+    pub(crate) fn size_limits(&self) -> &SizeLimits {
+        &self.config.size_limits
+    }
+}
+
+#[derive(Accounts)]
+pub struct UploadChunkAt<'info> {
+    #[account(
+        mut,
+        constraint = proof_data.load_current()?.can_upload(&uploader.key()) @ WhirError::Unauthorized,
+        constraint = !proof_data.load_current()?.is_finalized() @ WhirError::ProofFinalized
+    )]
+    pub proof_data: AccountLoader<'info, ProofData>,
+    pub uploader: Signer<'info>,
+    #[account(
+        seeds = [ProgramConfig::SEED],
+        bump = config.bump,
+        constraint = config.allows(&uploader.key(), allowlist.as_deref()) @ WhirError::NotAllowlisted
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    /// Required if the allowlist is enforced.
+    #[account(seeds = [Allowlist::SEED], bump = allowlist.bump)]
+    pub allowlist: Option<Account<'info, Allowlist>>,
+    /// Updated if passed, see [`ProgramStats`].
+    #[account(mut, seeds = [ProgramStats::SEED], bump = stats.bump)]
+    pub stats: Option<Account<'info, ProgramStats>>,
+}
+
+#[derive(Accounts)]
+pub struct ValidateProofInputs<'info> {
+    pub proof_data: AccountLoader<'info, ProofData>,
+    /// Shared statement to validate in addition to the proof's own constraints.
+    pub statement: Option<Account<'info, StatementData>>,
+    #[account(seeds = [ProgramConfig::SEED], bump = config.bump)]
+    pub config: Account<'info, ProgramConfig>,
 }
 
 #[derive(Accounts)]
 pub struct VerifyProof<'info> {
-    pub proof_data: Account<'info, ProofData>,
+    #[account(mut)]
+    pub proof_data: AccountLoader<'info, ProofData>,
+    /// Shared statement whose constraints are verified in addition to the proof's own ones.
+    /// Required if and only if the proof is bound to it, see `set_statement`.
+    #[account(constraint = statement.finalized @ WhirError::StatementNotFinalized)]
+    pub statement: Option<Account<'info, StatementData>>,
+    /// CHECK: Only read if it matches the proof account's `eval_value_source`.
+    pub eval_value_account: Option<UncheckedAccount<'info>>,
+    /// CHECK: Only read if it matches the proof account's `external_proof`, whose hash is checked.
+    pub external_proof_account: Option<UncheckedAccount<'info>>,
+    /// Cached transcript pattern replacing building the domain separator, see
+    /// [`TranscriptPattern`].
+    pub transcript_pattern: Option<Account<'info, TranscriptPattern>>,
+    pub fee: VerificationFee<'info>,
+}
+
+/// Accounts of the released `verify()`, which takes the proof account read-only and only checks
+/// the proof, without a fee and without recording it. The optional accounts are those of
+/// [`VerifyProof`].
+#[derive(Accounts)]
+pub struct VerifyProofV1<'info> {
+    pub proof_data: AccountLoader<'info, ProofData>,
+    #[account(constraint = statement.finalized @ WhirError::StatementNotFinalized)]
+    pub statement: Option<Account<'info, StatementData>>,
+    /// CHECK: Only read if it matches the proof account's `eval_value_source`.
+    pub eval_value_account: Option<UncheckedAccount<'info>>,
+    /// CHECK: Only read if it matches the proof account's `external_proof`, whose hash is checked.
+    pub external_proof_account: Option<UncheckedAccount<'info>>,
+    pub transcript_pattern: Option<Account<'info, TranscriptPattern>>,
+    /// CHECK: The instructions sysvar, checked by address. Read for the requested heap frame if
+    /// passed, see [`crate::heap`].
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyAndClose<'info> {
+    pub verify: VerifyProof<'info>,
+    /// Receives the proof account's rent.
+    #[account(mut, constraint = verify.proof_data.load_current()?.payer == payer.key() @ WhirError::Unauthorized)]
+    pub payer: SystemAccount<'info>,
+    #[account(constraint = verify.proof_data.load_current()?.authority == authority.key() @ WhirError::Unauthorized)]
+    pub authority: Signer<'info>,
+    /// Required, with the token accounts below, to mint a receipt token before closing.
+    #[account(mut, seeds = [RECEIPT_MINT_SEED], bump)]
+    pub receipt_mint: Option<InterfaceAccount<'info, Mint>>,
+    #[account(mut)]
+    pub recipient_token_account: Option<InterfaceAccount<'info, TokenAccount>>,
+    pub token_program: Option<Program<'info, Token2022>>,
+}
+
+#[derive(Accounts)]
+pub struct GetProofStatus<'info> {
+    pub proof_data: AccountLoader<'info, ProofData>,
 }
 
+#[derive(Accounts)]
+pub struct QuoteVerification {}
+
 #[derive(Accounts)]
 pub struct CloseProof<'info> {
-    #[account(mut, close = payer, has_one = payer)]
-    pub proof_data: Account<'info, ProofData>,
+    #[account(
+        mut,
+        close = payer,
+        has_one = payer,
+        constraint = proof_data.load_current()?.authority == authority.as_ref().map_or(payer.key(), Key::key) @ WhirError::Unauthorized,
+        constraint = proof_data.load_current()?.bounty_refundable()? @ WhirError::BountyEscrowed
+    )]
+    pub proof_data: AccountLoader<'info, ProofData>,
+    /// Receives the proof account's rent. Signs as the authority if `authority` isn't passed, as
+    /// in the released account list.
+    #[account(mut, constraint = authority.is_some() || payer.to_account_info().is_signer @ WhirError::Unauthorized)]
+    pub payer: SystemAccount<'info>,
+    pub authority: Option<Signer<'info>>,
+}
+
+#[derive(Accounts)]
+pub struct SetProofAuthority<'info> {
+    #[account(mut, has_one = authority)]
+    pub proof_data: AccountLoader<'info, ProofData>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ShrinkProof<'info> {
+    #[account(
+        mut,
+        has_one = payer,
+        has_one = authority,
+        constraint = proof_data.load_current()?.is_finalized() @ WhirError::ProofNotFinalized,
+        constraint = proof_data.load_current()?.bounty_lamports == 0 @ WhirError::BountyEscrowed,
+        realloc = ProofData::PROOF_OFFSET + proof_data.load_current()?.proof_len as usize,
+        realloc::payer = payer,
+        realloc::zero = false
+    )]
+    pub proof_data: AccountLoader<'info, ProofData>,
+    /// Receives the excess rent.
+    #[account(mut)]
+    pub payer: SystemAccount<'info>,
+    pub authority: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct EscrowBounty<'info> {
+    #[account(mut, has_one = payer, constraint = !proof_data.load_current()?.is_finalized() @ WhirError::ProofFinalized)]
+    pub proof_data: AccountLoader<'info, ProofData>,
     #[account(mut)]
     pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct InitBuffer<'info> {
+    #[account(zero)]
+    pub buffer: Account<'info, ProofBuffer>,
+    #[account(mut, has_one = authority, constraint = !proof_data.load_current()?.is_finalized() @ WhirError::ProofFinalized)]
+    pub proof_data: AccountLoader<'info, ProofData>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UploadBufferChunk<'info> {
+    #[account(mut, has_one = proof_data)]
+    pub buffer: Account<'info, ProofBuffer>,
+    #[account(has_one = authority, constraint = !proof_data.load_current()?.is_finalized() @ WhirError::ProofFinalized)]
+    pub proof_data: AccountLoader<'info, ProofData>,
+    pub authority: Signer<'info>,
+    #[account(seeds = [ProgramConfig::SEED], bump = config.bump)]
+    pub config: Account<'info, ProgramConfig>,
+    /// Updated if passed, see [`ProgramStats`].
+    #[account(mut, seeds = [ProgramStats::SEED], bump = stats.bump)]
+    pub stats: Option<Account<'info, ProgramStats>>,
+}
+
+#[derive(Accounts)]
+pub struct CloseBuffer<'info> {
+    #[account(mut, close = payer, has_one = proof_data)]
+    pub buffer: Account<'info, ProofBuffer>,
+    #[account(mut, has_one = payer, has_one = authority)]
+    pub proof_data: AccountLoader<'info, ProofData>,
+    /// Receives the buffer's rent.
+    #[account(mut)]
+    pub payer: SystemAccount<'info>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct VerifyInline<'info> {
+    pub fee: VerificationFee<'info>,
+}
+
+#[derive(Accounts)]
+pub struct CarryProofChunk {}
+
+#[derive(Accounts)]
+pub struct VerifyFromInstructions<'info> {
+    /// CHECK: The instructions sysvar, checked by address.
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+    pub fee: VerificationFee<'info>,
+}
+
+#[derive(Accounts)]
+pub struct InitStatement<'info> {
+    #[account(zero)]
+    pub statement: Account<'info, StatementData>,
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateStatement<'info> {
+    #[account(mut, has_one = authority, constraint = !statement.finalized @ WhirError::ProofFinalized)]
+    pub statement: Account<'info, StatementData>,
+    pub authority: Signer<'info>,
+    #[account(seeds = [ProgramConfig::SEED], bump = config.bump)]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+#[derive(Accounts)]
+pub struct CloseStatement<'info> {
+    #[account(mut, close = authority, has_one = authority)]
+    pub statement: Account<'info, StatementData>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
 }
 
 #[error_code]
@@ -154,15 +1657,966 @@ pub enum WhirError {
     DeserializationError,
     #[msg("Proof verification failed")]
     VerificationFailed,
+    #[msg("Unknown or malformed proof encoding")]
+    InvalidProofEncoding,
+    #[msg("Proof account is finalized and can't be modified")]
+    ProofFinalized,
+    #[msg("Evaluation point doesn't match the number of variables")]
+    InvalidEvalPoint,
+    #[msg("Statement account must be finalized before verification")]
+    StatementNotFinalized,
+    #[msg("Statement has no constraints")]
+    EmptyStatement,
+    #[msg("Account holding the evaluation value is missing or doesn't match")]
+    MissingEvalValueAccount,
+    #[msg("Domain separator label is too long")]
+    DomainLabelTooLong,
+    #[msg("No proof_chunk instructions precede the verify instruction")]
+    MissingProofChunks,
+    #[msg("Buffer accounts don't match the proof account's manifest")]
+    InvalidProofBuffers,
+    #[msg("External proof account is missing or its data doesn't match the declared hash")]
+    InvalidExternalProof,
+    #[msg("Signer isn't authorized to perform this operation")]
+    Unauthorized,
+    #[msg("Too many fee-exempt callers")]
+    TooManyFeeExemptions,
+    #[msg("Treasury balance is insufficient")]
+    InsufficientTreasuryBalance,
+    #[msg("Caller doesn't satisfy the token gate")]
+    TokenGateNotSatisfied,
+    #[msg("Signer is not on the allowlist")]
+    NotAllowlisted,
+    #[msg("Allowlist is full")]
+    AllowlistFull,
+    #[msg("Bounty overflows")]
+    BountyOverflow,
+    #[msg("Proof is not verified or its digest doesn't match")]
+    ProofNotVerified,
+    #[msg("A receipt was already minted for the proof")]
+    ReceiptAlreadyMinted,
+    #[msg("Proof evaluations are not fully stored in the proof account")]
+    EvaluationNotPublishable,
+    #[msg("Proof has expired")]
+    ProofExpired,
+    #[msg("Account type or layout version is not supported")]
+    UnsupportedAccountVersion,
+    #[msg("Chunk exceeds the maximum chunk length")]
+    ChunkTooLarge,
+    #[msg("Proof exceeds the maximum proof length")]
+    ProofTooLarge,
+    #[msg("Evaluation point exceeds the maximum length")]
+    EvalPointTooLarge,
+    #[msg("Folding factor is incompatible with the number of variables")]
+    InvalidFoldingFactor,
+    #[msg("Proof account holds no proof")]
+    EmptyProof,
+    #[msg("Proof doesn't match the transcript of the parameters, e.g. it is truncated")]
+    TranscriptMismatch,
+    #[msg("Proof contains malformed field elements or digests")]
+    ProofDeserializationError,
+    #[msg("Proof account is too small for the header or the uploaded proof")]
+    ProofAccountTooSmall,
+    #[msg("Proof metadata exceeds the capacity of the proof account header")]
+    ProofMetadataTooLarge,
+    #[msg("Receipt token account doesn't belong to the receipt mint")]
+    InvalidReceiptAccounts,
+    #[msg("Proof upload is not finalized")]
+    ProofNotFinalized,
+    #[msg("Too many uploaders on the proof account")]
+    TooManyUploaders,
+    #[msg("Escrow can't be refunded before it expires")]
+    EscrowNotExpired,
+    #[msg("Missing or mismatching escrow token accounts")]
+    InvalidEscrowAccounts,
+    #[msg("Parameters need more proof-of-work bits than pow_bits allows")]
+    InsufficientPowBits,
+    #[msg("Contained proof index is out of range or its bytes aren't uploaded")]
+    InvalidProofIndex,
+    #[msg("The proof's digest was already appended to the digest tree")]
+    DigestAlreadyAppended,
+    #[msg("Claim was already challenged or settled, or its challenge window closed")]
+    ClaimNotChallengeable,
+    #[msg("Claim is already settled or its deadline hasn't passed")]
+    ClaimNotSettleable,
+    #[msg("Sample is already fulfilled or the proof doesn't open the sampled commitment at the sampled point")]
+    SampleMismatch,
+    #[msg("User metadata exceeds the maximum length")]
+    UserMetadataTooLarge,
+    #[msg("Verification needs more heap than the requested heap frame")]
+    OutOfMemory,
+    #[msg(
+        "The transcript pattern was cached for other parameters, domain label or context length"
+    )]
+    TranscriptPatternMismatch,
+    /// No longer returned, the compute unit estimate is only logged. Kept so the codes of the
+    /// later errors don't shift.
+    #[msg("Verification needs more compute units than remain in the transaction")]
+    NeedsMoreCompute,
+    #[msg("This build only verifies its fixed parameters")]
+    UnsupportedParams,
+    #[msg("Invalid WHIR parameters: rate, domain size or security level")]
+    InvalidParams,
+    #[msg("The proof account records other parameters than the verified ones")]
+    ParamsMismatch,
+    #[msg("The proof account holds a bounty for its verifier")]
+    BountyEscrowed,
+    #[msg("Size limits must be between 1 byte and 10 MiB, with chunks no larger than proofs")]
+    InvalidSizeLimits,
+    #[msg("The treasury and system program accounts are required to pay the fee")]
+    MissingFeeAccounts,
+    #[msg("The statement account isn't the one the proof is bound to")]
+    StatementMismatch,
+    #[msg("The proof was already verified with another digest")]
+    VerifiedDigestMismatch,
+    #[msg("The parameters are below the configured minimum security level")]
+    InsufficientSecurity,
+    #[msg("Multisig signers must be distinct, with a threshold between one and their number")]
+    InvalidMultisig,
+    #[msg("Fewer multisig signers signed than its threshold")]
+    MultisigThresholdNotMet,
+}
+
+/// Initializes a proof account, shared by all versions of the init instruction.
+///
+/// A constraint with an empty evaluation point means the statement comes from a `StatementData`
+/// account.
+fn process_init_proof(
+    proof_data: &AccountLoader<ProofData>,
+    payer: Pubkey,
+    size_limits: &SizeLimits,
+    constraint: EvalConstraint,
+    encoding: u8,
+    domain_label: String,
+    user_metadata: Vec<u8>,
+) -> Result<()> {
+    ProofEncoding::try_from(encoding).map_err(|_| WhirError::InvalidProofEncoding)?;
+    require!(
+        domain_label.len() <= transcript::MAX_DOMAIN_LABEL_LEN,
+        WhirError::DomainLabelTooLong
+    );
+    require!(
+        user_metadata.len() <= MAX_USER_METADATA_LEN,
+        WhirError::UserMetadataTooLarge
+    );
+    size_limits.check_eval_point(constraint.eval_point.len())?;
+    require!(
+        proof_data.to_account_info().data_len() >= ProofData::PROOF_OFFSET,
+        WhirError::ProofAccountTooSmall
+    );
+
+    // The account is zeroed, so all flags and lengths start unset.
+    let mut proof_data = proof_data.load_init()?;
+    proof_data.version = PROOF_DATA_VERSION;
+    proof_data.payer = payer;
+    proof_data.authority = payer;
+    proof_data.encoding = encoding;
+    let constraints = if constraint.eval_point.is_empty() {
+        Vec::new()
+    } else {
+        vec![constraint]
+    };
+    proof_data.set_metadata(&ProofMetadata {
+        constraints,
+        domain_label,
+        user_metadata,
+        ..Default::default()
+    })
+}
+
+/// Writes a chunk to the proof region at `offset`, or appends it to the uploaded proof.
+fn process_upload_chunk(
+    proof_data: &AccountLoader<ProofData>,
+    size_limits: &SizeLimits,
+    offset: Option<u32>,
+    chunk: &[u8],
+) -> Result<()> {
+    size_limits.check_chunk(chunk.len())?;
+
+    // Write the chunk in place, without deserializing the proof uploaded so far.
+    let info = proof_data.to_account_info();
+    let mut data = info.try_borrow_mut_data()?;
+    let (proof_data, proof_region) = ProofData::split_mut(&mut data)?;
+    let start = offset.unwrap_or(proof_data.proof_len) as usize;
+    let end = start + chunk.len();
+    size_limits.check_proof(end)?;
+    proof_region
+        .get_mut(start..end)
+        .ok_or(WhirError::ProofAccountTooSmall)?
+        .copy_from_slice(chunk);
+    // Chunks uploaded at offsets may arrive out of order, the proof ends after the last byte.
+    proof_data.proof_len = proof_data.proof_len.max(end as u32);
+    Ok(())
+}
+
+/// WHIR parameters of a proof, as passed to the verify instructions from `verify_v2` on.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq, InitSpace)]
+pub struct WhirParamsArgs {
+    pub num_variables: u32,
+    pub security_level: u32,
+    pub pow_bits: u32,
+    pub folding_factor: u32,
+    pub starting_log_inv_rate: u32,
 }
 
-fn deserialize_eval_point(bytes: &[u8]) -> Result<MultilinearPoint<F>> {
-    let field_size = field_size_bytes();
-    let mut points = Vec::new();
-    for chunk in bytes.chunks_exact(field_size) {
-        let value =
-            F::deserialize_compressed(chunk).map_err(|_| WhirError::DeserializationError)?;
-        points.push(value);
+/// The only parameters verified by `fixed-params` builds: the defaults of `whir_config`.
+pub const FIXED_PARAMS: WhirParamsArgs = WhirParamsArgs {
+    num_variables: whir_config::NUM_VARIABLES as u32,
+    security_level: whir_config::SECURITY_LEVEL_BITS as u32,
+    pow_bits: whir_config::POW_BITS as u32,
+    folding_factor: whir_config::FOLDING_FACTOR as u32,
+    starting_log_inv_rate: whir_config::STARTING_LOG_INV_RATE as u32,
+};
+
+impl WhirParamsArgs {
+    /// Builds the WHIR parameters. `fixed-params` builds always build [`FIXED_PARAMS`] from
+    /// constants, so the compiler can specialize the parameter derivation to them.
+    pub fn to_whir_params(&self) -> WhirParams {
+        self.to_whir_params_with(SoundnessTypeArg::default())
+    }
+
+    /// Builds the WHIR parameters under the given soundness type, see [`Self::to_whir_params`].
+    pub fn to_whir_params_with(&self, soundness_type: SoundnessTypeArg) -> WhirParams {
+        #[cfg(feature = "fixed-params")]
+        let args = &FIXED_PARAMS;
+        #[cfg(not(feature = "fixed-params"))]
+        let args = self;
+        create_whir_params_with(
+            args.num_variables as usize,
+            args.security_level as usize,
+            args.pow_bits as usize,
+            args.folding_factor as usize,
+            args.starting_log_inv_rate as usize,
+            soundness_type.protocol_options(),
+            ReedSolomonBackend::default(),
+        )
+    }
+
+    /// Fails with `UnsupportedParams` if a `fixed-params` build is passed other parameters than
+    /// [`FIXED_PARAMS`].
+    pub fn check_fixed(&self) -> Result<()> {
+        #[cfg(feature = "fixed-params")]
+        require!(*self == FIXED_PARAMS, WhirError::UnsupportedParams);
+        Ok(())
     }
-    Ok(MultilinearPoint(points))
+}
+
+/// Soundness assumption the query counts of a proof's parameters are derived under, as passed to
+/// `verify_v3`. The other verify instructions use the default, the list-decoding conjecture.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SoundnessTypeArg {
+    #[default]
+    ConjectureList,
+    ProvableList,
+    UniqueDecoding,
+}
+
+impl SoundnessTypeArg {
+    /// Returns the protocol options of parameters derived under this soundness type.
+    pub fn protocol_options(self) -> ProtocolOptions {
+        ProtocolOptions {
+            soundness_type: self.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Fails with `UnsupportedParams` if a `fixed-params` build is passed another soundness type
+    /// than the default.
+    pub fn check_fixed(self) -> Result<()> {
+        #[cfg(feature = "fixed-params")]
+        require!(
+            self == SoundnessTypeArg::default(),
+            WhirError::UnsupportedParams
+        );
+        Ok(())
+    }
+}
+
+impl From<SoundnessTypeArg> for SoundnessType {
+    fn from(soundness_type: SoundnessTypeArg) -> Self {
+        match soundness_type {
+            SoundnessTypeArg::ConjectureList => SoundnessType::ConjectureList,
+            SoundnessTypeArg::ProvableList => SoundnessType::ProvableList,
+            SoundnessTypeArg::UniqueDecoding => SoundnessType::UniqueDecoding,
+        }
+    }
+}
+
+impl From<SoundnessTypeArg> for ParamsSoundness {
+    fn from(soundness_type: SoundnessTypeArg) -> Self {
+        match soundness_type {
+            SoundnessTypeArg::ConjectureList => ParamsSoundness::ConjectureList,
+            SoundnessTypeArg::ProvableList => ParamsSoundness::ProvableList,
+            SoundnessTypeArg::UniqueDecoding => ParamsSoundness::UniqueDecoding,
+        }
+    }
+}
+
+/// WHIR parameters in the versioned wire format of `whir_config::params::VersionedParams`, with
+/// the same Borsh encoding, as passed to `verify_v4` and `set_proof_params`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub enum VersionedParamsArgs {
+    V1(ParamsV1Args),
+}
+
+/// Version 1 of [`VersionedParamsArgs`], mirroring `whir_config::params::ParamsV1`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug, PartialEq)]
+pub struct ParamsV1Args {
+    pub params: WhirParamsArgs,
+    pub soundness_type: SoundnessTypeArg,
+}
+
+impl VersionedParamsArgs {
+    /// Returns the parameters `args` under `soundness_type` in the current wire format version.
+    pub fn new(args: &WhirParamsArgs, soundness_type: SoundnessTypeArg) -> Self {
+        VersionedParamsArgs::V1(ParamsV1Args {
+            params: args.clone(),
+            soundness_type,
+        })
+    }
+
+    /// Returns the parameters and soundness type the verify instructions take.
+    pub fn parts(&self) -> (&WhirParamsArgs, SoundnessTypeArg) {
+        let VersionedParamsArgs::V1(v1) = self;
+        (&v1.params, v1.soundness_type)
+    }
+
+    /// Returns whether these are the parameters `args` under `soundness_type`.
+    pub fn matches(&self, args: &WhirParamsArgs, soundness_type: SoundnessTypeArg) -> bool {
+        self.parts() == (args, soundness_type)
+    }
+}
+
+impl From<&VersionedParamsArgs> for VersionedParams {
+    fn from(params: &VersionedParamsArgs) -> Self {
+        let (args, soundness_type) = params.parts();
+        VersionedParams::V1(ParamsV1 {
+            num_variables: args.num_variables,
+            security_level: args.security_level,
+            pow_bits: args.pow_bits,
+            folding_factor: args.folding_factor,
+            starting_log_inv_rate: args.starting_log_inv_rate,
+            soundness_type: soundness_type.into(),
+        })
+    }
+}
+
+/// Builds the WHIR parameters, rejecting folding factors incompatible with the number of variables,
+/// parameters needing more proof of work than `pow_bits` and otherwise invalid parameters, see
+/// `whir_config::validation`.
+///
+/// `pow_bits` caps the grinding of every round, so `pow_bits = 0` selects parameters whose proofs
+/// carry no proof of work at all, reaching the security level through queries alone.
+fn checked_whir_params(args: &WhirParamsArgs) -> Result<WhirParams> {
+    checked_whir_params_with(args, SoundnessTypeArg::default())
+}
+
+/// Builds the WHIR parameters of [`checked_whir_params`] under `soundness_type`.
+fn checked_whir_params_with(
+    args: &WhirParamsArgs,
+    soundness_type: SoundnessTypeArg,
+) -> Result<WhirParams> {
+    args.check_fixed()?;
+    soundness_type.check_fixed()?;
+    validate_params_with(
+        args.num_variables as usize,
+        args.security_level as usize,
+        args.pow_bits as usize,
+        args.folding_factor as usize,
+        args.starting_log_inv_rate as usize,
+        soundness_type.protocol_options(),
+    )
+    .map_err(|error| {
+        msg!("Invalid WHIR parameters: {:?}", error);
+        match error {
+            ParamsError::InvalidFoldingFactor { .. } => WhirError::InvalidFoldingFactor,
+            ParamsError::InsufficientPowBits => WhirError::InsufficientPowBits,
+            _ => WhirError::InvalidParams,
+        }
+        .into()
+    })
+}
+
+fn process_quote_verification(args: &WhirParamsArgs) -> Result<VerificationQuote> {
+    let params = checked_whir_params(args)?;
+    let proof_size = cost::estimate_proof_size(&params);
+    let account_space = ProofData::PROOF_OFFSET + proof_size;
+    Ok(VerificationQuote {
+        proof_size: proof_size as u32,
+        account_space: account_space as u32,
+        rent_lamports: Rent::get()?.minimum_balance(account_space),
+        compute_units: cost::estimate_compute_units(&params),
+        commitment_size: commitment_size_bytes(&params) as u32,
+        params_digest: params_digest(&params),
+    })
+}
+
+fn process_validate_proof_inputs(
+    accounts: &ValidateProofInputs,
+    args: &WhirParamsArgs,
+) -> Result<()> {
+    let num_variables = args.num_variables as usize;
+    checked_whir_params(args)?;
+
+    let proof_data = accounts.proof_data.load_current()?;
+    let metadata = proof_data.metadata()?;
+    ProofEncoding::try_from(proof_data.encoding).map_err(|_| WhirError::InvalidProofEncoding)?;
+    let stored_len = match &metadata.external_proof {
+        Some(source) => source.len as usize,
+        None => proof_data.proof_len as usize,
+    };
+    require!(
+        stored_len > 0 || !metadata.buffers.is_empty(),
+        WhirError::EmptyProof
+    );
+    accounts.config.size_limits.check_proof(stored_len)?;
+    check_statement(&metadata, accounts.statement.as_ref())?;
+
+    let statement_constraints = accounts
+        .statement
+        .as_ref()
+        .map(|statement| statement.constraints.as_slice())
+        .unwrap_or_default();
+    require!(
+        !statement_constraints.is_empty() || !metadata.constraints.is_empty(),
+        WhirError::EmptyStatement
+    );
+    for constraint in statement_constraints {
+        validate_constraint(constraint, num_variables, true)?;
+    }
+    for (i, constraint) in metadata.constraints.iter().enumerate() {
+        // The value of the first constraint may be read from another account during verification.
+        let has_value = i > 0 || metadata.eval_value_source.is_none();
+        validate_constraint(constraint, num_variables, has_value)?;
+    }
+    Ok(())
+}
+
+/// Checks that the passed statement account is the one the proof is bound to, if any.
+fn check_statement(
+    metadata: &ProofMetadata,
+    statement: Option<&Account<StatementData>>,
+) -> Result<()> {
+    require!(
+        statement.map(Key::key) == metadata.statement,
+        WhirError::StatementMismatch
+    );
+    Ok(())
+}
+
+fn validate_constraint(
+    constraint: &EvalConstraint,
+    num_variables: usize,
+    has_value: bool,
+) -> Result<()> {
+    require_eq!(
+        constraint.eval_point.len(),
+        num_variables * field_size_bytes(),
+        WhirError::InvalidEvalPoint
+    );
+    deserialize_eval_point(&constraint.eval_point).map_err(WhirError::from)?;
+    if has_value {
+        decode_field(&constraint.eval_value).ok_or(WhirError::DeserializationError)?;
+    }
+    Ok(())
+}
+
+/// Verifies the proof in the proof account, shared by all versions of the verify instruction.
+/// Returns the verified digest.
+fn process_verify(
+    accounts: &mut VerifyProof,
+    remaining_accounts: &[AccountInfo],
+    args: &WhirParamsArgs,
+    soundness_type: SoundnessTypeArg,
+) -> Result<[u8; 32]> {
+    let stored_proof = StoredProofAccounts {
+        proof_data: &accounts.proof_data,
+        statement: accounts.statement.as_ref(),
+        eval_value_account: accounts.eval_value_account.as_ref(),
+        external_proof_account: accounts.external_proof_account.as_ref(),
+        transcript_pattern: accounts.transcript_pattern.as_deref(),
+    };
+    verify_and_record(
+        &stored_proof,
+        &mut accounts.fee,
+        remaining_accounts,
+        args,
+        soundness_type,
+    )
+}
+
+/// Verifies a stored proof, charging the fee, and records the verification.
+fn verify_and_record(
+    stored_proof: &StoredProofAccounts,
+    fee: &mut VerificationFee,
+    remaining_accounts: &[AccountInfo],
+    args: &WhirParamsArgs,
+    soundness_type: SoundnessTypeArg,
+) -> Result<[u8; 32]> {
+    fee.use_requested_heap_frame()?;
+    fee.charge()?;
+
+    let verified = verify_stored_proof(
+        stored_proof,
+        fee.size_limits(),
+        fee.min_security_level(),
+        remaining_accounts,
+        args,
+        soundness_type,
+    )?;
+    record_verified(stored_proof, fee, args, soundness_type, verified)
+}
+
+/// Like [`verify_and_record`], but completes with the failure instead of failing if the proof
+/// doesn't verify, counting it in the stats. Errors before verifying, e.g. of the fee, still fail.
+fn verify_and_record_outcome(
+    stored_proof: &StoredProofAccounts,
+    fee: &mut VerificationFee,
+    remaining_accounts: &[AccountInfo],
+    args: &WhirParamsArgs,
+) -> Result<SimulationResult> {
+    fee.use_requested_heap_frame()?;
+    fee.charge()?;
+
+    match verify_stored_proof(
+        stored_proof,
+        fee.size_limits(),
+        fee.min_security_level(),
+        remaining_accounts,
+        args,
+        SoundnessTypeArg::default(),
+    ) {
+        Ok(verified) => {
+            let digest = record_verified(
+                stored_proof,
+                fee,
+                args,
+                SoundnessTypeArg::default(),
+                verified,
+            )?;
+            Ok(SimulationResult::verified(digest))
+        }
+        Err(error) => {
+            msg!("Verification failed: {}", error);
+            fee.record_failure(args);
+            Ok(SimulationResult::failed(error))
+        }
+    }
+}
+
+/// Records a verification of [`verify_stored_proof`] in the proof account, the stats and the
+/// verification log, pays the proof account's bounty to the caller and emits its event.
+fn record_verified(
+    stored_proof: &StoredProofAccounts,
+    fee: &mut VerificationFee,
+    args: &WhirParamsArgs,
+    soundness_type: SoundnessTypeArg,
+    (digest, commitment_root, user_metadata): ([u8; 32], [u8; DIGEST_SIZE_BYTES], Vec<u8>),
+) -> Result<[u8; 32]> {
+    let proof_account = stored_proof.proof_data;
+    // The verified proof and statement must not change anymore.
+    let mut proof_data = proof_account.load_current_mut()?;
+    require!(
+        !matches!(proof_data.verified_digest(), Some(verified) if verified != digest),
+        WhirError::VerifiedDigestMismatch
+    );
+    proof_data.finalized = 1;
+    proof_data.verified = 1;
+    proof_data.verified_digest = digest;
+    proof_data.verified_commitment = commitment_root;
+    proof_data.update_metadata(|metadata| {
+        metadata.verified_params = Some(VersionedParamsArgs::new(args, soundness_type))
+    })?;
+    let bounty = std::mem::take(&mut proof_data.bounty_lamports);
+    drop(proof_data);
+    if bounty > 0 {
+        proof_account.to_account_info().sub_lamports(bounty)?;
+        fee.caller.add_lamports(bounty)?;
+    }
+
+    let proof_data_key = proof_account.key();
+    emit!(ProofVerified {
+        proof_data: proof_data_key,
+        digest,
+        caller: fee.caller.key(),
+        user_metadata,
+    });
+    fee.record_verification(
+        args,
+        digest,
+        ProofSource::ProofAccount,
+        Some(proof_data_key),
+    )?;
+    Ok(digest)
+}
+
+/// Accounts a stored proof and its statement are read from, see [`verify_stored_proof`].
+pub(crate) struct StoredProofAccounts<'a, 'info> {
+    pub proof_data: &'a AccountLoader<'info, ProofData>,
+    pub statement: Option<&'a Account<'info, StatementData>>,
+    pub eval_value_account: Option<&'a UncheckedAccount<'info>>,
+    pub external_proof_account: Option<&'a UncheckedAccount<'info>>,
+    pub transcript_pattern: Option<&'a TranscriptPattern>,
+}
+
+/// Verifies the proof stored in a proof account against its statement, without modifying it.
+/// Returns the verified digest, the commitment's Merkle root and the account's user metadata.
+///
+/// The transcript reads a raw proof stored in the proof account or in an external account in place,
+/// borrowing the account data. Only proofs split across buffers and encoded proofs are copied to
+/// the heap, assembled or decoded into one contiguous slice.
+fn verify_stored_proof(
+    accounts: &StoredProofAccounts,
+    size_limits: &SizeLimits,
+    min_security_level: u32,
+    remaining_accounts: &[AccountInfo],
+    args: &WhirParamsArgs,
+    soundness_type: SoundnessTypeArg,
+) -> Result<([u8; 32], [u8; DIGEST_SIZE_BYTES], Vec<u8>)> {
+    let StoredProofAccounts {
+        proof_data,
+        statement,
+        eval_value_account,
+        external_proof_account,
+        transcript_pattern,
+    } = *accounts;
+    let proof_data_info = proof_data.to_account_info();
+    let data = proof_data_info.try_borrow_data()?;
+    let (header, uploaded_proof) = ProofData::split(&data)?;
+    if let Some(expires_at_slot) = header.expires_at_slot() {
+        require!(
+            Clock::get()?.slot <= expires_at_slot,
+            WhirError::ProofExpired
+        );
+    }
+    let metadata = header.metadata()?;
+    if let Some(params) = &metadata.params {
+        require!(
+            params.matches(args, soundness_type),
+            WhirError::ParamsMismatch
+        );
+    }
+    check_statement(&metadata, statement)?;
+    let encoding =
+        ProofEncoding::try_from(header.encoding).map_err(|_| WhirError::InvalidProofEncoding)?;
+    let external_data;
+    let assembled_proof;
+    let stored_proof = if let Some(source) = &metadata.external_proof {
+        let account = external_proof_account.ok_or(WhirError::InvalidExternalProof)?;
+        require_keys_eq!(
+            account.key(),
+            source.account,
+            WhirError::InvalidExternalProof
+        );
+        external_data = account.try_borrow_data()?;
+        read_external_proof(source, &external_data)?
+    } else if metadata.buffers.is_empty() {
+        uploaded_proof
+    } else {
+        assembled_proof = assemble_buffered_proof(
+            uploaded_proof,
+            &metadata.buffers,
+            proof_data_info.key(),
+            remaining_accounts,
+        )?;
+        assembled_proof.as_slice()
+    };
+    size_limits.check_proof(stored_proof.len())?;
+    let decoded_proof;
+    let proof_bytes = match encoding {
+        ProofEncoding::Raw => stored_proof,
+        _ => {
+            // Decoding expands the stored bytes, the decoded proof must stay within the limit too.
+            decoded_proof = size_limits.decode_proof(encoding, stored_proof)?;
+            decoded_proof.as_slice()
+        }
+    };
+
+    // The value of the proof's first constraint may come from another account.
+    let external_value = metadata
+        .eval_value_source
+        .as_ref()
+        .map(|source| read_external_eval_value(source, eval_value_account))
+        .transpose()?;
+    let own_constraints =
+        metadata
+            .constraints
+            .iter()
+            .enumerate()
+            .map(|(i, c)| match (&external_value, i) {
+                (Some(value), 0) => (c.eval_point.as_slice(), value.as_slice()),
+                _ => (c.eval_point.as_slice(), c.eval_value.as_slice()),
+            });
+    let constraints = statement
+        .map(|statement| statement.constraints.as_slice())
+        .unwrap_or_default()
+        .iter()
+        .map(|c| (c.eval_point.as_slice(), c.eval_value.as_slice()))
+        .chain(own_constraints);
+
+    let digest = verify_whir_proof(
+        proof_bytes,
+        &metadata.domain_label,
+        &metadata.context,
+        constraints,
+        args,
+        soundness_type,
+        min_security_level,
+        transcript_pattern,
+    )?;
+    // A verified proof starts with its commitment, whose first bytes are the Merkle root.
+    let commitment_root: [u8; DIGEST_SIZE_BYTES] = proof_bytes[..DIGEST_SIZE_BYTES]
+        .try_into()
+        .expect("verified proof contains the commitment");
+    Ok((digest, commitment_root, metadata.user_metadata))
+}
+
+fn process_verify_and_close(
+    accounts: &mut VerifyAndClose,
+    remaining_accounts: &[AccountInfo],
+    args: &WhirParamsArgs,
+) -> Result<[u8; 32]> {
+    let digest = process_verify(
+        &mut accounts.verify,
+        remaining_accounts,
+        args,
+        SoundnessTypeArg::default(),
+    )?;
+
+    if let (Some(receipt_mint), Some(recipient_token_account), Some(token_program)) = (
+        &accounts.receipt_mint,
+        &accounts.recipient_token_account,
+        &accounts.token_program,
+    ) {
+        require!(
+            accounts.verify.proof_data.load_current()?.receipt_minted == 0,
+            WhirError::ReceiptAlreadyMinted
+        );
+        require_keys_eq!(
+            recipient_token_account.mint,
+            receipt_mint.key(),
+            WhirError::InvalidReceiptAccounts
+        );
+        mint_receipt_token(
+            token_program,
+            receipt_mint.to_account_info(),
+            recipient_token_account.to_account_info(),
+            &accounts.verify.proof_data,
+        )?;
+    }
+
+    accounts
+        .verify
+        .proof_data
+        .close(accounts.payer.to_account_info())?;
+    Ok(digest)
+}
+
+/// Verifies a WHIR proof for a statement given as (evaluation point, value) byte pairs, see
+/// [`whir_verifier_core::verify_proof`].
+///
+/// Returns the SHA-256 digest of the parameters, domain label and context followed by the
+/// commitment bytes and the statement's evaluation points and values in order, identifying the
+/// verified (commitment, statement) pair and what it was verified under, see
+/// [`whir_verifier_core::hash_binding`].
+///
+/// The parameters are validated, see [`checked_whir_params`], and must reach
+/// `min_security_level`, see `ProgramConfig::min_security_level`.
+///
+/// A cached `transcript_pattern` for the parameters, domain label and context length replaces
+/// building the domain separator. Patterns are cached for the default soundness type only.
+#[allow(clippy::too_many_arguments)]
+fn verify_whir_proof<'a>(
+    proof_bytes: &[u8],
+    domain_label: &str,
+    context: &[u8],
+    constraints: impl Iterator<Item = (&'a [u8], &'a [u8])>,
+    args: &WhirParamsArgs,
+    soundness_type: SoundnessTypeArg,
+    min_security_level: u32,
+    transcript_pattern: Option<&TranscriptPattern>,
+) -> Result<[u8; 32]> {
+    debug_msg!("WHIR Verifier: Starting verification");
+    debug_msg!(
+        "Config: num_vars={}, security={}, pow_bits={}",
+        args.num_variables,
+        args.security_level,
+        args.pow_bits
+    );
+
+    cu_checkpoint!("start");
+    require!(
+        args.security_level >= min_security_level,
+        WhirError::InsufficientSecurity
+    );
+    require!(
+        transcript_pattern.is_none() || soundness_type == SoundnessTypeArg::default(),
+        WhirError::TranscriptPatternMismatch
+    );
+    // Boxed to keep it out of the 4 KiB stack frame, see `whir_verifier_core`.
+    let params = Box::new(checked_whir_params_with(args, soundness_type)?);
+    // No-PoW configurations are explicit: their parameters must not grind in any round.
+    if args.pow_bits as usize == NO_POW_BITS {
+        require!(is_pow_free(&params), WhirError::InsufficientPowBits);
+    }
+    heap::check_heap(cost::estimate_heap_bytes(&params))?;
+    compute::log_compute_units(cost::estimate_compute_units(&params));
+    cu_checkpoint!("parameters");
+
+    let domainsep = match transcript_pattern {
+        Some(pattern) => pattern.domain_separator(args, domain_label, context.len())?,
+        None => transcript::domain_separator(&params, domain_label, context),
+    };
+    let digest = verify_proof(
+        &params,
+        &domainsep,
+        proof_bytes,
+        domain_label,
+        context,
+        constraints,
+        Sha256Digest::default(),
+        |phase| {
+            cu_checkpoint!(phase.name());
+        },
+    )
+    .map_err(WhirError::from)?;
+
+    // Failures are logged by the returned error.
+    msg!("WHIR proof verified");
+
+    Ok(digest)
+}
+
+/// SHA-256 of the verified digest, via the `sol_sha256` syscall.
+#[derive(Default)]
+pub(crate) struct Sha256Digest(Hasher);
+
+impl DigestHasher for Sha256Digest {
+    fn update(&mut self, bytes: &[u8]) {
+        self.0.hash(bytes);
+    }
+
+    fn finalize(self) -> [u8; 32] {
+        self.0.result().to_bytes()
+    }
+}
+
+impl From<VerifyError> for WhirError {
+    fn from(error: VerifyError) -> Self {
+        match error {
+            VerifyError::CommitmentParse => WhirError::CommitmentParseError,
+            VerifyError::InvalidEvalPoint => WhirError::InvalidEvalPoint,
+            VerifyError::Deserialization => WhirError::DeserializationError,
+            VerifyError::EmptyStatement => WhirError::EmptyStatement,
+            VerifyError::VerificationFailed => WhirError::VerificationFailed,
+            VerifyError::TranscriptMismatch => WhirError::TranscriptMismatch,
+            VerifyError::ProofDeserialization => WhirError::ProofDeserializationError,
+        }
+    }
+}
+
+/// Returns the proof bytes of an external source, checking them against the declared hash.
+fn read_external_proof<'a>(source: &ExternalProofSource, data: &'a [u8]) -> Result<&'a [u8]> {
+    let start = source.offset as usize;
+    let proof_bytes = data
+        .get(start..start + source.len as usize)
+        .ok_or(WhirError::InvalidExternalProof)?;
+    require!(
+        hash(proof_bytes).to_bytes() == source.hash,
+        WhirError::InvalidExternalProof
+    );
+    Ok(proof_bytes)
+}
+
+/// Concatenates the proof bytes uploaded to the proof account with the ones in its buffer accounts.
+///
+/// The buffers must be passed as remaining accounts in the order of the proof account's manifest.
+fn assemble_buffered_proof(
+    uploaded_proof: &[u8],
+    buffers: &[Pubkey],
+    proof_data_key: Pubkey,
+    remaining_accounts: &[AccountInfo],
+) -> Result<Vec<u8>> {
+    require_eq!(
+        remaining_accounts.len(),
+        buffers.len(),
+        WhirError::InvalidProofBuffers
+    );
+    // The buffers are read in place, so their bytes are copied once, into the assembled proof.
+    let mut buffer_data = Vec::with_capacity(buffers.len());
+    for (info, expected_key) in remaining_accounts.iter().zip(buffers) {
+        require_keys_eq!(info.key(), *expected_key, WhirError::InvalidProofBuffers);
+        require_keys_eq!(
+            *info.owner,
+            crate::ID,
+            ErrorCode::AccountOwnedByWrongProgram
+        );
+        buffer_data.push(info.try_borrow_data()?);
+    }
+    let mut parts = Vec::with_capacity(buffers.len());
+    for data in &buffer_data {
+        let (proof_data, part) = ProofBuffer::borrow_parts(data)?;
+        require_keys_eq!(proof_data, proof_data_key, WhirError::InvalidProofBuffers);
+        parts.push(part);
+    }
+    let total_len = uploaded_proof.len() + parts.iter().map(|part| part.len()).sum::<usize>();
+    let mut proof_bytes = Vec::with_capacity(total_len);
+    proof_bytes.extend_from_slice(uploaded_proof);
+    for part in parts {
+        proof_bytes.extend_from_slice(part);
+    }
+    Ok(proof_bytes)
+}
+
+/// Concatenates the chunks of the `proof_chunk` instructions between the previous
+/// `verify_from_instructions` instruction of the transaction, if any, and the current one.
+fn collect_proof_chunks(instructions: &AccountInfo) -> Result<Vec<u8>> {
+    let current_index = sysvar_instructions::load_current_index_checked(instructions)?;
+    // Chunks before an earlier verify belong to the proof it verified.
+    let mut start = 0;
+    for index in (0..current_index).rev() {
+        let ix = sysvar_instructions::load_instruction_at_checked(index.into(), instructions)?;
+        if ix.program_id == crate::ID
+            && ix
+                .data
+                .starts_with(instruction::VerifyFromInstructions::DISCRIMINATOR)
+        {
+            start = index + 1;
+            break;
+        }
+    }
+    let mut proof_bytes = Vec::new();
+    for index in start..current_index {
+        let ix = sysvar_instructions::load_instruction_at_checked(index.into(), instructions)?;
+        if ix.program_id != crate::ID
+            || !ix.data.starts_with(instruction::ProofChunk::DISCRIMINATOR)
+        {
+            continue;
+        }
+        let chunk_ix = instruction::ProofChunk::try_from_slice(
+            &ix.data[instruction::ProofChunk::DISCRIMINATOR.len()..],
+        )?;
+        proof_bytes.extend_from_slice(&chunk_ix._chunk);
+    }
+    require!(!proof_bytes.is_empty(), WhirError::MissingProofChunks);
+    Ok(proof_bytes)
+}
+
+/// Reads a serialized field element from the account designated by `source`.
+fn read_external_eval_value(
+    source: &EvalValueSource,
+    account: Option<&UncheckedAccount>,
+) -> Result<Vec<u8>> {
+    let account = account.ok_or(WhirError::MissingEvalValueAccount)?;
+    require_keys_eq!(
+        account.key(),
+        source.account,
+        WhirError::MissingEvalValueAccount
+    );
+    let data = account.try_borrow_data()?;
+    let start = source.offset as usize;
+    let value = data
+        .get(start..start + field_size_bytes())
+        .ok_or(WhirError::DeserializationError)?;
+    Ok(value.to_vec())
 }
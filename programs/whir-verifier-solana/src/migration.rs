@@ -0,0 +1,143 @@
+//! Account layout versions and migrations.
+//!
+//! Versioned accounts store their layout version right after the discriminator. A layout change
+//! bumps the account's version and adds a step upgrading the previous layout in [`upgrade`], so
+//! accounts created before a program upgrade can be migrated in place instead of being orphaned.
+//! Proof accounts of the released program predate the version, they're recognized by their
+//! discriminator instead, see [`BASELINE_PROOF_DATA_DISCRIMINATOR`].
+
+use anchor_lang::{
+    prelude::*,
+    system_program::{transfer, Transfer},
+    Discriminator,
+};
+use whir_config::encoding::ProofEncoding;
+
+use crate::{EvalConstraint, ProofBuffer, ProofData, ProofMetadata, StatementData, WhirError};
+
+/// Current layout version of [`ProofData`].
+pub const PROOF_DATA_VERSION: u8 = 1;
+
+/// Current layout version of [`ProofBuffer`].
+pub const PROOF_BUFFER_VERSION: u8 = 1;
+
+/// Current layout version of [`StatementData`].
+pub const STATEMENT_DATA_VERSION: u8 = 1;
+
+/// Discriminator of the released Borsh layout of [`ProofData`], Anchor's default for the account.
+/// The zero-copy layout has its own, see [`ProofData`].
+pub const BASELINE_PROOF_DATA_DISCRIMINATOR: [u8; 8] = [174, 109, 121, 220, 193, 80, 148, 121];
+
+/// Offset of the layout version in the account data, right after the discriminator.
+const VERSION_OFFSET: usize = 8;
+
+#[derive(Accounts)]
+pub struct Migrate<'info> {
+    /// CHECK: Parsed according to its discriminator and layout version.
+    #[account(mut, owner = crate::ID @ WhirError::UnsupportedAccountVersion)]
+    pub account: UncheckedAccount<'info>,
+    /// Pays the rent of accounts growing during the migration.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn process_migrate(ctx: Context<Migrate>) -> Result<()> {
+    let account = ctx.accounts.account.to_account_info();
+    let upgraded = upgrade(&account.try_borrow_data()?)?;
+    match upgraded {
+        Some(data) => write(&ctx.accounts, &data),
+        None => Ok(()),
+    }
+}
+
+/// Returns the account data upgraded to the current layout, or `None` if it's current.
+fn upgrade(data: &[u8]) -> Result<Option<Vec<u8>>> {
+    if data.starts_with(&BASELINE_PROOF_DATA_DISCRIMINATOR) {
+        return upgrade_baseline_proof_data(data).map(Some);
+    }
+    let current_version = if data.starts_with(ProofData::DISCRIMINATOR) {
+        PROOF_DATA_VERSION
+    } else if data.starts_with(ProofBuffer::DISCRIMINATOR) {
+        PROOF_BUFFER_VERSION
+    } else if data.starts_with(StatementData::DISCRIMINATOR) {
+        STATEMENT_DATA_VERSION
+    } else {
+        return err!(WhirError::UnsupportedAccountVersion);
+    };
+    let version = *data
+        .get(VERSION_OFFSET)
+        .ok_or(WhirError::UnsupportedAccountVersion)?;
+    require!(
+        version == current_version,
+        WhirError::UnsupportedAccountVersion
+    );
+    Ok(None)
+}
+
+/// Released Borsh layout of [`ProofData`], without a layout version.
+#[derive(AnchorDeserialize)]
+struct BaselineProofData {
+    payer: Pubkey,
+    proof: Vec<u8>,
+    eval_point: Vec<u8>,
+    eval_value: Vec<u8>,
+}
+
+/// Moves a proof account of the released layout to the zero-copy header and raw proof region,
+/// keeping the uploaded proof and its evaluation as the first constraint. The released program
+/// didn't record verifications, so there are none to carry over.
+fn upgrade_baseline_proof_data(data: &[u8]) -> Result<Vec<u8>> {
+    let old = BaselineProofData::deserialize(&mut &data[8..])
+        .map_err(|_| WhirError::UnsupportedAccountVersion)?;
+
+    // Built on the heap, the header is too large for the stack.
+    let mut header: Box<ProofData> = bytemuck::zeroed_box();
+    header.version = PROOF_DATA_VERSION;
+    header.payer = old.payer;
+    header.authority = old.payer;
+    header.encoding = ProofEncoding::Raw as u8;
+    header.proof_len = old.proof.len() as u32;
+    header.set_metadata(&ProofMetadata {
+        constraints: vec![EvalConstraint {
+            eval_point: old.eval_point,
+            eval_value: old.eval_value,
+        }],
+        ..Default::default()
+    })?;
+
+    let mut upgraded = Vec::with_capacity(ProofData::PROOF_OFFSET + old.proof.len());
+    upgraded.extend_from_slice(ProofData::DISCRIMINATOR);
+    upgraded.extend_from_slice(bytemuck::bytes_of(&*header));
+    upgraded.extend_from_slice(&old.proof);
+    Ok(upgraded)
+}
+
+/// Writes upgraded data to the account, growing it and topping up its rent if needed.
+fn write(accounts: &Migrate, data: &[u8]) -> Result<()> {
+    let account = accounts.account.to_account_info();
+    if data.len() > account.data_len() {
+        let rent = Rent::get()?
+            .minimum_balance(data.len())
+            .saturating_sub(account.lamports());
+        if rent > 0 {
+            transfer(
+                CpiContext::new(
+                    accounts.system_program.to_account_info(),
+                    Transfer {
+                        from: accounts.payer.to_account_info(),
+                        to: account.clone(),
+                    },
+                ),
+                rent,
+            )?;
+        }
+        account.resize(data.len())?;
+    }
+    let mut account_data = account.try_borrow_mut_data()?;
+    let (upgraded, rest) = account_data.split_at_mut(data.len());
+    upgraded.copy_from_slice(data);
+    // Bytes of the old layout past the upgraded data would read as uploaded proof bytes.
+    rest.fill(0);
+    Ok(())
+}
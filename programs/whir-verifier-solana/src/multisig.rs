@@ -0,0 +1,171 @@
+//! Threshold admin: an M-of-N multisig the admin role can be handed over to.
+//!
+//! The multisig is a PDA that signs instructions of this program via `execute_as_multisig` once
+//! at least `threshold` of its signers sign the same transaction, like the multisigs of the SPL
+//! token program. It takes over the admin role through the usual two-step handover: the admin
+//! proposes the multisig's address and the multisig executes `accept_admin`. Its signers and
+//! threshold are changed by executing `set_multisig_signers` through the multisig itself.
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{
+        instruction::{AccountMeta, Instruction},
+        program::invoke_signed,
+    },
+};
+
+use crate::{admin::ProgramConfig, program::WhirVerifierSolana, WhirError};
+
+/// Maximum number of signers of the admin multisig.
+pub const MAX_MULTISIG_SIGNERS: usize = 11;
+
+/// Signers and threshold of the admin multisig, a singleton PDA signing as itself.
+#[account]
+#[derive(InitSpace)]
+pub struct AdminMultisig {
+    #[max_len(MAX_MULTISIG_SIGNERS)]
+    pub signers: Vec<Pubkey>,
+    /// Number of distinct signers needed to execute an instruction.
+    pub threshold: u8,
+    pub bump: u8,
+}
+
+impl AdminMultisig {
+    pub const SEED: &'static [u8] = b"admin_multisig";
+
+    /// Checks that the signers are distinct and the threshold is between one and their number.
+    fn check(signers: &[Pubkey], threshold: u8) -> Result<()> {
+        let distinct = signers
+            .iter()
+            .enumerate()
+            .all(|(i, signer)| !signers[..i].contains(signer));
+        require!(
+            distinct
+                && signers.len() <= MAX_MULTISIG_SIGNERS
+                && (1..=signers.len()).contains(&(threshold as usize)),
+            WhirError::InvalidMultisig
+        );
+        Ok(())
+    }
+
+    /// Number of distinct signers of the multisig that signed among `accounts`.
+    pub fn approvals(&self, accounts: &[AccountInfo]) -> usize {
+        self.signers
+            .iter()
+            .filter(|signer| {
+                accounts
+                    .iter()
+                    .any(|account| account.is_signer && account.key == *signer)
+            })
+            .count()
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitAdminMultisig<'info> {
+    #[account(
+        seeds = [ProgramConfig::SEED],
+        bump = config.bump,
+        has_one = admin @ WhirError::Unauthorized
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + AdminMultisig::INIT_SPACE,
+        seeds = [AdminMultisig::SEED],
+        bump
+    )]
+    pub multisig: Account<'info, AdminMultisig>,
+    pub admin: Signer<'info>,
+    /// Pays the multisig's rent.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct UpdateAdminMultisig<'info> {
+    #[account(mut, seeds = [AdminMultisig::SEED], bump = multisig.bump)]
+    pub multisig: Account<'info, AdminMultisig>,
+    /// The multisig itself, signing via `execute_as_multisig`.
+    #[account(address = multisig.key() @ WhirError::Unauthorized)]
+    pub authority: Signer<'info>,
+}
+
+#[derive(Accounts)]
+pub struct ExecuteAsMultisig<'info> {
+    // Not `mut`: an executed `set_multisig_signers` writes the account, which mustn't be
+    // overwritten with this stale copy on exit.
+    #[account(seeds = [AdminMultisig::SEED], bump = multisig.bump)]
+    pub multisig: Account<'info, AdminMultisig>,
+    pub program: Program<'info, WhirVerifierSolana>,
+}
+
+pub fn process_init_admin_multisig(
+    ctx: Context<InitAdminMultisig>,
+    signers: Vec<Pubkey>,
+    threshold: u8,
+) -> Result<()> {
+    AdminMultisig::check(&signers, threshold)?;
+    let multisig = &mut ctx.accounts.multisig;
+    multisig.signers = signers;
+    multisig.threshold = threshold;
+    multisig.bump = ctx.bumps.multisig;
+    Ok(())
+}
+
+pub fn process_set_multisig_signers(
+    ctx: Context<UpdateAdminMultisig>,
+    signers: Vec<Pubkey>,
+    threshold: u8,
+) -> Result<()> {
+    AdminMultisig::check(&signers, threshold)?;
+    let multisig = &mut ctx.accounts.multisig;
+    multisig.signers = signers;
+    multisig.threshold = threshold;
+    Ok(())
+}
+
+/// Invokes an instruction of this program signed by the multisig. The first `signer_count`
+/// remaining accounts are the approving signers, the others the instruction's accounts, in
+/// which the multisig is marked as a signer.
+pub fn process_execute_as_multisig<'info>(
+    ctx: Context<'_, '_, 'info, 'info, ExecuteAsMultisig<'info>>,
+    signer_count: u8,
+    data: Vec<u8>,
+) -> Result<()> {
+    let remaining_accounts = ctx.remaining_accounts;
+    require!(
+        signer_count as usize <= remaining_accounts.len(),
+        WhirError::MultisigThresholdNotMet
+    );
+    let (approvers, accounts) = remaining_accounts.split_at(signer_count as usize);
+    let multisig = &ctx.accounts.multisig;
+    require!(
+        multisig.approvals(approvers) >= multisig.threshold as usize,
+        WhirError::MultisigThresholdNotMet
+    );
+
+    let multisig_key = multisig.key();
+    let instruction = Instruction {
+        program_id: crate::ID,
+        accounts: accounts
+            .iter()
+            .map(|account| AccountMeta {
+                pubkey: account.key(),
+                is_signer: account.is_signer || account.key() == multisig_key,
+                is_writable: account.is_writable,
+            })
+            .collect(),
+        data,
+    };
+    let mut account_infos = accounts.to_vec();
+    account_infos.push(ctx.accounts.program.to_account_info());
+    invoke_signed(
+        &instruction,
+        &account_infos,
+        &[&[AdminMultisig::SEED, &[multisig.bump]]],
+    )?;
+    Ok(())
+}
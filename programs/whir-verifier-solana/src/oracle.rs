@@ -0,0 +1,158 @@
+//! Oracle mode: verified evaluations published in a PDA per commitment and evaluation points.
+//!
+//! A commitment binds its polynomial, so the published evaluations are the same no matter
+//! which proof or caller publishes them first. Proofs opening the same commitment at other points
+//! publish into their own PDAs.
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::hash::hashv,
+    system_program::{
+        allocate, assign, create_account, transfer, Allocate, Assign, CreateAccount, Transfer,
+    },
+};
+
+use crate::{EvalConstraint, LoadProofData, ProgramConfig, ProofData, WhirError};
+
+/// Verified evaluations of a committed polynomial, a PDA keyed by the commitment's Merkle root and
+/// the hash of the evaluation points, see [`EvaluationResult::eval_points_hash`].
+#[account]
+pub struct EvaluationResult {
+    pub commitment: [u8; 32],
+    pub evaluations: Vec<EvalConstraint>,
+    /// The proof account the evaluations were verified in.
+    pub proof_data: Pubkey,
+    pub slot: u64,
+    pub bump: u8,
+}
+
+impl EvaluationResult {
+    pub const SEED: &'static [u8] = b"evaluation";
+
+    /// SHA-256 of the serialized evaluation points in order.
+    pub fn eval_points_hash(evaluations: &[EvalConstraint]) -> [u8; 32] {
+        let points: Vec<&[u8]> = evaluations
+            .iter()
+            .map(|e| e.eval_point.as_slice())
+            .collect();
+        hashv(&points).to_bytes()
+    }
+
+    /// Account size, including the discriminator, for the given evaluations.
+    pub fn space(evaluations: &[EvalConstraint]) -> usize {
+        let evaluations_size = evaluations
+            .iter()
+            .map(|e| 4 + e.eval_point.len() + 4 + e.eval_value.len())
+            .sum::<usize>();
+        8 + 32 + 4 + evaluations_size + 32 + 8 + 1
+    }
+}
+
+#[derive(Accounts)]
+pub struct PublishEvaluation<'info> {
+    pub proof_data: AccountLoader<'info, ProofData>,
+    /// CHECK: Created by the instruction at the PDA of the proof's verified commitment and
+    /// evaluation points.
+    #[account(mut)]
+    pub evaluation_result: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    /// Minimum security level of the verified parameters.
+    #[account(seeds = [ProgramConfig::SEED], bump = config.bump)]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+pub fn process_publish_evaluation(ctx: Context<PublishEvaluation>) -> Result<()> {
+    let accounts = &ctx.accounts;
+    let proof_data = accounts.proof_data.load_current()?;
+    let commitment = proof_data
+        .verified_commitment()
+        .ok_or(WhirError::ProofNotVerified)?;
+    let digest = proof_data
+        .verified_digest()
+        .ok_or(WhirError::ProofNotVerified)?;
+    proof_data.check_verified_params(&digest, &accounts.config)?;
+    let metadata = proof_data.metadata()?;
+    // Values read from another account at verification time aren't stored in the proof.
+    require!(
+        metadata.eval_value_source.is_none() && !metadata.constraints.is_empty(),
+        WhirError::EvaluationNotPublishable
+    );
+
+    // The seeds depend on the proof account's contents, so the PDA is derived and created here.
+    let points_hash = EvaluationResult::eval_points_hash(&metadata.constraints);
+    let (expected_key, bump) = Pubkey::find_program_address(
+        &[EvaluationResult::SEED, &commitment, &points_hash],
+        &crate::ID,
+    );
+    require_keys_eq!(
+        accounts.evaluation_result.key(),
+        expected_key,
+        ErrorCode::ConstraintSeeds
+    );
+    let result = EvaluationResult {
+        commitment,
+        evaluations: metadata.constraints,
+        proof_data: accounts.proof_data.key(),
+        slot: Clock::get()?.slot,
+        bump,
+    };
+    let space = EvaluationResult::space(&result.evaluations);
+    let signer_seeds: &[&[&[u8]]] =
+        &[&[EvaluationResult::SEED, &commitment, &points_hash, &[bump]]];
+    let target = accounts.evaluation_result.to_account_info();
+    let rent = Rent::get()?.minimum_balance(space);
+    if target.lamports() == 0 {
+        create_account(
+            CpiContext::new_with_signer(
+                accounts.system_program.to_account_info(),
+                CreateAccount {
+                    from: accounts.payer.to_account_info(),
+                    to: target.clone(),
+                },
+                signer_seeds,
+            ),
+            rent,
+            space as u64,
+            &crate::ID,
+        )?;
+    } else {
+        // Someone may have sent lamports to the address, which makes `create_account` fail.
+        let top_up = rent.saturating_sub(target.lamports());
+        if top_up > 0 {
+            transfer(
+                CpiContext::new(
+                    accounts.system_program.to_account_info(),
+                    Transfer {
+                        from: accounts.payer.to_account_info(),
+                        to: target.clone(),
+                    },
+                ),
+                top_up,
+            )?;
+        }
+        let system_program = accounts.system_program.to_account_info();
+        allocate(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                Allocate {
+                    account_to_allocate: target.clone(),
+                },
+                signer_seeds,
+            ),
+            space as u64,
+        )?;
+        assign(
+            CpiContext::new_with_signer(
+                system_program,
+                Assign {
+                    account_to_assign: target.clone(),
+                },
+                signer_seeds,
+            ),
+            &crate::ID,
+        )?;
+    }
+    result.try_serialize(&mut &mut target.try_borrow_mut_data()?[..])
+}
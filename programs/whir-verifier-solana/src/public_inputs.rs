@@ -0,0 +1,62 @@
+//! Statements over typed public inputs, see `whir_config::public_inputs`.
+//!
+//! Instead of raw evaluation point bytes, the authority of a proof account passes the proof's
+//! typed public inputs and the program derives the evaluation point from them. Consumers check a
+//! verified proof against their own public inputs by deriving the point the same way, e.g. via
+//! [`public_inputs_constraint`].
+
+use anchor_lang::prelude::*;
+use whir_config::{
+    field_size_bytes,
+    public_inputs::{public_inputs_eval_point_bytes, PublicInput},
+};
+
+use crate::{EvalConstraint, LoadProofData, UploadChunk};
+
+/// A typed public input, as passed to `add_public_inputs_constraint`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PublicInputArg {
+    U64(u64),
+    Hash([u8; 32]),
+    Pubkey(Pubkey),
+}
+
+impl From<&PublicInputArg> for PublicInput {
+    fn from(input: &PublicInputArg) -> Self {
+        match input {
+            PublicInputArg::U64(value) => PublicInput::U64(*value),
+            PublicInputArg::Hash(hash) => PublicInput::Hash(*hash),
+            PublicInputArg::Pubkey(key) => PublicInput::Pubkey(key.to_bytes()),
+        }
+    }
+}
+
+/// Returns the evaluation constraint binding the public inputs of a polynomial of
+/// `num_variables` variables to `eval_value_bytes`.
+pub fn public_inputs_constraint(
+    public_inputs: &[PublicInputArg],
+    num_variables: u32,
+    eval_value_bytes: Vec<u8>,
+) -> EvalConstraint {
+    let inputs: Vec<PublicInput> = public_inputs.iter().map(PublicInput::from).collect();
+    EvalConstraint {
+        eval_point: public_inputs_eval_point_bytes(&inputs, num_variables as usize),
+        eval_value: eval_value_bytes,
+    }
+}
+
+pub fn process_add_public_inputs_constraint(
+    ctx: Context<UploadChunk>,
+    num_variables: u32,
+    public_inputs: Vec<PublicInputArg>,
+    eval_value_bytes: Vec<u8>,
+) -> Result<()> {
+    ctx.accounts
+        .size_limits()
+        .check_eval_point(num_variables as usize * field_size_bytes())?;
+    let constraint = public_inputs_constraint(&public_inputs, num_variables, eval_value_bytes);
+    ctx.accounts
+        .proof_data
+        .load_current_mut()?
+        .update_metadata(|metadata| metadata.constraints.push(constraint))
+}
@@ -0,0 +1,162 @@
+//! Non-transferable receipt tokens minted for verified proofs.
+//!
+//! Wallets and other programs can treat holding a receipt token as proof of verification.
+
+use anchor_lang::{
+    prelude::*,
+    system_program::{create_account, CreateAccount},
+};
+use anchor_spl::{
+    token_2022::{
+        initialize_mint2,
+        spl_token_2022::{extension::ExtensionType, state::Mint as MintState},
+        InitializeMint2, Token2022,
+    },
+    token_2022_extensions::non_transferable::{
+        non_transferable_mint_initialize, NonTransferableMintInitialize,
+    },
+    token_interface::{mint_to, Mint, MintTo, TokenAccount},
+};
+
+use crate::{admin::ProgramConfig, LoadProofData, ProofData, WhirError};
+
+/// Seed of the receipt mint PDA, which is also its own mint authority.
+pub const RECEIPT_MINT_SEED: &[u8] = b"receipt_mint";
+
+#[derive(Accounts)]
+pub struct InitReceiptMint<'info> {
+    #[account(
+        seeds = [ProgramConfig::SEED],
+        bump = config.bump,
+        has_one = admin @ WhirError::Unauthorized
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    /// CHECK: Created and initialized as a non-transferable mint by the instruction.
+    #[account(mut, seeds = [RECEIPT_MINT_SEED], bump)]
+    pub receipt_mint: UncheckedAccount<'info>,
+    pub admin: Signer<'info>,
+    /// Funds the receipt mint's rent.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub token_program: Program<'info, Token2022>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MintReceipt<'info> {
+    #[account(
+        mut,
+        has_one = authority,
+        constraint = proof_data.load_current()?.verified_digest().is_some() @ WhirError::ProofNotVerified,
+        constraint = proof_data.load_current()?.receipt_minted == 0 @ WhirError::ReceiptAlreadyMinted
+    )]
+    pub proof_data: AccountLoader<'info, ProofData>,
+    pub authority: Signer<'info>,
+    #[account(mut, seeds = [RECEIPT_MINT_SEED], bump, mint::token_program = token_program)]
+    pub receipt_mint: InterfaceAccount<'info, Mint>,
+    /// Token account of the recipient, e.g. its associated token account.
+    #[account(mut, token::mint = receipt_mint, token::token_program = token_program)]
+    pub recipient_token_account: InterfaceAccount<'info, TokenAccount>,
+    pub token_program: Program<'info, Token2022>,
+    /// Minimum security level of the verified parameters.
+    #[account(seeds = [ProgramConfig::SEED], bump = config.bump)]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+pub fn process_init_receipt_mint(ctx: Context<InitReceiptMint>) -> Result<()> {
+    let accounts = &ctx.accounts;
+    let signer_seeds: &[&[&[u8]]] = &[&[RECEIPT_MINT_SEED, &[ctx.bumps.receipt_mint]]];
+    let space =
+        ExtensionType::try_calculate_account_len::<MintState>(&[ExtensionType::NonTransferable])?;
+
+    create_account(
+        CpiContext::new_with_signer(
+            accounts.system_program.to_account_info(),
+            CreateAccount {
+                from: accounts.payer.to_account_info(),
+                to: accounts.receipt_mint.to_account_info(),
+            },
+            signer_seeds,
+        ),
+        Rent::get()?.minimum_balance(space),
+        space as u64,
+        &accounts.token_program.key(),
+    )?;
+    non_transferable_mint_initialize(CpiContext::new(
+        accounts.token_program.to_account_info(),
+        NonTransferableMintInitialize {
+            token_program_id: accounts.token_program.to_account_info(),
+            mint: accounts.receipt_mint.to_account_info(),
+        },
+    ))?;
+    initialize_mint2(
+        CpiContext::new(
+            accounts.token_program.to_account_info(),
+            InitializeMint2 {
+                mint: accounts.receipt_mint.to_account_info(),
+            },
+        ),
+        0,
+        &accounts.receipt_mint.key(),
+        None,
+    )
+}
+
+/// Emitted when a receipt token is minted for a proof account.
+#[event]
+pub struct ReceiptMinted {
+    pub proof_data: Pubkey,
+    pub recipient_token_account: Pubkey,
+    /// The proof account's user metadata, see [`crate::ProofMetadata::user_metadata`].
+    pub user_metadata: Vec<u8>,
+}
+
+pub fn process_mint_receipt(ctx: Context<MintReceipt>) -> Result<()> {
+    {
+        let proof_data = ctx.accounts.proof_data.load_current()?;
+        let digest = proof_data
+            .verified_digest()
+            .ok_or(WhirError::ProofNotVerified)?;
+        proof_data.check_verified_params(&digest, &ctx.accounts.config)?;
+    }
+    mint_receipt_token(
+        &ctx.accounts.token_program,
+        ctx.accounts.receipt_mint.to_account_info(),
+        ctx.accounts.recipient_token_account.to_account_info(),
+        &ctx.accounts.proof_data,
+    )
+}
+
+/// Mints a single receipt token for `proof_data` to `recipient_token_account`, signed by the
+/// receipt mint PDA, and marks the receipt as minted.
+pub(crate) fn mint_receipt_token<'info>(
+    token_program: &Program<'info, Token2022>,
+    receipt_mint: AccountInfo<'info>,
+    recipient_token_account: AccountInfo<'info>,
+    proof_data: &AccountLoader<'info, ProofData>,
+) -> Result<()> {
+    let (_, bump) = Pubkey::find_program_address(&[RECEIPT_MINT_SEED], &crate::ID);
+    let signer_seeds: &[&[&[u8]]] = &[&[RECEIPT_MINT_SEED, &[bump]]];
+    let recipient = recipient_token_account.key();
+    mint_to(
+        CpiContext::new_with_signer(
+            token_program.to_account_info(),
+            MintTo {
+                mint: receipt_mint.clone(),
+                to: recipient_token_account,
+                authority: receipt_mint,
+            },
+            signer_seeds,
+        ),
+        1,
+    )?;
+
+    let mut header = proof_data.load_current_mut()?;
+    header.receipt_minted = 1;
+    emit!(ReceiptMinted {
+        proof_data: proof_data.key(),
+        recipient_token_account: recipient,
+        user_metadata: header.metadata()?.user_metadata,
+    });
+    Ok(())
+}
@@ -0,0 +1,68 @@
+//! Replay-protection registry of consumed (commitment, statement) digests.
+//!
+//! A digest binds the parameters it was verified under (see `ProofData::verified_digest`), and
+//! consuming it additionally requires them to meet the configured minimum security level, so
+//! consumers never accept a proof verified under parameters the configuration considers weak.
+
+use anchor_lang::prelude::*;
+
+use crate::{LoadProofData, ProgramConfig, ProofData};
+
+/// Registry entry of a consumed proof, a PDA per consumer and digest. Its existence marks the
+/// digest as used by the consumer.
+#[account]
+#[derive(InitSpace)]
+pub struct VerifiedDigest {
+    pub digest: [u8; 32],
+    /// The proof account the digest was verified in.
+    pub proof_data: Pubkey,
+    /// The signer that consumed the proof, e.g. a PDA of the consuming program.
+    pub consumer: Pubkey,
+    pub slot: u64,
+    pub bump: u8,
+}
+
+impl VerifiedDigest {
+    pub const SEED: &'static [u8] = b"verified";
+}
+
+#[derive(Accounts)]
+#[instruction(digest: [u8; 32])]
+pub struct ConsumeVerification<'info> {
+    /// Holds a proof verified with `digest`, under parameters meeting the minimum security level
+    /// of `config`.
+    pub proof_data: AccountLoader<'info, ProofData>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + VerifiedDigest::INIT_SPACE,
+        seeds = [VerifiedDigest::SEED, consumer.key().as_ref(), digest.as_ref()],
+        bump
+    )]
+    pub registry_entry: Account<'info, VerifiedDigest>,
+    /// Keys the registry entry, so nobody else can consume the digest in its place.
+    pub consumer: Signer<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+    /// Minimum security level of the verified parameters.
+    #[account(seeds = [ProgramConfig::SEED], bump = config.bump)]
+    pub config: Account<'info, ProgramConfig>,
+}
+
+pub fn process_consume_verification(
+    ctx: Context<ConsumeVerification>,
+    digest: [u8; 32],
+) -> Result<()> {
+    ctx.accounts
+        .proof_data
+        .load_current()?
+        .check_verified_params(&digest, &ctx.accounts.config)?;
+    let entry = &mut ctx.accounts.registry_entry;
+    entry.digest = digest;
+    entry.proof_data = ctx.accounts.proof_data.key();
+    entry.consumer = ctx.accounts.consumer.key();
+    entry.slot = Clock::get()?.slot;
+    entry.bump = ctx.bumps.registry_entry;
+    Ok(())
+}
@@ -0,0 +1,151 @@
+//! Random sampling of accepted commitments at evaluation points derived on-chain.
+//!
+//! For data-availability sampling, the evaluation point of a spot check must be unknown to the
+//! prover when it commits. A sample request derives the point from the most recent slot hash, after
+//! the commitment was accepted (see [`AcceptedCommitment`]), and the prover answers it with a proof
+//! opening the commitment at that point. The slot leader can see the slot hash ahead of others,
+//! so applications needing stronger unpredictability should derive points from a VRF instead.
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{hash::hashv, sysvar::slot_hashes},
+};
+use ark_serialize::CanonicalSerialize;
+use whir_config::{
+    field::{field_from_uniform_bytes, FIELD_SIZE_BYTES},
+    field_size_bytes,
+};
+
+use crate::{AcceptedCommitment, LoadProofData, ProofData, WhirError, WhirParamsArgs};
+
+/// A request to open a commitment at an evaluation point derived on-chain, a PDA per requester
+/// and sample id.
+#[account]
+pub struct SampleRequest {
+    pub requester: Pubkey,
+    pub sample_id: u64,
+    /// Merkle root of the sampled commitment.
+    pub root: [u8; 32],
+    /// Parameters of the sampled commitment, which the answering proof must be verified with.
+    pub params: WhirParamsArgs,
+    /// Slot of the slot hash the evaluation point was derived from.
+    pub slot: u64,
+    pub eval_point: Vec<u8>,
+    /// Evaluation at `eval_point`, set once a proof opening the commitment there is presented.
+    pub eval_value: Option<Vec<u8>>,
+    pub bump: u8,
+}
+
+impl SampleRequest {
+    pub const SEED: &'static [u8] = b"sample";
+
+    /// Account size, including the discriminator, for a polynomial of `num_variables` variables.
+    pub fn space(num_variables: usize) -> usize {
+        let eval_point_size = num_variables * field_size_bytes();
+        8 + 32
+            + 8
+            + 32
+            + WhirParamsArgs::INIT_SPACE
+            + 8
+            + 4
+            + eval_point_size
+            + 1
+            + 4
+            + field_size_bytes()
+            + 1
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(sample_id: u64)]
+pub struct RequestSample<'info> {
+    pub accepted_commitment: Account<'info, AcceptedCommitment>,
+    #[account(
+        init,
+        payer = requester,
+        space = SampleRequest::space(accepted_commitment.params.num_variables as usize),
+        seeds = [SampleRequest::SEED, requester.key().as_ref(), &sample_id.to_le_bytes()],
+        bump
+    )]
+    pub sample_request: Account<'info, SampleRequest>,
+    #[account(mut)]
+    pub requester: Signer<'info>,
+    /// CHECK: The slot hashes sysvar, too large to deserialize as a whole.
+    #[account(address = slot_hashes::ID)]
+    pub slot_hashes: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct FulfillSample<'info> {
+    #[account(mut, constraint = sample_request.eval_value.is_none() @ WhirError::SampleMismatch)]
+    pub sample_request: Account<'info, SampleRequest>,
+    pub proof_data: AccountLoader<'info, ProofData>,
+}
+
+pub fn process_request_sample(ctx: Context<RequestSample>, sample_id: u64) -> Result<()> {
+    // The sysvar starts with the number of entries, followed by (slot, hash) pairs, newest first.
+    let data = ctx.accounts.slot_hashes.try_borrow_data()?;
+    let slot = u64::from_le_bytes(data[8..16].try_into().unwrap());
+    let slot_hash = &data[16..48];
+
+    let root = ctx.accounts.accepted_commitment.root;
+    let requester = ctx.accounts.requester.key();
+    let num_variables = ctx.accounts.accepted_commitment.params.num_variables;
+    let mut eval_point = Vec::with_capacity(num_variables as usize * field_size_bytes());
+    for i in 0..num_variables {
+        let seed = hashv(&[
+            slot_hash,
+            &root,
+            requester.as_ref(),
+            &sample_id.to_le_bytes(),
+            &i.to_le_bytes(),
+        ])
+        .to_bytes();
+        let seed: &[u8; FIELD_SIZE_BYTES] = seed[..FIELD_SIZE_BYTES].try_into().unwrap();
+        field_from_uniform_bytes(seed)
+            .serialize_compressed(&mut eval_point)
+            .map_err(|_| WhirError::DeserializationError)?;
+    }
+    drop(data);
+
+    ctx.accounts.sample_request.set_inner(SampleRequest {
+        requester,
+        sample_id,
+        root,
+        params: ctx.accounts.accepted_commitment.params.clone(),
+        slot,
+        eval_point,
+        eval_value: None,
+        bump: ctx.bumps.sample_request,
+    });
+    Ok(())
+}
+
+pub fn process_fulfill_sample(ctx: Context<FulfillSample>) -> Result<()> {
+    let proof_data = ctx.accounts.proof_data.load_current()?;
+    let sample_request = &mut ctx.accounts.sample_request;
+    require!(
+        proof_data.verified_commitment() == Some(sample_request.root),
+        WhirError::SampleMismatch
+    );
+    let metadata = proof_data.metadata()?;
+    // A proof verified under other, possibly weaker, parameters doesn't answer the sample.
+    require!(
+        metadata
+            .verified_params
+            .as_ref()
+            .is_some_and(|params| *params.parts().0 == sample_request.params),
+        WhirError::ParamsMismatch
+    );
+    // A value read from another account at verification time isn't stored in the proof account.
+    let skip = usize::from(metadata.eval_value_source.is_some());
+    let constraint = metadata
+        .constraints
+        .iter()
+        .skip(skip)
+        .find(|c| c.eval_point == sample_request.eval_point)
+        .ok_or(WhirError::SampleMismatch)?;
+    sample_request.eval_value = Some(constraint.eval_value.clone());
+    Ok(())
+}
@@ -0,0 +1,155 @@
+//! Dry-run verification for RPC simulation.
+//!
+//! `simulate_verify` runs the same verification as `verify` but never fails and never modifies an
+//! account, reporting the outcome via return data instead. Clients simulate it to check a proof
+//! before sending a `verify` transaction, without paying for a failed one.
+
+use anchor_lang::{
+    prelude::*,
+    solana_program::{program_error::ProgramError, sysvar::instructions as sysvar_instructions},
+};
+
+use crate::{
+    admin::ProgramConfig, heap, verify_stored_proof, ProofData, SoundnessTypeArg, StatementData,
+    StoredProofAccounts, TranscriptPattern, WhirError, WhirParamsArgs,
+};
+
+/// Stage of the verification at which a simulated verification failed.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerificationStage {
+    /// Reading, assembling and decoding the stored proof.
+    LoadProof,
+    /// Parsing the commitment at the start of the proof.
+    Commitment,
+    /// Reading and deserializing the statement's constraints.
+    Statement,
+    /// Checking the proof against the commitment and statement.
+    Verification,
+}
+
+impl VerificationStage {
+    /// Returns the stage reporting the error with the given code.
+    fn of(error_code: u64) -> Self {
+        let is_any = |errors: &[WhirError]| {
+            errors
+                .iter()
+                .any(|&error| u64::from(u32::from(error)) == error_code)
+        };
+        if is_any(&[WhirError::CommitmentParseError]) {
+            Self::Commitment
+        } else if is_any(&[
+            WhirError::StatementNotFinalized,
+            WhirError::MissingEvalValueAccount,
+            WhirError::InvalidEvalPoint,
+            WhirError::DeserializationError,
+            WhirError::EmptyStatement,
+        ]) {
+            Self::Statement
+        } else if is_any(&[
+            WhirError::VerificationFailed,
+            WhirError::TranscriptMismatch,
+            WhirError::TranscriptPatternMismatch,
+            WhirError::ProofDeserializationError,
+            WhirError::OutOfMemory,
+        ]) {
+            Self::Verification
+        } else {
+            Self::LoadProof
+        }
+    }
+}
+
+/// Outcome of a simulated verification, returned by `simulate_verify`, or of a verification
+/// returned by `verify_with_outcome`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Debug)]
+pub struct SimulationResult {
+    /// Whether the proof verifies.
+    pub verified: bool,
+    /// Digest `verify` would record, set if the proof verifies.
+    pub digest: Option<[u8; 32]>,
+    /// Stage the verification failed at, set if the proof doesn't verify.
+    pub failed_stage: Option<VerificationStage>,
+    /// Error code `verify` would fail with, as reported by the runtime, set if the proof doesn't
+    /// verify.
+    pub error_code: Option<u64>,
+}
+
+impl SimulationResult {
+    pub(crate) fn verified(digest: [u8; 32]) -> Self {
+        Self {
+            verified: true,
+            digest: Some(digest),
+            failed_stage: None,
+            error_code: None,
+        }
+    }
+
+    pub(crate) fn failed(error: Error) -> Self {
+        let error_code = u64::from(ProgramError::from(error));
+        Self {
+            verified: false,
+            digest: None,
+            failed_stage: Some(VerificationStage::of(error_code)),
+            error_code: Some(error_code),
+        }
+    }
+}
+
+#[derive(Accounts)]
+pub struct SimulateVerify<'info> {
+    pub proof_data: AccountLoader<'info, ProofData>,
+    /// Shared statement whose constraints are verified in addition to the proof's own ones.
+    pub statement: Option<Account<'info, StatementData>>,
+    /// CHECK: Only read if it matches the proof account's `eval_value_source`.
+    pub eval_value_account: Option<UncheckedAccount<'info>>,
+    /// CHECK: Only read if it matches the proof account's `external_proof`, whose hash is checked.
+    pub external_proof_account: Option<UncheckedAccount<'info>>,
+    /// Cached transcript pattern replacing building the domain separator.
+    pub transcript_pattern: Option<Account<'info, TranscriptPattern>>,
+    #[account(seeds = [ProgramConfig::SEED], bump = config.bump)]
+    pub config: Account<'info, ProgramConfig>,
+    /// CHECK: The instructions sysvar, checked by address. Read for the requested heap frame if
+    /// passed, see [`crate::heap`].
+    #[account(address = sysvar_instructions::ID)]
+    pub instructions_sysvar: Option<UncheckedAccount<'info>>,
+}
+
+pub fn process_simulate_verify(
+    ctx: Context<SimulateVerify>,
+    params: &WhirParamsArgs,
+) -> Result<SimulationResult> {
+    let accounts = &ctx.accounts;
+    heap::use_requested_heap_frame(accounts.instructions_sysvar.as_ref())?;
+    // `verify` checks this in its account constraints.
+    let statement_unfinalized = accounts
+        .statement
+        .as_ref()
+        .is_some_and(|statement| !statement.finalized);
+    let result = if statement_unfinalized {
+        err!(WhirError::StatementNotFinalized)
+    } else {
+        let stored_proof = StoredProofAccounts {
+            proof_data: &accounts.proof_data,
+            statement: accounts.statement.as_ref(),
+            eval_value_account: accounts.eval_value_account.as_ref(),
+            external_proof_account: accounts.external_proof_account.as_ref(),
+            transcript_pattern: accounts.transcript_pattern.as_deref(),
+        };
+        verify_stored_proof(
+            &stored_proof,
+            &accounts.config.size_limits,
+            accounts.config.min_security_level,
+            ctx.remaining_accounts,
+            params,
+            SoundnessTypeArg::default(),
+        )
+    };
+
+    Ok(match result {
+        Ok((digest, _, _)) => SimulationResult::verified(digest),
+        Err(error) => {
+            msg!("Simulated verification failed: {}", error);
+            SimulationResult::failed(error)
+        }
+    })
+}
@@ -0,0 +1,114 @@
+//! Program-wide usage statistics, tracked on-chain for operators and dashboards.
+//!
+//! The stats account is optional in the instructions updating it, so existing clients keep
+//! working. A failed verification reverts its transaction, including any stats update, so the
+//! verify instructions only count successful verifications. `verify_with_outcome` instead
+//! completes with a failed outcome, counting the failure.
+
+use anchor_lang::prelude::*;
+
+use crate::{admin::ProgramConfig, WhirError, WhirParamsArgs};
+
+/// Maximum number of parameter sets with their own usage counter.
+pub const MAX_TRACKED_CONFIGS: usize = 16;
+
+/// Usage statistics of the program, a singleton PDA.
+#[account]
+#[derive(InitSpace)]
+pub struct ProgramStats {
+    /// Successful verifications over all verify instructions.
+    pub verifications: u64,
+    /// Failed verifications recorded by `verify_with_outcome`.
+    pub failures: u64,
+    /// Bytes uploaded to proof and buffer accounts.
+    pub bytes_uploaded: u64,
+    /// Verifications and failures per parameter set, in order of first use.
+    #[max_len(MAX_TRACKED_CONFIGS)]
+    pub config_usage: Vec<ConfigUsage>,
+    /// Verifications of parameter sets beyond the first `MAX_TRACKED_CONFIGS`.
+    pub untracked_verifications: u64,
+    pub bump: u8,
+}
+
+/// Number of successful and failed verifications of a parameter set.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, InitSpace)]
+pub struct ConfigUsage {
+    pub params: WhirParamsArgs,
+    pub verifications: u64,
+    pub failures: u64,
+}
+
+impl ProgramStats {
+    pub const SEED: &'static [u8] = b"stats";
+
+    pub fn record_upload(&mut self, len: usize) {
+        self.bytes_uploaded = self.bytes_uploaded.saturating_add(len as u64);
+    }
+
+    pub fn record_verification(&mut self, params: &WhirParamsArgs) {
+        self.verifications = self.verifications.saturating_add(1);
+        match self.usage_mut(params) {
+            Some(usage) => usage.verifications = usage.verifications.saturating_add(1),
+            None => self.untracked_verifications = self.untracked_verifications.saturating_add(1),
+        }
+    }
+
+    pub fn record_failure(&mut self, params: &WhirParamsArgs) {
+        self.failures = self.failures.saturating_add(1);
+        if let Some(usage) = self.usage_mut(params) {
+            usage.failures = usage.failures.saturating_add(1);
+        }
+    }
+
+    /// Returns the counters of a parameter set, tracking it if fewer than `MAX_TRACKED_CONFIGS`
+    /// are tracked.
+    fn usage_mut(&mut self, params: &WhirParamsArgs) -> Option<&mut ConfigUsage> {
+        let index = match self.config_usage.iter().position(|u| u.params == *params) {
+            Some(index) => index,
+            None if self.config_usage.len() < MAX_TRACKED_CONFIGS => {
+                self.config_usage.push(ConfigUsage {
+                    params: params.clone(),
+                    verifications: 0,
+                    failures: 0,
+                });
+                self.config_usage.len() - 1
+            }
+            None => return None,
+        };
+        self.config_usage.get_mut(index)
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitStats<'info> {
+    #[account(
+        seeds = [ProgramConfig::SEED],
+        bump = config.bump,
+        has_one = admin @ WhirError::Unauthorized
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + ProgramStats::INIT_SPACE,
+        seeds = [ProgramStats::SEED],
+        bump
+    )]
+    pub stats: Account<'info, ProgramStats>,
+    pub admin: Signer<'info>,
+    /// Pays the rent of the statistics account.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn process_init_stats(ctx: Context<InitStats>) -> Result<()> {
+    let stats = &mut ctx.accounts.stats;
+    stats.verifications = 0;
+    stats.failures = 0;
+    stats.bytes_uploaded = 0;
+    stats.config_usage = Vec::new();
+    stats.untracked_verifications = 0;
+    stats.bump = ctx.bumps.stats;
+    Ok(())
+}
@@ -0,0 +1,113 @@
+//! Cached transcript patterns.
+//!
+//! Every verification builds the Fiat-Shamir domain separator of its parameters, domain label and
+//! context length round by round. `cache_transcript_pattern` builds it once and stores the
+//! resulting pattern in a PDA, which the verify instructions accept as an optional account to load
+//! the domain separator from instead.
+
+use anchor_lang::{prelude::*, solana_program::hash::hashv};
+use whir_config::transcript::{self, TranscriptDomainSeparator, MAX_DOMAIN_LABEL_LEN};
+
+use crate::{checked_whir_params, WhirError, WhirParamsArgs};
+
+/// The transcript pattern of some parameters, domain label and context length, a PDA keyed by
+/// their hash, see [`TranscriptPattern::key_hash`].
+#[account]
+pub struct TranscriptPattern {
+    pub params: WhirParamsArgs,
+    pub domain_label: String,
+    pub context_len: u32,
+    /// Pattern of the domain separator, see `whir_config::transcript::transcript_pattern`.
+    pub pattern: Vec<u8>,
+    pub bump: u8,
+}
+
+impl TranscriptPattern {
+    pub const SEED: &'static [u8] = b"transcript_pattern";
+
+    /// Returns the hash identifying the parameters, domain label and context length.
+    pub fn key_hash(params: &WhirParamsArgs, domain_label: &str, context_len: u32) -> [u8; 32] {
+        hashv(&[
+            &params.num_variables.to_le_bytes(),
+            &params.security_level.to_le_bytes(),
+            &params.pow_bits.to_le_bytes(),
+            &params.folding_factor.to_le_bytes(),
+            &params.starting_log_inv_rate.to_le_bytes(),
+            domain_label.as_bytes(),
+            &context_len.to_le_bytes(),
+        ])
+        .to_bytes()
+    }
+
+    /// Account size, including the discriminator, for the given parameters, domain label and
+    /// context length.
+    pub fn space(params: &WhirParamsArgs, domain_label: &str, context_len: u32) -> usize {
+        let pattern_len = transcript::transcript_pattern(
+            &params.to_whir_params(),
+            domain_label,
+            context_len as usize,
+        )
+        .len();
+        8 + WhirParamsArgs::INIT_SPACE + 4 + domain_label.len() + 4 + 4 + pattern_len + 1
+    }
+
+    /// Returns the cached domain separator, failing if it was cached for other parameters, domain
+    /// label or context length.
+    pub fn domain_separator(
+        &self,
+        params: &WhirParamsArgs,
+        domain_label: &str,
+        context_len: usize,
+    ) -> Result<TranscriptDomainSeparator> {
+        require!(
+            self.params == *params
+                && self.domain_label == domain_label
+                && self.context_len as usize == context_len,
+            WhirError::TranscriptPatternMismatch
+        );
+        Ok(transcript::from_pattern(&self.pattern).expect("patterns are built by the program"))
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(params: WhirParamsArgs, domain_label: String, context_len: u32)]
+pub struct CacheTranscriptPattern<'info> {
+    #[account(
+        init,
+        payer = payer,
+        space = TranscriptPattern::space(&params, &domain_label, context_len),
+        seeds = [
+            TranscriptPattern::SEED,
+            TranscriptPattern::key_hash(&params, &domain_label, context_len).as_ref()
+        ],
+        bump
+    )]
+    pub transcript_pattern: Account<'info, TranscriptPattern>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn process_cache_transcript_pattern(
+    ctx: Context<CacheTranscriptPattern>,
+    params: WhirParamsArgs,
+    domain_label: String,
+    context_len: u32,
+) -> Result<()> {
+    require!(
+        domain_label.len() <= MAX_DOMAIN_LABEL_LEN,
+        WhirError::DomainLabelTooLong
+    );
+    let whir_params = checked_whir_params(&params)?;
+    let pattern = transcript::transcript_pattern(&whir_params, &domain_label, context_len as usize);
+    ctx.accounts
+        .transcript_pattern
+        .set_inner(TranscriptPattern {
+            params,
+            domain_label,
+            context_len,
+            pattern,
+            bump: ctx.bumps.transcript_pattern,
+        });
+    Ok(())
+}
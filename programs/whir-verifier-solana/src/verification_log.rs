@@ -0,0 +1,86 @@
+//! Ring buffer of recent verification outcomes, for indexers backfilling from account state.
+//!
+//! The log account is optional in the verify instructions. A failed verification reverts its
+//! transaction, including the log write, so only successful verifications are logged.
+
+use anchor_lang::prelude::*;
+
+use crate::{admin::ProgramConfig, WhirError};
+
+/// Number of entries kept in the log before the oldest ones are overwritten.
+pub const VERIFICATION_LOG_CAPACITY: usize = 64;
+
+/// Log of the latest verifications, a singleton PDA.
+#[account(zero_copy)]
+pub struct VerificationLog {
+    /// Number of entries ever written, the next entry goes to `len % VERIFICATION_LOG_CAPACITY`.
+    pub len: u64,
+    pub bump: u8,
+    pub padding: [u8; 7],
+    pub entries: [VerificationLogEntry; VERIFICATION_LOG_CAPACITY],
+}
+
+#[zero_copy]
+pub struct VerificationLogEntry {
+    /// Digest of the verified commitment and statement.
+    pub digest: [u8; 32],
+    /// The verified proof account, the default key unless `source` is a proof account.
+    pub proof_data: Pubkey,
+    /// The caller of the verify instruction.
+    pub caller: Pubkey,
+    pub slot: u64,
+    /// Where the proof was read from, a [`ProofSource`].
+    pub source: u8,
+    pub padding: [u8; 7],
+}
+
+/// Where a logged verification read its proof from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[repr(u8)]
+pub enum ProofSource {
+    /// A proof account, logged as the entry's `proof_data`.
+    ProofAccount = 0,
+    /// The data of the verify instruction, see `verify_inline`.
+    InstructionData = 1,
+    /// The `proof_chunk` instructions of the transaction, see `verify_from_instructions`.
+    ProofChunks = 2,
+}
+
+impl VerificationLog {
+    pub const SEED: &'static [u8] = b"verification_log";
+
+    pub fn push(&mut self, entry: VerificationLogEntry) {
+        let index = (self.len % VERIFICATION_LOG_CAPACITY as u64) as usize;
+        self.entries[index] = entry;
+        self.len += 1;
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitVerificationLog<'info> {
+    #[account(
+        seeds = [ProgramConfig::SEED],
+        bump = config.bump,
+        has_one = admin @ WhirError::Unauthorized
+    )]
+    pub config: Account<'info, ProgramConfig>,
+    #[account(
+        init,
+        payer = payer,
+        space = 8 + std::mem::size_of::<VerificationLog>(),
+        seeds = [VerificationLog::SEED],
+        bump
+    )]
+    pub verification_log: AccountLoader<'info, VerificationLog>,
+    pub admin: Signer<'info>,
+    /// Pays the rent of the log.
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+pub fn process_init_verification_log(ctx: Context<InitVerificationLog>) -> Result<()> {
+    // The account is zeroed, so the log starts empty.
+    ctx.accounts.verification_log.load_init()?.bump = ctx.bumps.verification_log;
+    Ok(())
+}
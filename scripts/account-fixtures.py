@@ -0,0 +1,93 @@
+#!/usr/bin/env python3
+"""Generates the proof account fixtures loaded by the test validator, see scripts/test.sh.
+
+The program can't be made to write these layouts anymore, so the tests load them as accounts.
+Run from the project root after changing a fixture: python3 scripts/account-fixtures.py
+"""
+
+import base64
+import hashlib
+import json
+import struct
+
+PROGRAM_ID = "AnycMJFRbi6gLYUtLH9YGVcE9F7PxnC1BijCWQMM3h9a"
+# Discriminator of the zero-copy `ProofData` layout.
+PROOF_DATA_DISCRIMINATOR = b"WHIRPROF"
+# Discriminator of the released Borsh `ProofData` layout, Anchor's default for the account.
+BASELINE_PROOF_DATA_DISCRIMINATOR = hashlib.sha256(b"account:ProofData").digest()[:8]
+PROOF_DATA_VERSION = 1
+# `ProofData` header: 8 flags, bounty, expiry, proof and metadata lengths, payer, authority,
+# 8 uploaders, verified digest and commitment, followed by 4096 bytes of metadata.
+HEADER_SIZE = 8 + 8 + 8 + 4 + 4 + 32 + 32 + 8 * 32 + 32 + 32 + 4096
+
+B58_ALPHABET = "123456789ABCDEFGHJKLMNPQRSTUVWXYZabcdefghijkmnopqrstuvwxyz"
+
+
+def b58encode(data: bytes) -> str:
+    n = int.from_bytes(data, "big")
+    out = ""
+    while n:
+        n, r = divmod(n, 58)
+        out = B58_ALPHABET[r] + out
+    return "1" * (len(data) - len(data.lstrip(b"\0"))) + out
+
+
+def address(label: str) -> str:
+    """A fixed address per fixture, not necessarily on the curve."""
+    return b58encode(hashlib.sha256(b"whir-fixture:" + label.encode()).digest())
+
+
+def header(version: int, verified: int, metadata_len: int, payer: bytes) -> bytes:
+    flags = bytes([version, 0, 1, verified, 0, 0, 0, 0])
+    data = flags + struct.pack("<QQII", 0, 0, 0, metadata_len) + payer + payer
+    data += bytes(8 * 32) + bytes([0xAB] * 32) + bytes([0xCD] * 32) + bytes(4096)
+    assert len(data) == HEADER_SIZE
+    return data
+
+
+def write(label: str, data: bytes) -> None:
+    # Rent exemption of the default rent: (128 + size) bytes at 3480 lamports per byte-year for
+    # two years.
+    lamports = (128 + len(data)) * 3480 * 2
+    account = {
+        "pubkey": address(label),
+        "account": {
+            "lamports": lamports,
+            "data": [base64.b64encode(data).decode(), "base64"],
+            "owner": PROGRAM_ID,
+            "executable": False,
+            "rentEpoch": 0,
+            "space": len(data),
+        },
+    }
+    with open(f"tests/fixtures/{label}.json", "w") as f:
+        json.dump(account, f, indent=2)
+        f.write("\n")
+    print(f"{label}: {address(label)}")
+
+
+def borsh_bytes(data: bytes) -> bytes:
+    return struct.pack("<I", len(data)) + data
+
+
+payer = bytes(range(32))
+# A header of another layout version, claiming a verification.
+write(
+    "stale-version-proof",
+    PROOF_DATA_DISCRIMINATOR + header(PROOF_DATA_VERSION + 1, 1, 0, payer),
+)
+# A current header whose metadata length exceeds the metadata capacity.
+write(
+    "oversized-metadata-proof",
+    PROOF_DATA_DISCRIMINATOR + header(PROOF_DATA_VERSION, 0, 0xFFFFFFFF, payer),
+)
+# A proof account of the released Borsh layout: payer, proof, evaluation point and value, in an
+# over-allocated account as created by the released clients.
+baseline = (
+    BASELINE_PROOF_DATA_DISCRIMINATOR
+    + payer
+    + borsh_bytes(bytes(i % 251 for i in range(1000)))
+    + borsh_bytes(bytes(range(1, 17)))
+    + borsh_bytes(bytes(range(100, 108)))
+)
+write("baseline-proof", baseline + bytes(2048 - len(baseline)))
@@ -36,6 +36,7 @@ fi
 # Copy program keypair to target/deploy/ so Anchor uses consistent program ID.
 mkdir -p target/deploy
 cp "$PROGRAM_KEYPAIR" target/deploy/whir_verifier_solana-keypair.json
+cp "$PROJECT_ROOT/rollup-example-keypair.json" target/deploy/rollup_example-keypair.json
 
 # Build and run prover to generate proof files.
 echo "Building and running prover..."
@@ -45,9 +46,9 @@ cargo run -p native-prover --release
 echo "Configuring Solana for localhost..."
 solana config set --url localhost > /dev/null
 
-# Start local validator in the background.
+# Start local validator in the background, with the account fixtures of scripts/account-fixtures.py.
 echo "Starting local validator..."
-solana-test-validator --reset --quiet &
+solana-test-validator --reset --quiet --account-dir tests/fixtures &
 VALIDATOR_PID=$!
 
 # Wait for validator to be ready.
@@ -0,0 +1,16 @@
+#!/bin/bash
+set -e
+
+# Vendors all Cargo dependencies, including the git dependencies on spongefish and the WHIR fork,
+# so the workspace builds, lints and tests without network access. Needs network access itself.
+# Afterwards, pass the generated source replacement to Cargo:
+#   cargo clippy --offline --config .cargo/vendor.toml --workspace --all-targets -- -D warnings
+
+# Change to project root directory.
+SCRIPT_DIR="$(cd "$(dirname "${BASH_SOURCE[0]}")" && pwd)"
+PROJECT_ROOT="$(dirname "$SCRIPT_DIR")"
+cd "$PROJECT_ROOT"
+
+# Resolves Cargo.lock if needed and prints the source replacement pointing at vendor/.
+cargo vendor --versioned-dirs "$PROJECT_ROOT/vendor" > .cargo/vendor.toml
+echo "Vendored the dependencies into vendor/, build offline with --config .cargo/vendor.toml"
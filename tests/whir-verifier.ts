@@ -1,7 +1,9 @@
 import * as anchor from "@coral-xyz/anchor";
 import { Program } from "@coral-xyz/anchor";
+import { RollupExample } from "../target/types/rollup_example";
 import { WhirVerifierSolana } from "../target/types/whir_verifier_solana";
 import * as fs from "fs";
+import { createHash } from "crypto";
 import { assert } from "chai";
 import { Keypair } from "@solana/web3.js";
 
@@ -13,11 +15,14 @@ describe("whir_verifier_solana", () => {
   const maxComputeUnits = 1_400_000;
   // Safe chunk size for transaction limits.
   const chunkSize = 800;
-  // Account size for proof storage (increase for bigger proofs).
+  // Account size for proof storage, including the 4512-byte header (increase for bigger proofs).
   const accountSize = 30 * 1024;
 
   interface ProofMetadata {
     num_variables: number;
+    encoding: number;
+    params_digest: number[];
+    versioned_params: number[];
     config: {
       security_level: number;
       pow_bits: number;
@@ -39,6 +44,241 @@ describe("whir_verifier_solana", () => {
     return { proof, evalPoint, evalValue, metadata };
   }
 
+  // Zero-run-length encoding, see `whir_config::encoding`.
+  function packProof(raw: Buffer): Buffer {
+    const out: number[] = [];
+    let i = 0;
+    while (i < raw.length) {
+      if (raw[i] !== 0) {
+        out.push(raw[i++]);
+        continue;
+      }
+      let run = 0;
+      while (i < raw.length && raw[i] === 0 && run < 255) {
+        run++;
+        i++;
+      }
+      out.push(0, run);
+    }
+    return Buffer.from(out);
+  }
+
+  const [configPda] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("config")],
+    program.programId
+  );
+  const [treasuryPda] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("treasury")],
+    program.programId
+  );
+  const [statsPda] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("stats")],
+    program.programId
+  );
+  const [verificationLogPda] = anchor.web3.PublicKey.findProgramAddressSync(
+    [Buffer.from("verification_log")],
+    program.programId
+  );
+
+  // Accounts charging the verification fee, paid by the provider wallet.
+  function feeAccounts() {
+    return {
+      config: configPda,
+      treasury: treasuryPda,
+      caller: provider.wallet.publicKey,
+      systemProgram: anchor.web3.SystemProgram.programId,
+      callerTokenAccount: null,
+      gateMint: null,
+      tokenProgram: null,
+      allowlist: null,
+      stats: null,
+      verificationLog: null,
+      instructionsSysvar: null,
+    };
+  }
+
+  // Accounts of the proof submission instructions, paid by the provider wallet.
+  function proofAccounts(proofData: anchor.web3.PublicKey) {
+    return {
+      proofData,
+      payer: provider.wallet.publicKey,
+      authority: provider.wallet.publicKey,
+      config: configPda,
+      allowlist: null,
+      stats: null,
+    };
+  }
+
+  // Accounts of the verify instructions, with all optional accounts unset unless overridden.
+  function verifyAccounts(proofData: anchor.web3.PublicKey, overrides: Record<string, any> = {}) {
+    return {
+      proofData,
+      statement: null,
+      evalValueAccount: null,
+      externalProofAccount: null,
+      transcriptPattern: null,
+      fee: feeAccounts(),
+      ...overrides,
+    };
+  }
+
+  // Accounts of the released, fee-free verify(), with optional accounts unset unless overridden.
+  function legacyVerifyAccounts(
+    proofData: anchor.web3.PublicKey,
+    overrides: Record<string, any> = {}
+  ) {
+    return {
+      proofData,
+      statement: null,
+      evalValueAccount: null,
+      externalProofAccount: null,
+      transcriptPattern: null,
+      instructionsSysvar: null,
+      ...overrides,
+    };
+  }
+
+  before(async () => {
+    // The program configuration is a singleton, initialized by the upgrade authority once.
+    if ((await provider.connection.getAccountInfo(configPda)) !== null) {
+      return;
+    }
+    const [programData] = anchor.web3.PublicKey.findProgramAddressSync(
+      [program.programId.toBuffer()],
+      new anchor.web3.PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+    );
+    await program.methods
+      .initializeConfig(new anchor.BN(0))
+      .accounts({
+        config: configPda,
+        treasury: treasuryPda,
+        admin: provider.wallet.publicKey,
+        payer: provider.wallet.publicKey,
+        program: program.programId,
+        programData,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+  });
+
+  async function createProofAccount(): Promise<Keypair> {
+    const proofDataKeypair = Keypair.generate();
+    const rentExemption = await provider.connection.getMinimumBalanceForRentExemption(accountSize);
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.createAccount({
+          fromPubkey: provider.wallet.publicKey,
+          newAccountPubkey: proofDataKeypair.publicKey,
+          space: accountSize,
+          lamports: rentExemption,
+          programId: program.programId,
+        })
+      ),
+      [proofDataKeypair]
+    );
+    return proofDataKeypair;
+  }
+
+  const token2022ProgramId = new anchor.web3.PublicKey(
+    "TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb"
+  );
+  const associatedTokenProgramId = new anchor.web3.PublicKey(
+    "ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL"
+  );
+
+  // Creates the associated token account of `owner` for `mint`, paid by the provider wallet.
+  async function createAssociatedTokenAccount(
+    owner: anchor.web3.PublicKey,
+    mint: anchor.web3.PublicKey,
+    tokenProgram: anchor.web3.PublicKey
+  ): Promise<anchor.web3.PublicKey> {
+    const [tokenAccount] = anchor.web3.PublicKey.findProgramAddressSync(
+      [owner.toBuffer(), tokenProgram.toBuffer(), mint.toBuffer()],
+      associatedTokenProgramId
+    );
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        new anchor.web3.TransactionInstruction({
+          programId: associatedTokenProgramId,
+          keys: [
+            { pubkey: provider.wallet.publicKey, isSigner: true, isWritable: true },
+            { pubkey: tokenAccount, isSigner: false, isWritable: true },
+            { pubkey: owner, isSigner: false, isWritable: false },
+            { pubkey: mint, isSigner: false, isWritable: false },
+            { pubkey: anchor.web3.SystemProgram.programId, isSigner: false, isWritable: false },
+            { pubkey: tokenProgram, isSigner: false, isWritable: false },
+          ],
+          data: Buffer.alloc(0),
+        })
+      )
+    );
+    return tokenAccount;
+  }
+
+  // Creates a Token-2022 mint with the provider wallet as its mint authority and mints `amount`
+  // tokens to the wallet's associated token account.
+  async function createFundedMint(
+    amount: number
+  ): Promise<{ mint: anchor.web3.PublicKey; tokenAccount: anchor.web3.PublicKey }> {
+    const mintKeypair = Keypair.generate();
+    const mintSize = 82;
+    const initializeMint2 = Buffer.concat([
+      Buffer.from([20, 0]),
+      provider.wallet.publicKey.toBuffer(),
+      Buffer.from([0]),
+    ]);
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.createAccount({
+          fromPubkey: provider.wallet.publicKey,
+          newAccountPubkey: mintKeypair.publicKey,
+          lamports: await provider.connection.getMinimumBalanceForRentExemption(mintSize),
+          space: mintSize,
+          programId: token2022ProgramId,
+        }),
+        new anchor.web3.TransactionInstruction({
+          programId: token2022ProgramId,
+          keys: [{ pubkey: mintKeypair.publicKey, isSigner: false, isWritable: true }],
+          data: initializeMint2,
+        })
+      ),
+      [mintKeypair]
+    );
+    const tokenAccount = await createAssociatedTokenAccount(
+      provider.wallet.publicKey,
+      mintKeypair.publicKey,
+      token2022ProgramId
+    );
+    const mintTo = Buffer.concat([
+      Buffer.from([7]),
+      new anchor.BN(amount).toArrayLike(Buffer, "le", 8),
+    ]);
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        new anchor.web3.TransactionInstruction({
+          programId: token2022ProgramId,
+          keys: [
+            { pubkey: mintKeypair.publicKey, isSigner: false, isWritable: true },
+            { pubkey: tokenAccount, isSigner: false, isWritable: true },
+            { pubkey: provider.wallet.publicKey, isSigner: true, isWritable: false },
+          ],
+          data: mintTo,
+        })
+      )
+    );
+    return { mint: mintKeypair.publicKey, tokenAccount };
+  }
+
+  async function uploadProof(proofData: anchor.web3.PublicKey, proof: Buffer) {
+    for (let start = 0; start < proof.length; start += chunkSize) {
+      const chunk = proof.subarray(start, Math.min(start + chunkSize, proof.length));
+      await program.methods
+        .uploadChunk(Buffer.from(chunk))
+        .accounts(proofAccounts(proofData))
+        .rpc();
+    }
+  }
+
   it("Verifies WHIR proof on-chain (multi-transaction)", async () => {
     console.log("\n=== WHIR PCS Verifier Test ===\n");
 
@@ -74,11 +314,8 @@ describe("whir_verifier_solana", () => {
     // Step 1: Initialize proof account
     console.log("1. Initializing proof account...");
     const initTx = await program.methods
-      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue))
-      .accounts({
-        proofData: proofDataKeypair.publicKey,
-        payer: provider.wallet.publicKey,
-      })
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
       .rpc();
     console.log(`   Init transaction: ${initTx}`);
 
@@ -93,10 +330,7 @@ describe("whir_verifier_solana", () => {
 
       const uploadTx = await program.methods
         .uploadChunk(Buffer.from(chunk))
-        .accounts({
-          proofData: proofDataKeypair.publicKey,
-          payer: provider.wallet.publicKey,
-        })
+        .accounts(proofAccounts(proofDataKeypair.publicKey))
         .rpc();
       console.log(`   Chunk ${i + 1}/${numChunks} (${chunk.length} bytes): ${uploadTx}`);
     }
@@ -115,9 +349,7 @@ describe("whir_verifier_solana", () => {
         metadata.config.folding_factor,
         metadata.config.starting_log_inv_rate
       )
-      .accounts({
-        proofData: proofDataKeypair.publicKey,
-      })
+      .accounts(legacyVerifyAccounts(proofDataKeypair.publicKey))
       .preInstructions([modifyComputeUnits])
       .rpc();
     console.log(`   Verify transaction: ${verifyTx}`);
@@ -127,13 +359,239 @@ describe("whir_verifier_solana", () => {
     console.log("4. Closing proof account...");
     const closeTx = await program.methods
       .closeProof()
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+    console.log(`   Close transaction: ${closeTx}`);
+    console.log("\nAll steps completed successfully!");
+  });
+
+  it("Creates and initializes the proof account in one instruction", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const proofDataKeypair = Keypair.generate();
+
+    // Accounts created by a program are limited to 10 KiB.
+    await program.methods
+      .createProof(10 * 1024, Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0, "")
+      .accounts({
+        ...proofAccounts(proofDataKeypair.publicKey),
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([proofDataKeypair])
+      .rpc();
+    await uploadProof(proofDataKeypair.publicKey, proof);
+
+    await program.methods
+      .verify(
+        metadata.num_variables,
+        metadata.config.security_level,
+        metadata.config.pow_bits,
+        metadata.config.folding_factor,
+        metadata.config.starting_log_inv_rate
+      )
+      .accounts(legacyVerifyAccounts(proofDataKeypair.publicKey))
+      .preInstructions([
+        anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+      ])
+      .rpc();
+
+    await program.methods
+      .closeProof()
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+  });
+
+  it("Shrinks a finalized proof account to its proof size", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const proofDataKeypair = await createProofAccount();
+    const accounts = proofAccounts(proofDataKeypair.publicKey);
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(accounts)
+      .rpc();
+    await uploadProof(proofDataKeypair.publicKey, proof);
+    await program.methods.finalizeUpload().accounts(accounts).rpc();
+
+    await program.methods
+      .shrinkProof()
       .accounts({
         proofData: proofDataKeypair.publicKey,
         payer: provider.wallet.publicKey,
+        authority: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
       })
       .rpc();
-    console.log(`   Close transaction: ${closeTx}`);
-    console.log("\nAll steps completed successfully!");
+
+    // Discriminator and header, followed by the proof.
+    const proofOffset = 8 + 4512;
+    const info = await provider.connection.getAccountInfo(proofDataKeypair.publicKey);
+    assert.equal(info.data.length, proofOffset + proof.length);
+
+    await program.methods
+      .verifyV2({
+        numVariables: metadata.num_variables,
+        securityLevel: metadata.config.security_level,
+        powBits: metadata.config.pow_bits,
+        foldingFactor: metadata.config.folding_factor,
+        startingLogInvRate: metadata.config.starting_log_inv_rate,
+      })
+      .accounts(verifyAccounts(proofDataKeypair.publicKey))
+      .preInstructions([
+        anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+      ])
+      .rpc();
+  });
+
+  it("Pays the escrowed bounty to the verifier and keeps it out of rent refunds", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const bounty = 50_000;
+    const proofDataKeypair = await createProofAccount();
+    const accounts = proofAccounts(proofDataKeypair.publicKey);
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(accounts)
+      .rpc();
+    await uploadProof(proofDataKeypair.publicKey, proof);
+    await program.methods
+      .escrowBounty(new anchor.BN(bounty))
+      .accounts({
+        proofData: proofDataKeypair.publicKey,
+        payer: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    try {
+      await program.methods.closeProof().accounts(accounts).rpc();
+      assert.fail("Should have kept the bounty out of the rent refund");
+    } catch (error: any) {
+      assert.include(error.toString(), "BountyEscrowed");
+    }
+    await program.methods.finalizeUpload().accounts(accounts).rpc();
+    try {
+      await program.methods
+        .shrinkProof()
+        .accounts({ ...accounts, systemProgram: anchor.web3.SystemProgram.programId })
+        .rpc();
+      assert.fail("Should have kept the bounty out of the shrink refund");
+    } catch (error: any) {
+      assert.include(error.toString(), "BountyEscrowed");
+    }
+
+    // The transaction fee is paid by the provider wallet, so the verifier only gains the bounty.
+    const verifier = Keypair.generate();
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({
+          fromPubkey: provider.wallet.publicKey,
+          toPubkey: verifier.publicKey,
+          lamports: anchor.web3.LAMPORTS_PER_SOL / 100,
+        })
+      )
+    );
+    const verifierBefore = await provider.connection.getBalance(verifier.publicKey);
+    await program.methods
+      .verifyV2({
+        numVariables: metadata.num_variables,
+        securityLevel: metadata.config.security_level,
+        powBits: metadata.config.pow_bits,
+        foldingFactor: metadata.config.folding_factor,
+        startingLogInvRate: metadata.config.starting_log_inv_rate,
+      })
+      .accounts(
+        verifyAccounts(proofDataKeypair.publicKey, {
+          fee: { ...feeAccounts(), caller: verifier.publicKey },
+        })
+      )
+      .preInstructions([
+        anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+      ])
+      .signers([verifier])
+      .rpc();
+    const verifierAfter = await provider.connection.getBalance(verifier.publicKey);
+    assert.equal(verifierAfter - verifierBefore, bounty);
+
+    await program.methods.closeProof().accounts(accounts).rpc();
+  });
+
+  it("Uploads chunks concurrently from a registered uploader", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const proofDataKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+
+    const uploader = Keypair.generate();
+    await program.methods
+      .setProofUploader(uploader.publicKey, true)
+      .accounts({ proofData: proofDataKeypair.publicKey, authority: provider.wallet.publicKey })
+      .rpc();
+
+    const uploads = [];
+    for (let start = 0; start < proof.length; start += chunkSize) {
+      const chunk = proof.subarray(start, Math.min(start + chunkSize, proof.length));
+      uploads.push(
+        program.methods
+          .uploadChunkV2(start, Buffer.from(chunk))
+          .accounts({
+            proofData: proofDataKeypair.publicKey,
+            uploader: uploader.publicKey,
+            config: configPda,
+            allowlist: null,
+            stats: null,
+          })
+          .signers([uploader])
+          .rpc()
+      );
+    }
+    await Promise.all(uploads);
+
+    await program.methods
+      .verifyV2({
+        numVariables: metadata.num_variables,
+        securityLevel: metadata.config.security_level,
+        powBits: metadata.config.pow_bits,
+        foldingFactor: metadata.config.folding_factor,
+        startingLogInvRate: metadata.config.starting_log_inv_rate,
+      })
+      .accounts(verifyAccounts(proofDataKeypair.publicKey))
+      .preInstructions([
+        anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+      ])
+      .rpc();
+  });
+
+  it("Verifies and closes the proof account in one instruction", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const proofDataKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+    await uploadProof(proofDataKeypair.publicKey, proof);
+
+    await program.methods
+      .verifyAndClose({
+        numVariables: metadata.num_variables,
+        securityLevel: metadata.config.security_level,
+        powBits: metadata.config.pow_bits,
+        foldingFactor: metadata.config.folding_factor,
+        startingLogInvRate: metadata.config.starting_log_inv_rate,
+      })
+      .accounts({
+        verify: verifyAccounts(proofDataKeypair.publicKey),
+        payer: provider.wallet.publicKey,
+        authority: provider.wallet.publicKey,
+        receiptMint: null,
+        recipientTokenAccount: null,
+        tokenProgram: null,
+      })
+      .preInstructions([
+        anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+      ])
+      .rpc();
+
+    assert.isNull(await provider.connection.getAccountInfo(proofDataKeypair.publicKey));
   });
 
   it("Rejects invalid proof", async () => {
@@ -165,11 +623,8 @@ describe("whir_verifier_solana", () => {
 
     // Initialize proof account.
     await program.methods
-      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue))
-      .accounts({
-        proofData: proofDataKeypair.publicKey,
-        payer: provider.wallet.publicKey,
-      })
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
       .rpc();
 
     // Upload corrupted proof.
@@ -181,10 +636,7 @@ describe("whir_verifier_solana", () => {
 
       await program.methods
         .uploadChunk(Buffer.from(chunk))
-        .accounts({
-          proofData: proofDataKeypair.publicKey,
-          payer: provider.wallet.publicKey,
-        })
+        .accounts(proofAccounts(proofDataKeypair.publicKey))
         .rpc();
     }
 
@@ -202,9 +654,7 @@ describe("whir_verifier_solana", () => {
           metadata.config.folding_factor,
           metadata.config.starting_log_inv_rate
         )
-        .accounts({
-          proofData: proofDataKeypair.publicKey,
-        })
+        .accounts(legacyVerifyAccounts(proofDataKeypair.publicKey))
         .preInstructions([modifyComputeUnits])
         .rpc();
 
@@ -217,10 +667,3064 @@ describe("whir_verifier_solana", () => {
     // Close proof.
     await program.methods
       .closeProof()
-      .accounts({
-        proofData: proofDataKeypair.publicKey,
-        payer: provider.wallet.publicKey,
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+  });
+
+  it("Verifies a zero-run-length packed proof", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    assert.equal(metadata.encoding ?? 0, 0, "Expected a raw proof from the prover");
+
+    const packed = packProof(proof);
+    console.log(`Raw proof: ${proof.length} bytes, packed: ${packed.length} bytes`);
+
+    const proofDataKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), 1)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+    await uploadProof(proofDataKeypair.publicKey, packed);
+
+    await program.methods
+      .verifyV2({
+        numVariables: metadata.num_variables,
+        securityLevel: metadata.config.security_level,
+        powBits: metadata.config.pow_bits,
+        foldingFactor: metadata.config.folding_factor,
+        startingLogInvRate: metadata.config.starting_log_inv_rate,
       })
+      .accounts(verifyAccounts(proofDataKeypair.publicKey))
+      .preInstructions([
+        anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+      ])
+      .rpc();
+
+    await program.methods
+      .closeProof()
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+  });
+
+  it("Rejects constraints after finalization", async () => {
+    const { evalPoint, evalValue } = loadProof();
+
+    const proofDataKeypair = await createProofAccount();
+    const accounts = proofAccounts(proofDataKeypair.publicKey);
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), 0)
+      .accounts(accounts)
+      .rpc();
+    await program.methods
+      .addConstraint(Buffer.from(evalPoint), Buffer.from(evalValue))
+      .accounts(accounts)
+      .rpc();
+    await program.methods.finalizeUpload().accounts(accounts).rpc();
+
+    const status = await program.methods
+      .getProofStatus()
+      .accounts({ proofData: proofDataKeypair.publicKey })
+      .view();
+    assert.isTrue(status.finalized);
+    assert.isFalse(status.verified);
+    assert.equal(status.numConstraints, 2);
+
+    try {
+      await program.methods
+        .addConstraint(Buffer.from(evalPoint), Buffer.from(evalValue))
+        .accounts(accounts)
+        .rpc();
+      assert.fail("Should have rejected a constraint after finalization");
+    } catch (error: any) {
+      assert.include(error.toString(), "ProofFinalized");
+    }
+
+    await program.methods.closeProof().accounts(accounts).rpc();
+  });
+
+  it("Verifies against a shared statement account", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+
+    const statementKeypair = Keypair.generate();
+    const statementSize = 1024;
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.createAccount({
+          fromPubkey: provider.wallet.publicKey,
+          newAccountPubkey: statementKeypair.publicKey,
+          space: statementSize,
+          lamports: await provider.connection.getMinimumBalanceForRentExemption(statementSize),
+          programId: program.programId,
+        })
+      ),
+      [statementKeypair]
+    );
+    const statementAccounts = {
+      statement: statementKeypair.publicKey,
+      authority: provider.wallet.publicKey,
+      config: configPda,
+    };
+    await program.methods.initStatement().accounts(statementAccounts).rpc();
+    await program.methods
+      .addStatementConstraint(Buffer.from(evalPoint), Buffer.from(evalValue))
+      .accounts(statementAccounts)
       .rpc();
+    await program.methods.finalizeStatement().accounts(statementAccounts).rpc();
+
+    // The proof account has no constraints of its own.
+    const proofDataKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.alloc(0), Buffer.alloc(0), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+    await uploadProof(proofDataKeypair.publicKey, proof);
+
+    const verifyWith = (statement: anchor.web3.PublicKey | null) =>
+      program.methods
+        .verify(
+          metadata.num_variables,
+          metadata.config.security_level,
+          metadata.config.pow_bits,
+          metadata.config.folding_factor,
+          metadata.config.starting_log_inv_rate
+        )
+        .accounts(legacyVerifyAccounts(proofDataKeypair.publicKey, { statement }))
+        .preInstructions([
+          anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+        ])
+        .rpc();
+
+    // Callers can only pass the statement the proof's authority bound it to.
+    try {
+      await verifyWith(statementKeypair.publicKey);
+      assert.fail("Should have rejected a statement the proof isn't bound to");
+    } catch (error: any) {
+      assert.include(error.toString(), "StatementMismatch");
+    }
+    await program.methods
+      .setStatement(statementKeypair.publicKey)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+    try {
+      await verifyWith(null);
+      assert.fail("Should have required the bound statement");
+    } catch (error: any) {
+      assert.include(error.toString(), "StatementMismatch");
+    }
+    await verifyWith(statementKeypair.publicKey);
+
+    await program.methods
+      .closeProof()
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+    await program.methods.closeStatement().accounts(statementAccounts).rpc();
+  });
+
+  it("Charges the verification fee into the treasury", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const fee = 10_000;
+    const adminAccounts = { config: configPda, admin: provider.wallet.publicKey };
+
+    await program.methods.setFee(new anchor.BN(fee)).accounts(adminAccounts).rpc();
+    try {
+      const proofDataKeypair = await createProofAccount();
+      await program.methods
+        .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+        .accounts(proofAccounts(proofDataKeypair.publicKey))
+        .rpc();
+      await uploadProof(proofDataKeypair.publicKey, proof);
+
+      const params = {
+        numVariables: metadata.num_variables,
+        securityLevel: metadata.config.security_level,
+        powBits: metadata.config.pow_bits,
+        foldingFactor: metadata.config.folding_factor,
+        startingLogInvRate: metadata.config.starting_log_inv_rate,
+      };
+      const computeBudget = anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({
+        units: maxComputeUnits,
+      });
+
+      // The configuration is required, leaving it out doesn't skip the fee.
+      const verifyIx = await program.methods
+        .verifyV2(params)
+        .accounts(verifyAccounts(proofDataKeypair.publicKey))
+        .instruction();
+      for (const key of verifyIx.keys) {
+        if (key.pubkey.equals(configPda)) {
+          key.pubkey = program.programId;
+        }
+      }
+      try {
+        await provider.sendAndConfirm(new anchor.web3.Transaction().add(computeBudget, verifyIx));
+        assert.fail("Should have required the configuration account");
+      } catch (error: any) {
+        assert.include(error.toString(), "AccountOwnedByWrongProgram");
+      }
+
+      const treasuryBefore = await provider.connection.getBalance(treasuryPda);
+      await program.methods
+        .verifyV2(params)
+        .accounts(verifyAccounts(proofDataKeypair.publicKey))
+        .preInstructions([computeBudget])
+        .rpc();
+      const treasuryAfter = await provider.connection.getBalance(treasuryPda);
+      assert.equal(treasuryAfter - treasuryBefore, fee);
+
+      await program.methods
+        .closeProof()
+        .accounts(proofAccounts(proofDataKeypair.publicKey))
+        .rpc();
+    } finally {
+      await program.methods.setFee(new anchor.BN(0)).accounts(adminAccounts).rpc();
+    }
+  });
+
+  it("Requires the configuration in the released init_proof() and upload_chunk()", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const proofDataKeypair = await createProofAccount();
+    // Only the proof account and its signer, without the configuration and allowlist accounts.
+    const releasedKeys = [
+      { pubkey: proofDataKeypair.publicKey, isSigner: false, isWritable: true },
+      { pubkey: provider.wallet.publicKey, isSigner: true, isWritable: false },
+    ];
+
+    const initIx = await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .instruction();
+    initIx.keys = releasedKeys;
+    try {
+      await provider.sendAndConfirm(new anchor.web3.Transaction().add(initIx));
+      assert.fail("Should have required the configuration account");
+    } catch (error: any) {
+      assert.include(error.toString(), "AccountNotEnoughKeys");
+    }
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+
+    const uploadIx = await program.methods
+      .uploadChunk(Buffer.from(proof.subarray(0, chunkSize)))
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .instruction();
+    uploadIx.keys = releasedKeys;
+    try {
+      await provider.sendAndConfirm(new anchor.web3.Transaction().add(uploadIx));
+      assert.fail("Should have required the configuration account");
+    } catch (error: any) {
+      assert.include(error.toString(), "AccountNotEnoughKeys");
+    }
+    await uploadProof(proofDataKeypair.publicKey, proof);
+    const status = await program.methods
+      .getProofStatus()
+      .accounts({ proofData: proofDataKeypair.publicKey })
+      .view();
+    assert.equal(status.uploadedBytes, proof.length);
+
+    // The released close_proof() account list has the payer sign as the authority.
+    const closeIx = await program.methods
+      .closeProof()
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .instruction();
+    closeIx.keys = [
+      { pubkey: proofDataKeypair.publicKey, isSigner: false, isWritable: true },
+      { pubkey: provider.wallet.publicKey, isSigner: true, isWritable: true },
+    ];
+    await provider.sendAndConfirm(new anchor.web3.Transaction().add(closeIx));
+  });
+
+  it("Verifies with the released verify() account list", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const proofDataKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+    await uploadProof(proofDataKeypair.publicKey, proof);
+
+    // Only the read-only proof account: the released verify() charges no fee and records nothing.
+    const verifyIx = await program.methods
+      .verify(
+        metadata.num_variables,
+        metadata.config.security_level,
+        metadata.config.pow_bits,
+        metadata.config.folding_factor,
+        metadata.config.starting_log_inv_rate
+      )
+      .accounts(legacyVerifyAccounts(proofDataKeypair.publicKey))
+      .instruction();
+    verifyIx.keys = [{ pubkey: proofDataKeypair.publicKey, isSigner: false, isWritable: false }];
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+        verifyIx
+      )
+    );
+
+    const status = await program.methods
+      .getProofStatus()
+      .accounts({ proofData: proofDataKeypair.publicKey })
+      .view();
+    assert.isFalse(status.verified);
+
+    await program.methods
+      .closeProof()
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+  });
+
+  it("Enforces and validates the size limits", async () => {
+    const { evalPoint, evalValue } = loadProof();
+    const adminAccounts = { config: configPda, admin: provider.wallet.publicKey };
+    const defaults = (await program.account.programConfig.fetch(configPda)).sizeLimits;
+    const tenMiB = 10 * 1024 * 1024;
+
+    for (const sizeLimits of [
+      { ...defaults, maxChunkLen: 0 },
+      { ...defaults, maxProofLen: tenMiB + 1 },
+      { ...defaults, maxEvalPointLen: 0 },
+      { ...defaults, maxChunkLen: 2048, maxProofLen: 1024 },
+    ]) {
+      try {
+        await program.methods.setSizeLimits(sizeLimits).accounts(adminAccounts).rpc();
+        assert.fail("Should have rejected invalid size limits");
+      } catch (error: any) {
+        assert.include(error.toString(), "InvalidSizeLimits");
+      }
+    }
+
+    await program.methods
+      .setSizeLimits({ ...defaults, maxChunkLen: 100 })
+      .accounts(adminAccounts)
+      .rpc();
+    try {
+      const proofDataKeypair = await createProofAccount();
+      await program.methods
+        .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), 0)
+        .accounts(proofAccounts(proofDataKeypair.publicKey))
+        .rpc();
+      try {
+        await program.methods
+          .uploadChunk(Buffer.alloc(101))
+          .accounts(proofAccounts(proofDataKeypair.publicKey))
+          .rpc();
+        assert.fail("Should have rejected a chunk above the limit");
+      } catch (error: any) {
+        assert.include(error.toString(), "ChunkTooLarge");
+      }
+      await program.methods
+        .closeProof()
+        .accounts(proofAccounts(proofDataKeypair.publicKey))
+        .rpc();
+    } finally {
+      await program.methods.setSizeLimits(defaults).accounts(adminAccounts).rpc();
+    }
+  });
+
+  it("Enforces the minimum security level", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const adminAccounts = { config: configPda, admin: provider.wallet.publicKey };
+    const config = await program.account.programConfig.fetch(configPda);
+    assert.equal(config.minSecurityLevel, 100);
+
+    const intruder = Keypair.generate();
+    try {
+      await program.methods
+        .setMinSecurityLevel(1)
+        .accounts({ config: configPda, admin: intruder.publicKey })
+        .signers([intruder])
+        .rpc();
+      assert.fail("Should have rejected a non-admin signer");
+    } catch (error: any) {
+      assert.include(error.toString(), "Unauthorized");
+    }
+
+    const proofDataKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+    await uploadProof(proofDataKeypair.publicKey, proof);
+    const verify = () =>
+      program.methods
+        .verifyV2({
+          numVariables: metadata.num_variables,
+          securityLevel: metadata.config.security_level,
+          powBits: metadata.config.pow_bits,
+          foldingFactor: metadata.config.folding_factor,
+          startingLogInvRate: metadata.config.starting_log_inv_rate,
+        })
+        .accounts(verifyAccounts(proofDataKeypair.publicKey))
+        .preInstructions([
+          anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+        ])
+        .rpc();
+
+    await program.methods
+      .setMinSecurityLevel(metadata.config.security_level + 1)
+      .accounts(adminAccounts)
+      .rpc();
+    try {
+      await verify();
+      assert.fail("Should have rejected parameters below the minimum security level");
+    } catch (error: any) {
+      assert.include(error.toString(), "InsufficientSecurity");
+    } finally {
+      await program.methods
+        .setMinSecurityLevel(metadata.config.security_level)
+        .accounts(adminAccounts)
+        .rpc();
+    }
+    await verify();
+    const proofData = await program.account.proofData.fetch(proofDataKeypair.publicKey);
+    assert.equal(proofData.verified, 1);
+  });
+
+  it("Governs the program through the admin multisig", async () => {
+    const [multisigPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("admin_multisig")],
+      program.programId
+    );
+    const admin = provider.wallet.publicKey;
+    const cosigner = Keypair.generate();
+    const initMultisig = (signers: anchor.web3.PublicKey[], threshold: number) =>
+      program.methods
+        .initAdminMultisig(signers, threshold)
+        .accounts({
+          config: configPda,
+          multisig: multisigPda,
+          admin,
+          payer: admin,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+    // The first remaining accounts approve, the multisig PDA itself is signed for by the program.
+    const execute = (ix: anchor.web3.TransactionInstruction, cosigners: Keypair[]) =>
+      program.methods
+        .executeAsMultisig(1 + cosigners.length, ix.data)
+        .accounts({ multisig: multisigPda, program: program.programId })
+        .remainingAccounts([
+          { pubkey: admin, isSigner: true, isWritable: false },
+          ...cosigners.map((c) => ({ pubkey: c.publicKey, isSigner: true, isWritable: false })),
+          ...ix.keys.map((key) => ({
+            ...key,
+            isSigner: key.isSigner && !key.pubkey.equals(multisigPda),
+          })),
+        ])
+        .signers(cosigners)
+        .rpc();
+
+    try {
+      await initMultisig([admin, cosigner.publicKey], 3);
+      assert.fail("Should have rejected a threshold above the number of signers");
+    } catch (error: any) {
+      assert.include(error.toString(), "InvalidMultisig");
+    }
+    await initMultisig([admin, cosigner.publicKey], 2);
+    await program.methods
+      .proposeAdmin(multisigPda)
+      .accounts({ config: configPda, admin })
+      .rpc();
+
+    const acceptAdmin = await program.methods
+      .acceptAdmin()
+      .accounts({ config: configPda, newAdmin: multisigPda })
+      .instruction();
+    try {
+      await execute(acceptAdmin, []);
+      assert.fail("Should have rejected fewer signers than the threshold");
+    } catch (error: any) {
+      assert.include(error.toString(), "MultisigThresholdNotMet");
+    }
+    await execute(acceptAdmin, [cosigner]);
+    assert.ok((await program.account.programConfig.fetch(configPda)).admin.equals(multisigPda));
+
+    // The former admin alone can't change the configuration anymore.
+    try {
+      await program.methods.setMinSecurityLevel(1).accounts({ config: configPda, admin }).rpc();
+      assert.fail("Should have rejected the former admin");
+    } catch (error: any) {
+      assert.include(error.toString(), "Unauthorized");
+    }
+
+    // The multisig creates accounts too, with the wallet paying their rent.
+    if ((await provider.connection.getAccountInfo(statsPda)) === null) {
+      await execute(
+        await program.methods
+          .initStats()
+          .accounts({
+            config: configPda,
+            stats: statsPda,
+            admin: multisigPda,
+            payer: admin,
+            systemProgram: anchor.web3.SystemProgram.programId,
+          })
+          .instruction(),
+        [cosigner]
+      );
+    }
+    assert.isNotNull(await provider.connection.getAccountInfo(statsPda));
+
+    await execute(
+      await program.methods
+        .setMultisigSigners([admin], 1)
+        .accounts({ multisig: multisigPda, authority: multisigPda })
+        .instruction(),
+      [cosigner]
+    );
+    const multisig = await program.account.adminMultisig.fetch(multisigPda);
+    assert.equal(multisig.threshold, 1);
+    assert.equal(multisig.signers.length, 1);
+
+    // Hands the admin role back to the provider wallet for the other tests.
+    await execute(
+      await program.methods
+        .proposeAdmin(admin)
+        .accounts({ config: configPda, admin: multisigPda })
+        .instruction(),
+      []
+    );
+    await program.methods.acceptAdmin().accounts({ config: configPda, newAdmin: admin }).rpc();
+    assert.ok((await program.account.programConfig.fetch(configPda)).admin.equals(admin));
+  });
+
+  it("Records usage statistics", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    if ((await provider.connection.getAccountInfo(statsPda)) === null) {
+      await program.methods
+        .initStats()
+        .accounts({
+          config: configPda,
+          stats: statsPda,
+          admin: provider.wallet.publicKey,
+          payer: provider.wallet.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+    }
+    const before = await program.account.programStats.fetch(statsPda);
+
+    const proofDataKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+    for (let start = 0; start < proof.length; start += chunkSize) {
+      const chunk = proof.subarray(start, Math.min(start + chunkSize, proof.length));
+      await program.methods
+        .uploadChunk(Buffer.from(chunk))
+        .accounts({ ...proofAccounts(proofDataKeypair.publicKey), stats: statsPda })
+        .rpc();
+    }
+    await program.methods
+      .verifyV2({
+        numVariables: metadata.num_variables,
+        securityLevel: metadata.config.security_level,
+        powBits: metadata.config.pow_bits,
+        foldingFactor: metadata.config.folding_factor,
+        startingLogInvRate: metadata.config.starting_log_inv_rate,
+      })
+      .accounts(verifyAccounts(proofDataKeypair.publicKey, { fee: { ...feeAccounts(), stats: statsPda } }))
+      .preInstructions([
+        anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+      ])
+      .rpc();
+
+    const after = await program.account.programStats.fetch(statsPda);
+    assert.equal(after.verifications.sub(before.verifications).toNumber(), 1);
+    assert.equal(after.bytesUploaded.sub(before.bytesUploaded).toNumber(), proof.length);
+    const usage = after.configUsage.find((u) => u.params.numVariables === metadata.num_variables);
+    assert.isDefined(usage);
+
+    // A failed verification with an outcome completes and is counted.
+    const params = {
+      numVariables: metadata.num_variables,
+      securityLevel: metadata.config.security_level,
+      powBits: metadata.config.pow_bits,
+      foldingFactor: metadata.config.folding_factor,
+      startingLogInvRate: metadata.config.starting_log_inv_rate,
+    };
+    const tampered = await createProofAccount();
+    const corruptedProof = Buffer.from(proof);
+    corruptedProof[100] ^= 0xff;
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(proofAccounts(tampered.publicKey))
+      .rpc();
+    await uploadProof(tampered.publicKey, corruptedProof);
+    await program.methods
+      .verifyWithOutcome(params)
+      .accounts(verifyAccounts(tampered.publicKey, { fee: { ...feeAccounts(), stats: statsPda } }))
+      .preInstructions([
+        anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+      ])
+      .rpc();
+    assert.equal((await program.account.proofData.fetch(tampered.publicKey)).verified, 0);
+
+    const failed = await program.account.programStats.fetch(statsPda);
+    assert.equal(failed.failures.sub(after.failures).toNumber(), 1);
+    assert.equal(failed.verifications.sub(after.verifications).toNumber(), 0);
+    const failedUsage = failed.configUsage.find((u) => u.params.numVariables === metadata.num_variables);
+    assert.equal(failedUsage.failures.sub(usage.failures).toNumber(), 1);
+  });
+
+  it("Quotes the costs of a verification", async () => {
+    const { metadata } = loadProof();
+    const quote = await program.methods
+      .quoteVerification({
+        numVariables: metadata.num_variables,
+        securityLevel: metadata.config.security_level,
+        powBits: metadata.config.pow_bits,
+        foldingFactor: metadata.config.folding_factor,
+        startingLogInvRate: metadata.config.starting_log_inv_rate,
+      })
+      .accounts({})
+      .view();
+    assert.isAbove(quote.proofSize, 0);
+    assert.equal(quote.accountSpace, 8 + 4512 + quote.proofSize);
+    assert.isAbove(quote.computeUnits.toNumber(), 0);
+    // The prover and the program derive the same parameters.
+    assert.deepEqual(Array.from(quote.paramsDigest), metadata.params_digest);
+  });
+
+  it("Logs verifications in the verification log", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    if ((await provider.connection.getAccountInfo(verificationLogPda)) === null) {
+      await program.methods
+        .initVerificationLog()
+        .accounts({
+          config: configPda,
+          verificationLog: verificationLogPda,
+          admin: provider.wallet.publicKey,
+          payer: provider.wallet.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+    }
+    const before = await program.account.verificationLog.fetch(verificationLogPda);
+
+    const proofDataKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+    await uploadProof(proofDataKeypair.publicKey, proof);
+    await program.methods
+      .verifyV2({
+        numVariables: metadata.num_variables,
+        securityLevel: metadata.config.security_level,
+        powBits: metadata.config.pow_bits,
+        foldingFactor: metadata.config.folding_factor,
+        startingLogInvRate: metadata.config.starting_log_inv_rate,
+      })
+      .accounts(
+        verifyAccounts(proofDataKeypair.publicKey, {
+          fee: { ...feeAccounts(), verificationLog: verificationLogPda },
+        })
+      )
+      .preInstructions([
+        anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+      ])
+      .rpc();
+
+    const log = await program.account.verificationLog.fetch(verificationLogPda);
+    assert.equal(log.len.sub(before.len).toNumber(), 1);
+    const entry = log.entries[before.len.toNumber() % log.entries.length];
+    assert.isTrue(entry.proofData.equals(proofDataKeypair.publicKey));
+    assert.isTrue(entry.caller.equals(provider.wallet.publicKey));
+    // Read from a proof account.
+    assert.equal(entry.source, 0);
+  });
+
+  it("Releases an escrow once its proof verifies", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const proofDataKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+    await uploadProof(proofDataKeypair.publicKey, proof);
+    await program.methods
+      .verifyV2({
+        numVariables: metadata.num_variables,
+        securityLevel: metadata.config.security_level,
+        powBits: metadata.config.pow_bits,
+        foldingFactor: metadata.config.folding_factor,
+        startingLogInvRate: metadata.config.starting_log_inv_rate,
+      })
+      .accounts(verifyAccounts(proofDataKeypair.publicKey))
+      .preInstructions([
+        anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+      ])
+      .rpc();
+    const proofData = await program.account.proofData.fetch(proofDataKeypair.publicKey);
+    const digest = proofData.verifiedDigest;
+
+    const recipient = Keypair.generate().publicKey;
+    const amount = anchor.web3.LAMPORTS_PER_SOL / 100;
+    const [escrowPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow"), provider.wallet.publicKey.toBuffer(), Buffer.from(digest)],
+      program.programId
+    );
+    await program.methods
+      .createEscrow(digest, recipient, new anchor.BN(amount), new anchor.BN(Number.MAX_SAFE_INTEGER))
+      .accounts({
+        escrow: escrowPda,
+        depositor: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    const releaseAccounts = {
+      escrow: escrowPda,
+      proofData: proofDataKeypair.publicKey,
+      recipient,
+      depositor: provider.wallet.publicKey,
+      vault: null,
+      mint: null,
+      destinationTokenAccount: null,
+      tokenProgram: null,
+      config: configPda,
+    };
+
+    // The configuration is required, so the minimum security level can't be skipped.
+    try {
+      await program.methods
+        .releaseEscrow()
+        .accounts({ ...releaseAccounts, config: program.programId })
+        .rpc();
+      assert.fail("Should have required the configuration account");
+    } catch (error: any) {
+      assert.include(error.toString(), "AccountOwnedByWrongProgram");
+    }
+
+    // The proof's parameters must still meet the minimum security level when releasing.
+    const adminAccounts = { config: configPda, admin: provider.wallet.publicKey };
+    await program.methods
+      .setMinSecurityLevel(metadata.config.security_level + 1)
+      .accounts(adminAccounts)
+      .rpc();
+    try {
+      await program.methods.releaseEscrow().accounts(releaseAccounts).rpc();
+      assert.fail("Should have rejected a proof below the minimum security level");
+    } catch (error: any) {
+      assert.include(error.toString(), "InsufficientSecurity");
+    } finally {
+      await program.methods
+        .setMinSecurityLevel(metadata.config.security_level)
+        .accounts(adminAccounts)
+        .rpc();
+    }
+    await program.methods.releaseEscrow().accounts(releaseAccounts).rpc();
+
+    assert.equal(await provider.connection.getBalance(recipient), amount);
+    assert.isNull(await provider.connection.getAccountInfo(escrowPda));
+  });
+
+  it("Consumes a verified digest exactly once", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const proofDataKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+    await uploadProof(proofDataKeypair.publicKey, proof);
+    const consume = (digest: number[], consumer: Keypair | null = null) =>
+      program.methods
+        .consumeVerification(digest)
+        .accounts({
+          proofData: proofDataKeypair.publicKey,
+          registryEntry: anchor.web3.PublicKey.findProgramAddressSync(
+            [
+              Buffer.from("verified"),
+              (consumer?.publicKey ?? provider.wallet.publicKey).toBuffer(),
+              Buffer.from(digest),
+            ],
+            program.programId
+          )[0],
+          consumer: consumer?.publicKey ?? provider.wallet.publicKey,
+          payer: provider.wallet.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          config: configPda,
+        })
+        .signers(consumer ? [consumer] : [])
+        .rpc();
+
+    const unverified = Array.from(Keypair.generate().publicKey.toBuffer());
+    try {
+      await consume(unverified);
+      assert.fail("Should have rejected an unverified digest");
+    } catch (error: any) {
+      assert.include(error.toString(), "ProofNotVerified");
+    }
+
+    await program.methods
+      .verifyV2({
+        numVariables: metadata.num_variables,
+        securityLevel: metadata.config.security_level,
+        powBits: metadata.config.pow_bits,
+        foldingFactor: metadata.config.folding_factor,
+        startingLogInvRate: metadata.config.starting_log_inv_rate,
+      })
+      .accounts(verifyAccounts(proofDataKeypair.publicKey))
+      .preInstructions([
+        anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+      ])
+      .rpc();
+    const digest = (await program.account.proofData.fetch(proofDataKeypair.publicKey))
+      .verifiedDigest;
+
+    // The recorded parameters must meet the minimum security level when consuming.
+    const adminAccounts = { config: configPda, admin: provider.wallet.publicKey };
+    await program.methods
+      .setMinSecurityLevel(metadata.config.security_level + 1)
+      .accounts(adminAccounts)
+      .rpc();
+    try {
+      await consume(digest);
+      assert.fail("Should have rejected a proof below the minimum security level");
+    } catch (error: any) {
+      assert.include(error.toString(), "InsufficientSecurity");
+    } finally {
+      await program.methods
+        .setMinSecurityLevel(metadata.config.security_level)
+        .accounts(adminAccounts)
+        .rpc();
+    }
+
+    // Another signer consuming the digest first doesn't block its consumer.
+    await consume(digest, Keypair.generate());
+    await consume(digest);
+    try {
+      await consume(digest);
+      assert.fail("Should have rejected consuming the digest twice");
+    } catch (error: any) {
+      assert.include(error.toString(), "custom program error: 0x0");
+    }
+  });
+
+  it("Publishes verified evaluations per commitment and evaluation point", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const proofDataKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+    await uploadProof(proofDataKeypair.publicKey, proof);
+    const publish = (evaluationResult: anchor.web3.PublicKey) =>
+      program.methods
+        .publishEvaluation()
+        .accounts({
+          proofData: proofDataKeypair.publicKey,
+          evaluationResult,
+          payer: provider.wallet.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          config: configPda,
+        })
+        .rpc();
+
+    try {
+      await publish(Keypair.generate().publicKey);
+      assert.fail("Should have rejected an unverified proof");
+    } catch (error: any) {
+      assert.include(error.toString(), "ProofNotVerified");
+    }
+
+    await program.methods
+      .verifyV2({
+        numVariables: metadata.num_variables,
+        securityLevel: metadata.config.security_level,
+        powBits: metadata.config.pow_bits,
+        foldingFactor: metadata.config.folding_factor,
+        startingLogInvRate: metadata.config.starting_log_inv_rate,
+      })
+      .accounts(verifyAccounts(proofDataKeypair.publicKey))
+      .preInstructions([
+        anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+      ])
+      .rpc();
+    const commitment = (await program.account.proofData.fetch(proofDataKeypair.publicKey))
+      .verifiedCommitment;
+    const pointsHash = createHash("sha256").update(evalPoint).digest();
+    const [evaluationPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("evaluation"), Buffer.from(commitment), pointsHash],
+      program.programId
+    );
+
+    // Keyed by the commitment only, another opening of the commitment would overwrite it.
+    const [commitmentOnly] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("evaluation"), Buffer.from(commitment)],
+      program.programId
+    );
+    try {
+      await publish(commitmentOnly);
+      assert.fail("Should have rejected the commitment-only address");
+    } catch (error: any) {
+      assert.include(error.toString(), "ConstraintSeeds");
+    }
+
+    const adminAccounts = { config: configPda, admin: provider.wallet.publicKey };
+    await program.methods
+      .setMinSecurityLevel(metadata.config.security_level + 1)
+      .accounts(adminAccounts)
+      .rpc();
+    try {
+      await publish(evaluationPda);
+      assert.fail("Should have rejected a proof below the minimum security level");
+    } catch (error: any) {
+      assert.include(error.toString(), "InsufficientSecurity");
+    } finally {
+      await program.methods
+        .setMinSecurityLevel(metadata.config.security_level)
+        .accounts(adminAccounts)
+        .rpc();
+    }
+
+    await publish(evaluationPda);
+    const result = await program.account.evaluationResult.fetch(evaluationPda);
+    assert.deepEqual(Buffer.from(result.commitment), Buffer.from(commitment));
+    assert.deepEqual(Buffer.from(result.evaluations[0].evalPoint), evalPoint);
+    assert.deepEqual(Buffer.from(result.evaluations[0].evalValue), evalValue);
+    assert.ok(result.proofData.equals(proofDataKeypair.publicKey));
+  });
+
+  it("Rejects a malformed commitment", async () => {
+    const { metadata } = loadProof();
+    const root = Keypair.generate().publicKey.toBuffer();
+    const [acceptedCommitmentPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("commitment"), root, provider.wallet.publicKey.toBuffer()],
+      program.programId
+    );
+    try {
+      await program.methods
+        .acceptCommitment(
+          {
+            numVariables: metadata.num_variables,
+            securityLevel: metadata.config.security_level,
+            powBits: metadata.config.pow_bits,
+            foldingFactor: metadata.config.folding_factor,
+            startingLogInvRate: metadata.config.starting_log_inv_rate,
+          },
+          Array.from(root),
+          Buffer.alloc(3)
+        )
+        .accounts({
+          acceptedCommitment: acceptedCommitmentPda,
+          payer: provider.wallet.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+      assert.fail("Should have rejected truncated out-of-domain answers");
+    } catch (error: any) {
+      assert.include(error.toString(), "CommitmentParseError");
+    }
+    assert.isNull(await provider.connection.getAccountInfo(acceptedCommitmentPda));
+  });
+
+  it("Keys accepted commitments by their payer", async () => {
+    const { metadata } = loadProof();
+    const params = {
+      numVariables: metadata.num_variables,
+      securityLevel: metadata.config.security_level,
+      powBits: metadata.config.pow_bits,
+      foldingFactor: metadata.config.folding_factor,
+      startingLogInvRate: metadata.config.starting_log_inv_rate,
+    };
+    const { commitmentSize } = await program.methods.quoteVerification(params).accounts({}).view();
+    const root = Keypair.generate().publicKey.toBuffer();
+    const other = Keypair.generate();
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({
+          fromPubkey: provider.wallet.publicKey,
+          toPubkey: other.publicKey,
+          lamports: anchor.web3.LAMPORTS_PER_SOL / 10,
+        })
+      )
+    );
+
+    // Accepting a root first doesn't keep another payer from accepting it.
+    for (const payer of [provider.wallet.publicKey, other.publicKey]) {
+      const [acceptedCommitmentPda] = anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("commitment"), root, payer.toBuffer()],
+        program.programId
+      );
+      await program.methods
+        .acceptCommitment(params, Array.from(root), Buffer.alloc(commitmentSize - 32))
+        .accounts({
+          acceptedCommitment: acceptedCommitmentPda,
+          payer,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers(payer.equals(other.publicKey) ? [other] : [])
+        .rpc();
+      const accepted = await program.account.acceptedCommitment.fetch(acceptedCommitmentPda);
+      assert.ok(accepted.payer.equals(payer));
+      assert.deepEqual(Buffer.from(accepted.root), root);
+    }
+  });
+
+  it("Verifies the proofs of a container by index", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const proofDataKeypair = await createProofAccount();
+    const accounts = proofAccounts(proofDataKeypair.publicKey);
+    // The container's own statement is empty, each contained proof carries its constraint.
+    await program.methods
+      .initProof(Buffer.alloc(0), Buffer.alloc(0), 0)
+      .accounts(accounts)
+      .rpc();
+    for (const offset of [0, proof.length]) {
+      await uploadProof(proofDataKeypair.publicKey, proof);
+      await program.methods
+        .addContainedProof(offset, proof.length, Buffer.from(evalPoint), Buffer.from(evalValue))
+        .accounts(accounts)
+        .rpc();
+    }
+    await program.methods.finalizeUpload().accounts(accounts).rpc();
+
+    const params = {
+      numVariables: metadata.num_variables,
+      securityLevel: metadata.config.security_level,
+      powBits: metadata.config.pow_bits,
+      foldingFactor: metadata.config.folding_factor,
+      startingLogInvRate: metadata.config.starting_log_inv_rate,
+    };
+    for (const index of [0, 1]) {
+      await program.methods
+        .verifyContainedProof(params, index)
+        .accounts(verifyAccounts(proofDataKeypair.publicKey))
+        .preInstructions([
+          anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+        ])
+        .rpc();
+    }
+
+    try {
+      await program.methods
+        .verifyContainedProof(params, 2)
+        .accounts(verifyAccounts(proofDataKeypair.publicKey))
+        .rpc();
+      assert.fail("Should have rejected an out-of-range index");
+    } catch (error: any) {
+      assert.include(error.toString(), "InvalidProofIndex");
+    }
+  });
+
+  it("Accepts a challenged claim once it's proven", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const params = {
+      numVariables: metadata.num_variables,
+      securityLevel: metadata.config.security_level,
+      powBits: metadata.config.pow_bits,
+      foldingFactor: metadata.config.folding_factor,
+      startingLogInvRate: metadata.config.starting_log_inv_rate,
+    };
+    const { commitmentSize } = await program.methods.quoteVerification(params).accounts({}).view();
+
+    const submitter = provider.wallet.publicKey;
+    const claimId = new anchor.BN(Date.now());
+    const [claimPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("claim"), submitter.toBuffer(), claimId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    const bond = new anchor.BN(anchor.web3.LAMPORTS_PER_SOL / 100);
+    await program.methods
+      .postClaim(
+        claimId,
+        params,
+        {
+          root: Array.from(proof.subarray(0, 32)),
+          oodAnswers: Buffer.from(proof.subarray(32, commitmentSize)),
+          evalPoint: Buffer.from(evalPoint),
+          evalValue: Buffer.from(evalValue),
+        },
+        bond
+      )
+      .accounts({
+        claim: claimPda,
+        submitter,
+        config: configPda,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const challenger = Keypair.generate();
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({
+          fromPubkey: submitter,
+          toPubkey: challenger.publicKey,
+          lamports: anchor.web3.LAMPORTS_PER_SOL / 10,
+        })
+      )
+    );
+    await program.methods
+      .challengeClaim()
+      .accounts({
+        claim: claimPda,
+        challenger: challenger.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .signers([challenger])
+      .rpc();
+    assert.deepEqual((await program.account.claim.fetch(claimPda)).status, { challenged: {} });
+
+    const proofDataKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+    await uploadProof(proofDataKeypair.publicKey, proof);
+    await program.methods
+      .proveClaim()
+      .accounts({
+        verify: verifyAccounts(proofDataKeypair.publicKey),
+        claim: claimPda,
+        submitter,
+      })
+      .preInstructions([
+        anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+      ])
+      .rpc();
+
+    const claim = await program.account.claim.fetch(claimPda);
+    assert.deepEqual(claim.status, { accepted: {} });
+    await program.methods.closeClaim().accounts({ claim: claimPda, submitter }).rpc();
+  });
+
+  it("Derives the evaluation point of a sample request on-chain", async () => {
+    const { proof, metadata } = loadProof();
+    const params = {
+      numVariables: metadata.num_variables,
+      securityLevel: metadata.config.security_level,
+      powBits: metadata.config.pow_bits,
+      foldingFactor: metadata.config.folding_factor,
+      startingLogInvRate: metadata.config.starting_log_inv_rate,
+    };
+    const root = proof.subarray(0, 32);
+    const [acceptedCommitmentPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("commitment"), root, provider.wallet.publicKey.toBuffer()],
+      program.programId
+    );
+    if ((await provider.connection.getAccountInfo(acceptedCommitmentPda)) === null) {
+      const { commitmentSize } = await program.methods.quoteVerification(params).accounts({}).view();
+      await program.methods
+        .acceptCommitment(params, Array.from(root), Buffer.from(proof.subarray(32, commitmentSize)))
+        .accounts({
+          acceptedCommitment: acceptedCommitmentPda,
+          payer: provider.wallet.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+    }
+
+    const requester = provider.wallet.publicKey;
+    const sampleId = new anchor.BN(Date.now());
+    const [sampleRequestPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("sample"), requester.toBuffer(), sampleId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    await program.methods
+      .requestSample(sampleId)
+      .accounts({
+        acceptedCommitment: acceptedCommitmentPda,
+        sampleRequest: sampleRequestPda,
+        requester,
+        slotHashes: anchor.web3.SYSVAR_SLOT_HASHES_PUBKEY,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const request = await program.account.sampleRequest.fetch(sampleRequestPda);
+    assert.equal(request.evalPoint.length, metadata.num_variables * 16);
+    assert.deepEqual(Array.from(request.root), Array.from(root));
+    assert.equal(request.params.securityLevel, params.securityLevel);
+    assert.isNull(request.evalValue);
+
+    // A proof of the commitment verified with its parameters, but not opening it at the point.
+    const { evalPoint, evalValue } = loadProof();
+    const proofDataKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+    await uploadProof(proofDataKeypair.publicKey, proof);
+    const fulfill = () =>
+      program.methods
+        .fulfillSample()
+        .accounts({ sampleRequest: sampleRequestPda, proofData: proofDataKeypair.publicKey })
+        .rpc();
+    try {
+      await fulfill();
+      assert.fail("Should have rejected an unverified proof");
+    } catch (error: any) {
+      assert.include(error.toString(), "SampleMismatch");
+    }
+    await program.methods
+      .verifyV2(params)
+      .accounts(verifyAccounts(proofDataKeypair.publicKey))
+      .preInstructions([
+        anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+      ])
+      .rpc();
+    try {
+      await fulfill();
+      assert.fail("Should have rejected a proof not opening the sampled point");
+    } catch (error: any) {
+      assert.include(error.toString(), "SampleMismatch");
+    }
+  });
+
+  it("Echoes the user metadata in the verification event", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const proofDataKeypair = await createProofAccount();
+    const userMetadata = Buffer.from("rollup-block-42");
+    await program.methods
+      .initProofV3(
+        Buffer.from(evalPoint),
+        Buffer.from(evalValue),
+        metadata.encoding ?? 0,
+        "",
+        userMetadata
+      )
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+    await uploadProof(proofDataKeypair.publicKey, proof);
+
+    let listener: number;
+    const event = new Promise<any>((resolve) => {
+      listener = program.addEventListener("proofVerified", resolve);
+    });
+    await program.methods
+      .verifyV2({
+        numVariables: metadata.num_variables,
+        securityLevel: metadata.config.security_level,
+        powBits: metadata.config.pow_bits,
+        foldingFactor: metadata.config.folding_factor,
+        startingLogInvRate: metadata.config.starting_log_inv_rate,
+      })
+      .accounts(verifyAccounts(proofDataKeypair.publicKey))
+      .preInstructions([
+        anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+      ])
+      .rpc();
+
+    const verified = await event;
+    await program.removeEventListener(listener);
+    assert.isTrue(verified.proofData.equals(proofDataKeypair.publicKey));
+    assert.deepEqual(Buffer.from(verified.userMetadata), userMetadata);
+  });
+
+  it("Reports the failing stage of a simulated verification", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const params = {
+      numVariables: metadata.num_variables,
+      securityLevel: metadata.config.security_level,
+      powBits: metadata.config.pow_bits,
+      foldingFactor: metadata.config.folding_factor,
+      startingLogInvRate: metadata.config.starting_log_inv_rate,
+    };
+    const simulate = async (proofData: anchor.web3.PublicKey) =>
+      program.methods
+        .simulateVerify(params)
+        .accounts({
+          proofData,
+          statement: null,
+          evalValueAccount: null,
+          externalProofAccount: null,
+          config: configPda,
+        })
+        .preInstructions([
+          anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+        ])
+        .view();
+
+    const validKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(proofAccounts(validKeypair.publicKey))
+      .rpc();
+    await uploadProof(validKeypair.publicKey, proof);
+    const valid = await simulate(validKeypair.publicKey);
+    assert.isTrue(valid.verified);
+    assert.isNotNull(valid.digest);
+
+    // A different evaluation value makes the verification itself fail.
+    const wrongValue = Buffer.from(evalValue);
+    wrongValue[0] ^= 1;
+    const invalidKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.from(evalPoint), wrongValue, metadata.encoding ?? 0)
+      .accounts(proofAccounts(invalidKeypair.publicKey))
+      .rpc();
+    await uploadProof(invalidKeypair.publicKey, proof);
+    const invalid = await simulate(invalidKeypair.publicKey);
+    assert.isFalse(invalid.verified);
+    assert.deepEqual(invalid.failedStage, { verification: {} });
+
+    const status = await program.methods
+      .getProofStatus()
+      .accounts({ proofData: invalidKeypair.publicKey })
+      .view();
+    assert.isFalse(status.verified);
+  });
+
+  it("Derives a constraint from typed public inputs", async () => {
+    const { evalValue, metadata } = loadProof();
+    const proofDataKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.alloc(0), Buffer.alloc(0), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+
+    await program.methods
+      .addPublicInputsConstraint(
+        metadata.num_variables,
+        [
+          { u64: { 0: new anchor.BN(42) } },
+          { hash: { 0: Array(32).fill(1) } },
+          { pubkey: { 0: program.programId } },
+        ],
+        Buffer.from(evalValue)
+      )
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+
+    const status = await program.methods
+      .getProofStatus()
+      .accounts({ proofData: proofDataKeypair.publicKey })
+      .view();
+    assert.equal(status.numConstraints, 1);
+  });
+
+  it("Settles a rollup transition verified via CPI", async () => {
+    const rollupProgram = anchor.workspace.RollupExample as Program<RollupExample>;
+    const { metadata } = loadProof();
+    // Proves the transition from the zero state root to `[1; 32]` at height 1, see the prover.
+    const proof = fs.readFileSync("proof/rollup-proof.bin");
+    const evalValue = fs.readFileSync("proof/rollup-eval-value.bin");
+    const oldRoot = Array(32).fill(0);
+    const newRoot = Array(32).fill(1);
+
+    const sequencer = provider.wallet.publicKey;
+    const [rollupPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("rollup"), sequencer.toBuffer()],
+      rollupProgram.programId
+    );
+    await rollupProgram.methods
+      .initialize(
+        {
+          numVariables: metadata.num_variables,
+          securityLevel: metadata.config.security_level,
+          powBits: metadata.config.pow_bits,
+          foldingFactor: metadata.config.folding_factor,
+          startingLogInvRate: metadata.config.starting_log_inv_rate,
+        },
+        oldRoot
+      )
+      .accounts({
+        rollup: rollupPda,
+        sequencer,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const proofDataKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.alloc(0), Buffer.alloc(0), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+    await program.methods
+      .addPublicInputsConstraint(
+        metadata.num_variables,
+        [{ hash: { 0: oldRoot } }, { hash: { 0: newRoot } }, { u64: { 0: new anchor.BN(1) } }],
+        Buffer.from(evalValue)
+      )
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+    await uploadProof(proofDataKeypair.publicKey, proof);
+
+    await rollupProgram.methods
+      .settle(newRoot)
+      .accounts({
+        rollup: rollupPda,
+        sequencer,
+        proofData: proofDataKeypair.publicKey,
+        verifierConfig: configPda,
+        treasury: treasuryPda,
+        verifierProgram: program.programId,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .preInstructions([
+        anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+      ])
+      .rpc();
+
+    const rollup = await rollupProgram.account.rollup.fetch(rollupPda);
+    assert.deepEqual(Array.from(rollup.stateRoot), newRoot);
+    assert.equal(rollup.blockHeight.toNumber(), 1);
+  });
+
+  it("Verifies with a requested heap frame", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const proofDataKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+    await uploadProof(proofDataKeypair.publicKey, proof);
+
+    const fee = { ...feeAccounts(), instructionsSysvar: anchor.web3.SYSVAR_INSTRUCTIONS_PUBKEY };
+    await program.methods
+      .verifyV2({
+        numVariables: metadata.num_variables,
+        securityLevel: metadata.config.security_level,
+        powBits: metadata.config.pow_bits,
+        foldingFactor: metadata.config.folding_factor,
+        startingLogInvRate: metadata.config.starting_log_inv_rate,
+      })
+      .accounts(verifyAccounts(proofDataKeypair.publicKey, { fee }))
+      .preInstructions([
+        anchor.web3.ComputeBudgetProgram.requestHeapFrame({ bytes: 256 * 1024 }),
+        anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+      ])
+      .rpc();
+
+    const proofData = await program.account.proofData.fetch(proofDataKeypair.publicKey);
+    assert.equal(proofData.verified, 1);
+  });
+
+  it("Verifies with a cached transcript pattern", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const params = {
+      numVariables: metadata.num_variables,
+      securityLevel: metadata.config.security_level,
+      powBits: metadata.config.pow_bits,
+      foldingFactor: metadata.config.folding_factor,
+      startingLogInvRate: metadata.config.starting_log_inv_rate,
+    };
+    const keyHash = createHash("sha256");
+    for (const value of [
+      params.numVariables,
+      params.securityLevel,
+      params.powBits,
+      params.foldingFactor,
+      params.startingLogInvRate,
+      0,
+    ]) {
+      const bytes = Buffer.alloc(4);
+      bytes.writeUInt32LE(value);
+      keyHash.update(bytes);
+    }
+    const [transcriptPattern] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("transcript_pattern"), keyHash.digest()],
+      program.programId
+    );
+    await program.methods
+      .cacheTranscriptPattern(params, "", 0)
+      .accounts({
+        transcriptPattern,
+        payer: provider.wallet.publicKey,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .preInstructions([
+        anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+      ])
+      .rpc();
+
+    const proofDataKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+    await uploadProof(proofDataKeypair.publicKey, proof);
+
+    await program.methods
+      .verifyV2(params)
+      .accounts(verifyAccounts(proofDataKeypair.publicKey, { transcriptPattern }))
+      .preInstructions([
+        anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+      ])
+      .rpc();
+
+    const proofData = await program.account.proofData.fetch(proofDataKeypair.publicKey);
+    assert.equal(proofData.verified, 1);
+  });
+
+  it("Verifies proofs under each soundness type", async () => {
+    const { evalPoint, evalValue, metadata } = loadProof();
+    const params = {
+      numVariables: metadata.num_variables,
+      securityLevel: metadata.config.security_level,
+      powBits: metadata.config.pow_bits,
+      foldingFactor: metadata.config.folding_factor,
+      startingLogInvRate: metadata.config.starting_log_inv_rate,
+    };
+    const proofs: [object, string][] = [
+      [{ conjectureList: {} }, "proof/proof.bin"],
+      [{ provableList: {} }, "proof/provable-list-proof.bin"],
+      [{ uniqueDecoding: {} }, "proof/unique-decoding-proof.bin"],
+    ];
+    for (const [soundnessType, path] of proofs) {
+      const proofDataKeypair = await createProofAccount();
+      await program.methods
+        .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+        .accounts(proofAccounts(proofDataKeypair.publicKey))
+        .rpc();
+      await uploadProof(proofDataKeypair.publicKey, fs.readFileSync(path));
+
+      await program.methods
+        .verifyV3(params, soundnessType)
+        .accounts(verifyAccounts(proofDataKeypair.publicKey))
+        .preInstructions([
+          anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+        ])
+        .rpc();
+
+      const proofData = await program.account.proofData.fetch(proofDataKeypair.publicKey);
+      assert.equal(proofData.verified, 1);
+    }
+  });
+
+  it("Verifies with the parameters recorded in the proof account", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const params = {
+      numVariables: metadata.num_variables,
+      securityLevel: metadata.config.security_level,
+      powBits: metadata.config.pow_bits,
+      foldingFactor: metadata.config.folding_factor,
+      startingLogInvRate: metadata.config.starting_log_inv_rate,
+    };
+    // Version 1 of the wire format, as written by the prover.
+    assert.equal(metadata.versioned_params[0], 0);
+    const versionedParams = (soundnessType: object) => ({ v1: { 0: { params, soundnessType } } });
+
+    const recordProof = async (soundnessType: object) => {
+      const proofDataKeypair = await createProofAccount();
+      await program.methods
+        .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+        .accounts(proofAccounts(proofDataKeypair.publicKey))
+        .rpc();
+      await uploadProof(proofDataKeypair.publicKey, proof);
+      await program.methods
+        .setProofParams(versionedParams(soundnessType))
+        .accounts(proofAccounts(proofDataKeypair.publicKey))
+        .rpc();
+      return proofDataKeypair.publicKey;
+    };
+    const computeBudget = [
+      anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+    ];
+
+    const proofData = await recordProof({ conjectureList: {} });
+    await program.methods
+      .verifyV4(versionedParams({ conjectureList: {} }))
+      .accounts(verifyAccounts(proofData))
+      .preInstructions(computeBudget)
+      .rpc();
+    assert.equal((await program.account.proofData.fetch(proofData)).verified, 1);
+
+    const mismatched = await recordProof({ provableList: {} });
+    try {
+      await program.methods
+        .verifyV2(params)
+        .accounts(verifyAccounts(mismatched))
+        .preInstructions(computeBudget)
+        .rpc();
+      assert.fail("Should have rejected parameters other than the recorded ones");
+    } catch (error: any) {
+      assert.include(error.toString(), "ParamsMismatch");
+    }
+  });
+
+  it("Rejects malformed proofs passed in the instruction data", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const params = {
+      numVariables: metadata.num_variables,
+      securityLevel: metadata.config.security_level,
+      powBits: metadata.config.pow_bits,
+      foldingFactor: metadata.config.folding_factor,
+      startingLogInvRate: metadata.config.starting_log_inv_rate,
+    };
+    // The test proof doesn't fit in a transaction, so only its start is passed inline.
+    const verifyInline = (args: typeof params, proofBytes: Buffer) =>
+      program.methods
+        .verifyInline(args, proofBytes, Buffer.from(evalPoint), Buffer.from(evalValue))
+        .accounts({ fee: feeAccounts() })
+        .preInstructions([
+          anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+        ])
+        .rpc();
+
+    try {
+      await verifyInline(params, proof.subarray(0, 16));
+      assert.fail("Should have rejected a truncated proof");
+    } catch (error: any) {
+      assert.include(error.toString(), "CommitmentParseError");
+    }
+    try {
+      await verifyInline({ ...params, securityLevel: params.securityLevel - 1 }, proof.subarray(0, 16));
+      assert.fail("Should have rejected parameters below the minimum security level");
+    } catch (error: any) {
+      assert.include(error.toString(), "InsufficientSecurity");
+    }
+  });
+
+  it("Verifies a proof split across buffer accounts", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const proofDataKeypair = await createProofAccount();
+    const accounts = proofAccounts(proofDataKeypair.publicKey);
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(accounts)
+      .rpc();
+    const split = Math.floor(proof.length / 2);
+    await uploadProof(proofDataKeypair.publicKey, proof.subarray(0, split));
+
+    // Discriminator, version, proof account and length prefix, followed by the buffered bytes.
+    const bufferKeypair = Keypair.generate();
+    const bufferSize = 8 + 1 + 32 + 4 + proof.length - split;
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.createAccount({
+          fromPubkey: provider.wallet.publicKey,
+          newAccountPubkey: bufferKeypair.publicKey,
+          space: bufferSize,
+          lamports: await provider.connection.getMinimumBalanceForRentExemption(bufferSize),
+          programId: program.programId,
+        })
+      ),
+      [bufferKeypair]
+    );
+    await program.methods
+      .initBuffer()
+      .accounts({
+        buffer: bufferKeypair.publicKey,
+        proofData: proofDataKeypair.publicKey,
+        authority: provider.wallet.publicKey,
+      })
+      .rpc();
+    const uploadBufferChunk = (chunk: Buffer, authority: Keypair | null) =>
+      program.methods
+        .uploadBufferChunk(chunk)
+        .accounts({
+          buffer: bufferKeypair.publicKey,
+          proofData: proofDataKeypair.publicKey,
+          authority: authority?.publicKey ?? provider.wallet.publicKey,
+          config: configPda,
+          stats: null,
+        })
+        .signers(authority ? [authority] : [])
+        .rpc();
+
+    const intruder = Keypair.generate();
+    try {
+      await uploadBufferChunk(Buffer.from(proof.subarray(split, split + 1)), intruder);
+      assert.fail("Should have rejected a signer other than the proof's authority");
+    } catch (error: any) {
+      assert.include(error.toString(), "ConstraintHasOne");
+    }
+    for (let start = split; start < proof.length; start += chunkSize) {
+      const chunk = proof.subarray(start, Math.min(start + chunkSize, proof.length));
+      await uploadBufferChunk(Buffer.from(chunk), null);
+    }
+    const status = await program.methods
+      .getProofStatus()
+      .accounts({ proofData: proofDataKeypair.publicKey })
+      .view();
+    assert.equal(status.uploadedBytes, split);
+    assert.equal(status.numBuffers, 1);
+
+    const verify = (buffers: anchor.web3.PublicKey[]) =>
+      program.methods
+        .verifyV2({
+          numVariables: metadata.num_variables,
+          securityLevel: metadata.config.security_level,
+          powBits: metadata.config.pow_bits,
+          foldingFactor: metadata.config.folding_factor,
+          startingLogInvRate: metadata.config.starting_log_inv_rate,
+        })
+        .accounts(verifyAccounts(proofDataKeypair.publicKey))
+        .remainingAccounts(
+          buffers.map((pubkey) => ({ pubkey, isSigner: false, isWritable: false }))
+        )
+        .preInstructions([
+          anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+        ])
+        .rpc();
+    try {
+      await verify([]);
+      assert.fail("Should have required the buffer accounts");
+    } catch (error: any) {
+      assert.include(error.toString(), "InvalidProofBuffers");
+    }
+    try {
+      await verify([Keypair.generate().publicKey]);
+      assert.fail("Should have rejected an account other than the buffer");
+    } catch (error: any) {
+      assert.include(error.toString(), "InvalidProofBuffers");
+    }
+    await verify([bufferKeypair.publicKey]);
+    assert.equal((await program.account.proofData.fetch(proofDataKeypair.publicKey)).verified, 1);
+
+    await program.methods
+      .closeBuffer()
+      .accounts({
+        buffer: bufferKeypair.publicKey,
+        proofData: proofDataKeypair.publicKey,
+        payer: provider.wallet.publicKey,
+        authority: provider.wallet.publicKey,
+      })
+      .rpc();
+    assert.isNull(await provider.connection.getAccountInfo(bufferKeypair.publicKey));
+    const closed = await program.methods
+      .getProofStatus()
+      .accounts({ proofData: proofDataKeypair.publicKey })
+      .view();
+    assert.equal(closed.numBuffers, 0);
+  });
+
+  it("Verifies a proof read from a hash-pinned external account", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    // Any account holding the proof bytes works, here the proof bytes of another proof account.
+    const sourceKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.alloc(0), Buffer.alloc(0), metadata.encoding ?? 0)
+      .accounts(proofAccounts(sourceKeypair.publicKey))
+      .rpc();
+    await uploadProof(sourceKeypair.publicKey, proof);
+    const proofOffset = 8 + 4512;
+    const proofHash = Array.from(createHash("sha256").update(proof).digest());
+
+    const proofDataKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+    const setExternalProof = (hash: number[]) =>
+      program.methods
+        .setExternalProof(sourceKeypair.publicKey, proofOffset, proof.length, hash)
+        .accounts(proofAccounts(proofDataKeypair.publicKey))
+        .rpc();
+    const verify = (externalProofAccount: anchor.web3.PublicKey | null) =>
+      program.methods
+        .verifyV2({
+          numVariables: metadata.num_variables,
+          securityLevel: metadata.config.security_level,
+          powBits: metadata.config.pow_bits,
+          foldingFactor: metadata.config.folding_factor,
+          startingLogInvRate: metadata.config.starting_log_inv_rate,
+        })
+        .accounts(verifyAccounts(proofDataKeypair.publicKey, { externalProofAccount }))
+        .preInstructions([
+          anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+        ])
+        .rpc();
+
+    const intruder = Keypair.generate();
+    try {
+      await program.methods
+        .setExternalProof(sourceKeypair.publicKey, proofOffset, proof.length, proofHash)
+        .accounts({ ...proofAccounts(proofDataKeypair.publicKey), payer: intruder.publicKey })
+        .signers([intruder])
+        .rpc();
+      assert.fail("Should have rejected a signer other than the proof's authority");
+    } catch (error: any) {
+      assert.include(error.toString(), "Unauthorized");
+    }
+
+    await setExternalProof(Array(32).fill(0));
+    try {
+      await verify(sourceKeypair.publicKey);
+      assert.fail("Should have rejected bytes not matching the pinned hash");
+    } catch (error: any) {
+      assert.include(error.toString(), "InvalidExternalProof");
+    }
+    await setExternalProof(proofHash);
+    try {
+      await verify(null);
+      assert.fail("Should have required the external proof account");
+    } catch (error: any) {
+      assert.include(error.toString(), "InvalidExternalProof");
+    }
+    await verify(sourceKeypair.publicKey);
+    assert.equal((await program.account.proofData.fetch(proofDataKeypair.publicKey)).verified, 1);
+  });
+
+  it("Mints one receipt token per verified proof", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const [receiptMint] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("receipt_mint")],
+      program.programId
+    );
+    const initReceiptMint = (admin: Keypair | null) =>
+      program.methods
+        .initReceiptMint()
+        .accounts({
+          config: configPda,
+          receiptMint,
+          admin: admin?.publicKey ?? provider.wallet.publicKey,
+          payer: provider.wallet.publicKey,
+          tokenProgram: token2022ProgramId,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers(admin ? [admin] : [])
+        .rpc();
+    try {
+      await initReceiptMint(Keypair.generate());
+      assert.fail("Should have rejected a non-admin signer");
+    } catch (error: any) {
+      assert.include(error.toString(), "Unauthorized");
+    }
+    if ((await provider.connection.getAccountInfo(receiptMint)) === null) {
+      await initReceiptMint(null);
+    }
+    const mintInfo = await provider.connection.getAccountInfo(receiptMint);
+    assert.isTrue(mintInfo.owner.equals(token2022ProgramId));
+
+    const proofDataKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+    await uploadProof(proofDataKeypair.publicKey, proof);
+    const recipientTokenAccount = await createAssociatedTokenAccount(
+      Keypair.generate().publicKey,
+      receiptMint,
+      token2022ProgramId
+    );
+    const mintReceipt = (authority: Keypair | null) =>
+      program.methods
+        .mintReceipt()
+        .accounts({
+          proofData: proofDataKeypair.publicKey,
+          authority: authority?.publicKey ?? provider.wallet.publicKey,
+          receiptMint,
+          recipientTokenAccount,
+          tokenProgram: token2022ProgramId,
+          config: configPda,
+        })
+        .signers(authority ? [authority] : [])
+        .rpc();
+
+    try {
+      await mintReceipt(null);
+      assert.fail("Should have rejected an unverified proof");
+    } catch (error: any) {
+      assert.include(error.toString(), "ProofNotVerified");
+    }
+    await program.methods
+      .verifyV2({
+        numVariables: metadata.num_variables,
+        securityLevel: metadata.config.security_level,
+        powBits: metadata.config.pow_bits,
+        foldingFactor: metadata.config.folding_factor,
+        startingLogInvRate: metadata.config.starting_log_inv_rate,
+      })
+      .accounts(verifyAccounts(proofDataKeypair.publicKey))
+      .preInstructions([
+        anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+      ])
+      .rpc();
+    try {
+      await mintReceipt(Keypair.generate());
+      assert.fail("Should have rejected a signer other than the proof's authority");
+    } catch (error: any) {
+      assert.include(error.toString(), "ConstraintHasOne");
+    }
+    const adminAccounts = { config: configPda, admin: provider.wallet.publicKey };
+    await program.methods
+      .setMinSecurityLevel(metadata.config.security_level + 1)
+      .accounts(adminAccounts)
+      .rpc();
+    try {
+      await mintReceipt(null);
+      assert.fail("Should have rejected parameters below the minimum security level");
+    } catch (error: any) {
+      assert.include(error.toString(), "InsufficientSecurity");
+    } finally {
+      await program.methods
+        .setMinSecurityLevel(metadata.config.security_level)
+        .accounts(adminAccounts)
+        .rpc();
+    }
+
+    await mintReceipt(null);
+    const balance = await provider.connection.getTokenAccountBalance(recipientTokenAccount);
+    assert.equal(balance.value.amount, "1");
+    try {
+      await mintReceipt(null);
+      assert.fail("Should have rejected a second receipt for the proof");
+    } catch (error: any) {
+      assert.include(error.toString(), "ReceiptAlreadyMinted");
+    }
+  });
+
+  it("Rejects proofs past their expiry slot", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const proofDataKeypair = await createProofAccount();
+    const accounts = proofAccounts(proofDataKeypair.publicKey);
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(accounts)
+      .rpc();
+    await uploadProof(proofDataKeypair.publicKey, proof);
+    const verify = () =>
+      program.methods
+        .verifyV2({
+          numVariables: metadata.num_variables,
+          securityLevel: metadata.config.security_level,
+          powBits: metadata.config.pow_bits,
+          foldingFactor: metadata.config.folding_factor,
+          startingLogInvRate: metadata.config.starting_log_inv_rate,
+        })
+        .accounts(verifyAccounts(proofDataKeypair.publicKey))
+        .preInstructions([
+          anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+        ])
+        .rpc();
+
+    const expired = new anchor.BN((await provider.connection.getSlot()) - 1);
+    const intruder = Keypair.generate();
+    try {
+      await program.methods
+        .setExpiry(expired)
+        .accounts({ ...accounts, payer: intruder.publicKey })
+        .signers([intruder])
+        .rpc();
+      assert.fail("Should have rejected a signer other than the proof's authority");
+    } catch (error: any) {
+      assert.include(error.toString(), "Unauthorized");
+    }
+
+    await program.methods.setExpiry(expired).accounts(accounts).rpc();
+    let proofData = await program.account.proofData.fetch(proofDataKeypair.publicKey);
+    assert.equal(proofData.hasExpiry, 1);
+    assert.equal(proofData.expiresAtSlot.toString(), expired.toString());
+    try {
+      await verify();
+      assert.fail("Should have rejected an expired proof");
+    } catch (error: any) {
+      assert.include(error.toString(), "ProofExpired");
+    }
+
+    await program.methods.setExpiry(null).accounts(accounts).rpc();
+    proofData = await program.account.proofData.fetch(proofDataKeypair.publicKey);
+    assert.equal(proofData.hasExpiry, 0);
+    await verify();
+    assert.equal((await program.account.proofData.fetch(proofDataKeypair.publicKey)).verified, 1);
+  });
+
+  it("Migrates only accounts of this program with a known layout", async () => {
+    const { evalPoint, evalValue, metadata } = loadProof();
+    const proofDataKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+    const migrate = (account: anchor.web3.PublicKey) =>
+      program.methods
+        .migrate()
+        .accounts({
+          account,
+          payer: provider.wallet.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+
+    // Accounts of the current layout are left as they are.
+    const before = await provider.connection.getAccountInfo(proofDataKeypair.publicKey);
+    await migrate(proofDataKeypair.publicKey);
+    const after = await provider.connection.getAccountInfo(proofDataKeypair.publicKey);
+    assert.deepEqual(after.data, before.data);
+    assert.equal(after.lamports, before.lamports);
+
+    // Loaded by the test validator with an unknown layout version, see scripts/account-fixtures.py.
+    const staleVersion = new anchor.web3.PublicKey("5Tg9bJzNwRAug1dJkAUUc7ePwvAFiQvYx8ae4kuBn3SK");
+    for (const account of [provider.wallet.publicKey, configPda, staleVersion]) {
+      try {
+        await migrate(account);
+        assert.fail("Should have rejected an account without a migratable layout");
+      } catch (error: any) {
+        assert.include(error.toString(), "UnsupportedAccountVersion");
+      }
+    }
+  });
+
+  it("Migrates a proof account of the released layout", async () => {
+    // Loaded by the test validator, see scripts/account-fixtures.py: a 2048-byte account holding
+    // the payer, a 1000-byte proof, a 16-byte evaluation point and an 8-byte value.
+    const baseline = new anchor.web3.PublicKey("9JSkgp6cz8MoYYB7Jy6AcUGhMTMjZrRyC3mD66eQ2zX1");
+    const payer = new anchor.web3.PublicKey(Buffer.from(Array.from({ length: 32 }, (_, i) => i)));
+    const proof = Buffer.from(Array.from({ length: 1000 }, (_, i) => i % 251));
+
+    // Released accounts aren't read as a header before they're migrated.
+    try {
+      await program.methods.getProofStatus().accounts({ proofData: baseline }).rpc();
+      assert.fail("Should have rejected the released layout");
+    } catch (error: any) {
+      assert.include(error.toString(), "AccountDiscriminatorMismatch");
+    }
+
+    const migrate = () =>
+      program.methods
+        .migrate()
+        .accounts({
+          account: baseline,
+          payer: provider.wallet.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+    await migrate();
+
+    const proofOffset = 8 + 4512;
+    const info = await provider.connection.getAccountInfo(baseline);
+    assert.equal(info.data.length, proofOffset + proof.length);
+    assert.deepEqual(info.data.subarray(proofOffset), proof);
+    const proofData = await program.account.proofData.fetch(baseline);
+    assert.equal(proofData.version, 1);
+    assert.equal(proofData.verified, 0);
+    assert.equal(proofData.finalized, 0);
+    assert.isTrue(proofData.payer.equals(payer));
+    assert.isTrue(proofData.authority.equals(payer));
+    const status = await program.methods
+      .getProofStatus()
+      .accounts({ proofData: baseline })
+      .view();
+    assert.equal(status.uploadedBytes, proof.length);
+    assert.equal(status.numConstraints, 1);
+
+    // The account is current now, migrating again leaves it as it is.
+    await migrate();
+    assert.deepEqual((await provider.connection.getAccountInfo(baseline)).data, info.data);
+  });
+
+  it("Validates the proof inputs without verifying", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const params = {
+      numVariables: metadata.num_variables,
+      securityLevel: metadata.config.security_level,
+      powBits: metadata.config.pow_bits,
+      foldingFactor: metadata.config.folding_factor,
+      startingLogInvRate: metadata.config.starting_log_inv_rate,
+    };
+    const createProof = async (point: Buffer, upload: boolean) => {
+      const proofDataKeypair = await createProofAccount();
+      await program.methods
+        .initProof(point, Buffer.from(evalValue), metadata.encoding ?? 0)
+        .accounts(proofAccounts(proofDataKeypair.publicKey))
+        .rpc();
+      if (upload) {
+        await uploadProof(proofDataKeypair.publicKey, proof);
+      }
+      return proofDataKeypair.publicKey;
+    };
+    const validate = (proofData: anchor.web3.PublicKey, args: typeof params) =>
+      program.methods
+        .validateProofInputs(args)
+        .accounts({ proofData, statement: null, config: configPda })
+        .rpc();
+
+    const valid = await createProof(Buffer.from(evalPoint), true);
+    await validate(valid, params);
+    try {
+      await validate(valid, { ...params, foldingFactor: params.numVariables + 1 });
+      assert.fail("Should have rejected a folding factor above the number of variables");
+    } catch (error: any) {
+      assert.include(error.toString(), "InvalidFoldingFactor");
+    }
+
+    try {
+      await validate(await createProof(Buffer.from(evalPoint), false), params);
+      assert.fail("Should have rejected a proof account without a proof");
+    } catch (error: any) {
+      assert.include(error.toString(), "EmptyProof");
+    }
+
+    // One variable short of the parameters.
+    const fieldSize = evalPoint.length / params.numVariables;
+    const shortPoint = Buffer.from(evalPoint.subarray(0, evalPoint.length - fieldSize));
+    try {
+      await validate(await createProof(shortPoint, true), params);
+      assert.fail("Should have rejected an evaluation point of the wrong length");
+    } catch (error: any) {
+      assert.include(error.toString(), "InvalidEvalPoint");
+    }
+
+    // Nothing is recorded.
+    const status = await program.methods.getProofStatus().accounts({ proofData: valid }).view();
+    assert.isFalse(status.verified);
+  });
+
+  it("Reports the cause of a failed verification", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const verifyWith = async (value: Buffer, proofBytes: Buffer) => {
+      const proofDataKeypair = await createProofAccount();
+      await program.methods
+        .initProof(Buffer.from(evalPoint), value, metadata.encoding ?? 0)
+        .accounts(proofAccounts(proofDataKeypair.publicKey))
+        .rpc();
+      await uploadProof(proofDataKeypair.publicKey, proofBytes);
+      await program.methods
+        .verifyV2({
+          numVariables: metadata.num_variables,
+          securityLevel: metadata.config.security_level,
+          powBits: metadata.config.pow_bits,
+          foldingFactor: metadata.config.folding_factor,
+          startingLogInvRate: metadata.config.starting_log_inv_rate,
+        })
+        .accounts(verifyAccounts(proofDataKeypair.publicKey))
+        .preInstructions([
+          anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+        ])
+        .rpc();
+    };
+
+    const wrongValue = Buffer.from(evalValue);
+    wrongValue[0] ^= 1;
+    const cases: [Buffer, Buffer, string][] = [
+      [wrongValue, proof, "VerificationFailed"],
+      [Buffer.alloc(evalValue.length, 0xff), proof, "DeserializationError"],
+      [Buffer.from(evalValue), proof.subarray(0, 16), "CommitmentParseError"],
+    ];
+    for (const [value, proofBytes, expected] of cases) {
+      try {
+        await verifyWith(value, Buffer.from(proofBytes));
+        assert.fail("Should have rejected the proof");
+      } catch (error: any) {
+        assert.include(error.toString(), expected);
+      }
+    }
+  });
+
+  it("Restricts creating the digest tree to the admin", async () => {
+    // The account-compression program isn't deployed on the local validator, so only the checks
+    // before its CPI run here.
+    const [digestTreePda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("digest_tree")],
+      program.programId
+    );
+    const intruder = Keypair.generate();
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({
+          fromPubkey: provider.wallet.publicKey,
+          toPubkey: intruder.publicKey,
+          lamports: anchor.web3.LAMPORTS_PER_SOL / 10,
+        })
+      )
+    );
+    const initDigestTree = (admin: Keypair | null, merkleTree: anchor.web3.PublicKey) =>
+      program.methods
+        .initDigestTree(14, 64)
+        .accounts({
+          config: configPda,
+          digestTree: digestTreePda,
+          merkleTree,
+          admin: admin?.publicKey ?? provider.wallet.publicKey,
+          payer: provider.wallet.publicKey,
+          compressionProgram: new anchor.web3.PublicKey(
+            "cmtDvXumGCrqC1Age74AVPhSRVXJMd8PJS91L8KbNCK"
+          ),
+          noopProgram: new anchor.web3.PublicKey("noopb9bkMVfRPU8AsbpTUg8AQkHtKwMYZiFUjNRtMmV"),
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers(admin ? [admin] : [])
+        .rpc();
+
+    try {
+      await initDigestTree(intruder, Keypair.generate().publicKey);
+      assert.fail("Should have rejected a non-admin signer");
+    } catch (error: any) {
+      assert.include(error.toString(), "Unauthorized");
+    }
+    // The tree must be allocated for and owned by the account-compression program.
+    try {
+      await initDigestTree(null, (await createProofAccount()).publicKey);
+      assert.fail("Should have rejected a tree the account-compression program doesn't own");
+    } catch (error: any) {
+      assert.include(error.toString(), "ConstraintOwner");
+    }
+    assert.isNull(await provider.connection.getAccountInfo(digestTreePda));
+  });
+
+  it("Keeps a claim open until its deadlines pass", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const params = {
+      numVariables: metadata.num_variables,
+      securityLevel: metadata.config.security_level,
+      powBits: metadata.config.pow_bits,
+      foldingFactor: metadata.config.folding_factor,
+      startingLogInvRate: metadata.config.starting_log_inv_rate,
+    };
+    const { commitmentSize } = await program.methods.quoteVerification(params).accounts({}).view();
+
+    // Claims a value the proof doesn't prove, so proving the claim with it must fail.
+    const wrongValue = Buffer.from(evalValue);
+    wrongValue[0] ^= 1;
+    const submitter = provider.wallet.publicKey;
+    const claimId = new anchor.BN(Date.now());
+    const [claimPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("claim"), submitter.toBuffer(), claimId.toArrayLike(Buffer, "le", 8)],
+      program.programId
+    );
+    await program.methods
+      .postClaim(
+        claimId,
+        params,
+        {
+          root: Array.from(proof.subarray(0, 32)),
+          oodAnswers: Buffer.from(proof.subarray(32, commitmentSize)),
+          evalPoint: Buffer.from(evalPoint),
+          evalValue: wrongValue,
+        },
+        new anchor.BN(anchor.web3.LAMPORTS_PER_SOL / 100)
+      )
+      .accounts({
+        claim: claimPda,
+        submitter,
+        config: configPda,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+
+    const settleClaim = (accounts: {
+      submitter: anchor.web3.PublicKey;
+      challenger: anchor.web3.PublicKey | null;
+    }) => program.methods.settleClaim().accounts({ claim: claimPda, ...accounts }).rpc();
+    try {
+      await settleClaim({ submitter, challenger: null });
+      assert.fail("Should have kept the claim open during its challenge window");
+    } catch (error: any) {
+      assert.include(error.toString(), "ClaimNotSettleable");
+    }
+    try {
+      await settleClaim({ submitter: Keypair.generate().publicKey, challenger: null });
+      assert.fail("Should have rejected another submitter");
+    } catch (error: any) {
+      assert.include(error.toString(), "ConstraintHasOne");
+    }
+    try {
+      await program.methods.closeClaim().accounts({ claim: claimPda, submitter }).rpc();
+      assert.fail("Should have kept an unsettled claim");
+    } catch (error: any) {
+      assert.include(error.toString(), "ClaimNotSettleable");
+    }
+
+    const challenger = Keypair.generate();
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({
+          fromPubkey: submitter,
+          toPubkey: challenger.publicKey,
+          lamports: anchor.web3.LAMPORTS_PER_SOL / 10,
+        })
+      )
+    );
+    const challengeClaim = () =>
+      program.methods
+        .challengeClaim()
+        .accounts({
+          claim: claimPda,
+          challenger: challenger.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers([challenger])
+        .rpc();
+    await challengeClaim();
+    try {
+      await challengeClaim();
+      assert.fail("Should have accepted a single challenge");
+    } catch (error: any) {
+      assert.include(error.toString(), "ClaimNotChallengeable");
+    }
+    try {
+      await settleClaim({ submitter, challenger: challenger.publicKey });
+      assert.fail("Should have kept the claim open during its response window");
+    } catch (error: any) {
+      assert.include(error.toString(), "ClaimNotSettleable");
+    }
+
+    const proofDataKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+    await uploadProof(proofDataKeypair.publicKey, proof);
+    try {
+      await program.methods
+        .proveClaim()
+        .accounts({
+          verify: verifyAccounts(proofDataKeypair.publicKey),
+          claim: claimPda,
+          submitter,
+        })
+        .preInstructions([
+          anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+        ])
+        .rpc();
+      assert.fail("Should have rejected a proof of another evaluation");
+    } catch (error: any) {
+      assert.include(error.toString(), "ProofNotVerified");
+    }
+
+    const claim = await program.account.claim.fetch(claimPda);
+    assert.deepEqual(claim.status, { challenged: {} });
+    assert.isTrue(claim.challenger.equals(challenger.publicKey));
+  });
+
+  it("Refunds an escrow to its depositor only after it expires", async () => {
+    const depositor = provider.wallet.publicKey;
+    const recipient = Keypair.generate().publicKey;
+    const escrowPda = (digest: Buffer) =>
+      anchor.web3.PublicKey.findProgramAddressSync(
+        [Buffer.from("escrow"), depositor.toBuffer(), digest],
+        program.programId
+      )[0];
+    const createEscrow = async (digest: Buffer, expiresAtSlot: number) => {
+      await program.methods
+        .createEscrow(
+          Array.from(digest),
+          recipient,
+          new anchor.BN(anchor.web3.LAMPORTS_PER_SOL / 100),
+          new anchor.BN(expiresAtSlot)
+        )
+        .accounts({
+          escrow: escrowPda(digest),
+          depositor,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .rpc();
+      return escrowPda(digest);
+    };
+    const noTokenAccounts = {
+      vault: null,
+      mint: null,
+      destinationTokenAccount: null,
+      tokenProgram: null,
+    };
+
+    const open = await createEscrow(
+      Keypair.generate().publicKey.toBuffer(),
+      Number.MAX_SAFE_INTEGER
+    );
+    try {
+      await program.methods
+        .refundEscrow()
+        .accounts({ escrow: open, depositor, ...noTokenAccounts })
+        .rpc();
+      assert.fail("Should have kept an unexpired escrow");
+    } catch (error: any) {
+      assert.include(error.toString(), "EscrowNotExpired");
+    }
+    // An escrow is only released for a verified proof of its digest.
+    const { evalPoint, evalValue, metadata } = loadProof();
+    const proofDataKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+    try {
+      await program.methods
+        .releaseEscrow()
+        .accounts({
+          escrow: open,
+          proofData: proofDataKeypair.publicKey,
+          recipient,
+          depositor,
+          ...noTokenAccounts,
+          config: configPda,
+        })
+        .rpc();
+      assert.fail("Should have rejected an unverified proof");
+    } catch (error: any) {
+      assert.include(error.toString(), "ProofNotVerified");
+    }
+
+    const expired = await createEscrow(Keypair.generate().publicKey.toBuffer(), 0);
+    const intruder = Keypair.generate();
+    try {
+      await program.methods
+        .refundEscrow()
+        .accounts({ escrow: expired, depositor: intruder.publicKey, ...noTokenAccounts })
+        .signers([intruder])
+        .rpc();
+      assert.fail("Should have rejected a signer other than the depositor");
+    } catch (error: any) {
+      assert.include(error.toString(), "ConstraintHasOne");
+    }
+    await program.methods
+      .refundEscrow()
+      .accounts({ escrow: expired, depositor, ...noTokenAccounts })
+      .rpc();
+    assert.isNull(await provider.connection.getAccountInfo(expired));
+    assert.isNotNull(await provider.connection.getAccountInfo(open));
+
+    // Token escrows return the tokens from their vault to a token account of the depositor.
+    const amount = 1000;
+    const { mint, tokenAccount } = await createFundedMint(amount);
+    const digest = Keypair.generate().publicKey.toBuffer();
+    const tokenEscrow = escrowPda(digest);
+    const [vault] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("escrow_vault"), tokenEscrow.toBuffer()],
+      program.programId
+    );
+    await program.methods
+      .createTokenEscrow(Array.from(digest), recipient, new anchor.BN(amount), new anchor.BN(0))
+      .accounts({
+        escrow: tokenEscrow,
+        vault,
+        mint,
+        depositorTokenAccount: tokenAccount,
+        depositor,
+        tokenProgram: token2022ProgramId,
+        systemProgram: anchor.web3.SystemProgram.programId,
+      })
+      .rpc();
+    assert.equal(
+      (await provider.connection.getTokenAccountBalance(vault)).value.amount,
+      String(amount)
+    );
+    const refundTokens = (accounts: {
+      vault: anchor.web3.PublicKey | null;
+      mint: anchor.web3.PublicKey | null;
+      destinationTokenAccount: anchor.web3.PublicKey | null;
+      tokenProgram: anchor.web3.PublicKey | null;
+    }) =>
+      program.methods
+        .refundEscrow()
+        .accounts({ escrow: tokenEscrow, depositor, ...accounts })
+        .rpc();
+    try {
+      await refundTokens(noTokenAccounts);
+      assert.fail("Should have required the token accounts of a token escrow");
+    } catch (error: any) {
+      assert.include(error.toString(), "InvalidEscrowAccounts");
+    }
+    const otherTokenAccount = await createAssociatedTokenAccount(
+      Keypair.generate().publicKey,
+      mint,
+      token2022ProgramId
+    );
+    const tokenAccounts = {
+      vault,
+      mint,
+      destinationTokenAccount: tokenAccount,
+      tokenProgram: token2022ProgramId,
+    };
+    try {
+      await refundTokens({ ...tokenAccounts, destinationTokenAccount: otherTokenAccount });
+      assert.fail("Should have rejected a token account the depositor doesn't own");
+    } catch (error: any) {
+      assert.include(error.toString(), "InvalidEscrowAccounts");
+    }
+    await refundTokens(tokenAccounts);
+    assert.equal(
+      (await provider.connection.getTokenAccountBalance(tokenAccount)).value.amount,
+      String(amount)
+    );
+    assert.isNull(await provider.connection.getAccountInfo(vault));
+    assert.isNull(await provider.connection.getAccountInfo(tokenEscrow));
+  });
+
+  it("Restricts submitting and verifying to allowlisted signers", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const [allowlistPda] = anchor.web3.PublicKey.findProgramAddressSync(
+      [Buffer.from("allowlist")],
+      program.programId
+    );
+    const wallet = provider.wallet.publicKey;
+    const adminAccounts = { config: configPda, admin: wallet };
+    const intruder = Keypair.generate();
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({
+          fromPubkey: wallet,
+          toPubkey: intruder.publicKey,
+          lamports: anchor.web3.LAMPORTS_PER_SOL / 10,
+        })
+      )
+    );
+    const initAllowlist = (admin: Keypair | null) =>
+      program.methods
+        .initAllowlist()
+        .accounts({
+          config: configPda,
+          allowlist: allowlistPda,
+          admin: admin?.publicKey ?? wallet,
+          payer: wallet,
+          systemProgram: anchor.web3.SystemProgram.programId,
+        })
+        .signers(admin ? [admin] : [])
+        .rpc();
+    if ((await provider.connection.getAccountInfo(allowlistPda)) === null) {
+      try {
+        await initAllowlist(intruder);
+        assert.fail("Should have rejected a non-admin signer");
+      } catch (error: any) {
+        assert.include(error.toString(), "Unauthorized");
+      }
+      await initAllowlist(null);
+    }
+    const setAllowlisted = (allowed: boolean, admin: Keypair | null = null) =>
+      program.methods
+        .setAllowlisted(wallet, allowed)
+        .accounts({ config: configPda, allowlist: allowlistPda, admin: admin?.publicKey ?? wallet })
+        .signers(admin ? [admin] : [])
+        .rpc();
+    try {
+      await setAllowlisted(true, intruder);
+      assert.fail("Should have rejected a non-admin signer");
+    } catch (error: any) {
+      assert.include(error.toString(), "Unauthorized");
+    }
+    try {
+      await program.methods
+        .setAllowlistEnforced(true)
+        .accounts({ config: configPda, admin: intruder.publicKey })
+        .signers([intruder])
+        .rpc();
+      assert.fail("Should have rejected a non-admin signer");
+    } catch (error: any) {
+      assert.include(error.toString(), "Unauthorized");
+    }
+
+    await program.methods.setAllowlistEnforced(true).accounts(adminAccounts).rpc();
+    try {
+      const proofDataKeypair = await createProofAccount();
+      const initProof = (allowlist: anchor.web3.PublicKey | null) =>
+        program.methods
+          .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+          .accounts({ ...proofAccounts(proofDataKeypair.publicKey), allowlist })
+          .rpc();
+      for (const allowlist of [null, allowlistPda]) {
+        try {
+          await initProof(allowlist);
+          assert.fail("Should have rejected a signer missing from the allowlist");
+        } catch (error: any) {
+          assert.include(error.toString(), "NotAllowlisted");
+        }
+      }
+
+      await setAllowlisted(true);
+      await initProof(allowlistPda);
+      for (let start = 0; start < proof.length; start += chunkSize) {
+        const chunk = proof.subarray(start, Math.min(start + chunkSize, proof.length));
+        await program.methods
+          .uploadChunk(Buffer.from(chunk))
+          .accounts({ ...proofAccounts(proofDataKeypair.publicKey), allowlist: allowlistPda })
+          .rpc();
+      }
+      const verify = (allowlist: anchor.web3.PublicKey | null) =>
+        program.methods
+          .verifyV2({
+            numVariables: metadata.num_variables,
+            securityLevel: metadata.config.security_level,
+            powBits: metadata.config.pow_bits,
+            foldingFactor: metadata.config.folding_factor,
+            startingLogInvRate: metadata.config.starting_log_inv_rate,
+          })
+          .accounts(
+            verifyAccounts(proofDataKeypair.publicKey, { fee: { ...feeAccounts(), allowlist } })
+          )
+          .preInstructions([
+            anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+          ])
+          .rpc();
+      try {
+        await verify(null);
+        assert.fail("Should have required the allowlist");
+      } catch (error: any) {
+        assert.include(error.toString(), "NotAllowlisted");
+      }
+      await verify(allowlistPda);
+      const proofData = await program.account.proofData.fetch(proofDataKeypair.publicKey);
+      assert.equal(proofData.verified, 1);
+    } finally {
+      await setAllowlisted(false);
+      await program.methods.setAllowlistEnforced(false).accounts(adminAccounts).rpc();
+    }
+    const allowlist = await program.account.allowlist.fetch(allowlistPda);
+    assert.isFalse(allowlist.signers.some((signer) => signer.equals(wallet)));
+  });
+
+  it("Burns the gate tokens of the caller per verification", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const adminAccounts = { config: configPda, admin: provider.wallet.publicKey };
+    const { mint, tokenAccount } = await createFundedMint(3);
+    const tokenGate = { mint, amount: new anchor.BN(2), burn: true };
+    const intruder = Keypair.generate();
+    try {
+      await program.methods
+        .setTokenGate(tokenGate)
+        .accounts({ config: configPda, admin: intruder.publicKey })
+        .signers([intruder])
+        .rpc();
+      assert.fail("Should have rejected a non-admin signer");
+    } catch (error: any) {
+      assert.include(error.toString(), "Unauthorized");
+    }
+
+    const proofDataKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+    await uploadProof(proofDataKeypair.publicKey, proof);
+    const gateAccounts = {
+      callerTokenAccount: tokenAccount,
+      gateMint: mint,
+      tokenProgram: token2022ProgramId,
+    };
+    const verify = (gate: Record<string, anchor.web3.PublicKey | null>) =>
+      program.methods
+        .verifyV2({
+          numVariables: metadata.num_variables,
+          securityLevel: metadata.config.security_level,
+          powBits: metadata.config.pow_bits,
+          foldingFactor: metadata.config.folding_factor,
+          startingLogInvRate: metadata.config.starting_log_inv_rate,
+        })
+        .accounts(
+          verifyAccounts(proofDataKeypair.publicKey, { fee: { ...feeAccounts(), ...gate } })
+        )
+        .preInstructions([
+          anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+        ])
+        .rpc();
+
+    await program.methods.setTokenGate(tokenGate).accounts(adminAccounts).rpc();
+    try {
+      for (const gate of [
+        { ...gateAccounts, callerTokenAccount: null },
+        { ...gateAccounts, gateMint: null },
+      ]) {
+        try {
+          await verify(gate);
+          assert.fail("Should have required the gate's accounts");
+        } catch (error: any) {
+          assert.include(error.toString(), "TokenGateNotSatisfied");
+        }
+      }
+      // Leaving out the configuration doesn't skip the gate either.
+      const ungatedIx = await program.methods
+        .verifyV2({
+          numVariables: metadata.num_variables,
+          securityLevel: metadata.config.security_level,
+          powBits: metadata.config.pow_bits,
+          foldingFactor: metadata.config.folding_factor,
+          startingLogInvRate: metadata.config.starting_log_inv_rate,
+        })
+        .accounts(verifyAccounts(proofDataKeypair.publicKey))
+        .instruction();
+      ungatedIx.keys = ungatedIx.keys.map((key) =>
+        key.pubkey.equals(configPda) ? { ...key, pubkey: program.programId } : key
+      );
+      try {
+        await provider.sendAndConfirm(
+          new anchor.web3.Transaction().add(
+            anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+            ungatedIx
+          )
+        );
+        assert.fail("Should have required the configuration account");
+      } catch (error: any) {
+        assert.include(error.toString(), "AccountOwnedByWrongProgram");
+      }
+      await verify(gateAccounts);
+    } finally {
+      await program.methods.setTokenGate(null).accounts(adminAccounts).rpc();
+    }
+    assert.equal((await program.account.proofData.fetch(proofDataKeypair.publicKey)).verified, 1);
+    const balance = await provider.connection.getTokenAccountBalance(tokenAccount);
+    assert.equal(balance.value.amount, "1");
+  });
+
+  it("Exempts callers from the fee and withdraws it from the treasury", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const wallet = provider.wallet.publicKey;
+    const adminAccounts = { config: configPda, admin: wallet };
+    const intruder = Keypair.generate();
+    try {
+      await program.methods
+        .setFeeExempt(intruder.publicKey, true)
+        .accounts({ config: configPda, admin: intruder.publicKey })
+        .signers([intruder])
+        .rpc();
+      assert.fail("Should have rejected a non-admin signer");
+    } catch (error: any) {
+      assert.include(error.toString(), "Unauthorized");
+    }
+
+    await program.methods.setFee(new anchor.BN(10_000)).accounts(adminAccounts).rpc();
+    await program.methods.setFeeExempt(wallet, true).accounts(adminAccounts).rpc();
+    try {
+      const proofDataKeypair = await createProofAccount();
+      await program.methods
+        .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+        .accounts(proofAccounts(proofDataKeypair.publicKey))
+        .rpc();
+      await uploadProof(proofDataKeypair.publicKey, proof);
+      const treasuryBefore = await provider.connection.getBalance(treasuryPda);
+      await program.methods
+        .verifyV2({
+          numVariables: metadata.num_variables,
+          securityLevel: metadata.config.security_level,
+          powBits: metadata.config.pow_bits,
+          foldingFactor: metadata.config.folding_factor,
+          startingLogInvRate: metadata.config.starting_log_inv_rate,
+        })
+        .accounts(verifyAccounts(proofDataKeypair.publicKey))
+        .preInstructions([
+          anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+        ])
+        .rpc();
+      assert.equal(await provider.connection.getBalance(treasuryPda), treasuryBefore);
+    } finally {
+      await program.methods.setFeeExempt(wallet, false).accounts(adminAccounts).rpc();
+      await program.methods.setFee(new anchor.BN(0)).accounts(adminAccounts).rpc();
+    }
+    const config = await program.account.programConfig.fetch(configPda);
+    assert.isFalse(config.feeExempt.some((caller) => caller.equals(wallet)));
+
+    // Only the fees above the treasury's rent exemption can be withdrawn.
+    const treasury = await provider.connection.getAccountInfo(treasuryPda);
+    const available =
+      treasury.lamports -
+      (await provider.connection.getMinimumBalanceForRentExemption(treasury.data.length));
+    // The recipient is funded so that small withdrawals keep it rent exempt.
+    const recipient = Keypair.generate().publicKey;
+    await provider.sendAndConfirm(
+      new anchor.web3.Transaction().add(
+        anchor.web3.SystemProgram.transfer({
+          fromPubkey: wallet,
+          toPubkey: recipient,
+          lamports: anchor.web3.LAMPORTS_PER_SOL / 100,
+        })
+      )
+    );
+    const withdrawFees = (amount: number, admin: Keypair | null = null) =>
+      program.methods
+        .withdrawFees(new anchor.BN(amount))
+        .accounts({
+          config: configPda,
+          treasury: treasuryPda,
+          admin: admin?.publicKey ?? wallet,
+          recipient,
+        })
+        .signers(admin ? [admin] : [])
+        .rpc();
+    try {
+      await withdrawFees(available, intruder);
+      assert.fail("Should have rejected a non-admin signer");
+    } catch (error: any) {
+      assert.include(error.toString(), "Unauthorized");
+    }
+    try {
+      await withdrawFees(available + 1);
+      assert.fail("Should have kept the treasury rent exempt");
+    } catch (error: any) {
+      assert.include(error.toString(), "InsufficientTreasuryBalance");
+    }
+    await withdrawFees(available);
+    assert.equal(
+      await provider.connection.getBalance(recipient),
+      anchor.web3.LAMPORTS_PER_SOL / 100 + available
+    );
+  });
+
+  it("Reads the evaluation value from the designated account", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    // Another proof account holds the value as its uploaded bytes.
+    const proofOffset = 8 + 4512;
+    const sourceKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(proofAccounts(sourceKeypair.publicKey))
+      .rpc();
+    await uploadProof(sourceKeypair.publicKey, Buffer.from(evalValue));
+
+    // The value stored in the proof account is wrong and only the source's makes it verify.
+    const proofDataKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.alloc(evalValue.length), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+    await uploadProof(proofDataKeypair.publicKey, proof);
+    const intruder = Keypair.generate();
+    try {
+      await program.methods
+        .setEvalValueSource(intruder.publicKey, 0)
+        .accounts({ ...proofAccounts(proofDataKeypair.publicKey), payer: intruder.publicKey })
+        .signers([intruder])
+        .rpc();
+      assert.fail("Should have rejected a signer other than the proof's authority");
+    } catch (error: any) {
+      assert.include(error.toString(), "Unauthorized");
+    }
+    await program.methods
+      .setEvalValueSource(sourceKeypair.publicKey, proofOffset)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+
+    const verify = (evalValueAccount: anchor.web3.PublicKey | null) =>
+      program.methods
+        .verifyV2({
+          numVariables: metadata.num_variables,
+          securityLevel: metadata.config.security_level,
+          powBits: metadata.config.pow_bits,
+          foldingFactor: metadata.config.folding_factor,
+          startingLogInvRate: metadata.config.starting_log_inv_rate,
+        })
+        .accounts(verifyAccounts(proofDataKeypair.publicKey, { evalValueAccount }))
+        .preInstructions([
+          anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+        ])
+        .rpc();
+    for (const evalValueAccount of [null, proofDataKeypair.publicKey]) {
+      try {
+        await verify(evalValueAccount);
+        assert.fail("Should have required the designated account");
+      } catch (error: any) {
+        assert.include(error.toString(), "MissingEvalValueAccount");
+      }
+    }
+    await verify(sourceKeypair.publicKey);
+    assert.equal((await program.account.proofData.fetch(proofDataKeypair.publicKey)).verified, 1);
+  });
+
+  it("Verifies a proof only under the context it was proven with", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const proofDataKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+    await uploadProof(proofDataKeypair.publicKey, proof);
+    const setContext = (context: Buffer, authority: Keypair | null = null) =>
+      program.methods
+        .setContext(context)
+        .accounts({
+          ...proofAccounts(proofDataKeypair.publicKey),
+          payer: authority?.publicKey ?? provider.wallet.publicKey,
+        })
+        .signers(authority ? [authority] : [])
+        .rpc();
+    try {
+      await setContext(Buffer.alloc(0), Keypair.generate());
+      assert.fail("Should have rejected a signer other than the proof's authority");
+    } catch (error: any) {
+      assert.include(error.toString(), "Unauthorized");
+    }
+
+    const verify = () =>
+      program.methods
+        .verifyV2({
+          numVariables: metadata.num_variables,
+          securityLevel: metadata.config.security_level,
+          powBits: metadata.config.pow_bits,
+          foldingFactor: metadata.config.folding_factor,
+          startingLogInvRate: metadata.config.starting_log_inv_rate,
+        })
+        .accounts(verifyAccounts(proofDataKeypair.publicKey))
+        .preInstructions([
+          anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+        ])
+        .rpc();
+    // The sample proof is bound to no context.
+    await setContext(Buffer.from("another-application"));
+    try {
+      await verify();
+      assert.fail("Should have rejected the proof under another context");
+    } catch (error: any) {
+      assert.include(error.toString(), "VerificationFailed");
+    }
+    await setContext(Buffer.alloc(0));
+    await verify();
+    assert.equal((await program.account.proofData.fetch(proofDataKeypair.publicKey)).verified, 1);
+  });
+
+  it("Verifies a proof only under the domain label it was proven with", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const verifyWithLabel = async (domainLabel: string) => {
+      const proofDataKeypair = await createProofAccount();
+      await program.methods
+        .initProofV2(
+          Buffer.from(evalPoint),
+          Buffer.from(evalValue),
+          metadata.encoding ?? 0,
+          domainLabel
+        )
+        .accounts(proofAccounts(proofDataKeypair.publicKey))
+        .rpc();
+      await uploadProof(proofDataKeypair.publicKey, proof);
+      await program.methods
+        .verifyV2({
+          numVariables: metadata.num_variables,
+          securityLevel: metadata.config.security_level,
+          powBits: metadata.config.pow_bits,
+          foldingFactor: metadata.config.folding_factor,
+          startingLogInvRate: metadata.config.starting_log_inv_rate,
+        })
+        .accounts(verifyAccounts(proofDataKeypair.publicKey))
+        .preInstructions([
+          anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+        ])
+        .rpc();
+    };
+
+    // The sample proof is proven under the default label, which the empty label selects.
+    await verifyWithLabel("");
+    const cases: [string, string][] = [
+      ["another-deployment", "VerificationFailed"],
+      ["x".repeat(65), "DomainLabelTooLong"],
+    ];
+    for (const [domainLabel, expected] of cases) {
+      try {
+        await verifyWithLabel(domainLabel);
+        assert.fail("Should have rejected the domain label");
+      } catch (error: any) {
+        assert.include(error.toString(), expected);
+      }
+    }
+  });
+
+  it("Hands over the proof account to a new authority", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const proofDataKeypair = await createProofAccount();
+    const proofData = proofDataKeypair.publicKey;
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofData))
+      .rpc();
+    const newAuthority = Keypair.generate();
+    try {
+      await program.methods
+        .setProofAuthority(newAuthority.publicKey)
+        .accounts({ proofData, authority: newAuthority.publicKey })
+        .signers([newAuthority])
+        .rpc();
+      assert.fail("Should have rejected a signer other than the proof's authority");
+    } catch (error: any) {
+      assert.include(error.toString(), "ConstraintHasOne");
+    }
+    await program.methods
+      .setProofAuthority(newAuthority.publicKey)
+      .accounts({ proofData, authority: provider.wallet.publicKey })
+      .rpc();
+
+    // The previous authority can no longer upload, the new one uploads the proof.
+    const chunk = Buffer.from(proof.subarray(0, chunkSize));
+    try {
+      await program.methods.uploadChunk(chunk).accounts(proofAccounts(proofData)).rpc();
+      assert.fail("Should have rejected the previous authority");
+    } catch (error: any) {
+      assert.include(error.toString(), "Unauthorized");
+    }
+    for (let start = 0; start < proof.length; start += chunkSize) {
+      await program.methods
+        .uploadChunk(Buffer.from(proof.subarray(start, Math.min(start + chunkSize, proof.length))))
+        .accounts({ ...proofAccounts(proofData), payer: newAuthority.publicKey })
+        .signers([newAuthority])
+        .rpc();
+    }
+    assert.equal((await program.account.proofData.fetch(proofData)).proofLen, proof.length);
+
+    // Closing needs the new authority, the rent still goes to the payer.
+    try {
+      await program.methods
+        .closeProof()
+        .accounts({ proofData, payer: provider.wallet.publicKey, authority: null })
+        .rpc();
+      assert.fail("Should have rejected the previous authority");
+    } catch (error: any) {
+      assert.include(error.toString(), "Unauthorized");
+    }
+    await program.methods
+      .closeProof()
+      .accounts({ proofData, payer: provider.wallet.publicKey, authority: newAuthority.publicKey })
+      .signers([newAuthority])
+      .rpc();
+    assert.isNull(await provider.connection.getAccountInfo(proofData));
+  });
+
+  it("Rejects proof accounts of another layout version", async () => {
+    // Loaded by the test validator, see scripts/account-fixtures.py.
+    const staleVersion = new anchor.web3.PublicKey("5Tg9bJzNwRAug1dJkAUUc7ePwvAFiQvYx8ae4kuBn3SK");
+    const oversizedMetadata = new anchor.web3.PublicKey(
+      "7qLszex778wXjFM2DeE1P1U97bHH9VFtqGFKvZ3uEAGE"
+    );
+    const status = (proofData: anchor.web3.PublicKey) =>
+      program.methods.getProofStatus().accounts({ proofData }).rpc();
+
+    // A header of another layout claiming a verification isn't read.
+    try {
+      await status(staleVersion);
+      assert.fail("Should have rejected another layout version");
+    } catch (error: any) {
+      assert.include(error.toString(), "UnsupportedAccountVersion");
+    }
+    try {
+      await program.methods
+        .publishEvaluation()
+        .accounts({
+          proofData: staleVersion,
+          evaluationResult: Keypair.generate().publicKey,
+          payer: provider.wallet.publicKey,
+          systemProgram: anchor.web3.SystemProgram.programId,
+          config: configPda,
+        })
+        .rpc();
+      assert.fail("Should have rejected another layout version");
+    } catch (error: any) {
+      assert.include(error.toString(), "UnsupportedAccountVersion");
+    }
+
+    // A metadata length beyond the capacity fails to deserialize instead of aborting.
+    try {
+      await status(oversizedMetadata);
+      assert.fail("Should have rejected the metadata");
+    } catch (error: any) {
+      assert.include(error.toString(), "AccountDidNotDeserialize");
+    }
+  });
+
+  it("Estimates the compute units of a verification within half of the measured cost", async () => {
+    const { proof, evalPoint, evalValue, metadata } = loadProof();
+    const params = {
+      numVariables: metadata.num_variables,
+      securityLevel: metadata.config.security_level,
+      powBits: metadata.config.pow_bits,
+      foldingFactor: metadata.config.folding_factor,
+      startingLogInvRate: metadata.config.starting_log_inv_rate,
+    };
+    const proofDataKeypair = await createProofAccount();
+    await program.methods
+      .initProof(Buffer.from(evalPoint), Buffer.from(evalValue), metadata.encoding ?? 0)
+      .accounts(proofAccounts(proofDataKeypair.publicKey))
+      .rpc();
+    await uploadProof(proofDataKeypair.publicKey, proof);
+    const signature = await program.methods
+      .verifyV2(params)
+      .accounts(verifyAccounts(proofDataKeypair.publicKey))
+      .preInstructions([
+        anchor.web3.ComputeBudgetProgram.setComputeUnitLimit({ units: maxComputeUnits }),
+      ])
+      .rpc({ commitment: "confirmed" });
+    const transaction = await provider.connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const measured = transaction.meta.computeUnitsConsumed;
+    assert.isAbove(measured, 0);
+
+    // The tolerance of `whir_config::cost`, whose per-unit costs aren't derived from measurements.
+    const { computeUnits } = await program.methods.quoteVerification(params).accounts({}).view();
+    const estimate = computeUnits.toNumber();
+    assert.isAtMost(
+      Math.abs(estimate - measured),
+      measured / 2,
+      `estimated ${estimate} compute units, measured ${measured}`
+    );
   });
 });
@@ -0,0 +1,210 @@
+//! Anchor-free WHIR proof verification.
+//!
+//! The verification logic of the Solana program without any Solana or Anchor dependency: reading
+//! the transcript, parsing the commitment, building the statement from serialized evaluation
+//! constraints and running the WHIR verifier. Other runtimes (sBPF programs without Anchor, WASM,
+//! light clients) verify the same proofs with it, computing the same digests.
+//!
+//! On Solana, every function gets a 4 KiB stack frame, which the parsed commitment and the WHIR
+//! verifier's round states overflow for larger folding factors when kept in one frame. The
+//! verification therefore runs each phase in its own non-inlined frame and keeps the verifier
+//! state, parsed commitment and statement boxed on the heap in between.
+//!
+//! The crate itself only uses `core` and `alloc`. Building it without the default `std` feature
+//! additionally requires `no_std` builds of `whir-config` and of the WHIR fork.
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+use alloc::{boxed::Box, vec::Vec};
+
+use spongefish::ProofError;
+use whir_common::{
+    poly_utils::multilinear::MultilinearPoint,
+    whir::{
+        committer::reader::CommitmentReader,
+        statement::{Statement, Weights},
+    },
+};
+use whir_config::{
+    commitment_size_bytes,
+    field::{decode_field, decode_field_elements},
+    params::params_digest,
+    transcript::{resolve_domain_label, TranscriptDomainSeparator},
+};
+pub use whir_config::{create_whir_params, transcript, WhirParams, F};
+use whir_verifier::Verifier;
+
+/// Reasons a verification fails.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VerifyError {
+    /// The commitment at the start of the proof can't be read.
+    CommitmentParse,
+    /// A constraint's evaluation point has the wrong number of variables.
+    InvalidEvalPoint,
+    /// A constraint's evaluation point or value isn't a valid field element.
+    Deserialization,
+    /// The statement has no constraints.
+    EmptyStatement,
+    /// A check of the WHIR verifier failed.
+    VerificationFailed,
+    /// The proof doesn't follow the transcript pattern.
+    TranscriptMismatch,
+    /// A proof element can't be deserialized.
+    ProofDeserialization,
+}
+
+/// Maps the error kinds of the WHIR verifier.
+///
+/// `whir_verifier` reports every failed check (sumcheck, Merkle paths, OOD answers, proof of
+/// work) as `ProofError::InvalidProof`, so those share `VerificationFailed`.
+impl From<ProofError> for VerifyError {
+    fn from(error: ProofError) -> Self {
+        match error {
+            ProofError::InvalidProof => VerifyError::VerificationFailed,
+            ProofError::InvalidDomainSeparator(_) => VerifyError::TranscriptMismatch,
+            ProofError::SerializationError => VerifyError::ProofDeserialization,
+        }
+    }
+}
+
+/// Phases of a verification, reported to [`verify_proof`]'s observer as they complete.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Phase {
+    Transcript,
+    Commitment,
+    Statement,
+    /// The sumcheck rounds, Merkle queries and proof-of-work checks of the WHIR verifier.
+    Verification,
+}
+
+impl Phase {
+    pub fn name(self) -> &'static str {
+        match self {
+            Phase::Transcript => "transcript",
+            Phase::Commitment => "commitment",
+            Phase::Statement => "statement",
+            Phase::Verification => "sumcheck, queries and proof of work",
+        }
+    }
+}
+
+/// Hash function of the verified digest, SHA-256 on Solana.
+pub trait DigestHasher {
+    fn update(&mut self, bytes: &[u8]);
+    fn finalize(self) -> [u8; 32];
+}
+
+/// Verifies a WHIR proof for a statement given as (evaluation point, value) byte pairs.
+///
+/// `domainsep` is the proof's domain separator, built from `domain_label` and `context`, see
+/// [`transcript::domain_separator`]. `on_phase` is called after each [`Phase`].
+///
+/// Returns the digest of the proof's binding (see [`hash_binding`]), the commitment bytes and the
+/// statement's evaluation points and values in order, identifying the verified (commitment,
+/// statement) pair and what it was verified under.
+#[allow(clippy::too_many_arguments)]
+pub fn verify_proof<'a, H: DigestHasher>(
+    params: &WhirParams,
+    domainsep: &TranscriptDomainSeparator,
+    proof_bytes: &[u8],
+    domain_label: &str,
+    context: &[u8],
+    constraints: impl Iterator<Item = (&'a [u8], &'a [u8])>,
+    mut hasher: H,
+    mut on_phase: impl FnMut(Phase),
+) -> Result<[u8; 32], VerifyError> {
+    let mut verifier_state = in_own_frame(|| {
+        let mut verifier_state = domainsep.to_verifier_state(proof_bytes);
+        transcript::bind_context(&mut verifier_state, context)
+            .map_err(|_| VerifyError::CommitmentParse)?;
+        Ok(verifier_state)
+    })?;
+    on_phase(Phase::Transcript);
+
+    let parsed_commitment = in_own_frame(|| {
+        CommitmentReader::new(params)
+            .parse_commitment(&mut *verifier_state)
+            .map_err(|_| VerifyError::CommitmentParse)
+    })?;
+    on_phase(Phase::Commitment);
+
+    hash_binding(&mut hasher, params, domain_label, context);
+    hasher.update(
+        proof_bytes
+            .get(..commitment_size_bytes(params))
+            .ok_or(VerifyError::CommitmentParse)?,
+    );
+    let constraints = constraints.inspect(|(eval_point_bytes, eval_value_bytes)| {
+        hasher.update(eval_point_bytes);
+        hasher.update(eval_value_bytes);
+    });
+    let statement =
+        in_own_frame(|| build_statement(params.mv_parameters.num_variables, constraints))?;
+    on_phase(Phase::Statement);
+
+    in_own_frame(move || {
+        Verifier::new(params).verify(&mut *verifier_state, *parsed_commitment, *statement)?;
+        Ok(())
+    })?;
+    on_phase(Phase::Verification);
+
+    Ok(hasher.finalize())
+}
+
+/// Hashes what a verified digest binds besides the commitment and statement: the digest of the
+/// parameters, see [`params_digest`], which covers the soundness type, and the transcript's
+/// resolved domain label and context, each prefixed with its length as a little-endian `u32`.
+///
+/// A proof verified under weaker parameters, another domain or another context therefore never
+/// produces the digest of the same commitment and statement verified under the intended ones.
+pub fn hash_binding<H: DigestHasher>(
+    hasher: &mut H,
+    params: &WhirParams,
+    domain_label: &str,
+    context: &[u8],
+) {
+    hasher.update(&params_digest(params));
+    let label = resolve_domain_label(domain_label).as_bytes();
+    hasher.update(&(label.len() as u32).to_le_bytes());
+    hasher.update(label);
+    hasher.update(&(context.len() as u32).to_le_bytes());
+    hasher.update(context);
+}
+
+/// Runs `phase` in its own stack frame and boxes its result, see the crate docs.
+#[inline(never)]
+fn in_own_frame<T>(phase: impl FnOnce() -> Result<T, VerifyError>) -> Result<Box<T>, VerifyError> {
+    phase().map(Box::new)
+}
+
+/// Builds the statement of serialized (evaluation point, value) constraints.
+pub fn build_statement<'a>(
+    num_variables: usize,
+    constraints: impl Iterator<Item = (&'a [u8], &'a [u8])>,
+) -> Result<Statement<F>, VerifyError> {
+    let mut statement = Statement::new(num_variables);
+    let mut num_constraints = 0;
+    for (eval_point_bytes, eval_value_bytes) in constraints {
+        let eval_point = deserialize_eval_point(eval_point_bytes)?;
+        if eval_point.num_variables() != num_variables {
+            return Err(VerifyError::InvalidEvalPoint);
+        }
+        let eval_value = decode_field(eval_value_bytes).ok_or(VerifyError::Deserialization)?;
+        statement.add_constraint(Weights::evaluation(eval_point), eval_value);
+        num_constraints += 1;
+    }
+    if num_constraints == 0 {
+        return Err(VerifyError::EmptyStatement);
+    }
+    Ok(statement)
+}
+
+/// Deserializes an evaluation point of consecutive field elements.
+pub fn deserialize_eval_point(bytes: &[u8]) -> Result<MultilinearPoint<F>, VerifyError> {
+    let points = decode_field_elements(bytes)
+        .collect::<Option<Vec<F>>>()
+        .ok_or(VerifyError::Deserialization)?;
+    Ok(MultilinearPoint(points))
+}